@@ -25,6 +25,40 @@ impl S3Provider {
         Ok(presigned_url)
     }
 
+    /// Presigns an object for display in the page rather than as a download, like card media
+    pub async fn get_presigned_inline_url(&self, key: &str) -> Result<String, S3Error> {
+        let presigned_req = self
+            .client
+            .get_object()
+            .bucket(&self.bucket_name)
+            .key(key)
+            .presigned(aws_sdk_s3::presigning::PresigningConfig::expires_in(
+                std::time::Duration::from_secs(60 * 60),
+            )?)
+            .await
+            .map_err(|e| S3Error::Internal(format!("Failed to create presigned URL: {e}")))?;
+
+        Ok(presigned_req.uri().to_string())
+    }
+
+    /// Downloads a whole object, for small files we repackage like card media
+    pub async fn download_object(&self, s3_key: &str) -> Result<Vec<u8>, S3Error> {
+        let object = self
+            .client
+            .get_object()
+            .bucket(&self.bucket_name)
+            .key(s3_key)
+            .send()
+            .await?;
+
+        let data = object
+            .body
+            .collect()
+            .await
+            .map_err(|e| S3Error::Internal(format!("Failed to read object {s3_key}: {e}")))?;
+        Ok(data.into_bytes().to_vec())
+    }
+
     pub async fn check_s3_connection(&self) -> Result<StatusCode, S3Error> {
         let result = self.client.list_buckets().send().await.map_err(|e| {
             tracing::error!("S3 connection test failed: {e:?}");
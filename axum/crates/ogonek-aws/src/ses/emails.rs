@@ -81,4 +81,95 @@ impl SESProvider {
         tracing::info!("Confirm email sent");
         Ok(())
     }
+
+    pub async fn send_password_reset(
+        &self,
+        to: &str,
+        name: &str,
+        token: &str,
+    ) -> Result<(), SESError> {
+        let tera = &TEMPLATES;
+
+        let address = std::env::var("FRONTEND_URL").unwrap_or("http://ogonek.app".to_string());
+
+        let mut ctx = tera::Context::new();
+        ctx.insert("name", name);
+        ctx.insert(
+            "app_url",
+            format!("{}/reset-password/?token={}", address, token).as_str(),
+        );
+
+        let html = tera.render("reset.html", &ctx)?;
+
+        self.client
+            .send_email()
+            .from_email_address(&self.from_email)
+            .destination(Destination::builder().to_addresses(to).build())
+            .content(
+                EmailContent::builder()
+                    .simple(
+                        Message::builder()
+                            .subject(Content::builder().data("Сброс пароля").build()?)
+                            .body(
+                                Body::builder()
+                                    .html(Content::builder().data(html).build()?)
+                                    .build(),
+                            )
+                            .build(),
+                    )
+                    .build(),
+            )
+            .send()
+            .await?;
+
+        tracing::info!("Password reset email sent");
+        Ok(())
+    }
+
+    pub async fn send_event_reminder(
+        &self,
+        to: &str,
+        summary: &str,
+        starts_at: &str,
+        description: Option<&str>,
+    ) -> Result<(), SESError> {
+        let tera = &TEMPLATES;
+
+        let address = std::env::var("FRONTEND_URL").unwrap_or("http://ogonek.app".to_string());
+
+        let mut ctx = tera::Context::new();
+        ctx.insert("summary", summary);
+        ctx.insert("starts_at", starts_at);
+        ctx.insert("description", &description);
+        ctx.insert("app_url", &address);
+
+        let html = tera.render("reminder.html", &ctx)?;
+
+        self.client
+            .send_email()
+            .from_email_address(&self.from_email)
+            .destination(Destination::builder().to_addresses(to).build())
+            .content(
+                EmailContent::builder()
+                    .simple(
+                        Message::builder()
+                            .subject(
+                                Content::builder()
+                                    .data(format!("Напоминание: {summary}"))
+                                    .build()?,
+                            )
+                            .body(
+                                Body::builder()
+                                    .html(Content::builder().data(html).build()?)
+                                    .build(),
+                            )
+                            .build(),
+                    )
+                    .build(),
+            )
+            .send()
+            .await?;
+
+        Ok(())
+    }
 }
@@ -0,0 +1,18 @@
+-- Add migration script here
+-- Append-only log of every answer given to a card
+CREATE TABLE card_reviews (
+    id VARCHAR(21) PRIMARY KEY,
+    user_id VARCHAR(21) NOT NULL REFERENCES "user"(id) ON DELETE CASCADE,
+    card_id VARCHAR(21) NOT NULL REFERENCES cards(id) ON DELETE CASCADE,
+    deck_id VARCHAR(21) NOT NULL REFERENCES decks(id) ON DELETE CASCADE,
+    quality INT NOT NULL, -- Self-graded recall, 0-5
+    ease_before FLOAT NOT NULL,
+    ease_after FLOAT NOT NULL,
+    interval_before INT NOT NULL, -- Days
+    interval_after INT NOT NULL, -- Days
+    elapsed_seconds BIGINT, -- Time since the previous review, NULL on the first one
+    reviewed_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_card_reviews_user_id ON card_reviews(user_id, reviewed_at DESC);
+CREATE INDEX idx_card_reviews_deck_id ON card_reviews(deck_id, reviewed_at DESC);
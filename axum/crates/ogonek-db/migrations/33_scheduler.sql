@@ -0,0 +1,12 @@
+-- Add migration script here
+-- Memory state for FSRS, empty until the card is first scheduled by it
+ALTER TABLE card_progress
+ADD COLUMN stability FLOAT, -- Days until recall probability drops to 90%
+ADD COLUMN difficulty FLOAT; -- 1 (easy) to 10 (hard)
+
+ALTER TABLE user_preferences
+ADD COLUMN scheduler VARCHAR(10) NOT NULL DEFAULT 'sm2' CHECK (scheduler IN ('sm2', 'fsrs'));
+
+-- NULL means the subscriber's own preference applies
+ALTER TABLE decks
+ADD COLUMN scheduler VARCHAR(10) CHECK (scheduler IN ('sm2', 'fsrs'));
@@ -0,0 +1,15 @@
+-- Add migration script here
+ALTER TABLE card_progress
+ADD COLUMN state VARCHAR(12) NOT NULL DEFAULT 'new' CHECK (state IN ('new', 'learning', 'review', 'relearning')),
+ADD COLUMN step INT NOT NULL DEFAULT 0, -- Position in the (re)learning steps
+ADD COLUMN lapses INT NOT NULL DEFAULT 0; -- Times the card was forgotten after graduating
+
+-- Everything answered at least once so far has already graduated
+UPDATE card_progress SET state = 'review' WHERE review_count > 0;
+
+-- Intra-day steps in minutes
+ALTER TABLE user_preferences
+ADD COLUMN learning_steps INT[] NOT NULL DEFAULT '{1, 10}' CHECK (0 < ALL(learning_steps)),
+ADD COLUMN relearning_steps INT[] NOT NULL DEFAULT '{10}' CHECK (0 < ALL(relearning_steps));
+
+CREATE INDEX idx_card_progress_user_due ON card_progress(user_id, due_date);
@@ -0,0 +1,19 @@
+-- Add migration script here
+ALTER TABLE user_preferences
+ADD COLUMN new_cards_per_day INT NOT NULL DEFAULT 20 CHECK (new_cards_per_day >= 0),
+ADD COLUMN reviews_per_day INT NOT NULL DEFAULT 200 CHECK (reviews_per_day >= 0);
+
+-- NULL means the subscriber's own limit applies
+ALTER TABLE decks
+ADD COLUMN new_cards_per_day INT CHECK (new_cards_per_day >= 0),
+ADD COLUMN reviews_per_day INT CHECK (reviews_per_day >= 0);
+
+-- When the card was first answered, counts towards the new card limit of that day
+ALTER TABLE card_progress
+ADD COLUMN introduced_at TIMESTAMPTZ;
+
+UPDATE card_progress
+SET introduced_at = last_reviewed
+WHERE review_count > 0;
+
+CREATE INDEX idx_card_progress_user_introduced ON card_progress(user_id, introduced_at);
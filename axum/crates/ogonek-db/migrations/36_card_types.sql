@@ -0,0 +1,62 @@
+-- Add migration script here
+ALTER TABLE cards
+ADD COLUMN card_type VARCHAR(10) NOT NULL DEFAULT 'basic'
+    CHECK (card_type IN ('basic', 'reversed', 'cloze', 'typed'));
+
+-- Side of the card a progress row tracks: 1 is the reverse of a reversed card,
+-- cloze cards get one row per deletion number
+ALTER TABLE card_progress
+ADD COLUMN ordinal INT NOT NULL DEFAULT 0;
+
+ALTER TABLE card_progress
+DROP CONSTRAINT unique_card_user,
+ADD CONSTRAINT unique_card_user_ordinal UNIQUE (user_id, card_id, ordinal);
+
+-- Every side of a card a learner gets a progress row for
+CREATE OR REPLACE FUNCTION card_ordinals(card_type VARCHAR, front TEXT)
+RETURNS SETOF INT AS $$
+BEGIN
+    IF card_type = 'reversed' THEN
+        RETURN QUERY SELECT unnest(ARRAY[0, 1]);
+    ELSIF card_type = 'cloze' AND front ~ '\{\{c\d+::' THEN
+        RETURN QUERY
+            SELECT DISTINCT m[1]::INT
+            FROM regexp_matches(front, '\{\{c(\d+)::', 'g') AS m;
+    ELSE
+        RETURN QUERY SELECT 0;
+    END IF;
+END;
+$$ LANGUAGE plpgsql IMMUTABLE;
+
+CREATE OR REPLACE FUNCTION create_card_progress_for_subscribers()
+RETURNS TRIGGER AS $$
+BEGIN
+    INSERT INTO card_progress (
+        id, user_id, card_id, ordinal, review_count, due_date, ease_factor, interval
+    )
+    SELECT nanoid(), ds.user_id, NEW.id, o.ordinal, 0, CURRENT_TIMESTAMP, 2.5, 1
+    FROM deck_subscriptions ds
+    CROSS JOIN card_ordinals(NEW.card_type, NEW.front) AS o(ordinal)
+    WHERE ds.deck_id = NEW.deck_id
+    ON CONFLICT (user_id, card_id, ordinal) DO NOTHING;
+
+    -- Drop the progress of sides an edit removed, like a deleted cloze
+    IF TG_OP = 'UPDATE' THEN
+        DELETE FROM card_progress
+        WHERE card_id = NEW.id
+        AND ordinal NOT IN (SELECT card_ordinals(NEW.card_type, NEW.front));
+    END IF;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER sync_card_progress_on_card_update_trigger
+AFTER UPDATE OF card_type, front, deck_id ON cards
+FOR EACH ROW
+WHEN (
+    OLD.card_type IS DISTINCT FROM NEW.card_type
+    OR OLD.front IS DISTINCT FROM NEW.front
+    OR OLD.deck_id IS DISTINCT FROM NEW.deck_id
+)
+EXECUTE FUNCTION create_card_progress_for_subscribers();
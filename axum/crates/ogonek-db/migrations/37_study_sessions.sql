@@ -0,0 +1,25 @@
+-- Add migration script here
+CREATE TABLE study_sessions (
+    id VARCHAR(21) PRIMARY KEY,
+    user_id VARCHAR(21) NOT NULL REFERENCES "user"(id) ON DELETE CASCADE,
+    deck_id VARCHAR(21) REFERENCES decks(id) ON DELETE CASCADE, -- NULL studies every subscribed deck
+    started_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    finished_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX idx_study_sessions_user_id ON study_sessions(user_id, started_at DESC);
+
+-- The queue of a session. Cards still in their learning steps after an answer are queued again
+CREATE TABLE study_session_cards (
+    session_id VARCHAR(21) NOT NULL REFERENCES study_sessions(id) ON DELETE CASCADE,
+    position INT NOT NULL,
+    progress_id VARCHAR(21) NOT NULL REFERENCES card_progress(id) ON DELETE CASCADE,
+    shown_at TIMESTAMP WITH TIME ZONE,
+    answered_at TIMESTAMP WITH TIME ZONE,
+    quality INT,
+    -- What undoing the answer restores
+    previous_progress JSONB,
+    review_id VARCHAR(21) REFERENCES card_reviews(id) ON DELETE SET NULL,
+    requeued_position INT,
+    PRIMARY KEY (session_id, position)
+);
@@ -0,0 +1,4 @@
+-- Add migration script here
+ALTER TABLE user_preferences
+ADD COLUMN daily_review_goal INT NOT NULL DEFAULT 20 CHECK (daily_review_goal >= 0),
+ADD COLUMN daily_task_goal INT NOT NULL DEFAULT 1 CHECK (daily_task_goal >= 0);
@@ -0,0 +1,15 @@
+-- Add migration script here
+ALTER TABLE cards
+ADD COLUMN tags TEXT[] NOT NULL DEFAULT '{}',
+-- 'simple' keeps words as they are, decks mix languages
+ADD COLUMN search_vector TSVECTOR GENERATED ALWAYS AS (
+    to_tsvector('simple', front || ' ' || back)
+) STORED;
+
+CREATE INDEX idx_cards_tags ON cards USING GIN (tags);
+CREATE INDEX idx_cards_search_vector ON cards USING GIN (search_vector);
+CREATE INDEX idx_card_reviews_card_id ON card_reviews(card_id, reviewed_at DESC);
+
+-- Filter expression a study session was started from
+ALTER TABLE study_sessions
+ADD COLUMN filter TEXT;
@@ -0,0 +1,3 @@
+-- Add migration script here
+ALTER TABLE calendars
+ADD COLUMN feed_token VARCHAR(64) UNIQUE;
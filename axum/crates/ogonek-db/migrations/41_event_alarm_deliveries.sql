@@ -0,0 +1,14 @@
+-- Add migration script here
+CREATE TABLE event_alarm_deliveries (
+    id VARCHAR(21) PRIMARY KEY,
+    alarm_id VARCHAR(21) NOT NULL REFERENCES event_alarms(id) ON DELETE CASCADE,
+    occurrence TIMESTAMPTZ NOT NULL, -- Start of the occurrence the alarm fired for
+    repeat_index INTEGER NOT NULL DEFAULT 0,
+    channel VARCHAR(20) NOT NULL CHECK (channel IN ('email', 'telegram', 'apns')),
+    recipient TEXT NOT NULL,
+    delivered_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    UNIQUE (alarm_id, occurrence, repeat_index, channel, recipient)
+);
+
+CREATE INDEX idx_alarm_deliveries_alarm_id ON event_alarm_deliveries(alarm_id);
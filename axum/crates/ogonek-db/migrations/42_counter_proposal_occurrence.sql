@@ -0,0 +1,16 @@
+-- Add migration script here
+ALTER TABLE event_counter_proposals
+ADD COLUMN recurrence_id TIMESTAMPTZ, -- The occurrence proposed for, NULL for a whole event
+ADD COLUMN responded_at TIMESTAMPTZ;
+
+ALTER TABLE event_counter_proposals
+DROP CONSTRAINT event_counter_proposals_event_id_fkey,
+ADD CONSTRAINT event_counter_proposals_event_id_fkey
+    FOREIGN KEY (event_id) REFERENCES calendar_events(id) ON DELETE CASCADE;
+
+-- One pending proposal per person and occurrence
+CREATE UNIQUE INDEX unique_pending_proposal
+ON event_counter_proposals(event_id, recurrence_id, proposer) NULLS NOT DISTINCT
+WHERE status = 'pending';
+
+CREATE INDEX idx_counter_proposals_event_id ON event_counter_proposals(event_id);
@@ -0,0 +1,33 @@
+-- Add migration script here
+CREATE TYPE attendance_status AS ENUM (
+    'attended',
+    'cancelled_by_student',
+    'cancelled_by_teacher',
+    'no_show'
+);
+
+-- What became of an event, or of one occurrence of a series, for one student
+CREATE TABLE event_attendance (
+    id VARCHAR(21) PRIMARY KEY,
+    event_id VARCHAR(21) NOT NULL REFERENCES calendar_events(id) ON DELETE CASCADE, -- The master for occurrences
+    recurrence_id TIMESTAMPTZ, -- The occurrence, NULL for single events
+    student_id VARCHAR(21) NOT NULL REFERENCES "user"(id) ON DELETE CASCADE,
+    status attendance_status NOT NULL,
+    lesson_id VARCHAR(21) REFERENCES lessons(id) ON DELETE SET NULL,
+    note TEXT,
+    -- When the occurrence took place and how long it was, kept for reports
+    starts_at TIMESTAMPTZ NOT NULL,
+    duration_minutes INTEGER NOT NULL DEFAULT 0,
+    recorded_by VARCHAR(21) NOT NULL REFERENCES "user"(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT unique_occurrence_attendance UNIQUE NULLS NOT DISTINCT (event_id, recurrence_id, student_id)
+);
+
+CREATE INDEX idx_event_attendance_recorded_by_starts_at ON event_attendance(recorded_by, starts_at);
+CREATE INDEX idx_event_attendance_student_id ON event_attendance(student_id);
+
+CREATE TRIGGER update_event_attendance_updated_at
+    BEFORE UPDATE ON event_attendance
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
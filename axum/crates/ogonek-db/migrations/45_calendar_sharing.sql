@@ -0,0 +1,19 @@
+-- Add migration script here
+ALTER TABLE calendars DROP CONSTRAINT unique_user_calendar;
+
+-- The calendar events land in unless another one is picked
+ALTER TABLE calendars ADD COLUMN is_default BOOLEAN NOT NULL DEFAULT FALSE;
+UPDATE calendars SET is_default = TRUE;
+CREATE UNIQUE INDEX unique_default_calendar ON calendars(owner_id) WHERE is_default;
+
+CREATE TYPE calendar_permission AS ENUM ('read', 'read_write');
+
+CREATE TABLE calendar_shares (
+    calendar_id VARCHAR(21) NOT NULL REFERENCES calendars(id) ON DELETE CASCADE,
+    user_id VARCHAR(21) NOT NULL REFERENCES "user"(id) ON DELETE CASCADE,
+    permission calendar_permission NOT NULL DEFAULT 'read',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (calendar_id, user_id)
+);
+
+CREATE INDEX idx_calendar_shares_user ON calendar_shares(user_id);
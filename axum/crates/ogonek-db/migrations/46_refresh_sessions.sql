@@ -0,0 +1,26 @@
+-- Add migration script here
+-- A signed-in device. Its refresh tokens form one family, rotated on every refresh
+CREATE TABLE refresh_sessions (
+    id VARCHAR(21) PRIMARY KEY,
+    user_id VARCHAR(21) NOT NULL REFERENCES "user"(id) ON DELETE CASCADE,
+    device VARCHAR(255),
+    user_agent TEXT,
+    ip_address INET,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_used_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ,
+    revoked_reason VARCHAR(32) CHECK (revoked_reason IN ('logout', 'logout_all', 'reuse', 'password_reset'))
+);
+
+CREATE INDEX idx_refresh_sessions_user_id ON refresh_sessions(user_id);
+
+-- Only hashes are stored, a token presented after it was rotated gives the family away
+CREATE TABLE refresh_tokens (
+    token_hash VARCHAR(64) PRIMARY KEY,
+    session_id VARCHAR(21) NOT NULL REFERENCES refresh_sessions(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    rotated_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_refresh_tokens_session_id ON refresh_tokens(session_id);
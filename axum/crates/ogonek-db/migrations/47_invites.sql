@@ -0,0 +1,22 @@
+-- Add migration script here
+-- Invite links are handed out as signed tokens naming one of these
+CREATE TABLE invites (
+    id VARCHAR(21) PRIMARY KEY,
+    teacher_id VARCHAR(21) NOT NULL REFERENCES "user"(id) ON DELETE CASCADE,
+    email VARCHAR(255), -- Only this student can use the invite when set
+    max_uses INTEGER CHECK (max_uses > 0), -- Unlimited when NULL
+    use_count INTEGER NOT NULL DEFAULT 0,
+    expires_at TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_invites_teacher_id ON invites(teacher_id);
+
+CREATE TABLE invite_redemptions (
+    id VARCHAR(21) PRIMARY KEY,
+    invite_id VARCHAR(21) NOT NULL REFERENCES invites(id) ON DELETE CASCADE,
+    student_id VARCHAR(21) NOT NULL REFERENCES "user"(id) ON DELETE CASCADE,
+    redeemed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT unique_invite_redemption UNIQUE (invite_id, student_id)
+);
@@ -0,0 +1,19 @@
+-- Add migration script here
+-- TOTP secrets, pending until the user confirms a first code
+CREATE TABLE user_totp (
+    user_id VARCHAR(21) PRIMARY KEY REFERENCES "user"(id) ON DELETE CASCADE,
+    secret VARCHAR(64) NOT NULL,
+    enabled_at TIMESTAMPTZ,
+    last_used_step BIGINT, -- Codes from this time step or before are spent
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Single-use codes for when the authenticator is lost, only their hashes are kept
+CREATE TABLE recovery_codes (
+    id VARCHAR(21) PRIMARY KEY,
+    user_id VARCHAR(21) NOT NULL REFERENCES "user"(id) ON DELETE CASCADE,
+    code_hash VARCHAR(64) NOT NULL,
+    used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT unique_recovery_code UNIQUE (user_id, code_hash)
+);
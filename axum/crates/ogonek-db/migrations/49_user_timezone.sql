@@ -0,0 +1,10 @@
+-- Add migration script here
+-- Where a user's day starts, for daily card limits, goals and streaks.
+-- Follows their default calendar, UTC until they have one
+CREATE OR REPLACE FUNCTION user_timezone(owner VARCHAR)
+RETURNS VARCHAR AS $$
+    SELECT COALESCE(
+        (SELECT timezone FROM calendars WHERE owner_id = owner AND is_default),
+        'UTC'
+    );
+$$ LANGUAGE sql STABLE;
@@ -81,6 +81,42 @@ pub async fn verify_email(db: &PgPool, email: &str) -> Result<(), DbError> {
     Ok(())
 }
 
+/// Finds the id and name of the user registered with the email
+pub async fn read_by_email(db: &PgPool, email: &str) -> Result<Option<(String, String)>, DbError> {
+    let user = sqlx::query!(
+        r#"
+        SELECT id, name
+        FROM "user"
+        WHERE lower(email) = lower($1)
+        "#,
+        email
+    )
+    .fetch_optional(db)
+    .await?;
+
+    Ok(user.map(|user| (user.id, user.name)))
+}
+
+/// Replaces the user's password hash
+pub async fn set_password(db: &PgPool, user_id: &str, pass: &str) -> Result<(), DbError> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE "user"
+        SET pass = $1
+        WHERE id = $2
+        "#,
+        pass,
+        user_id
+    )
+    .execute(db)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(DbError::NotFound("User not found".into()));
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -298,6 +334,36 @@ mod tests {
         Ok(())
     }
 
+    #[sqlx::test]
+    async fn test_reset_password(pool: PgPool) -> sqlx::Result<()> {
+        let signup_data = SignUpPayload {
+            name: "John Doe".to_string(),
+            username: "johndoe".to_string(),
+            email: "John@Example.com".to_string(),
+            role: "student".to_string(),
+            pass: "hashedpassword123".to_string(),
+        };
+        let user_id = signup(&pool, &signup_data).await.unwrap();
+
+        let found = read_by_email(&pool, "john@example.com").await.unwrap();
+        assert_eq!(found, Some((user_id.clone(), "John Doe".to_string())));
+        assert!(
+            read_by_email(&pool, "jane@example.com")
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        set_password(&pool, &user_id, "newhash").await.unwrap();
+        let user = read_by_username(&pool, "johndoe").await.unwrap();
+        assert_eq!(user.pass, "newhash");
+
+        let missing = set_password(&pool, "fake-user-id", "newhash").await;
+        assert!(matches!(missing, Err(DbError::NotFound(_))));
+
+        Ok(())
+    }
+
     #[sqlx::test]
     async fn test_bind_nonexistent_users(pool: PgPool) -> sqlx::Result<()> {
         let result = bind(&pool, "fake-teacher-id", "fake-student-id").await;
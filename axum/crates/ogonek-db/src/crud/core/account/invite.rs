@@ -0,0 +1,308 @@
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+
+use crate::DbError;
+
+use ogonek_types::{Invite, InviteCreate, InviteRedemption};
+
+/// Invites last a week unless the teacher picks otherwise
+const DEFAULT_EXPIRY_DAYS: i64 = 7;
+
+/// Creates an invite for the teacher
+pub async fn create(
+    db: &PgPool,
+    teacher_id: &str,
+    create: &InviteCreate,
+) -> Result<Invite, DbError> {
+    let expires_at =
+        Utc::now() + Duration::days(create.expires_in_days.unwrap_or(DEFAULT_EXPIRY_DAYS));
+
+    let invite = sqlx::query_as!(
+        Invite,
+        r#"
+        INSERT INTO invites (id, teacher_id, email, max_uses, expires_at)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id, teacher_id, email, max_uses, use_count, expires_at, revoked_at, created_at
+        "#,
+        nanoid::nanoid!(),
+        teacher_id,
+        create.email,
+        create.max_uses,
+        expires_at
+    )
+    .fetch_one(db)
+    .await?;
+
+    Ok(invite)
+}
+
+/// Lists the teacher's invites, newest first
+pub async fn read_all(db: &PgPool, teacher_id: &str) -> Result<Vec<Invite>, DbError> {
+    let invites = sqlx::query_as!(
+        Invite,
+        r#"
+        SELECT id, teacher_id, email, max_uses, use_count, expires_at, revoked_at, created_at
+        FROM invites
+        WHERE teacher_id = $1
+        ORDER BY created_at DESC
+        "#,
+        teacher_id
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(invites)
+}
+
+/// Reads an invite that can still be used
+pub async fn read_valid(db: &PgPool, invite_id: &str) -> Result<Invite, DbError> {
+    let invite = sqlx::query_as!(
+        Invite,
+        r#"
+        SELECT id, teacher_id, email, max_uses, use_count, expires_at, revoked_at, created_at
+        FROM invites
+        WHERE id = $1
+        "#,
+        invite_id
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| DbError::NotFound("Invite not found".into()))?;
+
+    check_usable(&invite)?;
+    Ok(invite)
+}
+
+/// Revokes one of the teacher's invites, the link stops working
+pub async fn revoke(db: &PgPool, invite_id: &str, teacher_id: &str) -> Result<(), DbError> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE invites
+        SET revoked_at = COALESCE(revoked_at, NOW())
+        WHERE id = $1 AND teacher_id = $2
+        "#,
+        invite_id,
+        teacher_id
+    )
+    .execute(db)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(DbError::NotFound("Invite not found".into()));
+    }
+    Ok(())
+}
+
+/// Lists the students who joined through one of the teacher's invites
+pub async fn read_redemptions(
+    db: &PgPool,
+    invite_id: &str,
+    teacher_id: &str,
+) -> Result<Vec<InviteRedemption>, DbError> {
+    let redemptions = sqlx::query_as!(
+        InviteRedemption,
+        r#"
+        SELECT r.student_id, u.name AS student_name, r.redeemed_at
+        FROM invite_redemptions r
+        JOIN invites i ON i.id = r.invite_id
+        JOIN "user" u ON u.id = r.student_id
+        WHERE r.invite_id = $1 AND i.teacher_id = $2
+        ORDER BY r.redeemed_at
+        "#,
+        invite_id,
+        teacher_id
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(redemptions)
+}
+
+/// Binds the student to the teacher of the invite and logs the redemption. Returns the
+/// invite, or `None` when the student had already used it.
+pub async fn redeem(
+    db: &PgPool,
+    invite_id: &str,
+    student_id: &str,
+) -> Result<Option<Invite>, DbError> {
+    let mut tx = db.begin().await?;
+
+    let invite = sqlx::query_as!(
+        Invite,
+        r#"
+        SELECT id, teacher_id, email, max_uses, use_count, expires_at, revoked_at, created_at
+        FROM invites
+        WHERE id = $1
+        FOR UPDATE
+        "#,
+        invite_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| DbError::NotFound("Invite not found".into()))?;
+
+    let already_redeemed = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM invite_redemptions WHERE invite_id = $1 AND student_id = $2
+        ) AS "exists!"
+        "#,
+        invite_id,
+        student_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    if already_redeemed {
+        return Ok(None);
+    }
+
+    check_usable(&invite)?;
+    if invite.teacher_id == student_id {
+        return Err(DbError::PreconditionFailed(
+            "Teachers can't use their own invites".into(),
+        ));
+    }
+    if let Some(email) = &invite.email {
+        let student_email =
+            sqlx::query_scalar!(r#"SELECT email FROM "user" WHERE id = $1"#, student_id)
+                .fetch_optional(&mut *tx)
+                .await?
+                .ok_or_else(|| DbError::NotFound("User not found".into()))?;
+        if !student_email.eq_ignore_ascii_case(email) {
+            return Err(DbError::PreconditionFailed(
+                "The invite is for another email".into(),
+            ));
+        }
+    }
+
+    sqlx::query!(
+        r#"
+        INSERT INTO teacher_student (teacher_id, student_id)
+        VALUES ($1, $2)
+        ON CONFLICT DO NOTHING
+        "#,
+        invite.teacher_id,
+        student_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!(
+        "INSERT INTO invite_redemptions (id, invite_id, student_id) VALUES ($1, $2, $3)",
+        nanoid::nanoid!(),
+        invite_id,
+        student_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    let invite = sqlx::query_as!(
+        Invite,
+        r#"
+        UPDATE invites
+        SET use_count = use_count + 1
+        WHERE id = $1
+        RETURNING id, teacher_id, email, max_uses, use_count, expires_at, revoked_at, created_at
+        "#,
+        invite_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(Some(invite))
+}
+
+fn check_usable(invite: &Invite) -> Result<(), DbError> {
+    if invite.revoked_at.is_some() {
+        return Err(DbError::PreconditionFailed("The invite was revoked".into()));
+    }
+    if invite.expires_at <= Utc::now() {
+        return Err(DbError::PreconditionFailed("The invite has expired".into()));
+    }
+    if invite
+        .max_uses
+        .is_some_and(|max_uses| invite.use_count >= max_uses)
+    {
+        return Err(DbError::PreconditionFailed(
+            "The invite has been used up".into(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::create_test_user;
+
+    #[sqlx::test]
+    async fn test_redeem(db: PgPool) {
+        let teacher = create_test_user(&db, "teacher", "teacher@example.com").await;
+        let first = create_test_user(&db, "first", "first@example.com").await;
+        let second = create_test_user(&db, "second", "second@example.com").await;
+        let invite = create(
+            &db,
+            &teacher,
+            &InviteCreate {
+                max_uses: Some(1),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let redeemed = redeem(&db, &invite.id, &first).await.unwrap().unwrap();
+        assert_eq!(redeemed.use_count, 1);
+        // Using it again changes nothing
+        assert!(redeem(&db, &invite.id, &first).await.unwrap().is_none());
+
+        let used_up = redeem(&db, &invite.id, &second).await;
+        assert!(matches!(used_up, Err(DbError::PreconditionFailed(_))));
+
+        let bound = sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM teacher_student WHERE teacher_id = $1",
+            teacher
+        )
+        .fetch_one(&db)
+        .await
+        .unwrap();
+        assert_eq!(bound, Some(1));
+
+        let redemptions = read_redemptions(&db, &invite.id, &teacher).await.unwrap();
+        assert_eq!(redemptions.len(), 1);
+        assert_eq!(redemptions[0].student_id, first);
+    }
+
+    #[sqlx::test]
+    async fn test_email_and_revocation(db: PgPool) {
+        let teacher = create_test_user(&db, "teacher", "teacher@example.com").await;
+        let student = create_test_user(&db, "student", "student@example.com").await;
+        let other = create_test_user(&db, "other", "other@example.com").await;
+        let invite = create(
+            &db,
+            &teacher,
+            &InviteCreate {
+                email: Some("Student@Example.com".to_string()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let wrong_email = redeem(&db, &invite.id, &other).await;
+        assert!(matches!(wrong_email, Err(DbError::PreconditionFailed(_))));
+
+        // Only the teacher who made it can revoke it
+        let foreign = revoke(&db, &invite.id, &other).await;
+        assert!(matches!(foreign, Err(DbError::NotFound(_))));
+        revoke(&db, &invite.id, &teacher).await.unwrap();
+
+        assert!(read_valid(&db, &invite.id).await.is_err());
+        let revoked = redeem(&db, &invite.id, &student).await;
+        assert!(matches!(revoked, Err(DbError::PreconditionFailed(_))));
+        assert!(
+            read_all(&db, &teacher).await.unwrap()[0]
+                .revoked_at
+                .is_some()
+        );
+    }
+}
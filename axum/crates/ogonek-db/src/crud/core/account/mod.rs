@@ -1,5 +1,8 @@
 pub mod auth;
+pub mod invite;
 pub mod preferences;
 pub mod profile;
+pub mod session;
 pub mod student;
+pub mod two_factor;
 pub mod user;
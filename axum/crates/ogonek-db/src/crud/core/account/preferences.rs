@@ -33,7 +33,14 @@ pub async fn upsert(
             email_notifications = COALESCE($3, email_notifications),
             push_notifications = COALESCE($4, push_notifications),
             theme = COALESCE($5, theme),
-            language = COALESCE($6, language)
+            language = COALESCE($6, language),
+            scheduler = COALESCE($7, scheduler),
+            learning_steps = COALESCE($8, learning_steps),
+            relearning_steps = COALESCE($9, relearning_steps),
+            new_cards_per_day = COALESCE($10, new_cards_per_day),
+            reviews_per_day = COALESCE($11, reviews_per_day),
+            daily_review_goal = COALESCE($12, daily_review_goal),
+            daily_task_goal = COALESCE($13, daily_task_goal)
         WHERE user_id = $1
         "#,
         user_id,
@@ -42,6 +49,13 @@ pub async fn upsert(
         update.push_notifications,
         update.theme,
         update.language,
+        update.scheduler.map(|s| s.to_string()),
+        update.learning_steps.as_deref(),
+        update.relearning_steps.as_deref(),
+        update.new_cards_per_day,
+        update.reviews_per_day,
+        update.daily_review_goal,
+        update.daily_task_goal,
     )
     .execute(db)
     .await?;
@@ -84,6 +98,13 @@ mod additional_tests {
             push_notifications: Some(false),
             theme: Some("dark".to_string()),
             language: Some("es".to_string()),
+            scheduler: None,
+            learning_steps: None,
+            relearning_steps: None,
+            new_cards_per_day: None,
+            reviews_per_day: None,
+            daily_review_goal: None,
+            daily_task_goal: None,
         };
 
         // This should succeed but affect 0 rows since user doesn't exist
@@ -103,6 +124,13 @@ mod additional_tests {
             push_notifications: Some(true),
             theme: Some("light".to_string()),
             language: Some("en".to_string()),
+            scheduler: None,
+            learning_steps: None,
+            relearning_steps: None,
+            new_cards_per_day: None,
+            reviews_per_day: None,
+            daily_review_goal: None,
+            daily_task_goal: None,
         };
 
         let result = upsert(&db, "", &update).await;
@@ -139,6 +167,13 @@ mod additional_tests {
             push_notifications: Some(false),
             theme: Some("dark".to_string()),
             language: Some("fr".to_string()),
+            scheduler: None,
+            learning_steps: None,
+            relearning_steps: None,
+            new_cards_per_day: None,
+            reviews_per_day: None,
+            daily_review_goal: None,
+            daily_task_goal: None,
         };
 
         // Manually insert with custom values
@@ -183,6 +218,13 @@ mod additional_tests {
             push_notifications: None,
             theme: Some("dark".to_string()),
             language: None,
+            scheduler: None,
+            learning_steps: None,
+            relearning_steps: None,
+            new_cards_per_day: None,
+            reviews_per_day: None,
+            daily_review_goal: None,
+            daily_task_goal: None,
         };
 
         upsert(&db, &user_id, &theme_update).await.unwrap();
@@ -210,6 +252,13 @@ mod additional_tests {
             push_notifications: None,
             theme: None,
             language: None,
+            scheduler: None,
+            learning_steps: None,
+            relearning_steps: None,
+            new_cards_per_day: None,
+            reviews_per_day: None,
+            daily_review_goal: None,
+            daily_task_goal: None,
         };
         upsert(&db, &user_id, &update1).await.unwrap();
 
@@ -220,6 +269,13 @@ mod additional_tests {
             push_notifications: None,
             theme: Some("light".to_string()),
             language: None,
+            scheduler: None,
+            learning_steps: None,
+            relearning_steps: None,
+            new_cards_per_day: None,
+            reviews_per_day: None,
+            daily_review_goal: None,
+            daily_task_goal: None,
         };
         upsert(&db, &user_id, &update2).await.unwrap();
 
@@ -230,6 +286,13 @@ mod additional_tests {
             push_notifications: None,
             theme: None,
             language: Some("de".to_string()),
+            scheduler: None,
+            learning_steps: None,
+            relearning_steps: None,
+            new_cards_per_day: None,
+            reviews_per_day: None,
+            daily_review_goal: None,
+            daily_task_goal: None,
         };
         upsert(&db, &user_id, &update3).await.unwrap();
 
@@ -271,6 +334,13 @@ mod additional_tests {
             push_notifications: Some(false),
             theme: Some(long_theme.clone()),
             language: Some(long_language.to_string()),
+            scheduler: None,
+            learning_steps: None,
+            relearning_steps: None,
+            new_cards_per_day: None,
+            reviews_per_day: None,
+            daily_review_goal: None,
+            daily_task_goal: None,
         };
 
         let result = upsert(&db, &user_id, &update).await;
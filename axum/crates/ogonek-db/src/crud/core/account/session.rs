@@ -0,0 +1,309 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::DbError;
+
+use ogonek_types::{RefreshSession, SessionOrigin};
+
+/// What became of a refresh token handed in for a new one
+#[derive(Debug, PartialEq)]
+pub enum Rotation {
+    /// The token was current and has been replaced. Sessions expire at a fixed time,
+    /// rotation doesn't extend them.
+    Rotated {
+        session_id: String,
+        user_id: String,
+        expires_at: DateTime<Utc>,
+    },
+    /// The token had already been replaced, so it leaked. The session is revoked.
+    Reused { session_id: String, user_id: String },
+}
+
+/// Starts a session with its first refresh token, returns the session id
+pub async fn create(
+    db: &PgPool,
+    user_id: &str,
+    token_hash: &str,
+    origin: &SessionOrigin,
+    expires_at: DateTime<Utc>,
+) -> Result<String, DbError> {
+    let mut tx = db.begin().await?;
+
+    let session_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO refresh_sessions (id, user_id, device, user_agent, ip_address, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id
+        "#,
+        nanoid::nanoid!(),
+        user_id,
+        origin.device,
+        origin.user_agent,
+        origin.ip_address,
+        expires_at
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "INSERT INTO refresh_tokens (token_hash, session_id) VALUES ($1, $2)",
+        token_hash,
+        session_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(session_id)
+}
+
+/// Exchanges a refresh token for the next one in its family. Unknown tokens and those
+/// of revoked or expired sessions are not found.
+pub async fn rotate(
+    db: &PgPool,
+    token_hash: &str,
+    next_hash: &str,
+    origin: &SessionOrigin,
+) -> Result<Rotation, DbError> {
+    let mut tx = db.begin().await?;
+
+    // Locking the token makes a concurrent refresh with it wait for this one, after which
+    // it sees the token rotated and is taken for a reuse
+    let token = sqlx::query!(
+        r#"
+        SELECT t.rotated_at, s.id AS session_id, s.user_id, s.expires_at
+        FROM refresh_tokens t
+        JOIN refresh_sessions s ON s.id = t.session_id
+        WHERE t.token_hash = $1 AND s.revoked_at IS NULL AND s.expires_at > NOW()
+        FOR UPDATE OF t, s
+        "#,
+        token_hash
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| DbError::NotFound("Session not found".into()))?;
+
+    if token.rotated_at.is_some() {
+        sqlx::query!(
+            r#"
+            UPDATE refresh_sessions
+            SET revoked_at = NOW(), revoked_reason = 'reuse'
+            WHERE id = $1
+            "#,
+            token.session_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        return Ok(Rotation::Reused {
+            session_id: token.session_id,
+            user_id: token.user_id,
+        });
+    }
+
+    sqlx::query!(
+        "UPDATE refresh_tokens SET rotated_at = NOW() WHERE token_hash = $1",
+        token_hash
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!(
+        "INSERT INTO refresh_tokens (token_hash, session_id) VALUES ($1, $2)",
+        next_hash,
+        token.session_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!(
+        r#"
+        UPDATE refresh_sessions
+        SET
+            last_used_at = NOW(),
+            device = COALESCE($2, device),
+            user_agent = COALESCE($3, user_agent),
+            ip_address = COALESCE($4, ip_address)
+        WHERE id = $1
+        "#,
+        token.session_id,
+        origin.device,
+        origin.user_agent,
+        origin.ip_address
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(Rotation::Rotated {
+        session_id: token.session_id,
+        user_id: token.user_id,
+        expires_at: token.expires_at,
+    })
+}
+
+/// Lists the user's active sessions, most recently used first
+pub async fn read_all(
+    db: &PgPool,
+    user_id: &str,
+    current_session: Option<&str>,
+) -> Result<Vec<RefreshSession>, DbError> {
+    let sessions = sqlx::query_as!(
+        RefreshSession,
+        r#"
+        SELECT
+            id,
+            device,
+            user_agent,
+            host(ip_address) AS ip_address,
+            created_at,
+            last_used_at,
+            expires_at,
+            id IS NOT DISTINCT FROM $2 AS "is_current!"
+        FROM refresh_sessions
+        WHERE user_id = $1 AND revoked_at IS NULL AND expires_at > NOW()
+        ORDER BY last_used_at DESC
+        "#,
+        user_id,
+        current_session
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(sessions)
+}
+
+/// Signs a session of the user out
+pub async fn revoke(db: &PgPool, session_id: &str, user_id: &str) -> Result<(), DbError> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE refresh_sessions
+        SET revoked_at = NOW(), revoked_reason = 'logout'
+        WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL
+        "#,
+        session_id,
+        user_id
+    )
+    .execute(db)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(DbError::NotFound("Session not found".into()));
+    }
+    Ok(())
+}
+
+/// Signs the user out everywhere, returns how many sessions were active
+pub async fn revoke_all(db: &PgPool, user_id: &str, reason: &str) -> Result<u64, DbError> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE refresh_sessions
+        SET revoked_at = NOW(), revoked_reason = $2
+        WHERE user_id = $1 AND revoked_at IS NULL
+        "#,
+        user_id,
+        reason
+    )
+    .execute(db)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::create_test_user;
+    use chrono::Duration;
+
+    fn origin() -> SessionOrigin {
+        SessionOrigin {
+            device: Some("iPhone".to_string()),
+            user_agent: Some("Ogonek/1.0".to_string()),
+            ip_address: "192.0.2.1".parse().ok(),
+        }
+    }
+
+    #[sqlx::test]
+    async fn test_rotation_and_reuse(db: PgPool) {
+        let user_id = create_test_user(&db, "student", "student@example.com").await;
+        let expires_at = Utc::now() + Duration::days(30);
+        let session_id = create(&db, &user_id, "first", &origin(), expires_at)
+            .await
+            .unwrap();
+
+        let rotated = rotate(&db, "first", "second", &origin()).await.unwrap();
+        assert!(matches!(
+            rotated,
+            Rotation::Rotated { session_id: ref id, .. } if *id == session_id
+        ));
+        rotate(&db, "second", "third", &origin()).await.unwrap();
+
+        let sessions = read_all(&db, &user_id, Some(&session_id)).await.unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert!(sessions[0].is_current);
+        assert_eq!(sessions[0].ip_address.as_deref(), Some("192.0.2.1"));
+
+        // Replaying a rotated token revokes the family, the current token included
+        let reused = rotate(&db, "first", "fourth", &origin()).await.unwrap();
+        assert!(matches!(reused, Rotation::Reused { .. }));
+        let current = rotate(&db, "third", "fifth", &origin()).await;
+        assert!(matches!(current, Err(DbError::NotFound(_))));
+        assert!(read_all(&db, &user_id, None).await.unwrap().is_empty());
+
+        let unknown = rotate(&db, "unknown", "sixth", &origin()).await;
+        assert!(matches!(unknown, Err(DbError::NotFound(_))));
+    }
+
+    #[sqlx::test]
+    async fn test_concurrent_rotation(db: PgPool) {
+        let user_id = create_test_user(&db, "student", "student@example.com").await;
+        let expires_at = Utc::now() + Duration::days(30);
+        create(&db, &user_id, "first", &origin(), expires_at)
+            .await
+            .unwrap();
+
+        let origin = origin();
+        let (a, b) = tokio::join!(
+            rotate(&db, "first", "second", &origin),
+            rotate(&db, "first", "third", &origin)
+        );
+        let outcomes = [a.unwrap(), b.unwrap()];
+        let rotated = outcomes
+            .iter()
+            .filter(|o| matches!(o, Rotation::Rotated { .. }))
+            .count();
+        assert_eq!(rotated, 1);
+        assert!(read_all(&db, &user_id, None).await.unwrap().is_empty());
+    }
+
+    #[sqlx::test]
+    async fn test_logout(db: PgPool) {
+        let user_id = create_test_user(&db, "student", "student@example.com").await;
+        let other = create_test_user(&db, "other", "other@example.com").await;
+        let expires_at = Utc::now() + Duration::days(30);
+        let phone = create(&db, &user_id, "phone", &origin(), expires_at)
+            .await
+            .unwrap();
+        create(
+            &db,
+            &user_id,
+            "laptop",
+            &SessionOrigin::default(),
+            expires_at,
+        )
+        .await
+        .unwrap();
+
+        // Sessions of other users are out of reach
+        let foreign = revoke(&db, &phone, &other).await;
+        assert!(matches!(foreign, Err(DbError::NotFound(_))));
+
+        revoke(&db, &phone, &user_id).await.unwrap();
+        let refused = rotate(&db, "phone", "next", &origin()).await;
+        assert!(matches!(refused, Err(DbError::NotFound(_))));
+        assert_eq!(read_all(&db, &user_id, None).await.unwrap().len(), 1);
+
+        assert_eq!(revoke_all(&db, &user_id, "logout_all").await.unwrap(), 1);
+        assert!(read_all(&db, &user_id, None).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,252 @@
+use sqlx::PgPool;
+
+use crate::DbError;
+
+/// A user's TOTP secret
+#[derive(Debug)]
+pub struct TotpSecret {
+    pub secret: String,
+    /// False until the user has confirmed a first code
+    pub enabled: bool,
+}
+
+/// Stores a fresh secret awaiting confirmation, replacing any earlier pending one.
+/// Fails when 2FA is already on.
+pub async fn set_pending(db: &PgPool, user_id: &str, secret: &str) -> Result<(), DbError> {
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO user_totp (user_id, secret)
+        VALUES ($1, $2)
+        ON CONFLICT (user_id) DO UPDATE
+        SET secret = EXCLUDED.secret, created_at = NOW(), last_used_step = NULL
+        WHERE user_totp.enabled_at IS NULL
+        "#,
+        user_id,
+        secret
+    )
+    .execute(db)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(DbError::AlreadyExists(
+            "Two-factor authentication is already enabled".into(),
+        ));
+    }
+    Ok(())
+}
+
+pub async fn read_secret(db: &PgPool, user_id: &str) -> Result<Option<TotpSecret>, DbError> {
+    let secret = sqlx::query_as!(
+        TotpSecret,
+        r#"
+        SELECT secret, enabled_at IS NOT NULL AS "enabled!"
+        FROM user_totp
+        WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .fetch_optional(db)
+    .await?;
+
+    Ok(secret)
+}
+
+pub async fn is_enabled(db: &PgPool, user_id: &str) -> Result<bool, DbError> {
+    Ok(read_secret(db, user_id)
+        .await?
+        .is_some_and(|secret| secret.enabled))
+}
+
+/// Turns 2FA on with the pending secret and a first set of recovery codes
+pub async fn enable(
+    db: &PgPool,
+    user_id: &str,
+    recovery_code_hashes: &[String],
+) -> Result<(), DbError> {
+    let mut tx = db.begin().await?;
+
+    let result = sqlx::query!(
+        "UPDATE user_totp SET enabled_at = NOW() WHERE user_id = $1 AND enabled_at IS NULL",
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Err(DbError::PreconditionFailed(
+            "No two-factor setup is pending".into(),
+        ));
+    }
+
+    insert_recovery_codes(&mut tx, user_id, recovery_code_hashes).await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Turns 2FA off, dropping the secret and the recovery codes
+pub async fn disable(db: &PgPool, user_id: &str) -> Result<(), DbError> {
+    let mut tx = db.begin().await?;
+
+    sqlx::query!("DELETE FROM user_totp WHERE user_id = $1", user_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!("DELETE FROM recovery_codes WHERE user_id = $1", user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Marks the time step of an accepted code as spent. Returns false when that step,
+/// or a later one, was used already, so the code is a replay.
+pub async fn use_step(db: &PgPool, user_id: &str, step: i64) -> Result<bool, DbError> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE user_totp
+        SET last_used_step = $2
+        WHERE user_id = $1
+            AND enabled_at IS NOT NULL
+            AND (last_used_step IS NULL OR last_used_step < $2)
+        "#,
+        user_id,
+        step
+    )
+    .execute(db)
+    .await?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+/// Spends a recovery code, returns false when it's unknown or spent
+pub async fn use_recovery_code(
+    db: &PgPool,
+    user_id: &str,
+    code_hash: &str,
+) -> Result<bool, DbError> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE recovery_codes
+        SET used_at = NOW()
+        WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL
+        "#,
+        user_id,
+        code_hash
+    )
+    .execute(db)
+    .await?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+/// Swaps every recovery code, spent or not, for a new set
+pub async fn replace_recovery_codes(
+    db: &PgPool,
+    user_id: &str,
+    recovery_code_hashes: &[String],
+) -> Result<(), DbError> {
+    let mut tx = db.begin().await?;
+    insert_recovery_codes(&mut tx, user_id, recovery_code_hashes).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+pub async fn count_recovery_codes(db: &PgPool, user_id: &str) -> Result<i64, DbError> {
+    let count = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) AS "count!"
+        FROM recovery_codes
+        WHERE user_id = $1 AND used_at IS NULL
+        "#,
+        user_id
+    )
+    .fetch_one(db)
+    .await?;
+
+    Ok(count)
+}
+
+async fn insert_recovery_codes(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    user_id: &str,
+    recovery_code_hashes: &[String],
+) -> Result<(), DbError> {
+    sqlx::query!("DELETE FROM recovery_codes WHERE user_id = $1", user_id)
+        .execute(&mut **tx)
+        .await?;
+
+    let ids: Vec<String> = recovery_code_hashes
+        .iter()
+        .map(|_| nanoid::nanoid!())
+        .collect();
+    sqlx::query!(
+        r#"
+        INSERT INTO recovery_codes (id, user_id, code_hash)
+        SELECT id, $2, code_hash
+        FROM UNNEST($1::VARCHAR[], $3::VARCHAR[]) AS codes(id, code_hash)
+        "#,
+        &ids,
+        user_id,
+        recovery_code_hashes
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::create_test_user;
+
+    #[sqlx::test]
+    async fn test_enrolment(db: PgPool) {
+        let user_id = create_test_user(&db, "admin", "admin@example.com").await;
+        let codes = vec!["first".to_string(), "second".to_string()];
+
+        // Nothing to confirm yet
+        let early = enable(&db, &user_id, &codes).await;
+        assert!(matches!(early, Err(DbError::PreconditionFailed(_))));
+
+        set_pending(&db, &user_id, "OLDSECRET").await.unwrap();
+        set_pending(&db, &user_id, "NEWSECRET").await.unwrap();
+        assert!(!is_enabled(&db, &user_id).await.unwrap());
+
+        enable(&db, &user_id, &codes).await.unwrap();
+        let secret = read_secret(&db, &user_id).await.unwrap().unwrap();
+        assert_eq!(secret.secret, "NEWSECRET");
+        assert!(secret.enabled);
+        assert_eq!(count_recovery_codes(&db, &user_id).await.unwrap(), 2);
+
+        // The secret can't be swapped out behind the user's back
+        let again = set_pending(&db, &user_id, "OTHER").await;
+        assert!(matches!(again, Err(DbError::AlreadyExists(_))));
+
+        disable(&db, &user_id).await.unwrap();
+        assert!(read_secret(&db, &user_id).await.unwrap().is_none());
+        assert_eq!(count_recovery_codes(&db, &user_id).await.unwrap(), 0);
+    }
+
+    #[sqlx::test]
+    async fn test_codes_are_single_use(db: PgPool) {
+        let user_id = create_test_user(&db, "admin", "admin@example.com").await;
+        set_pending(&db, &user_id, "SECRET").await.unwrap();
+        enable(&db, &user_id, &["first".to_string()]).await.unwrap();
+
+        assert!(use_step(&db, &user_id, 100).await.unwrap());
+        assert!(!use_step(&db, &user_id, 100).await.unwrap());
+        assert!(!use_step(&db, &user_id, 99).await.unwrap());
+        assert!(use_step(&db, &user_id, 101).await.unwrap());
+
+        assert!(use_recovery_code(&db, &user_id, "first").await.unwrap());
+        assert!(!use_recovery_code(&db, &user_id, "first").await.unwrap());
+        assert!(!use_recovery_code(&db, &user_id, "unknown").await.unwrap());
+        assert_eq!(count_recovery_codes(&db, &user_id).await.unwrap(), 0);
+
+        replace_recovery_codes(&db, &user_id, &["third".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(count_recovery_codes(&db, &user_id).await.unwrap(), 1);
+    }
+}
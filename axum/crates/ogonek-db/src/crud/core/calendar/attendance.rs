@@ -0,0 +1,602 @@
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgPool};
+
+use crate::{
+    DbError,
+    core::account::user::read_name,
+    helpers::{extract_id_and_occurence, occurrence_id},
+};
+
+use ogonek_types::{
+    AttendanceStatus, AttendanceTotals, EventAttendance, EventAttendanceUpsert, MonthlyAttendance,
+    StudentAttendanceReport,
+};
+
+/// The event or occurrence attendance is recorded for
+struct Occurrence {
+    /// The master for occurrences of a series
+    event_id: String,
+    /// The exception standing in for the occurrence, if any
+    exception_id: Option<String>,
+    recurrence_id: Option<DateTime<Utc>>,
+    starts_at: DateTime<Utc>,
+    duration_minutes: i32,
+}
+
+/// Records the outcome of an event or occurrence on the user's calendar for one of
+/// its attendees, replacing the one already recorded
+pub async fn upsert(
+    db: &PgPool,
+    user_id: &str,
+    event_id: &str,
+    upsert: &EventAttendanceUpsert,
+) -> Result<String, DbError> {
+    let mut tx = db.begin().await?;
+    let occurrence = resolve_occurrence(&mut tx, user_id, event_id).await?;
+
+    sqlx::query_scalar!(
+        r#"
+        SELECT user_id FROM event_attendees
+        WHERE (event_id = $1 OR event_id = $2) AND user_id = $3
+        LIMIT 1
+        "#,
+        occurrence.event_id,
+        occurrence.exception_id,
+        upsert.student_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| DbError::NotFound("Attendee not found".into()))?;
+
+    if let Some(lesson_id) = &upsert.lesson_id {
+        sqlx::query_scalar!(
+            "SELECT id FROM lessons WHERE id = $1 AND created_by = $2",
+            lesson_id,
+            user_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| DbError::NotFound("Lesson not found".into()))?;
+    }
+
+    let id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO event_attendance (
+            id, event_id, recurrence_id, student_id, status, lesson_id, note,
+            starts_at, duration_minutes, recorded_by
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        ON CONFLICT ON CONSTRAINT unique_occurrence_attendance DO UPDATE
+        SET
+            status = EXCLUDED.status,
+            lesson_id = EXCLUDED.lesson_id,
+            note = EXCLUDED.note,
+            starts_at = EXCLUDED.starts_at,
+            duration_minutes = EXCLUDED.duration_minutes,
+            recorded_by = EXCLUDED.recorded_by
+        RETURNING id
+        "#,
+        nanoid::nanoid!(),
+        occurrence.event_id,
+        occurrence.recurrence_id,
+        upsert.student_id,
+        upsert.status.clone() as _,
+        upsert.lesson_id,
+        upsert.note,
+        occurrence.starts_at,
+        occurrence.duration_minutes,
+        user_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(id)
+}
+
+/// Reads the attendance recorded for an event or occurrence on the user's calendar
+pub async fn read_by_event(
+    db: &PgPool,
+    user_id: &str,
+    event_id: &str,
+) -> Result<Vec<EventAttendance>, DbError> {
+    let mut conn = db.acquire().await?;
+    let occurrence = resolve_occurrence(&mut conn, user_id, event_id).await?;
+
+    let mut records = sqlx::query_as!(
+        EventAttendance,
+        r#"
+        SELECT
+            a.id,
+            a.event_id,
+            a.recurrence_id,
+            a.student_id,
+            u.name AS student_name,
+            a.status AS "status!: AttendanceStatus",
+            a.lesson_id,
+            l.title AS "lesson_title?",
+            a.note,
+            a.starts_at,
+            a.duration_minutes,
+            a.updated_at
+        FROM event_attendance a
+        JOIN "user" u ON u.id = a.student_id
+        LEFT JOIN lessons l ON l.id = a.lesson_id
+        WHERE a.event_id = $1 AND a.recurrence_id IS NOT DISTINCT FROM $2
+        ORDER BY u.name
+        "#,
+        occurrence.event_id,
+        occurrence.recurrence_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    for record in &mut records {
+        record.event_id = occurrence_id(&record.event_id, record.recurrence_id);
+    }
+    Ok(records)
+}
+
+/// Deletes an attendance record of the user's calendar
+pub async fn delete(db: &PgPool, user_id: &str, attendance_id: &str) -> Result<(), DbError> {
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM event_attendance a
+        USING calendar_events e, calendars c
+        WHERE a.id = $1 AND e.id = a.event_id AND c.id = e.calendar_id AND c.owner_id = $2
+        "#,
+        attendance_id,
+        user_id
+    )
+    .execute(db)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(DbError::NotFound("Attendance not found".into()));
+    }
+    Ok(())
+}
+
+/// Reads a student's attendance on the user's calendar, optionally within a range
+pub async fn read_student_report(
+    db: &PgPool,
+    user_id: &str,
+    student_id: &str,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+) -> Result<StudentAttendanceReport, DbError> {
+    let student_name = read_name(db, student_id).await?;
+
+    let mut records = sqlx::query_as!(
+        EventAttendance,
+        r#"
+        SELECT
+            a.id,
+            a.event_id,
+            a.recurrence_id,
+            a.student_id,
+            u.name AS student_name,
+            a.status AS "status!: AttendanceStatus",
+            a.lesson_id,
+            l.title AS "lesson_title?",
+            a.note,
+            a.starts_at,
+            a.duration_minutes,
+            a.updated_at
+        FROM event_attendance a
+        JOIN calendar_events e ON e.id = a.event_id
+        JOIN calendars c ON c.id = e.calendar_id
+        JOIN "user" u ON u.id = a.student_id
+        LEFT JOIN lessons l ON l.id = a.lesson_id
+        WHERE c.owner_id = $1
+            AND a.student_id = $2
+            AND ($3::TIMESTAMPTZ IS NULL OR a.starts_at >= $3)
+            AND ($4::TIMESTAMPTZ IS NULL OR a.starts_at < $4)
+        ORDER BY a.starts_at DESC
+        "#,
+        user_id,
+        student_id,
+        from,
+        to
+    )
+    .fetch_all(db)
+    .await?;
+
+    let mut totals = AttendanceTotals::default();
+    for record in &mut records {
+        record.event_id = occurrence_id(&record.event_id, record.recurrence_id);
+        match record.status {
+            AttendanceStatus::Attended => {
+                totals.attended += 1;
+                totals.attended_minutes += i64::from(record.duration_minutes);
+            }
+            AttendanceStatus::CancelledByStudent => totals.cancelled_by_student += 1,
+            AttendanceStatus::CancelledByTeacher => totals.cancelled_by_teacher += 1,
+            AttendanceStatus::NoShow => totals.no_show += 1,
+        }
+    }
+
+    Ok(StudentAttendanceReport {
+        student_id: student_id.to_string(),
+        student_name,
+        totals,
+        records,
+    })
+}
+
+/// Sums up attendance on the user's calendar per month of its timezone and per student
+pub async fn read_monthly_report(
+    db: &PgPool,
+    user_id: &str,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+) -> Result<Vec<MonthlyAttendance>, DbError> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            to_char(a.starts_at AT TIME ZONE c.timezone, 'YYYY-MM') AS "month!",
+            a.student_id,
+            u.name AS student_name,
+            COUNT(*) FILTER (WHERE a.status = 'attended') AS "attended!",
+            COUNT(*) FILTER (WHERE a.status = 'cancelled_by_student') AS "cancelled_by_student!",
+            COUNT(*) FILTER (WHERE a.status = 'cancelled_by_teacher') AS "cancelled_by_teacher!",
+            COUNT(*) FILTER (WHERE a.status = 'no_show') AS "no_show!",
+            COALESCE(SUM(a.duration_minutes) FILTER (WHERE a.status = 'attended'), 0)::BIGINT
+                AS "attended_minutes!"
+        FROM event_attendance a
+        JOIN calendar_events e ON e.id = a.event_id
+        JOIN calendars c ON c.id = e.calendar_id
+        JOIN "user" u ON u.id = a.student_id
+        WHERE c.owner_id = $1
+            AND ($2::TIMESTAMPTZ IS NULL OR a.starts_at >= $2)
+            AND ($3::TIMESTAMPTZ IS NULL OR a.starts_at < $3)
+        GROUP BY 1, a.student_id, u.name
+        ORDER BY 1 DESC, u.name
+        "#,
+        user_id,
+        from,
+        to
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| MonthlyAttendance {
+            month: row.month,
+            student_id: row.student_id,
+            student_name: row.student_name,
+            totals: AttendanceTotals {
+                attended: row.attended,
+                cancelled_by_student: row.cancelled_by_student,
+                cancelled_by_teacher: row.cancelled_by_teacher,
+                no_show: row.no_show,
+                attended_minutes: row.attended_minutes,
+            },
+        })
+        .collect())
+}
+
+/// Finds the occurrence an event id points to on the user's calendar. Exceptions and
+/// series without an occurrence are keyed by their master and occurrence, like virtual ids.
+async fn resolve_occurrence(
+    conn: &mut PgConnection,
+    user_id: &str,
+    event_id: &str,
+) -> Result<Occurrence, DbError> {
+    let (event_id, occurrence) = extract_id_and_occurence(event_id.to_string());
+
+    let event = sqlx::query!(
+        r#"
+        SELECT e.id, e.calendar_id, e.uid, e.recurrence_id, e.rrule, e.dtstart_time, e.dtend_time
+        FROM calendar_events e
+        JOIN calendars c ON c.id = e.calendar_id
+        WHERE e.id = $1 AND c.owner_id = $2 AND e.deleted_at IS NULL
+        "#,
+        event_id,
+        user_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or_else(|| DbError::NotFound("Event not found".into()))?;
+
+    let duration_minutes = |start: DateTime<Utc>, end: Option<DateTime<Utc>>| {
+        end.map_or(0, |end| (end - start).num_minutes() as i32)
+    };
+
+    if let Some(recurrence_id) = event.recurrence_id {
+        // UIDs are only unique within a calendar
+        let master_id = sqlx::query_scalar!(
+            r#"
+            SELECT id FROM calendar_events
+            WHERE calendar_id = $1 AND uid = $2 AND recurrence_id IS NULL
+            "#,
+            event.calendar_id,
+            event.uid
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| DbError::NotFound("Event not found".into()))?;
+
+        return Ok(Occurrence {
+            event_id: master_id,
+            exception_id: Some(event.id),
+            recurrence_id: Some(recurrence_id),
+            starts_at: event.dtstart_time,
+            duration_minutes: duration_minutes(event.dtstart_time, event.dtend_time),
+        });
+    }
+
+    let recurrence_id = match (occurrence, &event.rrule) {
+        (Some(occurrence), _) => occurrence,
+        (None, Some(_)) => event.dtstart_time,
+        (None, None) => {
+            return Ok(Occurrence {
+                event_id: event.id,
+                exception_id: None,
+                recurrence_id: None,
+                starts_at: event.dtstart_time,
+                duration_minutes: duration_minutes(event.dtstart_time, event.dtend_time),
+            });
+        }
+    };
+
+    // A moved occurrence took place where its exception put it
+    let exception = sqlx::query!(
+        r#"
+        SELECT id, dtstart_time, dtend_time
+        FROM calendar_events
+        WHERE calendar_id = $1 AND uid = $2 AND recurrence_id = $3 AND deleted_at IS NULL
+        "#,
+        event.calendar_id,
+        event.uid,
+        recurrence_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok(match exception {
+        Some(exception) => Occurrence {
+            event_id: event.id,
+            exception_id: Some(exception.id),
+            recurrence_id: Some(recurrence_id),
+            starts_at: exception.dtstart_time,
+            duration_minutes: duration_minutes(exception.dtstart_time, exception.dtend_time),
+        },
+        None => Occurrence {
+            event_id: event.id,
+            exception_id: None,
+            recurrence_id: Some(recurrence_id),
+            starts_at: recurrence_id,
+            duration_minutes: duration_minutes(event.dtstart_time, event.dtend_time),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::{calendar::event::import, lesson::create_with_defaults},
+        tests::create_test_user,
+    };
+    use chrono::{Duration, TimeZone};
+    use ogonek_types::{
+        AttendeeImport, EventAttendeeRole, EventAttendeeStatus, EventClass, EventImport,
+        EventStatus, EventTransp,
+    };
+
+    fn lesson(start: DateTime<Utc>) -> EventImport {
+        EventImport {
+            uid: "weekly@example.com".to_string(),
+            summary: "Spanish".to_string(),
+            description: None,
+            location: None,
+            url: None,
+            dtstart_time: start,
+            dtend_time: Some(start + Duration::minutes(50)),
+            dtstart_tz: None,
+            rrule: Some("FREQ=WEEKLY;COUNT=6".to_string()),
+            rdate: None,
+            exdate: None,
+            recurrence_id: None,
+            status: EventStatus::Confirmed,
+            class: EventClass::Public,
+            transp: EventTransp::Opaque,
+            priority: None,
+            categories: None,
+            sequence: 0,
+            attendees: vec![AttendeeImport {
+                email: "student@example.com".to_string(),
+                name: None,
+                role: EventAttendeeRole::ReqParticipant,
+                status: EventAttendeeStatus::Accepted,
+            }],
+        }
+    }
+
+    async fn master_id(db: &PgPool, owner_id: &str) -> String {
+        sqlx::query_scalar!(
+            r#"
+            SELECT e.id FROM calendar_events e
+            JOIN calendars c ON c.id = e.calendar_id
+            WHERE e.uid = 'weekly@example.com' AND e.recurrence_id IS NULL AND c.owner_id = $1
+            "#,
+            owner_id
+        )
+        .fetch_one(db)
+        .await
+        .unwrap()
+    }
+
+    fn record(student_id: &str, status: AttendanceStatus) -> EventAttendanceUpsert {
+        EventAttendanceUpsert {
+            student_id: student_id.to_string(),
+            status,
+            lesson_id: None,
+            note: None,
+        }
+    }
+
+    #[sqlx::test]
+    async fn test_attendance_reports(db: PgPool) {
+        let teacher = create_test_user(&db, "teacher", "teacher@example.com").await;
+        let student = create_test_user(&db, "student", "student@example.com").await;
+        // Fridays, the fifth lesson falls in April
+        let start = Utc.with_ymd_and_hms(2025, 3, 7, 15, 0, 0).unwrap();
+        import(&db, &teacher, &[lesson(start)]).await.unwrap();
+        let master = master_id(&db, &teacher).await;
+        let week = |n| occurrence_id(&master, Some(start + Duration::weeks(n)));
+
+        let mut attended = record(&student, AttendanceStatus::Attended);
+        attended.lesson_id = Some(create_with_defaults(&db, &teacher).await.unwrap());
+        upsert(&db, &teacher, &week(0), &attended).await.unwrap();
+        upsert(
+            &db,
+            &teacher,
+            &week(1),
+            &record(&student, AttendanceStatus::NoShow),
+        )
+        .await
+        .unwrap();
+        // Recording again replaces the outcome
+        let late = record(&student, AttendanceStatus::CancelledByStudent);
+        upsert(&db, &teacher, &week(1), &late).await.unwrap();
+        upsert(&db, &teacher, &week(4), &attended).await.unwrap();
+
+        let recorded = read_by_event(&db, &teacher, &week(1)).await.unwrap();
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(recorded[0].status, AttendanceStatus::CancelledByStudent);
+        assert_eq!(recorded[0].event_id, week(1));
+
+        // The series itself stands for its first occurrence
+        let first = read_by_event(&db, &teacher, &master).await.unwrap();
+        assert_eq!(first[0].lesson_title.as_deref(), Some("Default Title"));
+
+        let report = read_student_report(&db, &teacher, &student, None, None)
+            .await
+            .unwrap();
+        assert_eq!(
+            report.totals,
+            AttendanceTotals {
+                attended: 2,
+                cancelled_by_student: 1,
+                cancelled_by_teacher: 0,
+                no_show: 0,
+                attended_minutes: 100,
+            }
+        );
+
+        let months = read_monthly_report(&db, &teacher, None, None)
+            .await
+            .unwrap();
+        let summary: Vec<_> = months
+            .iter()
+            .map(|m| {
+                (
+                    m.month.as_str(),
+                    m.totals.attended,
+                    m.totals.cancelled_by_student,
+                )
+            })
+            .collect();
+        assert_eq!(summary, [("2025-04", 1, 0), ("2025-03", 1, 1)]);
+    }
+
+    #[sqlx::test]
+    async fn test_attendance_checks(db: PgPool) {
+        let teacher = create_test_user(&db, "teacher", "teacher@example.com").await;
+        let student = create_test_user(&db, "student", "student@example.com").await;
+        let other = create_test_user(&db, "other", "other@example.com").await;
+        let start = Utc.with_ymd_and_hms(2025, 3, 7, 15, 0, 0).unwrap();
+        import(&db, &teacher, &[lesson(start)]).await.unwrap();
+        let master = master_id(&db, &teacher).await;
+
+        // Only attendees, only on the owner's calendar, only the owner's lessons
+        let stranger = upsert(
+            &db,
+            &teacher,
+            &master,
+            &record(&other, AttendanceStatus::NoShow),
+        )
+        .await;
+        assert!(matches!(stranger, Err(DbError::NotFound(_))));
+        let by_other = upsert(
+            &db,
+            &other,
+            &master,
+            &record(&student, AttendanceStatus::NoShow),
+        )
+        .await;
+        assert!(matches!(by_other, Err(DbError::NotFound(_))));
+        let mut foreign_lesson = record(&student, AttendanceStatus::Attended);
+        foreign_lesson.lesson_id = Some(create_with_defaults(&db, &other).await.unwrap());
+        let foreign = upsert(&db, &teacher, &master, &foreign_lesson).await;
+        assert!(matches!(foreign, Err(DbError::NotFound(_))));
+
+        let id = upsert(
+            &db,
+            &teacher,
+            &master,
+            &record(&student, AttendanceStatus::CancelledByTeacher),
+        )
+        .await
+        .unwrap();
+        assert!(matches!(
+            delete(&db, &other, &id).await,
+            Err(DbError::NotFound(_))
+        ));
+        delete(&db, &teacher, &id).await.unwrap();
+        assert!(
+            read_by_event(&db, &teacher, &master)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[sqlx::test]
+    async fn test_attendance_stays_on_own_calendar(db: PgPool) {
+        let teacher = create_test_user(&db, "teacher", "teacher@example.com").await;
+        let other = create_test_user(&db, "other", "other@example.com").await;
+        let student = create_test_user(&db, "student", "student@example.com").await;
+        let start = Utc.with_ymd_and_hms(2025, 3, 7, 15, 0, 0).unwrap();
+        let week = start + Duration::weeks(1);
+
+        // Both teachers imported the same feed, only the other one moved a lesson
+        import(&db, &teacher, &[lesson(start)]).await.unwrap();
+        let mut moved = lesson(week + Duration::days(1));
+        moved.rrule = None;
+        moved.recurrence_id = Some(week);
+        import(&db, &other, &[lesson(start), moved]).await.unwrap();
+        let master = master_id(&db, &teacher).await;
+        let other_master = master_id(&db, &other).await;
+
+        let attended = record(&student, AttendanceStatus::Attended);
+        upsert(
+            &db,
+            &teacher,
+            &occurrence_id(&master, Some(week)),
+            &attended,
+        )
+        .await
+        .unwrap();
+        let recorded = read_by_event(&db, &teacher, &occurrence_id(&master, Some(week)))
+            .await
+            .unwrap();
+        assert_eq!(recorded[0].starts_at, week);
+
+        let exception_id: String =
+            sqlx::query_scalar!("SELECT id FROM calendar_events WHERE recurrence_id IS NOT NULL")
+                .fetch_one(&db)
+                .await
+                .unwrap();
+        upsert(&db, &other, &exception_id, &attended).await.unwrap();
+        let recorded = read_by_event(&db, &other, &exception_id).await.unwrap();
+        assert_eq!(
+            recorded[0].event_id,
+            occurrence_id(&other_master, Some(week))
+        );
+        assert_eq!(recorded[0].starts_at, week + Duration::days(1));
+    }
+}
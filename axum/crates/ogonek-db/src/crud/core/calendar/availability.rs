@@ -0,0 +1,587 @@
+use chrono::{DateTime, Datelike, Duration, Utc};
+use sqlx::{PgConnection, PgPool};
+
+use crate::{
+    DbError,
+    core::account::{
+        profile::read_call_url,
+        user::{read_email, read_name},
+    },
+    crud::core::calendar::{
+        cal::read_calendar_id,
+        event_attendee,
+        freebusy::{Period, read_busy},
+    },
+    helpers::{event_zone, local_to_utc},
+};
+
+use ogonek_types::{
+    Availability, AvailabilityException, AvailabilityExceptionCreate, AvailabilityRule,
+    AvailabilityRuleCreate, AvailabilitySettings, BookableSlot, BusyPeriod, EventAttendeeCreate,
+    EventStatus,
+};
+
+/// Reads the teacher's availability rules, upcoming exceptions and settings
+pub async fn read(db: &PgPool, user_id: &str) -> Result<Availability, DbError> {
+    let mut conn = db.acquire().await?;
+    let settings = read_settings(&mut conn, user_id).await?;
+
+    let rules = sqlx::query_as!(
+        AvailabilityRule,
+        r#"
+        SELECT id, weekday, start_time, end_time
+        FROM availability_rules
+        WHERE user_id = $1
+        ORDER BY weekday, start_time
+        "#,
+        user_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let exceptions = sqlx::query_as!(
+        AvailabilityException,
+        r#"
+        SELECT id, starts_at, ends_at, available
+        FROM availability_exceptions
+        WHERE user_id = $1 AND ends_at > NOW()
+        ORDER BY starts_at
+        "#,
+        user_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(Availability {
+        settings,
+        rules,
+        exceptions,
+    })
+}
+
+/// Sets the lesson length and the buffer kept around events
+pub async fn update_settings(
+    db: &PgPool,
+    user_id: &str,
+    settings: &AvailabilitySettings,
+) -> Result<(), DbError> {
+    sqlx::query!(
+        r#"
+        INSERT INTO availability_settings (user_id, lesson_minutes, buffer_minutes)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (user_id) DO UPDATE
+        SET
+            lesson_minutes = EXCLUDED.lesson_minutes,
+            buffer_minutes = EXCLUDED.buffer_minutes,
+            updated_at = NOW()
+        "#,
+        user_id,
+        settings.lesson_minutes,
+        settings.buffer_minutes
+    )
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn create_rule(
+    db: &PgPool,
+    user_id: &str,
+    create: &AvailabilityRuleCreate,
+) -> Result<String, DbError> {
+    let id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO availability_rules (id, user_id, weekday, start_time, end_time)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id
+        "#,
+        nanoid::nanoid!(),
+        user_id,
+        create.weekday,
+        create.start_time,
+        create.end_time
+    )
+    .fetch_one(db)
+    .await?;
+
+    Ok(id)
+}
+
+pub async fn delete_rule(db: &PgPool, user_id: &str, rule_id: &str) -> Result<(), DbError> {
+    let result = sqlx::query!(
+        "DELETE FROM availability_rules WHERE id = $1 AND user_id = $2",
+        rule_id,
+        user_id
+    )
+    .execute(db)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(DbError::NotFound("Availability rule not found".into()));
+    }
+    Ok(())
+}
+
+pub async fn create_exception(
+    db: &PgPool,
+    user_id: &str,
+    create: &AvailabilityExceptionCreate,
+) -> Result<String, DbError> {
+    let id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO availability_exceptions (id, user_id, starts_at, ends_at, available)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id
+        "#,
+        nanoid::nanoid!(),
+        user_id,
+        create.starts_at,
+        create.ends_at,
+        create.available
+    )
+    .fetch_one(db)
+    .await?;
+
+    Ok(id)
+}
+
+pub async fn delete_exception(
+    db: &PgPool,
+    user_id: &str,
+    exception_id: &str,
+) -> Result<(), DbError> {
+    let result = sqlx::query!(
+        "DELETE FROM availability_exceptions WHERE id = $1 AND user_id = $2",
+        exception_id,
+        user_id
+    )
+    .execute(db)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(DbError::NotFound("Availability exception not found".into()));
+    }
+    Ok(())
+}
+
+/// Lists the teacher's free slots within the range for one of their active students
+pub async fn read_slots(
+    db: &PgPool,
+    teacher_id: &str,
+    student_id: &str,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Vec<BookableSlot>, DbError> {
+    let mut conn = db.acquire().await?;
+    check_student(&mut conn, teacher_id, student_id).await?;
+    free_slots(&mut conn, teacher_id, start, end).await
+}
+
+/// Books a free slot as a tentative event on the teacher's calendar with the student
+/// attending. Bookings of the same teacher are serialised on their calendar row, so
+/// two students cannot take the same slot.
+pub async fn book(
+    db: &PgPool,
+    teacher_id: &str,
+    student_id: &str,
+    start: DateTime<Utc>,
+) -> Result<String, DbError> {
+    let mut tx = db.begin().await?;
+    check_student(&mut tx, teacher_id, student_id).await?;
+
+    let calendar_id = read_calendar_id(&mut *tx, teacher_id).await?;
+    sqlx::query!(
+        "SELECT id FROM calendars WHERE id = $1 FOR UPDATE",
+        calendar_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let settings = read_settings(&mut tx, teacher_id).await?;
+    let end = start + Duration::minutes(settings.lesson_minutes.into());
+    let is_free = free_slots(&mut tx, teacher_id, start, end)
+        .await?
+        .iter()
+        .any(|slot| slot.start == start);
+    if !is_free {
+        return Err(DbError::AlreadyExists("This slot is not available".into()));
+    }
+
+    let student_name = read_name(&mut *tx, student_id).await?;
+    let video_call_url = read_call_url(&mut *tx, teacher_id).await?;
+
+    let event_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO calendar_events (
+            id, calendar_id, uid, summary, dtstart_time, dtend_time, location, dtstart_tz, status
+        )
+        VALUES (
+            $1, $2, $3, $4, $5, $6, $7,
+            (SELECT timezone FROM calendars WHERE id = $2::VARCHAR),
+            $8
+        )
+        RETURNING id
+        "#,
+        nanoid::nanoid!(),
+        calendar_id,
+        nanoid::nanoid!(),
+        student_name,
+        start,
+        end,
+        video_call_url,
+        EventStatus::Tentative as _
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let attendee = EventAttendeeCreate {
+        email: read_email(&mut *tx, student_id).await?,
+        name: Some(student_name),
+    };
+    event_attendee::create(&mut *tx, &event_id, student_id, attendee).await?;
+
+    tx.commit().await?;
+    Ok(event_id)
+}
+
+async fn check_student(
+    conn: &mut PgConnection,
+    teacher_id: &str,
+    student_id: &str,
+) -> Result<(), DbError> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT student_id FROM teacher_student
+        WHERE teacher_id = $1 AND student_id = $2 AND status = 'active'
+        "#,
+        teacher_id,
+        student_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or_else(|| DbError::NotFound("Teacher not found".into()))?;
+
+    Ok(())
+}
+
+async fn read_settings(
+    conn: &mut PgConnection,
+    user_id: &str,
+) -> Result<AvailabilitySettings, DbError> {
+    let settings = sqlx::query_as!(
+        AvailabilitySettings,
+        "SELECT lesson_minutes, buffer_minutes FROM availability_settings WHERE user_id = $1",
+        user_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok(settings.unwrap_or_default())
+}
+
+/// Computes the free slots of the teacher starting within the range
+async fn free_slots(
+    conn: &mut PgConnection,
+    user_id: &str,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Vec<BookableSlot>, DbError> {
+    let settings = read_settings(conn, user_id).await?;
+    let lesson = Duration::minutes(settings.lesson_minutes.into());
+    let buffer = Duration::minutes(settings.buffer_minutes.into());
+
+    let calendar_id = read_calendar_id(&mut *conn, user_id).await?;
+    let timezone = sqlx::query_scalar!("SELECT timezone FROM calendars WHERE id = $1", calendar_id)
+        .fetch_one(&mut *conn)
+        .await?;
+    let tz = event_zone(Some(&timezone));
+
+    let rules = sqlx::query!(
+        "SELECT weekday, start_time, end_time FROM availability_rules WHERE user_id = $1",
+        user_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let exceptions = sqlx::query!(
+        r#"
+        SELECT starts_at, ends_at, available
+        FROM availability_exceptions
+        WHERE user_id = $1 AND starts_at < $3 AND ends_at > $2
+        "#,
+        user_id,
+        start,
+        end + lesson
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    // Weekly windows on every local day the range touches
+    let mut windows = Vec::new();
+    let mut day = start.with_timezone(&tz).date_naive();
+    while day <= end.with_timezone(&tz).date_naive() {
+        let weekday = day.weekday().num_days_from_monday() as i16;
+        windows.extend(
+            rules
+                .iter()
+                .filter(|rule| rule.weekday == weekday)
+                .map(|rule| {
+                    (
+                        local_to_utc(&tz, day.and_time(rule.start_time)),
+                        local_to_utc(&tz, day.and_time(rule.end_time)),
+                    )
+                }),
+        );
+        let Some(next) = day.succ_opt() else { break };
+        day = next;
+    }
+
+    let (extra, blocked): (Vec<_>, Vec<_>) = exceptions.into_iter().partition(|e| e.available);
+    windows.extend(extra.iter().map(|e| (e.starts_at, e.ends_at)));
+    let blocked: Vec<Period> = blocked.iter().map(|e| (e.starts_at, e.ends_at)).collect();
+    let windows = subtract(merge(windows), &blocked);
+
+    let busy = read_busy(
+        conn,
+        user_id,
+        start - lesson - buffer,
+        end + lesson + buffer,
+    )
+    .await?;
+    Ok(slots_within(&windows, &busy, lesson, buffer)
+        .into_iter()
+        .filter(|slot| slot.start >= start && slot.start < end && slot.start >= Utc::now())
+        .collect())
+}
+
+/// Sorts the periods and joins those which overlap or touch
+fn merge(mut periods: Vec<Period>) -> Vec<Period> {
+    periods.sort();
+    let mut merged: Vec<Period> = Vec::with_capacity(periods.len());
+    for (start, end) in periods {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// Removes the blocked periods from the windows
+fn subtract(windows: Vec<Period>, blocked: &[Period]) -> Vec<Period> {
+    let mut free = windows;
+    for &(block_start, block_end) in blocked {
+        free = free
+            .into_iter()
+            .flat_map(|(start, end)| {
+                if block_end <= start || block_start >= end {
+                    return vec![(start, end)];
+                }
+                [(start, block_start), (block_end, end)]
+                    .into_iter()
+                    .filter(|(start, end)| start < end)
+                    .collect()
+            })
+            .collect();
+    }
+    free
+}
+
+/// Lays lessons out from the start of every window, a lesson and a buffer apart, and
+/// keeps those clear of busy time and its buffer
+fn slots_within(
+    windows: &[Period],
+    busy: &[BusyPeriod],
+    lesson: Duration,
+    buffer: Duration,
+) -> Vec<BookableSlot> {
+    let mut slots = Vec::new();
+    for &(window_start, window_end) in windows {
+        let mut start = window_start;
+        while start + lesson <= window_end {
+            let end = start + lesson;
+            if !busy
+                .iter()
+                .any(|period| period.overlaps(start - buffer, end + buffer))
+            {
+                slots.push(BookableSlot { start, end });
+            }
+            start = end + buffer;
+        }
+    }
+    slots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{core::calendar::event::import, tests::create_test_user};
+    use chrono::{NaiveDate, NaiveTime, Weekday};
+    use chrono_tz::Tz;
+    use ogonek_types::{EventClass, EventImport, EventTransp};
+
+    const MOSCOW: Tz = chrono_tz::Europe::Moscow;
+
+    /// A Monday at least a week ahead, so that its slots are in the future
+    fn next_monday() -> NaiveDate {
+        let mut day = (Utc::now() + Duration::weeks(1)).date_naive();
+        while day.weekday() != Weekday::Mon {
+            day = day.succ_opt().unwrap();
+        }
+        day
+    }
+
+    fn at(day: NaiveDate, hour: u32, minute: u32) -> DateTime<Utc> {
+        local_to_utc(&MOSCOW, day.and_hms_opt(hour, minute, 0).unwrap())
+    }
+
+    async fn teacher_with_student(db: &PgPool) -> (String, String) {
+        let teacher = create_test_user(db, "teacher", "teacher@example.com").await;
+        let student = create_test_user(db, "student", "student@example.com").await;
+        sqlx::query!(
+            "INSERT INTO teacher_student (teacher_id, student_id, status) VALUES ($1, $2, 'active')",
+            teacher,
+            student
+        )
+        .execute(db)
+        .await
+        .unwrap();
+
+        let rule = AvailabilityRuleCreate {
+            weekday: 0,
+            start_time: NaiveTime::from_hms_opt(10, 0, 0).unwrap(),
+            end_time: NaiveTime::from_hms_opt(14, 0, 0).unwrap(),
+        };
+        create_rule(db, &teacher, &rule).await.unwrap();
+        (teacher, student)
+    }
+
+    fn event(uid: &str, start: DateTime<Utc>, transp: EventTransp) -> EventImport {
+        EventImport {
+            uid: uid.to_string(),
+            summary: "Meeting".to_string(),
+            description: None,
+            location: None,
+            url: None,
+            dtstart_time: start,
+            dtend_time: Some(start + Duration::minutes(30)),
+            dtstart_tz: None,
+            rrule: None,
+            rdate: None,
+            exdate: None,
+            recurrence_id: None,
+            status: EventStatus::Confirmed,
+            class: EventClass::Public,
+            transp,
+            priority: None,
+            categories: None,
+            sequence: 0,
+            attendees: vec![],
+        }
+    }
+
+    #[sqlx::test]
+    async fn test_slots_leave_out_busy_time(db: PgPool) {
+        let (teacher, student) = teacher_with_student(&db).await;
+        let settings = AvailabilitySettings {
+            lesson_minutes: 60,
+            buffer_minutes: 15,
+        };
+        update_settings(&db, &teacher, &settings).await.unwrap();
+
+        let monday = next_monday();
+        let tuesday = monday.succ_opt().unwrap();
+        import(
+            &db,
+            &teacher,
+            &[
+                event("busy@example.com", at(monday, 12, 0), EventTransp::Opaque),
+                event(
+                    "free@example.com",
+                    at(monday, 10, 0),
+                    EventTransp::Transparent,
+                ),
+            ],
+        )
+        .await
+        .unwrap();
+        let extra = AvailabilityExceptionCreate {
+            starts_at: at(tuesday, 9, 0),
+            ends_at: at(tuesday, 10, 0),
+            available: true,
+        };
+        create_exception(&db, &teacher, &extra).await.unwrap();
+
+        let slots = read_slots(
+            &db,
+            &teacher,
+            &student,
+            at(monday, 0, 0),
+            at(tuesday, 23, 0),
+        )
+        .await
+        .unwrap();
+        let starts: Vec<_> = slots.iter().map(|slot| slot.start).collect();
+        // 11:15 and 12:30 fall within the buffer around the 12:00 event
+        assert_eq!(starts, [at(monday, 10, 0), at(tuesday, 9, 0)]);
+
+        // Blocked time cuts the window, lessons then start where it ends
+        let blocked = AvailabilityExceptionCreate {
+            starts_at: at(monday, 9, 0),
+            ends_at: at(monday, 10, 30),
+            available: false,
+        };
+        create_exception(&db, &teacher, &blocked).await.unwrap();
+        let slots = read_slots(&db, &teacher, &student, at(monday, 0, 0), at(monday, 23, 0))
+            .await
+            .unwrap();
+        assert_eq!(
+            slots,
+            [
+                BookableSlot {
+                    start: at(monday, 10, 30),
+                    end: at(monday, 11, 30),
+                },
+                BookableSlot {
+                    start: at(monday, 13, 0),
+                    end: at(monday, 14, 0),
+                },
+            ]
+        );
+    }
+
+    #[sqlx::test]
+    async fn test_book_slot_once(db: PgPool) {
+        let (teacher, student) = teacher_with_student(&db).await;
+        let stranger = create_test_user(&db, "stranger", "stranger@example.com").await;
+        let start = at(next_monday(), 11, 0);
+
+        let by_stranger = book(&db, &teacher, &stranger, start).await;
+        assert!(matches!(by_stranger, Err(DbError::NotFound(_))));
+
+        // Not on the lesson grid
+        let off_grid = book(&db, &teacher, &student, start + Duration::minutes(20)).await;
+        assert!(matches!(off_grid, Err(DbError::AlreadyExists(_))));
+
+        let event_id = book(&db, &teacher, &student, start).await.unwrap();
+        let booked = sqlx::query!(
+            r#"
+            SELECT e.dtend_time, e.status::TEXT AS "status!", ea.user_id
+            FROM calendar_events e
+            JOIN event_attendees ea ON ea.event_id = e.id
+            WHERE e.id = $1
+            "#,
+            event_id
+        )
+        .fetch_one(&db)
+        .await
+        .unwrap();
+        assert_eq!(booked.dtend_time, Some(start + Duration::hours(1)));
+        assert_eq!(booked.status, "tentative");
+        assert_eq!(booked.user_id, student);
+
+        let twice = book(&db, &teacher, &student, start).await;
+        assert!(matches!(twice, Err(DbError::AlreadyExists(_))));
+    }
+}
@@ -2,18 +2,18 @@ use sqlx::PgPool;
 
 use crate::DbError;
 
-use ogonek_types::{CalendarFull, CalendarUpdate};
-/// Finds the calendar by user id
+use ogonek_types::{
+    CalendarCreate, CalendarFull, CalendarPermission, CalendarSmall, CalendarUpdate,
+};
+/// Finds the user's default calendar, creating it on first use
 pub async fn get_or_create(db: &PgPool, user_id: &str) -> Result<CalendarFull, DbError> {
     let calendar = sqlx::query_as!(
         CalendarFull,
         r#"
-        INSERT INTO calendars (id, name, owner_id)
-        VALUES ($1, $2, $3)
-        ON CONFLICT (owner_id) 
-        DO UPDATE SET 
-            name = EXCLUDED.name,
-            updated_at = CURRENT_TIMESTAMP
+        INSERT INTO calendars (id, name, owner_id, is_default)
+        VALUES ($1, $2, $3, TRUE)
+        ON CONFLICT (owner_id) WHERE is_default
+        DO UPDATE SET owner_id = EXCLUDED.owner_id
         RETURNING *
         "#,
         nanoid::nanoid!(),
@@ -25,15 +25,17 @@ pub async fn get_or_create(db: &PgPool, user_id: &str) -> Result<CalendarFull, D
 
     Ok(calendar)
 }
+
+/// Returns the id of the user's default calendar, creating it on first use
 pub async fn read_calendar_id(
     db: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
     user_id: &str,
 ) -> Result<String, DbError> {
     let calendar_id = sqlx::query_scalar!(
         r#"
-        INSERT INTO calendars (id, name, owner_id)
-        VALUES ($1, $2, $3)
-        ON CONFLICT (owner_id) 
+        INSERT INTO calendars (id, name, owner_id, is_default)
+        VALUES ($1, $2, $3, TRUE)
+        ON CONFLICT (owner_id) WHERE is_default
         DO UPDATE SET owner_id = EXCLUDED.owner_id
         RETURNING id
         "#,
@@ -47,8 +49,245 @@ pub async fn read_calendar_id(
     Ok(calendar_id)
 }
 
-/// Deletes a cal
+/// Creates another calendar for the user, the default one if they have none yet
+pub async fn create(
+    db: &PgPool,
+    user_id: &str,
+    create: &CalendarCreate,
+) -> Result<String, DbError> {
+    let calendar_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO calendars (id, name, description, colour, timezone, owner_id, is_default)
+        VALUES (
+            $1, $2, $3, COALESCE($4, '#df7055'), COALESCE($5, 'Europe/Moscow'), $6,
+            NOT EXISTS (SELECT 1 FROM calendars WHERE owner_id = $6::VARCHAR AND is_default)
+        )
+        RETURNING id
+        "#,
+        nanoid::nanoid!(),
+        create.name,
+        create.description,
+        create.colour,
+        create.timezone,
+        user_id
+    )
+    .fetch_one(db)
+    .await
+    .map_err(|e| match e {
+        sqlx::Error::Database(dbe) if dbe.constraint() == Some("calendars_timezone_fkey") => {
+            DbError::NotFound("Timezone not found".into())
+        }
+        other => other.into(),
+    })?;
+
+    Ok(calendar_id)
+}
+
+/// Lists the calendars the user owns, the default one first, then those shared with them
+pub async fn read_all(db: &PgPool, user_id: &str) -> Result<Vec<CalendarSmall>, DbError> {
+    let mut tx = db.begin().await?;
+    read_calendar_id(&mut *tx, user_id).await?;
+
+    let calendars = sqlx::query_as!(
+        CalendarSmall,
+        r#"
+        SELECT
+            c.id,
+            c.name,
+            c.description,
+            c.colour,
+            c.timezone,
+            c.owner_id,
+            u.name AS owner_name,
+            c.is_default,
+            c.owner_id = $1 AS "is_owner!",
+            COALESCE(s.permission, 'read_write') AS "permission!: CalendarPermission"
+        FROM calendars c
+        JOIN "user" u ON u.id = c.owner_id
+        LEFT JOIN calendar_shares s ON s.calendar_id = c.id AND s.user_id = $1
+        WHERE c.owner_id = $1 OR s.user_id IS NOT NULL
+        ORDER BY c.owner_id = $1 DESC, c.is_default DESC, c.name
+        "#,
+        user_id
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(calendars)
+}
+
+/// Lists every calendar the user owns, the default one first
+pub async fn read_all_owned(db: &PgPool, user_id: &str) -> Result<Vec<CalendarFull>, DbError> {
+    let mut tx = db.begin().await?;
+    read_calendar_id(&mut *tx, user_id).await?;
+
+    let calendars = sqlx::query_as!(
+        CalendarFull,
+        "SELECT * FROM calendars WHERE owner_id = $1 ORDER BY is_default DESC, name",
+        user_id
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(calendars)
+}
+
+/// Makes the calendar the one events land in by default
+pub async fn set_default(db: &PgPool, calendar_id: &str, user_id: &str) -> Result<(), DbError> {
+    let mut tx = db.begin().await?;
+    read_owned(&mut *tx, calendar_id, user_id).await?;
+
+    sqlx::query!(
+        "UPDATE calendars SET is_default = FALSE WHERE owner_id = $1 AND is_default",
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!(
+        "UPDATE calendars SET is_default = TRUE WHERE id = $1",
+        calendar_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// The user's access to a calendar they own or that has been shared with them
+pub(in crate::crud::core::calendar) async fn read_permission(
+    db: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    calendar_id: &str,
+    user_id: &str,
+) -> Result<CalendarPermission, DbError> {
+    let permission = sqlx::query_scalar!(
+        r#"
+        SELECT COALESCE(s.permission, 'read_write') AS "permission!: CalendarPermission"
+        FROM calendars c
+        LEFT JOIN calendar_shares s ON s.calendar_id = c.id AND s.user_id = $2
+        WHERE c.id = $1 AND (c.owner_id = $2 OR s.user_id IS NOT NULL)
+        "#,
+        calendar_id,
+        user_id
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| DbError::NotFound("Calendar not found".into()))?;
+
+    Ok(permission)
+}
+
+/// The calendar to write new events to: the picked one if the user may write to it,
+/// their default calendar otherwise
+pub(in crate::crud::core::calendar) async fn read_writable_id(
+    conn: &mut sqlx::PgConnection,
+    user_id: &str,
+    calendar_id: Option<&str>,
+) -> Result<String, DbError> {
+    let Some(calendar_id) = calendar_id else {
+        return read_calendar_id(&mut *conn, user_id).await;
+    };
+
+    match read_permission(&mut *conn, calendar_id, user_id).await? {
+        CalendarPermission::ReadWrite => Ok(calendar_id.to_string()),
+        CalendarPermission::Read => Err(DbError::PreconditionFailed(
+            "The calendar is shared read-only".into(),
+        )),
+    }
+}
+
+/// Reads a calendar owned by the user
+pub async fn read_owned(
+    db: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    calendar_id: &str,
+    user_id: &str,
+) -> Result<CalendarFull, DbError> {
+    let calendar = sqlx::query_as!(
+        CalendarFull,
+        "SELECT * FROM calendars WHERE id = $1 AND owner_id = $2",
+        calendar_id,
+        user_id
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| DbError::NotFound("Calendar not found".into()))?;
+
+    Ok(calendar)
+}
+
+/// Returns the IANA zone of the user's calendar
+pub async fn read_timezone(db: &PgPool, user_id: &str) -> Result<String, DbError> {
+    let mut tx = db.begin().await?;
+    let calendar_id = read_calendar_id(&mut *tx, user_id).await?;
+
+    let timezone = sqlx::query_scalar!("SELECT timezone FROM calendars WHERE id = $1", calendar_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(timezone)
+}
+
+/// Returns the token of the calendar's iCalendar feed, creating one on first use
+pub async fn read_feed_token(db: &PgPool, user_id: &str) -> Result<String, DbError> {
+    let mut tx = db.begin().await?;
+    let calendar_id = read_calendar_id(&mut *tx, user_id).await?;
+
+    let token = sqlx::query_scalar!(
+        r#"
+        UPDATE calendars
+        SET feed_token = COALESCE(feed_token, $2)
+        WHERE id = $1
+        RETURNING feed_token AS "feed_token!"
+        "#,
+        calendar_id,
+        nanoid::nanoid!(32)
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(token)
+}
+
+/// Revokes the feed token, existing subscriptions stop working
+pub async fn revoke_feed_token(db: &PgPool, user_id: &str) -> Result<(), DbError> {
+    sqlx::query!(
+        "UPDATE calendars SET feed_token = NULL WHERE owner_id = $1",
+        user_id
+    )
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+/// Finds the calendar a feed token belongs to
+pub async fn read_by_feed_token(db: &PgPool, token: &str) -> Result<CalendarFull, DbError> {
+    let calendar = sqlx::query_as!(
+        CalendarFull,
+        "SELECT * FROM calendars WHERE feed_token = $1",
+        token
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| DbError::NotFound("Calendar feed not found".into()))?;
+
+    Ok(calendar)
+}
+
+/// Deletes a calendar, the default one has to be swapped out first
 pub async fn delete(db: &PgPool, calendar_id: &str, user_id: &str) -> Result<(), DbError> {
+    let mut tx = db.begin().await?;
+    let calendar = read_owned(&mut *tx, calendar_id, user_id).await?;
+    if calendar.is_default {
+        return Err(DbError::PreconditionFailed(
+            "The default calendar can't be deleted".into(),
+        ));
+    }
+
     sqlx::query!(
         r#"
     DELETE
@@ -58,9 +297,10 @@ pub async fn delete(db: &PgPool, calendar_id: &str, user_id: &str) -> Result<(),
         calendar_id,
         user_id
     )
-    .execute(db)
+    .execute(&mut *tx)
     .await?;
 
+    tx.commit().await?;
     Ok(())
 }
 
@@ -0,0 +1,284 @@
+use sqlx::PgPool;
+
+use crate::{DbError, crud::core::calendar::cal::read_owned};
+
+use ogonek_types::{CalendarPermission, CalendarShare, CalendarShareUpsert};
+
+/// Lists the users a calendar the user owns is shared with
+pub async fn read_all(
+    db: &PgPool,
+    calendar_id: &str,
+    user_id: &str,
+) -> Result<Vec<CalendarShare>, DbError> {
+    let shares = sqlx::query_as!(
+        CalendarShare,
+        r#"
+        SELECT
+            s.user_id,
+            u.name,
+            u.username,
+            s.permission AS "permission: CalendarPermission",
+            s.created_at
+        FROM calendar_shares s
+        JOIN calendars c ON c.id = s.calendar_id
+        JOIN "user" u ON u.id = s.user_id
+        WHERE s.calendar_id = $1 AND c.owner_id = $2
+        ORDER BY s.created_at
+        "#,
+        calendar_id,
+        user_id
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(shares)
+}
+
+/// Shares a calendar the user owns, or changes the access of someone it is shared with
+pub async fn upsert(
+    db: &PgPool,
+    calendar_id: &str,
+    user_id: &str,
+    share: &CalendarShareUpsert,
+) -> Result<(), DbError> {
+    let mut tx = db.begin().await?;
+    let calendar = read_owned(&mut *tx, calendar_id, user_id).await?;
+    if calendar.owner_id == share.user_id {
+        return Err(DbError::PreconditionFailed(
+            "A calendar can't be shared with its owner".into(),
+        ));
+    }
+
+    sqlx::query!(
+        r#"
+        INSERT INTO calendar_shares (calendar_id, user_id, permission)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (calendar_id, user_id)
+        DO UPDATE SET permission = EXCLUDED.permission
+        "#,
+        calendar_id,
+        share.user_id,
+        share.permission.clone() as _
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| match e {
+        sqlx::Error::Database(dbe) if dbe.constraint() == Some("calendar_shares_user_id_fkey") => {
+            DbError::NotFound("User not found".into())
+        }
+        other => other.into(),
+    })?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Stops sharing a calendar. The owner can remove anyone, others only themselves.
+pub async fn delete(
+    db: &PgPool,
+    calendar_id: &str,
+    user_id: &str,
+    shared_with: &str,
+) -> Result<(), DbError> {
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM calendar_shares s
+        USING calendars c
+        WHERE s.calendar_id = $1
+            AND s.user_id = $3
+            AND c.id = s.calendar_id
+            AND (c.owner_id = $2 OR s.user_id = $2)
+        "#,
+        calendar_id,
+        user_id,
+        shared_with
+    )
+    .execute(db)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(DbError::NotFound("Share not found".into()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::calendar::{cal, event},
+        tests::create_test_user,
+    };
+    use chrono::{Duration, TimeZone, Utc};
+    use ogonek_types::{
+        CalendarCreate, CalendarRole, DeleteScope, EditScope, EventCreate, EventDelete,
+        EventUpdate, EventUpdateRequest,
+    };
+
+    fn lesson(calendar_id: &str, attendee: &str, hour: u32) -> EventCreate {
+        let start = Utc.with_ymd_and_hms(2025, 3, 3, hour, 0, 0).unwrap();
+        EventCreate {
+            attendee: attendee.to_string(),
+            calendar_id: Some(calendar_id.to_string()),
+            dtstart_time: start,
+            dtend_time: Some(start + Duration::hours(1)),
+            allow_conflicts: false,
+        }
+    }
+
+    #[sqlx::test]
+    async fn test_shared_calendars(db: PgPool) {
+        let teacher = create_test_user(&db, "teacher", "teacher@example.com").await;
+        let colleague = create_test_user(&db, "colleague", "colleague@example.com").await;
+        let student = create_test_user(&db, "student", "student@example.com").await;
+
+        let private = cal::read_calendar_id(&db, &teacher).await.unwrap();
+        let group = cal::create(
+            &db,
+            &teacher,
+            &CalendarCreate {
+                name: "Group classes".to_string(),
+                description: None,
+                colour: Some("#3366ff".to_string()),
+                timezone: None,
+            },
+        )
+        .await
+        .unwrap();
+        event::create(&db, &teacher, lesson(&private, &student, 10))
+            .await
+            .unwrap();
+        event::create(&db, &teacher, lesson(&group, &student, 12))
+            .await
+            .unwrap();
+
+        let read_only = CalendarShareUpsert {
+            user_id: colleague.clone(),
+            permission: CalendarPermission::Read,
+        };
+        upsert(&db, &group, &teacher, &read_only).await.unwrap();
+        let refused = event::create(&db, &colleague, lesson(&group, &student, 14)).await;
+        assert!(matches!(refused, Err(DbError::PreconditionFailed(_))));
+
+        // Reading a shared event is fine, changing it is not. Private events stay hidden.
+        let group_event = sqlx::query_scalar!(
+            "SELECT id FROM calendar_events WHERE calendar_id = $1",
+            group
+        )
+        .fetch_one(&db)
+        .await
+        .unwrap();
+        let private_event = sqlx::query_scalar!(
+            "SELECT id FROM calendar_events WHERE calendar_id = $1",
+            private
+        )
+        .fetch_one(&db)
+        .await
+        .unwrap();
+        event::read_one(&db, &colleague, group_event.clone())
+            .await
+            .unwrap();
+        event::read_one(&db, &student, private_event.clone())
+            .await
+            .unwrap();
+        let hidden = event::read_one(&db, &colleague, private_event.clone()).await;
+        assert!(matches!(hidden, Err(DbError::NotFound(_))));
+        let relocate = || EventUpdateRequest {
+            scope: EditScope::ThisOnly,
+            allow_conflicts: false,
+            updates: EventUpdate {
+                description: None,
+                attendee: None,
+                location: Some("Room 4".to_string()),
+                dtstart_time: None,
+                dtend_time: None,
+                dtstart_tz: None,
+                dtend_tz: None,
+                rrule: None,
+            },
+        };
+        let refused = event::update(&db, &colleague, group_event.clone(), relocate()).await;
+        assert!(matches!(refused, Err(DbError::PreconditionFailed(_))));
+        let refused = event::delete(
+            &db,
+            &colleague,
+            private_event.clone(),
+            EventDelete {
+                scope: DeleteScope::ThisOnly,
+            },
+        )
+        .await;
+        assert!(matches!(refused, Err(DbError::NotFound(_))));
+
+        let read_write = CalendarShareUpsert {
+            permission: CalendarPermission::ReadWrite,
+            ..read_only
+        };
+        upsert(&db, &group, &teacher, &read_write).await.unwrap();
+        event::create(&db, &colleague, lesson(&group, &student, 14))
+            .await
+            .unwrap();
+        event::update(&db, &colleague, group_event, relocate())
+            .await
+            .unwrap();
+        // Nobody else can add to the teacher's private calendar
+        let foreign = event::create(&db, &colleague, lesson(&private, &student, 16)).await;
+        assert!(matches!(foreign, Err(DbError::NotFound(_))));
+
+        let calendars = cal::read_all(&db, &colleague).await.unwrap();
+        assert_eq!(calendars.len(), 2);
+        assert!(calendars[0].is_owner && calendars[0].is_default);
+        assert_eq!(calendars[1].id, group);
+        assert_eq!(calendars[1].permission, CalendarPermission::ReadWrite);
+
+        let start = Utc.with_ymd_and_hms(2025, 3, 3, 0, 0, 0).unwrap();
+        let end = start + Duration::days(1);
+        let merged = event::read_all(&db, &teacher, start, end, CalendarRole::Teacher, &[])
+            .await
+            .unwrap();
+        assert_eq!(merged.len(), 3);
+        let picked = event::read_all(
+            &db,
+            &teacher,
+            start,
+            end,
+            CalendarRole::Teacher,
+            std::slice::from_ref(&group),
+        )
+        .await
+        .unwrap();
+        assert_eq!(picked.len(), 2);
+        assert!(picked.iter().all(|e| e.db_data.calendar_id == group));
+        let shared = event::read_all(&db, &colleague, start, end, CalendarRole::Teacher, &[])
+            .await
+            .unwrap();
+        assert_eq!(shared.len(), 2);
+
+        // The colleague can leave, the calendar then disappears for them
+        delete(&db, &group, &colleague, &colleague).await.unwrap();
+        assert_eq!(cal::read_all(&db, &colleague).await.unwrap().len(), 1);
+        assert!(read_all(&db, &group, &teacher).await.unwrap().is_empty());
+    }
+
+    #[sqlx::test]
+    async fn test_default_calendar(db: PgPool) {
+        let user_id = create_test_user(&db, "teacher", "teacher@example.com").await;
+        let first = cal::read_calendar_id(&db, &user_id).await.unwrap();
+        let create = CalendarCreate {
+            name: "Personal".to_string(),
+            description: None,
+            colour: None,
+            timezone: None,
+        };
+        let second = cal::create(&db, &user_id, &create).await.unwrap();
+        assert_eq!(cal::read_calendar_id(&db, &user_id).await.unwrap(), first);
+
+        let refused = cal::delete(&db, &first, &user_id).await;
+        assert!(matches!(refused, Err(DbError::PreconditionFailed(_))));
+
+        cal::set_default(&db, &second, &user_id).await.unwrap();
+        assert_eq!(cal::read_calendar_id(&db, &user_id).await.unwrap(), second);
+        cal::delete(&db, &first, &user_id).await.unwrap();
+        assert_eq!(cal::read_all_owned(&db, &user_id).await.unwrap().len(), 1);
+    }
+}
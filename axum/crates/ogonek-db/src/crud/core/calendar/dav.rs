@@ -0,0 +1,542 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::{DbError, crud::core::calendar::event::upsert_event};
+
+use ogonek_types::{
+    CalendarResource, EventClass, EventDBFull, EventImport, EventStatus, EventTransp,
+    ResourcePrecondition,
+};
+
+// A resource is every row sharing a UID. Events created in the app have no href of their
+// own and are served as `{uid}.ics`; the etag changes whenever any of the rows does.
+
+/// Live resources of a calendar. With a time range, only those with an event in it;
+/// recurring ones are always included
+pub async fn read_resources(
+    db: &PgPool,
+    calendar_id: &str,
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+) -> Result<Vec<CalendarResource>, DbError> {
+    let resources = sqlx::query_as!(
+        CalendarResource,
+        r#"
+        SELECT
+            COALESCE(MAX(caldav_href), uid || '.ics') AS "href!",
+            uid,
+            md5(string_agg(etag, ',' ORDER BY id)) AS "etag!",
+            false AS "deleted!",
+            MAX(updated_at) AS "updated_at!"
+        FROM calendar_events
+        WHERE calendar_id = $1 AND deleted_at IS NULL
+        GROUP BY uid
+        HAVING bool_or(
+            rrule IS NOT NULL
+            OR (
+                ($3::TIMESTAMPTZ IS NULL OR dtstart_time < $3)
+                AND ($2::TIMESTAMPTZ IS NULL OR COALESCE(dtend_time, dtstart_time) >= $2)
+            )
+        )
+        ORDER BY uid
+        "#,
+        calendar_id,
+        start,
+        end
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(resources)
+}
+
+/// Live resources at the given hrefs
+pub async fn read_resources_by_href(
+    db: &PgPool,
+    calendar_id: &str,
+    hrefs: &[String],
+) -> Result<Vec<CalendarResource>, DbError> {
+    let resources = sqlx::query_as!(
+        CalendarResource,
+        r#"
+        SELECT
+            COALESCE(MAX(caldav_href), uid || '.ics') AS "href!",
+            uid,
+            md5(string_agg(etag, ',' ORDER BY id)) AS "etag!",
+            false AS "deleted!",
+            MAX(updated_at) AS "updated_at!"
+        FROM calendar_events
+        WHERE calendar_id = $1 AND deleted_at IS NULL
+        GROUP BY uid
+        HAVING COALESCE(MAX(caldav_href), uid || '.ics') = ANY($2)
+        ORDER BY uid
+        "#,
+        calendar_id,
+        hrefs
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(resources)
+}
+
+/// Resources changed after the given moment, deleted ones included
+pub async fn read_changes(
+    db: &PgPool,
+    calendar_id: &str,
+    since: DateTime<Utc>,
+) -> Result<Vec<CalendarResource>, DbError> {
+    let resources = sqlx::query_as!(
+        CalendarResource,
+        r#"
+        SELECT
+            COALESCE(MAX(caldav_href), uid || '.ics') AS "href!",
+            uid,
+            md5(COALESCE(
+                string_agg(etag, ',' ORDER BY id) FILTER (WHERE deleted_at IS NULL),
+                ''
+            )) AS "etag!",
+            bool_and(deleted_at IS NOT NULL) AS "deleted!",
+            MAX(updated_at) AS "updated_at!"
+        FROM calendar_events
+        WHERE calendar_id = $1
+        GROUP BY uid
+        HAVING MAX(updated_at) > $2
+        ORDER BY uid
+        "#,
+        calendar_id,
+        since
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(resources)
+}
+
+/// The moment of the calendar's latest change, which sync tokens are made of
+pub async fn read_synced_at(db: &PgPool, calendar_id: &str) -> Result<DateTime<Utc>, DbError> {
+    let synced_at = sqlx::query_scalar!(
+        r#"
+        SELECT GREATEST(c.created_at, MAX(e.updated_at)) AS "synced_at!"
+        FROM calendars c
+        LEFT JOIN calendar_events e ON e.calendar_id = c.id
+        WHERE c.id = $1
+        GROUP BY c.id
+        "#,
+        calendar_id
+    )
+    .fetch_one(db)
+    .await?;
+
+    Ok(synced_at)
+}
+
+/// Reads the stored events of the given UIDs, each master before its overrides
+pub async fn read_resource_events(
+    db: &PgPool,
+    calendar_id: &str,
+    uids: &[String],
+) -> Result<Vec<EventDBFull>, DbError> {
+    let events = sqlx::query_as!(
+        EventDBFull,
+        r#"
+        SELECT
+            id,
+            uid,
+            created_at,
+            updated_at,
+            calendar_id,
+            summary,
+            description,
+            location,
+            url,
+            dtstart_time,
+            dtend_time,
+            dtend_tz,
+            dtstart_tz,
+            rrule,
+            rdate,
+            exdate,
+            recurrence_id,
+            status AS "status!: EventStatus",
+            class AS "class!: EventClass",
+            transp AS "transp!: EventTransp",
+            priority,
+            categories,
+            sequence,
+            etag,
+            deleted_at,
+            caldav_href,
+            content_type
+        FROM calendar_events
+        WHERE calendar_id = $1 AND uid = ANY($2) AND deleted_at IS NULL
+        ORDER BY uid, recurrence_id NULLS FIRST
+        "#,
+        calendar_id,
+        uids
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(events)
+}
+
+/// Stores a resource, replacing whatever was at the href.
+/// Returns whether it was created along with its new etag
+pub async fn put_resource(
+    db: &PgPool,
+    calendar_id: &str,
+    href: &str,
+    events: &[EventImport],
+    precondition: &ResourcePrecondition,
+) -> Result<(bool, String), DbError> {
+    let uid = match events {
+        [first, rest @ ..] if rest.iter().all(|e| e.uid == first.uid) => &first.uid,
+        _ => {
+            return Err(DbError::ParseError(
+                "A resource holds the events of exactly one UID".into(),
+            ));
+        }
+    };
+
+    let mut tx = db.begin().await?;
+    lock_calendar(&mut tx, calendar_id).await?;
+
+    let existing = find_live(&mut tx, calendar_id, href).await?;
+    check_precondition(existing.as_ref(), precondition)?;
+
+    if let Some(existing) = &existing
+        && &existing.uid != uid
+    {
+        return Err(DbError::AlreadyExists(
+            "The resource holds another UID".into(),
+        ));
+    }
+    let other_href = sqlx::query_scalar!(
+        r#"
+        SELECT COALESCE(MAX(caldav_href), uid || '.ics') AS "href!"
+        FROM calendar_events
+        WHERE calendar_id = $1 AND uid = $2 AND deleted_at IS NULL
+        GROUP BY uid
+        "#,
+        calendar_id,
+        uid
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+    if other_href.is_some_and(|other| other != href) {
+        return Err(DbError::AlreadyExists(
+            "The UID is used by another resource".into(),
+        ));
+    }
+
+    let mut ids = Vec::with_capacity(events.len());
+    for event in events {
+        let (id, _) = upsert_event(&mut tx, calendar_id, event).await?;
+        ids.push(id);
+    }
+
+    // Overrides left out of the new body are gone
+    sqlx::query!(
+        "DELETE FROM calendar_events WHERE calendar_id = $1 AND uid = $2 AND NOT (id = ANY($3))",
+        calendar_id,
+        uid,
+        &ids
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "UPDATE calendar_events SET caldav_href = $3 WHERE calendar_id = $1 AND uid = $2",
+        calendar_id,
+        uid,
+        href
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let etag = find_live(&mut tx, calendar_id, href)
+        .await?
+        .map(|resource| resource.etag)
+        .ok_or(DbError::TransactionFailed)?;
+
+    tx.commit().await?;
+    Ok((existing.is_none(), etag))
+}
+
+/// Deletes a resource with its overrides
+pub async fn delete_resource(
+    db: &PgPool,
+    calendar_id: &str,
+    href: &str,
+    precondition: &ResourcePrecondition,
+) -> Result<(), DbError> {
+    let mut tx = db.begin().await?;
+    lock_calendar(&mut tx, calendar_id).await?;
+
+    let existing = find_live(&mut tx, calendar_id, href).await?;
+    check_precondition(existing.as_ref(), precondition)?;
+    let existing = existing.ok_or_else(|| DbError::NotFound("Resource not found".into()))?;
+
+    sqlx::query!(
+        r#"
+        UPDATE calendar_events
+        SET deleted_at = NOW()
+        WHERE calendar_id = $1 AND uid = $2 AND deleted_at IS NULL
+        "#,
+        calendar_id,
+        existing.uid
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+// Serialises writes to a calendar so etags are checked against what gets replaced
+async fn lock_calendar(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    calendar_id: &str,
+) -> Result<(), DbError> {
+    sqlx::query_scalar!(
+        "SELECT id FROM calendars WHERE id = $1 FOR UPDATE",
+        calendar_id
+    )
+    .fetch_optional(&mut **tx)
+    .await?
+    .ok_or_else(|| DbError::NotFound("Calendar not found".into()))?;
+
+    Ok(())
+}
+
+async fn find_live(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    calendar_id: &str,
+    href: &str,
+) -> Result<Option<CalendarResource>, DbError> {
+    let resource = sqlx::query_as!(
+        CalendarResource,
+        r#"
+        SELECT
+            COALESCE(MAX(caldav_href), uid || '.ics') AS "href!",
+            uid,
+            md5(string_agg(etag, ',' ORDER BY id)) AS "etag!",
+            false AS "deleted!",
+            MAX(updated_at) AS "updated_at!"
+        FROM calendar_events
+        WHERE calendar_id = $1 AND deleted_at IS NULL
+        GROUP BY uid
+        HAVING COALESCE(MAX(caldav_href), uid || '.ics') = $2
+        "#,
+        calendar_id,
+        href
+    )
+    .fetch_optional(&mut **tx)
+    .await?;
+
+    Ok(resource)
+}
+
+fn check_precondition(
+    existing: Option<&CalendarResource>,
+    precondition: &ResourcePrecondition,
+) -> Result<(), DbError> {
+    let holds = match (precondition, existing) {
+        (ResourcePrecondition::None, _) => true,
+        (ResourcePrecondition::IfMatch(etag), Some(existing)) => {
+            etag == "*" || *etag == existing.etag
+        }
+        (ResourcePrecondition::IfMatch(_), None) => false,
+        (ResourcePrecondition::IfNoneMatch, existing) => existing.is_none(),
+    };
+
+    if holds {
+        Ok(())
+    } else {
+        Err(DbError::PreconditionFailed(
+            "The resource has changed".into(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{core::calendar::cal::read_calendar_id, tests::create_test_user};
+    use chrono::{Duration, TimeZone};
+
+    fn test_event(uid: &str) -> EventImport {
+        let start = Utc.with_ymd_and_hms(2025, 3, 3, 15, 0, 0).unwrap();
+        EventImport {
+            uid: uid.to_string(),
+            summary: "Spanish".to_string(),
+            description: None,
+            location: None,
+            url: None,
+            dtstart_time: start,
+            dtend_time: Some(start + Duration::hours(1)),
+            dtstart_tz: None,
+            rrule: None,
+            rdate: None,
+            exdate: None,
+            recurrence_id: None,
+            status: EventStatus::Confirmed,
+            class: EventClass::Public,
+            transp: EventTransp::Opaque,
+            priority: None,
+            categories: None,
+            sequence: 0,
+            attendees: vec![],
+        }
+    }
+
+    #[sqlx::test]
+    async fn test_put_resource_checks_etags(db: PgPool) {
+        let user_id = create_test_user(&db, "teacher", "teacher@example.com").await;
+        let calendar_id = read_calendar_id(&db, &user_id).await.unwrap();
+        let event = test_event("lesson@example.com");
+
+        let (created, etag) = put_resource(
+            &db,
+            &calendar_id,
+            "lesson.ics",
+            std::slice::from_ref(&event),
+            &ResourcePrecondition::IfNoneMatch,
+        )
+        .await
+        .unwrap();
+        assert!(created);
+
+        // Creating it again must fail, as must an update against a stale etag
+        let result = put_resource(
+            &db,
+            &calendar_id,
+            "lesson.ics",
+            std::slice::from_ref(&event),
+            &ResourcePrecondition::IfNoneMatch,
+        )
+        .await;
+        assert!(matches!(result, Err(DbError::PreconditionFailed(_))));
+
+        let mut moved = event.clone();
+        moved.dtstart_time += Duration::hours(2);
+        let (created, new_etag) = put_resource(
+            &db,
+            &calendar_id,
+            "lesson.ics",
+            std::slice::from_ref(&moved),
+            &ResourcePrecondition::IfMatch(etag.clone()),
+        )
+        .await
+        .unwrap();
+        assert!(!created);
+        assert_ne!(etag, new_etag);
+
+        let result = put_resource(
+            &db,
+            &calendar_id,
+            "lesson.ics",
+            &[moved],
+            &ResourcePrecondition::IfMatch(etag),
+        )
+        .await;
+        assert!(matches!(result, Err(DbError::PreconditionFailed(_))));
+
+        // The same UID cannot live at two hrefs
+        let result = put_resource(
+            &db,
+            &calendar_id,
+            "copy.ics",
+            &[event],
+            &ResourcePrecondition::None,
+        )
+        .await;
+        assert!(matches!(result, Err(DbError::AlreadyExists(_))));
+
+        let resources = read_resources_by_href(&db, &calendar_id, &["lesson.ics".into()])
+            .await
+            .unwrap();
+        assert_eq!(resources.len(), 1);
+        assert_eq!(resources[0].etag, new_etag);
+    }
+
+    #[sqlx::test]
+    async fn test_put_resource_replaces_overrides(db: PgPool) {
+        let user_id = create_test_user(&db, "teacher", "teacher@example.com").await;
+        let calendar_id = read_calendar_id(&db, &user_id).await.unwrap();
+
+        let mut master = test_event("weekly@example.com");
+        master.rrule = Some("FREQ=WEEKLY".to_string());
+        let mut moved = test_event("weekly@example.com");
+        moved.recurrence_id = Some(master.dtstart_time + Duration::weeks(1));
+
+        let none = ResourcePrecondition::None;
+        put_resource(
+            &db,
+            &calendar_id,
+            "weekly.ics",
+            &[master.clone(), moved],
+            &none,
+        )
+        .await
+        .unwrap();
+        let events = read_resource_events(&db, &calendar_id, &[master.uid.clone()])
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].recurrence_id, None);
+
+        put_resource(&db, &calendar_id, "weekly.ics", &[master.clone()], &none)
+            .await
+            .unwrap();
+        let events = read_resource_events(&db, &calendar_id, &[master.uid.clone()])
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].caldav_href.as_deref(), Some("weekly.ics"));
+    }
+
+    #[sqlx::test]
+    async fn test_read_changes_reports_deletions(db: PgPool) {
+        let user_id = create_test_user(&db, "teacher", "teacher@example.com").await;
+        let calendar_id = read_calendar_id(&db, &user_id).await.unwrap();
+        let none = ResourcePrecondition::None;
+
+        put_resource(&db, &calendar_id, "a.ics", &[test_event("a")], &none)
+            .await
+            .unwrap();
+        put_resource(&db, &calendar_id, "b.ics", &[test_event("b")], &none)
+            .await
+            .unwrap();
+        let synced_at = read_synced_at(&db, &calendar_id).await.unwrap();
+        assert!(
+            read_changes(&db, &calendar_id, synced_at)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        delete_resource(&db, &calendar_id, "a.ics", &none)
+            .await
+            .unwrap();
+        let changes = read_changes(&db, &calendar_id, synced_at).await.unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].href, "a.ics");
+        assert!(changes[0].deleted);
+
+        let live = read_resources(&db, &calendar_id, None, None).await.unwrap();
+        assert_eq!(live.len(), 1);
+        assert_eq!(live[0].href, "b.ics");
+
+        let result = delete_resource(&db, &calendar_id, "a.ics", &none).await;
+        assert!(matches!(result, Err(DbError::NotFound(_))));
+
+        // Outside of the time range
+        let later = Utc::now() + Duration::days(3650);
+        let ranged = read_resources(&db, &calendar_id, Some(later), None)
+            .await
+            .unwrap();
+        assert!(ranged.is_empty());
+    }
+}
@@ -7,15 +7,21 @@ use crate::{
         profile::read_call_url,
         user::{read_email, read_name},
     },
-    crud::core::calendar::{cal::read_calendar_id, event_attendee},
+    crud::core::calendar::{cal::read_writable_id, event_attendee, freebusy::check_conflicts},
 };
 
 use ogonek_types::{EventAttendeeCreate, EventCreate, EventDBFull, EventUpdate};
 
-/// Creates a master calendar event
+/// Creates a master calendar event in the picked or the default calendar, failing on
+/// overlaps with busy time unless allowed
 pub async fn create(db: &PgPool, user_id: &str, create: EventCreate) -> Result<(), DbError> {
     let mut tx = db.begin().await?;
-    let calendar_id = read_calendar_id(&mut *tx, user_id).await?;
+    let calendar_id = read_writable_id(&mut tx, user_id, create.calendar_id.as_deref()).await?;
+
+    if !create.allow_conflicts {
+        let end = create.dtend_time.unwrap_or(create.dtstart_time);
+        check_conflicts(&mut tx, &calendar_id, &[(create.dtstart_time, end)], None).await?;
+    }
 
     let attendee_name = read_name(&mut *tx, &create.attendee).await?;
 
@@ -24,10 +30,11 @@ pub async fn create(db: &PgPool, user_id: &str, create: EventCreate) -> Result<(
     let id = sqlx::query_scalar!(
         r#"
         INSERT INTO calendar_events (
-            id, calendar_id, uid, summary, dtstart_time, dtend_time, location
+            id, calendar_id, uid, summary, dtstart_time, dtend_time, location, dtstart_tz
         )
         VALUES (
-            $1, $2, $3, $4, $5, $6, $7
+            $1, $2, $3, $4, $5, $6, $7,
+            (SELECT timezone FROM calendars WHERE id = $2::VARCHAR)
         )
         RETURNING id
         "#,
@@ -61,6 +68,14 @@ pub(super) async fn create_exception(
     update: &EventUpdate,
     occurrence_date: &DateTime<Utc>,
 ) -> Result<(), DbError> {
+    // Changes which keep the time keep the occurrence where it was, and its length
+    let dtstart_time = update.dtstart_time.unwrap_or(*occurrence_date);
+    let dtend_time = update.dtend_time.or_else(|| {
+        master
+            .dtend_time
+            .map(|end| dtstart_time + (end - master.dtstart_time))
+    });
+
     let exception_id = sqlx::query_scalar!(
         r#"
             INSERT INTO calendar_events (
@@ -72,9 +87,13 @@ pub(super) async fn create_exception(
                 location,
                 dtstart_time, 
                 dtend_time, 
-                recurrence_id
+                recurrence_id,
+                dtstart_tz,
+                sequence
             ) VALUES (
-                $1, $2, $3, $4, $5, $6, $7, $8, $9
+                $1, $2, $3, $4, $5, $6, $7, $8, $9,
+                COALESCE($10, (SELECT timezone FROM calendars WHERE id = $3::VARCHAR)),
+                $11
             )
             RETURNING id
             "#,
@@ -84,9 +103,11 @@ pub(super) async fn create_exception(
         master.summary,
         master.description,
         update.location.as_ref().or(master.location.as_ref()),
-        update.dtstart_time,
-        update.dtend_time,
-        occurrence_date
+        dtstart_time,
+        dtend_time,
+        occurrence_date,
+        master.dtstart_tz,
+        master.sequence + 1
     )
     .fetch_one(&mut **tx)
     .await?;
@@ -128,9 +149,11 @@ pub(super) async fn create_master(
                 location,
                 dtstart_time, 
                 dtend_time, 
-                rrule
+                rrule,
+                dtstart_tz
             ) VALUES (
-                $1, $2, $3, $4, $5, $6, $7, $8, $9
+                $1, $2, $3, $4, $5, $6, $7, $8, $9,
+                COALESCE($10, (SELECT timezone FROM calendars WHERE id = $3::VARCHAR))
             )
             RETURNING id
         "#,
@@ -142,7 +165,8 @@ pub(super) async fn create_master(
         update.location.as_ref().or(master.location.as_ref()),
         update.dtstart_time,
         update.dtend_time,
-        new_rrule
+        new_rrule,
+        master.dtstart_tz
     )
     .fetch_one(&mut **tx)
     .await?;
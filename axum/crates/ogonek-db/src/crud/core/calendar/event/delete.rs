@@ -2,15 +2,27 @@ use sqlx::PgPool;
 
 use crate::{
     DbError,
-    crud::core::calendar::event::{read::read_one_internal, update::truncate_master},
+    crud::core::calendar::event::{
+        read::{read_one_internal, read_writable},
+        update::truncate_master,
+    },
     helpers::extract_id_and_occurence,
 };
 
 use ogonek_types::{DeleteScope, EventDelete};
-pub async fn delete(db: &PgPool, event_id: String, req: EventDelete) -> Result<(), DbError> {
+
+/// Deletes an event, an occurrence or the rest of a series. The user needs write access
+/// to the event's calendar.
+pub async fn delete(
+    db: &PgPool,
+    user_id: &str,
+    event_id: String,
+    req: EventDelete,
+) -> Result<(), DbError> {
     let mut tx = db.begin().await?;
 
     let (master_id, occurrence_date) = extract_id_and_occurence(event_id);
+    read_writable(&mut tx, &master_id, user_id).await?;
 
     if let Some(occurrence) = occurrence_date {
         match req.scope {
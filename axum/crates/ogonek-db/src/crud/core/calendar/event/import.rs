@@ -0,0 +1,338 @@
+use sqlx::PgPool;
+
+use crate::{DbError, crud::core::calendar::cal::read_calendar_id};
+
+use ogonek_types::{EventImport, EventImportSummary};
+
+/// Imports events into the user's calendar.
+/// An event with the same UID and RECURRENCE-ID as a stored one replaces it, so importing
+/// the same file twice does not duplicate anything. Attendees are kept when their email
+/// belongs to an Ogonek account.
+pub async fn import(
+    db: &PgPool,
+    user_id: &str,
+    events: &[EventImport],
+) -> Result<EventImportSummary, DbError> {
+    let mut tx = db.begin().await?;
+    let calendar_id = read_calendar_id(&mut *tx, user_id).await?;
+
+    let mut summary = EventImportSummary::default();
+    for event in events {
+        match upsert_event(&mut tx, &calendar_id, event).await? {
+            (_, true) => summary.created += 1,
+            (_, false) => summary.updated += 1,
+        }
+    }
+
+    tx.commit().await?;
+    Ok(summary)
+}
+
+/// Creates or replaces the stored event with the same UID and RECURRENCE-ID,
+/// returns its id and whether it was created
+pub(in crate::crud::core::calendar) async fn upsert_event(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    calendar_id: &str,
+    event: &EventImport,
+) -> Result<(String, bool), DbError> {
+    let existing_id = sqlx::query_scalar!(
+        r#"
+        UPDATE calendar_events
+        SET
+            summary = $4,
+            description = $5,
+            location = $6,
+            url = $7,
+            dtstart_time = $8,
+            dtend_time = $9,
+            dtstart_tz = COALESCE(
+                (SELECT tzid FROM timezones WHERE tzid = $10),
+                (SELECT timezone FROM calendars WHERE id = $1)
+            ),
+            duration_iso = NULL,
+            rrule = $11,
+            rdate = $12,
+            exdate = $13,
+            status = $14,
+            class = $15,
+            transp = $16,
+            priority = $17,
+            categories = $18,
+            sequence = GREATEST(sequence + 1, $19),
+            deleted_at = NULL
+        WHERE calendar_id = $1 AND uid = $2 AND recurrence_id IS NOT DISTINCT FROM $3
+        RETURNING id
+        "#,
+        calendar_id,
+        event.uid,
+        event.recurrence_id,
+        event.summary,
+        event.description,
+        event.location,
+        event.url,
+        event.dtstart_time,
+        event.dtend_time,
+        event.dtstart_tz,
+        event.rrule,
+        event.rdate.as_deref(),
+        event.exdate.as_deref(),
+        event.status.clone() as _,
+        event.class.clone() as _,
+        event.transp.clone() as _,
+        event.priority,
+        event.categories.as_deref(),
+        event.sequence
+    )
+    .fetch_optional(&mut **tx)
+    .await?;
+
+    let (event_id, created) = match existing_id {
+        Some(id) => (id, false),
+        None => {
+            let id = sqlx::query_scalar!(
+                r#"
+                INSERT INTO calendar_events (
+                    id, calendar_id, uid, recurrence_id, summary, description, location, url,
+                    dtstart_time, dtend_time, dtstart_tz, rrule, rdate, exdate,
+                    status, class, transp, priority, categories, sequence
+                )
+                VALUES (
+                    $1, $2, $3, $4, $5, $6, $7, $8, $9, $10,
+                    COALESCE(
+                        (SELECT tzid FROM timezones WHERE tzid = $11::VARCHAR),
+                        (SELECT timezone FROM calendars WHERE id = $2::VARCHAR)
+                    ),
+                    $12, $13, $14, $15, $16, $17, $18, $19, $20
+                )
+                RETURNING id
+                "#,
+                nanoid::nanoid!(),
+                calendar_id,
+                event.uid,
+                event.recurrence_id,
+                event.summary,
+                event.description,
+                event.location,
+                event.url,
+                event.dtstart_time,
+                event.dtend_time,
+                event.dtstart_tz,
+                event.rrule,
+                event.rdate.as_deref(),
+                event.exdate.as_deref(),
+                event.status.clone() as _,
+                event.class.clone() as _,
+                event.transp.clone() as _,
+                event.priority,
+                event.categories.as_deref(),
+                event.sequence
+            )
+            .fetch_one(&mut **tx)
+            .await?;
+            (id, true)
+        }
+    };
+
+    for attendee in &event.attendees {
+        sqlx::query!(
+            r#"
+            INSERT INTO event_attendees (id, event_id, user_id, email, name, role, status)
+            SELECT $1, $2, u.id, $3, $4, $5, $6
+            FROM "user" u
+            WHERE lower(u.email) = lower($3::VARCHAR)
+            ON CONFLICT (event_id, email) DO UPDATE
+            SET name = EXCLUDED.name, role = EXCLUDED.role, status = EXCLUDED.status
+            "#,
+            nanoid::nanoid!(),
+            event_id,
+            attendee.email,
+            attendee.name,
+            attendee.role.to_string(),
+            attendee.status.to_string()
+        )
+        .execute(&mut **tx)
+        .await?;
+    }
+
+    Ok((event_id, created))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::calendar::{event::read_all, event_attendee::find_by_event_ids},
+        tests::create_test_user,
+    };
+    use chrono::{Duration, TimeZone, Utc};
+    use ogonek_types::{
+        AttendeeImport, CalendarRole, EventAttendeeRole, EventAttendeeStatus, EventClass,
+        EventStatus, EventTransp,
+    };
+
+    fn test_event(uid: &str) -> EventImport {
+        let start = Utc.with_ymd_and_hms(2025, 3, 3, 15, 0, 0).unwrap();
+        EventImport {
+            uid: uid.to_string(),
+            summary: "Spanish".to_string(),
+            description: None,
+            location: None,
+            url: None,
+            dtstart_time: start,
+            dtend_time: Some(start + Duration::hours(1)),
+            dtstart_tz: Some("Mars/Olympus_Mons".to_string()),
+            rrule: None,
+            rdate: None,
+            exdate: None,
+            recurrence_id: None,
+            status: EventStatus::Confirmed,
+            class: EventClass::Public,
+            transp: EventTransp::Opaque,
+            priority: None,
+            categories: None,
+            sequence: 0,
+            attendees: vec![],
+        }
+    }
+
+    #[sqlx::test]
+    async fn test_import_recurring_event_with_override(db: PgPool) {
+        let user_id = create_test_user(&db, "teacher", "teacher@example.com").await;
+        create_test_user(&db, "student", "student@example.com").await;
+
+        let mut master = test_event("weekly@example.com");
+        master.rrule = Some("FREQ=WEEKLY;COUNT=3".to_string());
+        master.attendees = vec![
+            AttendeeImport {
+                email: "Student@example.com".to_string(),
+                name: Some("Student".to_string()),
+                role: EventAttendeeRole::ReqParticipant,
+                status: EventAttendeeStatus::Accepted,
+            },
+            AttendeeImport {
+                email: "stranger@example.com".to_string(),
+                name: None,
+                role: EventAttendeeRole::OptParticipant,
+                status: EventAttendeeStatus::NeedsAction,
+            },
+        ];
+
+        // The second lesson moved an hour later
+        let moved = master.dtstart_time + Duration::weeks(1);
+        let mut exception = test_event("weekly@example.com");
+        exception.recurrence_id = Some(moved);
+        exception.dtstart_time = moved + Duration::hours(1);
+        exception.dtend_time = Some(moved + Duration::hours(2));
+
+        let summary = import(&db, &user_id, &[master.clone(), exception])
+            .await
+            .unwrap();
+        assert_eq!(summary.created, 2);
+        assert_eq!(summary.updated, 0);
+
+        let events = read_all(
+            &db,
+            &user_id,
+            master.dtstart_time - Duration::days(1),
+            master.dtstart_time + Duration::weeks(4),
+            CalendarRole::Teacher,
+            &[],
+        )
+        .await
+        .unwrap();
+        let mut starts: Vec<_> = events.iter().map(|e| e.db_data.dtstart_time).collect();
+        starts.sort();
+        assert_eq!(
+            starts,
+            [
+                master.dtstart_time,
+                moved + Duration::hours(1),
+                master.dtstart_time + Duration::weeks(2)
+            ]
+        );
+
+        // Only the attendee with an account is kept
+        let master_id = sqlx::query_scalar!(
+            "SELECT id FROM calendar_events WHERE uid = $1 AND recurrence_id IS NULL",
+            master.uid
+        )
+        .fetch_one(&db)
+        .await
+        .unwrap();
+        let attendees = find_by_event_ids(&db, &[master_id]).await.unwrap();
+        assert_eq!(attendees.len(), 1);
+        assert_eq!(attendees[0].status, EventAttendeeStatus::Accepted);
+    }
+
+    #[sqlx::test]
+    async fn test_recurrence_follows_event_zone(db: PgPool) {
+        let user_id = create_test_user(&db, "teacher", "teacher@example.com").await;
+        sqlx::query!(
+            "INSERT INTO timezones (tzid, display_name, utc_offset_std, utc_offset_dst)
+             VALUES ('Europe/Berlin', 'Berlin', 3600, 7200)"
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+
+        // 18:00 in Berlin, clocks go forward between the first two lessons
+        let mut master = test_event("berlin@example.com");
+        master.dtstart_time = Utc.with_ymd_and_hms(2025, 3, 24, 17, 0, 0).unwrap();
+        master.dtend_time = Some(master.dtstart_time + Duration::hours(1));
+        master.dtstart_tz = Some("Europe/Berlin".to_string());
+        master.rrule = Some("FREQ=WEEKLY;COUNT=4".to_string());
+        // A bare date excludes the lesson on that local day
+        master.exdate = Some(vec!["20250407".to_string()]);
+        import(&db, &user_id, &[master.clone()]).await.unwrap();
+
+        let events = read_all(
+            &db,
+            &user_id,
+            master.dtstart_time - Duration::days(1),
+            master.dtstart_time + Duration::weeks(5),
+            CalendarRole::Teacher,
+            &[],
+        )
+        .await
+        .unwrap();
+        let mut starts: Vec<_> = events.iter().map(|e| e.db_data.dtstart_time).collect();
+        starts.sort();
+        assert_eq!(
+            starts,
+            [
+                master.dtstart_time,
+                Utc.with_ymd_and_hms(2025, 3, 31, 16, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2025, 4, 14, 16, 0, 0).unwrap(),
+            ]
+        );
+    }
+
+    #[sqlx::test]
+    async fn test_import_twice_updates(db: PgPool) {
+        let user_id = create_test_user(&db, "teacher", "teacher@example.com").await;
+
+        let event = test_event("once@example.com");
+        import(&db, &user_id, std::slice::from_ref(&event))
+            .await
+            .unwrap();
+
+        let mut renamed = event.clone();
+        renamed.summary = "French".to_string();
+        let summary = import(&db, &user_id, &[renamed]).await.unwrap();
+        assert_eq!(summary.created, 0);
+        assert_eq!(summary.updated, 1);
+
+        let stored = sqlx::query!(
+            "SELECT summary, sequence, dtstart_tz FROM calendar_events WHERE uid = $1",
+            event.uid
+        )
+        .fetch_all(&db)
+        .await
+        .unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].summary, "French");
+        assert_eq!(stored[0].sequence, 1);
+        // Unknown zones fall back to the calendar's
+        assert_eq!(stored[0].dtstart_tz, "Europe/Moscow");
+    }
+}
@@ -1,9 +1,14 @@
 mod create;
 mod delete;
+mod import;
 pub mod read;
 mod update;
 
 pub use create::create;
 pub use delete::delete;
-pub use read::{read_all, read_one};
+pub use import::import;
+pub(super) use import::upsert_event;
+pub(super) use read::expand_master;
+pub use read::{read_all, read_all_for_feed, read_one};
 pub use update::update;
+pub(super) use update::update_in;
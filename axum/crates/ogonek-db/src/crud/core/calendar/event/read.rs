@@ -1,24 +1,49 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap};
 
 use chrono::{DateTime, Utc};
 use sqlx::PgPool;
 
-use crate::{crud::core::calendar::cal::read_calendar_id, error::DbError};
+use crate::{
+    crud::core::calendar::cal::{read_calendar_id, read_permission},
+    error::DbError,
+};
 
 use crate::helpers::{
-    OCCURRENCE_SEPARATOR, RRule, extract_id_and_occurence, parse_date_flexible, parse_exdates,
+    OCCURRENCE_SEPARATOR, RRule, RecurrenceDate, event_zone, extract_id_and_occurence,
 };
 use ogonek_types::{
-    CalendarRole, EventClass, EventDB, EventDBFull, EventFull, EventSmall, EventStatus, EventTransp,
+    CalendarFull, CalendarPermission, CalendarRole, EventClass, EventDB, EventDBFull, EventFull,
+    EventSmall, EventStatus, EventTransp,
 };
 
-/// Reads a calendar event by id (supports virtual instances)
-pub async fn read_one(db: &PgPool, event_id: String) -> Result<EventFull, DbError> {
+/// Reads a calendar event by id (supports virtual instances). Only events on calendars
+/// the user owns or that are shared with them, and those they attend, are found.
+pub async fn read_one(db: &PgPool, user_id: &str, event_id: String) -> Result<EventFull, DbError> {
     let (master_id, occurrence_date) = extract_id_and_occurence(event_id.clone());
 
     let mut tx = db.begin().await?;
 
     let mut master = read_one_internal(&mut *tx, &master_id).await?;
+    let attends = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1
+            FROM event_attendees ea
+            JOIN calendar_events e ON e.id = ea.event_id
+            WHERE e.calendar_id = $1 AND e.uid = $2 AND ea.user_id = $3
+        ) AS "attends!"
+        "#,
+        master.calendar_id,
+        master.uid,
+        user_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    if !attends {
+        read_permission(&mut *tx, &master.calendar_id, user_id)
+            .await
+            .map_err(not_found)?;
+    }
 
     // If this is a virtual instance, modify the dates
     if let Some(occurrence_dt) = occurrence_date {
@@ -41,6 +66,33 @@ pub async fn read_one(db: &PgPool, event_id: String) -> Result<EventFull, DbErro
     Ok(master.into())
 }
 
+/// Reads an event the user may change: one on their own calendars or on a calendar
+/// shared with them read-write
+pub(super) async fn read_writable(
+    conn: &mut sqlx::PgConnection,
+    event_id: &str,
+    user_id: &str,
+) -> Result<EventDBFull, DbError> {
+    let event = read_one_internal(&mut *conn, event_id).await?;
+    match read_permission(&mut *conn, &event.calendar_id, user_id)
+        .await
+        .map_err(not_found)?
+    {
+        CalendarPermission::ReadWrite => Ok(event),
+        CalendarPermission::Read => Err(DbError::PreconditionFailed(
+            "The calendar is shared read-only".into(),
+        )),
+    }
+}
+
+/// Calendars out of the user's reach hide their events
+fn not_found(error: DbError) -> DbError {
+    match error {
+        DbError::NotFound(_) => DbError::NotFound("Event not found".into()),
+        other => other,
+    }
+}
+
 /// Reads a calendar event by id
 pub(super) async fn read_one_internal(
     db: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
@@ -88,44 +140,119 @@ pub(super) async fn read_one_internal(
     Ok(event)
 }
 
-/// Reads all events for a calendar within a specific time frame
+/// Reads every stored event of the calendar, masters and exceptions alike. The feed of
+/// a default calendar also carries the events its owner attends.
+pub async fn read_all_for_feed(
+    db: &PgPool,
+    calendar: &CalendarFull,
+) -> Result<Vec<EventDBFull>, DbError> {
+    let events = sqlx::query_as!(
+        EventDBFull,
+        r#"
+        SELECT 
+            e.id,
+            e.uid,
+            e.created_at,
+            e.updated_at,
+            e.calendar_id,
+            e.summary,
+            e.description,
+            e.location,
+            e.url,
+            e.dtstart_time,
+            e.dtend_time,
+            e.dtend_tz,
+            e.dtstart_tz,
+            e.rrule,
+            e.rdate,
+            e.exdate,
+            e.recurrence_id,
+            e.status AS "status!: EventStatus",
+            e.class AS "class!: EventClass", 
+            e.transp AS "transp!: EventTransp",
+            e.priority,
+            e.categories,
+            e.sequence,
+            e.etag,
+            e.deleted_at,
+            e.caldav_href,
+            e.content_type
+        FROM calendar_events e
+        WHERE e.deleted_at IS NULL
+            AND (
+                e.calendar_id = $1
+                OR (
+                    $3
+                    AND EXISTS (
+                        SELECT 1 FROM event_attendees ea
+                        WHERE ea.event_id = e.id AND ea.user_id = $2 AND ea.status <> 'declined'
+                    )
+                )
+            )
+        ORDER BY e.uid, e.recurrence_id NULLS FIRST
+        "#,
+        calendar.id,
+        calendar.owner_id,
+        calendar.is_default
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(events)
+}
+
+/// Reads all events within a specific time frame from the picked calendars the user
+/// can see, every one of them when none are picked
 pub(super) async fn read_all_internal(
     db: &PgPool,
     user_id: &str,
     start: DateTime<Utc>,
     end: DateTime<Utc>,
+    calendar_ids: &[String],
 ) -> Result<Vec<EventDB>, DbError> {
     let mut tx = db.begin().await?;
 
-    let calendar_id = read_calendar_id(&mut *tx, user_id).await?;
+    // Users always have their default calendar
+    read_calendar_id(&mut *tx, user_id).await?;
     let events = sqlx::query_as!(
         EventDB,
         r#"
         SELECT 
-            id,
-            uid,
-            summary,
-            location,
-            dtstart_time,
-            dtend_time,
-            rdate,
-            status AS "status!: EventStatus",
-            exdate,
-            recurrence_id,
-            rrule
-        FROM calendar_events
-        WHERE calendar_id = $1 
-            AND deleted_at IS NULL
+            e.id,
+            e.uid,
+            e.calendar_id,
+            e.summary,
+            e.location,
+            e.dtstart_time,
+            e.dtend_time,
+            e.dtstart_tz,
+            e.rdate,
+            e.status AS "status!: EventStatus",
+            e.exdate,
+            e.recurrence_id,
+            e.rrule
+        FROM calendar_events e
+        JOIN calendars c ON c.id = e.calendar_id
+        WHERE (
+                c.owner_id = $1
+                OR EXISTS (
+                    SELECT 1 FROM calendar_shares s
+                    WHERE s.calendar_id = c.id AND s.user_id = $1
+                )
+            )
+            AND (cardinality($4::VARCHAR[]) = 0 OR e.calendar_id = ANY($4))
+            AND e.deleted_at IS NULL
             AND (
-                (rrule IS NOT NULL AND recurrence_id IS NULL) -- Master events
-            OR (dtstart_time BETWEEN $2 AND $3) -- Single events in range
-            OR (recurrence_id IS NOT NULL) -- All exceptions (we'll filter later)
+                (e.rrule IS NOT NULL AND e.recurrence_id IS NULL) -- Master events
+            OR (e.dtstart_time BETWEEN $2 AND $3) -- Single events in range
+            OR (e.recurrence_id IS NOT NULL) -- All exceptions (we'll filter later)
             )
-        ORDER BY dtstart_time ASC
+        ORDER BY e.dtstart_time ASC
         "#,
-        calendar_id,
+        user_id,
         start,
-        end
+        end,
+        calendar_ids
     )
     .fetch_all(&mut *tx)
     .await?;
@@ -147,10 +274,12 @@ pub async fn read_events_as_invitee(
         SELECT 
         e.id,
         e.uid,
+        e.calendar_id,
         e.summary,
         e.location,
         e.dtstart_time,
         e.dtend_time,
+        e.dtstart_tz,
         e.rdate,
         e.status AS "status!: EventStatus",
         e.exdate,
@@ -176,6 +305,49 @@ pub async fn read_events_as_invitee(
     .fetch_all(pool)
     .await
 }
+/// Expands a master event into the starts of its occurrences within the range, EXDATEs
+/// left out. Returns `None` for events which do not recur.
+pub(in crate::crud::core::calendar) fn expand_master(
+    master: &EventDB,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Option<BTreeSet<DateTime<Utc>>>, DbError> {
+    let Some(rrule) = RRule::parse(master.rrule.clone())? else {
+        return Ok(None);
+    };
+
+    // Recurrences follow the wall clock of the event's zone
+    let tz = event_zone(master.dtstart_tz.as_deref());
+    let mut occurrences = BTreeSet::new();
+
+    // Generate regular RRULE occurrences
+    occurrences.extend(rrule.generate_occurrences(master.dtstart_time, tz, start, end));
+
+    // Always include the original dtstart_time if it's in range
+    // This ensures the first occurrence isn't lost when adding recurrence
+    if master.dtstart_time >= start && master.dtstart_time <= end {
+        occurrences.insert(master.dtstart_time);
+    }
+
+    // Add RDATE occurrences (additional dates)
+    for rdate in RecurrenceDate::parse_all(&master.rdate, &tz)? {
+        let rdate = rdate.occurrence(master.dtstart_time, &tz);
+        if rdate >= start && rdate <= end {
+            occurrences.insert(rdate);
+        }
+    }
+
+    // Parse EXDATE array from master event
+    let exdates = RecurrenceDate::parse_all(&master.exdate, &tz)?;
+    occurrences.retain(|occurrence| {
+        !exdates
+            .iter()
+            .any(|exdate| exdate.matches(*occurrence, &tz))
+    });
+
+    Ok(Some(occurrences))
+}
+
 /// Public function that returns an array of events
 pub async fn read_all(
     db: &PgPool,
@@ -183,9 +355,10 @@ pub async fn read_all(
     start: DateTime<Utc>,
     end: DateTime<Utc>,
     role: CalendarRole,
+    calendar_ids: &[String],
 ) -> Result<Vec<EventSmall>, DbError> {
     let events = match role {
-        CalendarRole::Teacher => read_all_internal(db, user_id, start, end).await?,
+        CalendarRole::Teacher => read_all_internal(db, user_id, start, end, calendar_ids).await?,
         CalendarRole::Student => read_events_as_invitee(db, user_id, start, end).await?,
     };
 
@@ -194,50 +367,24 @@ pub async fn read_all(
         .filter(|e| e.recurrence_id.is_none())
         .collect();
 
-    let mut exceptions: HashMap<String, Vec<&EventDB>> = HashMap::new();
+    // UIDs are only unique within a calendar
+    let mut exceptions: HashMap<(&str, &str), Vec<&EventDB>> = HashMap::new();
     for e in events.iter().filter(|e| e.recurrence_id.is_some()) {
-        exceptions.entry(e.uid.clone()).or_default().push(e);
+        exceptions
+            .entry((e.calendar_id.as_str(), e.uid.as_str()))
+            .or_default()
+            .push(e);
     }
 
     let mut calendar_events: Vec<EventSmall> = Vec::new();
 
     for master in masters {
-        match RRule::parse(master.rrule.clone())? {
-            Some(rrule) => {
-                let mut all_occurrences = HashSet::new();
-
-                // Generate regular RRULE occurrences
-                let rrule_occurrences = rrule.generate_occurrences(master.dtstart_time, start, end);
-                all_occurrences.extend(rrule_occurrences);
-
-                // Always include the original dtstart_time if it's in range
-                // This ensures the first occurrence isn't lost when adding recurrence
-                if master.dtstart_time >= start && master.dtstart_time <= end {
-                    all_occurrences.insert(master.dtstart_time);
-                }
-
-                // Add RDATE occurrences (additional dates)
-                if let Some(rdate_list) = &master.rdate {
-                    for date_str in rdate_list {
-                        let rdate = parse_date_flexible(date_str)?;
-                        if rdate >= start && rdate <= end {
-                            all_occurrences.insert(rdate);
-                        }
-                    }
-                }
-
-                // Parse EXDATE array from master event
-                let exdates = parse_exdates(&master.exdate)?;
-
-                for occurrence in all_occurrences {
-                    // Skip if this occurrence is in EXDATE
-                    if exdates.contains(&occurrence) {
-                        continue;
-                    }
-
+        match expand_master(master, start, end)? {
+            Some(occurrences) => {
+                for occurrence in occurrences {
                     // Check if this occurrence has an exception (modified instance)
                     let has_exception = exceptions
-                        .get(&master.uid)
+                        .get(&(master.calendar_id.as_str(), master.uid.as_str()))
                         .map(|excs| excs.iter().any(|exc| exc.recurrence_id == Some(occurrence)))
                         .unwrap_or(false);
 
@@ -252,10 +399,11 @@ pub async fn read_all(
                                     occurrence.timestamp()
                                 ),
                                 uid: master.uid.clone(),
+                                calendar_id: master.calendar_id.clone(),
                                 rrule: master.rrule.clone(),
                                 status: master.status.clone(),
                                 rdate: master.rdate.clone(),
-                                exdate: master.rdate.clone(),
+                                exdate: master.exdate.clone(),
                                 recurrence_id: None,
                                 summary: master.summary.clone(),
                                 dtstart_time: occurrence,
@@ -263,6 +411,7 @@ pub async fn read_all(
                                 dtend_time: master
                                     .dtend_time
                                     .map(|end| occurrence + (end - master.dtstart_time)),
+                                dtstart_tz: master.dtstart_tz.clone(),
                             },
                             is_recurring: true,
                             is_exception: false,
@@ -276,6 +425,7 @@ pub async fn read_all(
                     db_data: EventDB {
                         id: master.id.clone(),
                         uid: master.uid.clone(),
+                        calendar_id: master.calendar_id.clone(),
                         recurrence_id: None,
                         rrule: None,
                         status: master.status.clone(),
@@ -285,6 +435,7 @@ pub async fn read_all(
                         location: master.location.clone(),
                         dtstart_time: master.dtstart_time,
                         dtend_time: master.dtend_time,
+                        dtstart_tz: master.dtstart_tz.clone(),
                     },
                     is_recurring: false,
                     is_exception: false,
@@ -294,13 +445,14 @@ pub async fn read_all(
     }
 
     // Add exception instances (modified occurrences)
-    for (uid, exception_list) in exceptions {
+    for ((_, uid), exception_list) in exceptions {
         for exception in exception_list {
             if exception.dtstart_time >= start && exception.dtstart_time <= end {
                 calendar_events.push(EventSmall {
                     db_data: EventDB {
                         id: exception.id.clone(),
-                        uid: uid.clone(),
+                        uid: uid.to_string(),
+                        calendar_id: exception.calendar_id.clone(),
                         recurrence_id: exception.recurrence_id,
                         status: exception.status.clone(),
                         rrule: None,
@@ -310,6 +462,7 @@ pub async fn read_all(
                         location: exception.location.clone(),
                         dtstart_time: exception.dtstart_time,
                         dtend_time: exception.dtend_time,
+                        dtstart_tz: exception.dtstart_tz.clone(),
                     },
                     is_recurring: true,
                     is_exception: true,
@@ -6,43 +6,126 @@ use crate::{
     crud::core::calendar::{
         event::{
             create::{create_exception, create_master},
-            read::read_one_internal,
+            read::{expand_master, read_one_internal, read_writable},
         },
         event_attendee,
+        freebusy::{Moving, Period, check_conflicts},
     },
     error::DbError,
-    helpers::{extract_id_and_occurence, remove_until_from_rrule},
+    helpers::{RRule, extract_id_and_occurence, remove_until_from_rrule},
 };
 
-use ogonek_types::{EditScope, EventAttendeeCreate, EventDBFull, EventUpdate, EventUpdateRequest};
+use ogonek_types::{
+    EditScope, EventAttendeeCreate, EventDB, EventDBFull, EventUpdate, EventUpdateRequest,
+};
+
+/// How far ahead a moved series is checked for conflicts
+const CONFLICT_HORIZON: Duration = Duration::days(180);
+
 /// The super handler for recurring or single events
-/// The id param is gonna be the master/regular event or a recurrence instance if there is an id_timestamp in it
-pub async fn update(db: &PgPool, event_id: String, req: EventUpdateRequest) -> Result<(), DbError> {
+/// The id param is gonna be the master/regular event or a recurrence instance if there is an id_timestamp in it.
+/// The user needs write access to the event's calendar.
+pub async fn update(
+    db: &PgPool,
+    user_id: &str,
+    event_id: String,
+    req: EventUpdateRequest,
+) -> Result<(), DbError> {
+    let mut tx = db.begin().await?;
+    let (master_id, _) = extract_id_and_occurence(event_id.clone());
+    read_writable(&mut tx, &master_id, user_id).await?;
+    update_in(&mut tx, event_id, &req).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Same as `update`, within a running transaction. Fails on overlaps with busy time
+/// when the event moves, unless the request allows them.
+pub(in crate::crud::core::calendar) async fn update_in(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    event_id: String,
+    req: &EventUpdateRequest,
+) -> Result<(), DbError> {
     // Extract the goddamn id and occurence to spot a virtual/real event
     let (master_id, occurrence_date) = extract_id_and_occurence(event_id);
 
-    let mut tx = db.begin().await?;
+    let master = read_one_internal(&mut **tx, &master_id).await?;
 
-    let master = read_one_internal(&mut *tx, &master_id).await?;
+    if !req.allow_conflicts {
+        let spans = moved_spans(&master, occurrence_date, req)?;
+        let moving = Moving {
+            uid: &master.uid,
+            occurrence: occurrence_date.filter(|_| matches!(req.scope, EditScope::ThisOnly)),
+        };
+        check_conflicts(tx, &master.calendar_id, &spans, Some(moving)).await?;
+    }
 
     if let Some(occurrence) = occurrence_date {
         match req.scope {
             EditScope::ThisOnly => {
-                edit_single_occurrence(&master, occurrence, &req.updates, &mut tx).await?;
+                edit_single_occurrence(&master, occurrence, &req.updates, tx).await?;
             }
             EditScope::ThisAndFuture => {
-                edit_this_and_future(&master, occurrence, &req.updates, &mut tx).await?;
+                edit_this_and_future(&master, occurrence, &req.updates, tx).await?;
             }
         }
     } else {
-        let attendee_name = update_attendee(&mut tx, &master_id, &req.updates.attendee).await?;
-        edit_single(&mut tx, &master_id, attendee_name, &req.updates).await?;
+        let attendee_name = update_attendee(tx, &master_id, &req.updates.attendee).await?;
+        edit_single(tx, &master_id, attendee_name, &req.updates).await?;
     }
 
-    tx.commit().await?;
     Ok(())
 }
 
+/// Where the event lands once updated, nothing when its time stays. Series are
+/// expanded over `CONFLICT_HORIZON` from their new start.
+fn moved_spans(
+    master: &EventDBFull,
+    occurrence_date: Option<DateTime<Utc>>,
+    req: &EventUpdateRequest,
+) -> Result<Vec<Period>, DbError> {
+    let updates = &req.updates;
+    if updates.dtstart_time.is_none() && updates.dtend_time.is_none() && updates.rrule.is_none() {
+        return Ok(vec![]);
+    }
+
+    let start = updates
+        .dtstart_time
+        .or(occurrence_date)
+        .unwrap_or(master.dtstart_time);
+    let duration = master
+        .dtend_time
+        .map_or(Duration::zero(), |end| end - master.dtstart_time);
+    let end = updates.dtend_time.unwrap_or(start + duration);
+
+    if occurrence_date.is_some() && matches!(req.scope, EditScope::ThisOnly) {
+        return Ok(vec![(start, end)]);
+    }
+
+    let series = EventDB {
+        id: master.id.clone(),
+        uid: master.uid.clone(),
+        calendar_id: master.calendar_id.clone(),
+        summary: master.summary.clone(),
+        location: master.location.clone(),
+        dtstart_time: start,
+        dtend_time: Some(end),
+        dtstart_tz: updates.dtstart_tz.clone().or(master.dtstart_tz.clone()),
+        rdate: None,
+        status: master.status.clone(),
+        exdate: master.exdate.clone(),
+        recurrence_id: None,
+        rrule: updates.rrule.clone().or(master.rrule.clone()),
+    };
+    match expand_master(&series, start, start + CONFLICT_HORIZON)? {
+        Some(occurrences) => Ok(occurrences
+            .into_iter()
+            .map(|occurrence| (occurrence, occurrence + (end - start)))
+            .collect()),
+        None => Ok(vec![(start, end)]),
+    }
+}
+
 /// EDIT ONE EVENT
 async fn edit_single(
     db: &mut sqlx::Transaction<'_, sqlx::Postgres>,
@@ -50,6 +133,12 @@ async fn edit_single(
     attendee_name: Option<String>,
     update: &EventUpdate,
 ) -> Result<(), DbError> {
+    // Rules are stored in canonical form, an empty one clears the recurrence
+    let rrule = match RRule::parse(update.rrule.clone())? {
+        Some(rule) => Some(rule.to_string()),
+        None => update.rrule.clone(),
+    };
+
     sqlx::query!(
         r#"
         UPDATE calendar_events 
@@ -73,7 +162,7 @@ async fn edit_single(
         update.dtend_time,
         update.dtstart_tz,
         update.dtend_tz,
-        update.rrule,
+        rrule,
     )
     .execute(&mut **db)
     .await?;
@@ -0,0 +1,805 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgPool;
+
+use crate::{
+    DbError,
+    crud::core::calendar::event::expand_master,
+    helpers::{OCCURRENCE_SEPARATOR, extract_id_and_occurence},
+};
+
+use ogonek_types::{
+    AlarmChannel, DueAlarm, EventAlarm, EventAlarmAction, EventAlarmCreate, EventAlarmUpdate,
+    EventDB, EventStatus,
+};
+
+/// Reads the alarms of an event the user owns. For an occurrence of a recurring event,
+/// `fires_at` is when each alarm first goes off for that occurrence
+pub async fn read_by_event(
+    db: &PgPool,
+    user_id: &str,
+    event_id: &str,
+) -> Result<Vec<EventAlarm>, DbError> {
+    let (master_id, occurrence) = extract_id_and_occurence(event_id.to_string());
+
+    let dtstart = sqlx::query_scalar!(
+        r#"
+        SELECT e.dtstart_time
+        FROM calendar_events e
+        JOIN calendars c ON c.id = e.calendar_id
+        WHERE e.id = $1 AND c.owner_id = $2 AND e.deleted_at IS NULL
+        "#,
+        master_id,
+        user_id
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| DbError::NotFound("Event not found".into()))?;
+
+    let mut alarms = sqlx::query_as!(
+        EventAlarm,
+        r#"
+        SELECT
+            a.id,
+            a.event_id,
+            EXTRACT(EPOCH FROM a.trigger_offset)::BIGINT AS trigger_offset_seconds,
+            a.trigger_datetime,
+            a.action AS "action!: EventAlarmAction",
+            a.description,
+            a.summary,
+            COALESCE(a.attendee_email, ARRAY(
+                SELECT ea.email::TEXT FROM event_attendees ea WHERE ea.event_id = a.event_id
+            )) AS "attendee_email!",
+            COALESCE(a.attendee_telegram_id, ARRAY(
+                SELECT p.telegram_id::TEXT
+                FROM event_attendees ea
+                JOIN profile p ON p.user_id = ea.user_id
+                WHERE ea.event_id = a.event_id AND p.telegram_id IS NOT NULL
+            )) AS "attendee_telegram_id!",
+            COALESCE(a.attendee_apns, ARRAY(
+                SELECT ea.user_id::TEXT FROM event_attendees ea WHERE ea.event_id = a.event_id
+            )) AS "attendee_apns!",
+            a.repeat_count,
+            EXTRACT(EPOCH FROM a.repeat_interval)::BIGINT AS repeat_interval_seconds,
+            NULL::TIMESTAMPTZ AS fires_at,
+            a.created_at
+        FROM event_alarms a
+        WHERE a.event_id = $1
+        ORDER BY a.created_at
+        "#,
+        master_id
+    )
+    .fetch_all(db)
+    .await?;
+
+    let start = occurrence.unwrap_or(dtstart);
+    for alarm in &mut alarms {
+        alarm.fires_at = first_trigger(alarm.trigger_datetime, alarm.trigger_offset_seconds, start);
+    }
+
+    Ok(alarms)
+}
+
+/// Adds an alarm to an event the user owns. Alarms set on an occurrence belong to
+/// the whole series. Recipients are limited to the event's attendees and its owner,
+/// anyone else given is dropped. Recipients left out follow the attendees as they
+/// change, see [`read_due`].
+pub async fn create(
+    db: &PgPool,
+    user_id: &str,
+    event_id: &str,
+    create: &EventAlarmCreate,
+) -> Result<String, DbError> {
+    let (master_id, _) = extract_id_and_occurence(event_id.to_string());
+
+    let id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO event_alarms (
+            id, event_id, trigger_offset, trigger_datetime, action, description, summary,
+            attendee_email, attendee_telegram_id, attendee_apns, repeat_count, repeat_interval
+        )
+        SELECT
+            $1,
+            e.id,
+            $3::BIGINT * INTERVAL '1 second',
+            $4,
+            $5,
+            $6,
+            $7,
+            CASE
+                WHEN $8::TEXT[] IS NULL THEN NULL
+                ELSE ARRAY(
+                    SELECT r FROM unnest($8::TEXT[]) r
+                    WHERE lower(r) IN (
+                        SELECT lower(ea.email) FROM event_attendees ea WHERE ea.event_id = e.id
+                        UNION SELECT lower(u.email) FROM "user" u WHERE u.id = c.owner_id
+                    )
+                )
+            END,
+            CASE
+                WHEN $9::TEXT[] IS NULL THEN NULL
+                ELSE ARRAY(
+                    SELECT r FROM unnest($9::TEXT[]) r
+                    WHERE r IN (
+                        SELECT p.telegram_id::TEXT
+                        FROM profile p
+                        WHERE p.user_id = c.owner_id
+                            OR p.user_id IN (
+                                SELECT ea.user_id FROM event_attendees ea WHERE ea.event_id = e.id
+                            )
+                    )
+                )
+            END,
+            CASE
+                WHEN $10::TEXT[] IS NULL THEN NULL
+                ELSE ARRAY(
+                    SELECT r FROM unnest($10::TEXT[]) r
+                    WHERE r = c.owner_id
+                        OR r IN (
+                            SELECT ea.user_id FROM event_attendees ea WHERE ea.event_id = e.id
+                        )
+                )
+            END,
+            $11,
+            $12::BIGINT * INTERVAL '1 second'
+        FROM calendar_events e
+        JOIN calendars c ON c.id = e.calendar_id
+        WHERE e.id = $2 AND c.owner_id = $13 AND e.deleted_at IS NULL
+        RETURNING id
+        "#,
+        nanoid::nanoid!(),
+        master_id,
+        create.trigger_offset_seconds,
+        create.trigger_datetime,
+        create.action.clone() as _,
+        create.description,
+        create.summary,
+        create.attendee_email.as_deref(),
+        create.attendee_telegram_id.as_deref(),
+        create.attendee_apns.as_deref(),
+        create.repeat_count,
+        create.repeat_interval_seconds,
+        user_id
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| DbError::NotFound("Event not found".into()))?;
+
+    Ok(id)
+}
+
+/// Updates an alarm on an event the user owns, limiting recipients as [`create`] does
+pub async fn update(
+    db: &PgPool,
+    user_id: &str,
+    alarm_id: &str,
+    update: &EventAlarmUpdate,
+) -> Result<(), DbError> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE event_alarms a
+        SET
+            trigger_offset = CASE
+                WHEN $2::BIGINT IS NOT NULL THEN $2 * INTERVAL '1 second'
+                WHEN $3::TIMESTAMPTZ IS NOT NULL THEN NULL
+                ELSE a.trigger_offset
+            END,
+            trigger_datetime = CASE
+                WHEN $3::TIMESTAMPTZ IS NOT NULL THEN $3
+                WHEN $2::BIGINT IS NOT NULL THEN NULL
+                ELSE a.trigger_datetime
+            END,
+            action = COALESCE($4, a.action),
+            description = COALESCE($5, a.description),
+            summary = COALESCE($6, a.summary),
+            attendee_email = CASE
+                WHEN $7::TEXT[] IS NULL THEN a.attendee_email
+                ELSE ARRAY(
+                    SELECT r FROM unnest($7::TEXT[]) r
+                    WHERE lower(r) IN (
+                        SELECT lower(ea.email) FROM event_attendees ea WHERE ea.event_id = e.id
+                        UNION SELECT lower(u.email) FROM "user" u WHERE u.id = c.owner_id
+                    )
+                )
+            END,
+            attendee_telegram_id = CASE
+                WHEN $8::TEXT[] IS NULL THEN a.attendee_telegram_id
+                ELSE ARRAY(
+                    SELECT r FROM unnest($8::TEXT[]) r
+                    WHERE r IN (
+                        SELECT p.telegram_id::TEXT
+                        FROM profile p
+                        WHERE p.user_id = c.owner_id
+                            OR p.user_id IN (
+                                SELECT ea.user_id FROM event_attendees ea WHERE ea.event_id = e.id
+                            )
+                    )
+                )
+            END,
+            attendee_apns = CASE
+                WHEN $9::TEXT[] IS NULL THEN a.attendee_apns
+                ELSE ARRAY(
+                    SELECT r FROM unnest($9::TEXT[]) r
+                    WHERE r = c.owner_id
+                        OR r IN (
+                            SELECT ea.user_id FROM event_attendees ea WHERE ea.event_id = e.id
+                        )
+                )
+            END,
+            repeat_count = COALESCE($10, a.repeat_count),
+            repeat_interval = COALESCE($11::BIGINT * INTERVAL '1 second', a.repeat_interval)
+        FROM calendar_events e
+        JOIN calendars c ON c.id = e.calendar_id
+        WHERE a.id = $1 AND e.id = a.event_id AND c.owner_id = $12
+        "#,
+        alarm_id,
+        update.trigger_offset_seconds,
+        update.trigger_datetime,
+        update.action.clone() as _,
+        update.description,
+        update.summary,
+        update.attendee_email.as_deref(),
+        update.attendee_telegram_id.as_deref(),
+        update.attendee_apns.as_deref(),
+        update.repeat_count,
+        update.repeat_interval_seconds,
+        user_id
+    )
+    .execute(db)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(DbError::NotFound("Alarm not found".into()));
+    }
+
+    Ok(())
+}
+
+/// Deletes an alarm on an event the user owns
+pub async fn delete(db: &PgPool, user_id: &str, alarm_id: &str) -> Result<(), DbError> {
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM event_alarms a
+        WHERE a.id = $1
+            AND EXISTS (
+                SELECT 1
+                FROM calendar_events e
+                JOIN calendars c ON c.id = e.calendar_id
+                WHERE e.id = a.event_id AND c.owner_id = $2
+            )
+        "#,
+        alarm_id,
+        user_id
+    )
+    .execute(db)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(DbError::NotFound("Alarm not found".into()));
+    }
+
+    Ok(())
+}
+
+/// Finds the alarms firing between `since` and `now`, one entry per occurrence and
+/// repeat. Alarms set on a recurring event follow its moved occurrences. Alarms without
+/// recipients of their own go to the attendees the event has now.
+pub async fn read_due(
+    db: &PgPool,
+    since: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Result<Vec<DueAlarm>, DbError> {
+    let alarms = sqlx::query!(
+        r#"
+        SELECT
+            a.id,
+            a.event_id,
+            EXTRACT(EPOCH FROM a.trigger_offset)::BIGINT AS trigger_offset_seconds,
+            a.trigger_datetime,
+            a.summary,
+            a.description,
+            COALESCE(a.attendee_email, ARRAY(
+                SELECT ea.email::TEXT FROM event_attendees ea WHERE ea.event_id = a.event_id
+            )) AS "attendee_email!",
+            COALESCE(a.attendee_telegram_id, ARRAY(
+                SELECT p.telegram_id::TEXT
+                FROM event_attendees ea
+                JOIN profile p ON p.user_id = ea.user_id
+                WHERE ea.event_id = a.event_id AND p.telegram_id IS NOT NULL
+            )) AS "attendee_telegram_id!",
+            COALESCE(a.attendee_apns, ARRAY(
+                SELECT ea.user_id::TEXT FROM event_attendees ea WHERE ea.event_id = a.event_id
+            )) AS "attendee_apns!",
+            a.repeat_count,
+            EXTRACT(EPOCH FROM a.repeat_interval)::BIGINT AS repeat_interval_seconds
+        FROM event_alarms a
+        JOIN calendar_events e ON e.id = a.event_id
+        WHERE e.deleted_at IS NULL
+            AND e.status <> 'cancelled'
+            AND (
+                (e.rrule IS NOT NULL AND e.recurrence_id IS NULL AND a.trigger_offset IS NOT NULL)
+                OR COALESCE(a.trigger_datetime, e.dtstart_time + a.trigger_offset)
+                    BETWEEN $1::TIMESTAMPTZ - a.repeat_count * COALESCE(a.repeat_interval, INTERVAL '0')
+                    AND $2::TIMESTAMPTZ
+            )
+        "#,
+        since,
+        now
+    )
+    .fetch_all(db)
+    .await?;
+
+    let event_ids: Vec<String> = alarms.iter().map(|alarm| alarm.event_id.clone()).collect();
+    let events = sqlx::query_as!(
+        EventDB,
+        r#"
+        SELECT
+            id,
+            uid,
+            calendar_id,
+            summary,
+            location,
+            dtstart_time,
+            dtend_time,
+            dtstart_tz,
+            rdate,
+            status AS "status!: EventStatus",
+            exdate,
+            recurrence_id,
+            rrule
+        FROM calendar_events
+        WHERE deleted_at IS NULL
+            AND (
+                id = ANY($1)
+                OR (
+                    recurrence_id IS NOT NULL
+                    AND (calendar_id, uid) IN (
+                        SELECT calendar_id, uid FROM calendar_events
+                        WHERE id = ANY($1) AND rrule IS NOT NULL
+                    )
+                )
+            )
+        "#,
+        &event_ids
+    )
+    .fetch_all(db)
+    .await?;
+
+    let by_id: HashMap<&str, &EventDB> = events.iter().map(|e| (e.id.as_str(), e)).collect();
+    // UIDs are only unique within a calendar
+    let mut exceptions: HashMap<(&str, &str), Vec<&EventDB>> = HashMap::new();
+    for exception in events.iter().filter(|e| e.recurrence_id.is_some()) {
+        exceptions
+            .entry((exception.calendar_id.as_str(), exception.uid.as_str()))
+            .or_default()
+            .push(exception);
+    }
+
+    let mut due = Vec::new();
+    for alarm in alarms {
+        let Some(event) = by_id.get(alarm.event_id.as_str()) else {
+            continue;
+        };
+
+        let interval = alarm
+            .repeat_interval_seconds
+            .filter(|seconds| *seconds > 0)
+            .map(Duration::seconds);
+        let repeats = interval.map_or(0, |_| alarm.repeat_count.max(0));
+        let span = interval.unwrap_or_default() * repeats;
+
+        // (occurrence id, occurrence start, the event holding its details)
+        let mut occurrences = vec![(event.id.clone(), event.dtstart_time, *event)];
+        if let (Some(offset), None) = (alarm.trigger_offset_seconds, alarm.trigger_datetime) {
+            let offset = Duration::seconds(offset);
+            let (from, to) = (since - offset - span, now - offset);
+
+            // A broken rule must not hold back the other alarms
+            if let Ok(Some(starts)) = expand_master(event, from, to) {
+                let overrides = exceptions.get(&(event.calendar_id.as_str(), event.uid.as_str()));
+                let overridden = |start: &DateTime<Utc>| {
+                    overrides.is_some_and(|overrides| {
+                        overrides.iter().any(|e| e.recurrence_id == Some(*start))
+                    })
+                };
+
+                occurrences = starts
+                    .into_iter()
+                    .filter(|start| !overridden(start))
+                    .map(|start| {
+                        let id = format!("{}{OCCURRENCE_SEPARATOR}{}", event.id, start.timestamp());
+                        (id, start, *event)
+                    })
+                    .collect();
+                occurrences.extend(
+                    overrides
+                        .into_iter()
+                        .flatten()
+                        .filter(|e| e.status != EventStatus::Cancelled)
+                        .filter(|e| e.dtstart_time >= from && e.dtstart_time <= to)
+                        .map(|e| (e.id.clone(), e.dtstart_time, *e)),
+                );
+            }
+        }
+
+        for (event_id, start, occurrence) in occurrences {
+            let Some(first) =
+                first_trigger(alarm.trigger_datetime, alarm.trigger_offset_seconds, start)
+            else {
+                continue;
+            };
+
+            for repeat_index in 0..=repeats {
+                let fires_at = first + interval.unwrap_or_default() * repeat_index;
+                if fires_at < since || fires_at > now {
+                    continue;
+                }
+
+                due.push(DueAlarm {
+                    alarm_id: alarm.id.clone(),
+                    event_id: event_id.clone(),
+                    summary: alarm
+                        .summary
+                        .clone()
+                        .unwrap_or_else(|| occurrence.summary.clone()),
+                    description: alarm.description.clone(),
+                    location: occurrence.location.clone(),
+                    occurrence: start,
+                    dtstart_tz: occurrence.dtstart_tz.clone(),
+                    repeat_index,
+                    fires_at,
+                    attendee_email: alarm.attendee_email.clone(),
+                    attendee_telegram_id: alarm.attendee_telegram_id.clone(),
+                    attendee_apns: alarm.attendee_apns.clone(),
+                });
+            }
+        }
+    }
+
+    due.sort_by_key(|alarm| alarm.fires_at);
+    Ok(due)
+}
+
+/// Records a delivery before it is sent, returns false when it already was
+pub async fn claim_delivery(
+    db: &PgPool,
+    alarm: &DueAlarm,
+    channel: AlarmChannel,
+    recipient: &str,
+) -> Result<bool, DbError> {
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO event_alarm_deliveries (
+            id, alarm_id, occurrence, repeat_index, channel, recipient
+        )
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (alarm_id, occurrence, repeat_index, channel, recipient) DO NOTHING
+        "#,
+        nanoid::nanoid!(),
+        alarm.alarm_id,
+        alarm.occurrence,
+        alarm.repeat_index,
+        channel as _,
+        recipient
+    )
+    .execute(db)
+    .await?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+/// Forgets a delivery which failed so that it is retried
+pub async fn release_delivery(
+    db: &PgPool,
+    alarm: &DueAlarm,
+    channel: AlarmChannel,
+    recipient: &str,
+) -> Result<(), DbError> {
+    sqlx::query!(
+        r#"
+        DELETE FROM event_alarm_deliveries
+        WHERE alarm_id = $1
+            AND occurrence = $2
+            AND repeat_index = $3
+            AND channel = $4
+            AND recipient = $5
+        "#,
+        alarm.alarm_id,
+        alarm.occurrence,
+        alarm.repeat_index,
+        channel as _,
+        recipient
+    )
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+fn first_trigger(
+    trigger_datetime: Option<DateTime<Utc>>,
+    trigger_offset_seconds: Option<i64>,
+    start: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    trigger_datetime
+        .or_else(|| trigger_offset_seconds.map(|offset| start + Duration::seconds(offset)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{core::calendar::event::import, tests::create_test_user};
+    use chrono::TimeZone;
+    use ogonek_types::{
+        AttendeeImport, EventAttendeeRole, EventAttendeeStatus, EventClass, EventImport,
+        EventTransp,
+    };
+
+    fn lesson(uid: &str, start: DateTime<Utc>) -> EventImport {
+        EventImport {
+            uid: uid.to_string(),
+            summary: "Spanish".to_string(),
+            description: None,
+            location: None,
+            url: None,
+            dtstart_time: start,
+            dtend_time: Some(start + Duration::hours(1)),
+            dtstart_tz: None,
+            rrule: Some("FREQ=WEEKLY;COUNT=3".to_string()),
+            rdate: None,
+            exdate: None,
+            recurrence_id: None,
+            status: EventStatus::Confirmed,
+            class: EventClass::Public,
+            transp: EventTransp::Opaque,
+            priority: None,
+            categories: None,
+            sequence: 0,
+            attendees: vec![AttendeeImport {
+                email: "student@example.com".to_string(),
+                name: None,
+                role: EventAttendeeRole::ReqParticipant,
+                status: EventAttendeeStatus::Accepted,
+            }],
+        }
+    }
+
+    async fn master_id(db: &PgPool, owner_id: &str, uid: &str) -> String {
+        sqlx::query_scalar!(
+            r#"
+            SELECT e.id FROM calendar_events e
+            JOIN calendars c ON c.id = e.calendar_id
+            WHERE e.uid = $1 AND e.recurrence_id IS NULL AND c.owner_id = $2
+            "#,
+            uid,
+            owner_id
+        )
+        .fetch_one(db)
+        .await
+        .unwrap()
+    }
+
+    fn reminder(offset: i64) -> EventAlarmCreate {
+        EventAlarmCreate {
+            trigger_offset_seconds: Some(offset),
+            trigger_datetime: None,
+            action: EventAlarmAction::Display,
+            description: None,
+            summary: None,
+            attendee_email: None,
+            attendee_telegram_id: None,
+            attendee_apns: None,
+            repeat_count: 0,
+            repeat_interval_seconds: None,
+        }
+    }
+
+    #[sqlx::test]
+    async fn test_alarm_crud_per_occurrence(db: PgPool) {
+        let teacher = create_test_user(&db, "teacher", "teacher@example.com").await;
+        let student = create_test_user(&db, "student", "student@example.com").await;
+        sqlx::query!(
+            "UPDATE profile SET telegram_id = '4242' WHERE user_id = $1",
+            student
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+
+        let start = Utc.with_ymd_and_hms(2025, 3, 3, 15, 0, 0).unwrap();
+        import(&db, &teacher, &[lesson("alarm@example.com", start)])
+            .await
+            .unwrap();
+        let event_id = master_id(&db, &teacher, "alarm@example.com").await;
+
+        // Recipients default to the attendees
+        let alarm_id = create(&db, &teacher, &event_id, &reminder(-900))
+            .await
+            .unwrap();
+        let alarms = read_by_event(&db, &teacher, &event_id).await.unwrap();
+        assert_eq!(alarms.len(), 1);
+        assert_eq!(alarms[0].attendee_email, ["student@example.com"]);
+        assert_eq!(alarms[0].attendee_telegram_id, ["4242"]);
+        assert_eq!(alarms[0].attendee_apns, std::slice::from_ref(&student));
+        assert_eq!(alarms[0].fires_at, Some(start - Duration::minutes(15)));
+
+        // Attendees added later are reminded too
+        let late = create_test_user(&db, "late", "late@example.com").await;
+        sqlx::query!(
+            "INSERT INTO event_attendees (id, user_id, event_id, email) VALUES ($1, $2, $3, $4)",
+            nanoid::nanoid!(),
+            late,
+            event_id,
+            "late@example.com"
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+        let alarms = read_by_event(&db, &teacher, &event_id).await.unwrap();
+        assert_eq!(alarms[0].attendee_email.len(), 2);
+        assert!(alarms[0].attendee_apns.contains(&late));
+
+        // Each occurrence has its own trigger
+        let second = start + Duration::weeks(1);
+        let occurrence_id = format!("{event_id}{OCCURRENCE_SEPARATOR}{}", second.timestamp());
+        let alarms = read_by_event(&db, &teacher, &occurrence_id).await.unwrap();
+        assert_eq!(alarms[0].fires_at, Some(second - Duration::minutes(15)));
+
+        // Only the owner sees or changes them
+        assert!(matches!(
+            read_by_event(&db, &student, &event_id).await,
+            Err(DbError::NotFound(_))
+        ));
+        assert!(matches!(
+            delete(&db, &student, &alarm_id).await,
+            Err(DbError::NotFound(_))
+        ));
+
+        let at = start - Duration::days(1);
+        let update_payload = EventAlarmUpdate {
+            trigger_datetime: Some(at),
+            ..Default::default()
+        };
+        update(&db, &teacher, &alarm_id, &update_payload)
+            .await
+            .unwrap();
+        let alarms = read_by_event(&db, &teacher, &occurrence_id).await.unwrap();
+        assert_eq!(alarms[0].trigger_offset_seconds, None);
+        assert_eq!(alarms[0].fires_at, Some(at));
+
+        // Recipients outside the event are dropped
+        let recipients = EventAlarmUpdate {
+            attendee_email: Some(vec![
+                "Student@example.com".to_string(),
+                "teacher@example.com".to_string(),
+                "stranger@example.com".to_string(),
+            ]),
+            attendee_telegram_id: Some(vec!["4242".to_string(), "1337".to_string()]),
+            attendee_apns: Some(vec![teacher.clone(), "someone-else".to_string()]),
+            ..Default::default()
+        };
+        update(&db, &teacher, &alarm_id, &recipients).await.unwrap();
+        let alarms = read_by_event(&db, &teacher, &event_id).await.unwrap();
+        assert_eq!(
+            alarms[0].attendee_email,
+            ["Student@example.com", "teacher@example.com"]
+        );
+        assert_eq!(alarms[0].attendee_telegram_id, ["4242"]);
+        assert_eq!(alarms[0].attendee_apns, std::slice::from_ref(&teacher));
+
+        let mut spam = reminder(-60);
+        spam.attendee_email = Some(vec!["stranger@example.com".to_string()]);
+        let spam_id = create(&db, &teacher, &event_id, &spam).await.unwrap();
+        let alarms = read_by_event(&db, &teacher, &event_id).await.unwrap();
+        let spam = alarms.iter().find(|alarm| alarm.id == spam_id).unwrap();
+        assert!(spam.attendee_email.is_empty());
+        delete(&db, &teacher, &spam_id).await.unwrap();
+
+        delete(&db, &teacher, &alarm_id).await.unwrap();
+        assert!(
+            read_by_event(&db, &teacher, &event_id)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[sqlx::test]
+    async fn test_due_alarms_follow_occurrences(db: PgPool) {
+        let teacher = create_test_user(&db, "teacher", "teacher@example.com").await;
+        create_test_user(&db, "student", "student@example.com").await;
+
+        // The second lesson moved an hour later
+        let start = Utc.with_ymd_and_hms(2025, 3, 3, 15, 0, 0).unwrap();
+        let second = start + Duration::weeks(1);
+        let mut moved = lesson("due@example.com", start);
+        moved.rrule = None;
+        moved.recurrence_id = Some(second);
+        moved.dtstart_time = second + Duration::hours(1);
+        import(&db, &teacher, &[lesson("due@example.com", start), moved])
+            .await
+            .unwrap();
+        let event_id = master_id(&db, &teacher, "due@example.com").await;
+
+        let mut payload = reminder(-900);
+        payload.repeat_count = 1;
+        payload.repeat_interval_seconds = Some(300);
+        create(&db, &teacher, &event_id, &payload).await.unwrap();
+
+        // Nothing fires at the original time of the moved lesson
+        let due = read_due(&db, second - Duration::minutes(30), second)
+            .await
+            .unwrap();
+        assert!(due.is_empty());
+
+        let moved_start = second + Duration::hours(1);
+        let due = read_due(&db, moved_start - Duration::minutes(30), moved_start)
+            .await
+            .unwrap();
+        assert_eq!(due.len(), 2);
+        assert_ne!(due[0].event_id, event_id);
+        assert_eq!(due[0].occurrence, moved_start);
+        assert_eq!(due[0].fires_at, moved_start - Duration::minutes(15));
+        assert_eq!(due[1].repeat_index, 1);
+        assert_eq!(due[1].fires_at, moved_start - Duration::minutes(10));
+
+        // Another teacher moving the same lesson in their calendar changes nothing here
+        let other = create_test_user(&db, "other", "other@example.com").await;
+        let third = start + Duration::weeks(2);
+        let mut other_moved = lesson("due@example.com", start);
+        other_moved.rrule = None;
+        other_moved.recurrence_id = Some(third);
+        other_moved.dtstart_time = third + Duration::days(1);
+        import(
+            &db,
+            &other,
+            &[lesson("due@example.com", start), other_moved],
+        )
+        .await
+        .unwrap();
+        let other_event_id = master_id(&db, &other, "due@example.com").await;
+        create(&db, &other, &other_event_id, &reminder(-900))
+            .await
+            .unwrap();
+
+        let due = read_due(
+            &db,
+            third - Duration::minutes(20),
+            third - Duration::minutes(12),
+        )
+        .await
+        .unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(
+            due[0].event_id,
+            format!("{event_id}{OCCURRENCE_SEPARATOR}{}", third.timestamp())
+        );
+
+        // A delivery is only claimed once
+        let email = "student@example.com";
+        assert!(
+            claim_delivery(&db, &due[0], AlarmChannel::Email, email)
+                .await
+                .unwrap()
+        );
+        assert!(
+            !claim_delivery(&db, &due[0], AlarmChannel::Email, email)
+                .await
+                .unwrap()
+        );
+        assert!(
+            claim_delivery(&db, &due[0], AlarmChannel::Apns, email)
+                .await
+                .unwrap()
+        );
+        release_delivery(&db, &due[0], AlarmChannel::Email, email)
+            .await
+            .unwrap();
+        assert!(
+            claim_delivery(&db, &due[0], AlarmChannel::Email, email)
+                .await
+                .unwrap()
+        );
+    }
+}
@@ -59,6 +59,37 @@ pub async fn find_by_event_id(db: &PgPool, event_id: &str) -> Result<Vec<EventAt
     Ok(attendees)
 }
 
+/// Finds the attendees of several events at once
+pub async fn find_by_event_ids(
+    db: &PgPool,
+    event_ids: &[String],
+) -> Result<Vec<EventAttendee>, DbError> {
+    let attendees = sqlx::query_as!(
+        EventAttendee,
+        r#"
+        SELECT 
+            id,
+            user_id,
+            event_id,
+            email,
+            name,
+            role as "role: EventAttendeeRole",
+            status as "status: EventAttendeeStatus",
+            rsvp,
+            created_at,
+            updated_at
+        FROM event_attendees
+        WHERE event_id = ANY($1)
+        ORDER BY created_at
+        "#,
+        event_ids
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(attendees)
+}
+
 /// Deletes an event attendee
 pub async fn delete(db: &PgPool, attendee_id: &str) -> Result<(), DbError> {
     sqlx::query!(
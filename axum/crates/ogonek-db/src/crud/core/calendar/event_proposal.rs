@@ -0,0 +1,394 @@
+use sqlx::PgPool;
+
+use crate::{
+    DbError,
+    crud::core::calendar::event::update_in,
+    helpers::{extract_id_and_occurence, occurrence_id},
+};
+
+use ogonek_types::{
+    EditScope, EventProposal, EventProposalCreate, EventUpdate, EventUpdateRequest, ProposalStatus,
+};
+
+/// Proposes a new time or place for an event the user attends. A proposal for a
+/// recurring event without an occurrence targets its first occurrence.
+pub async fn create(
+    db: &PgPool,
+    user_id: &str,
+    event_id: &str,
+    create: &EventProposalCreate,
+) -> Result<String, DbError> {
+    let (event_id, occurrence) = extract_id_and_occurence(event_id.to_string());
+
+    let event = sqlx::query!(
+        r#"
+        SELECT e.dtstart_time, e.rrule
+        FROM calendar_events e
+        WHERE e.id = $1 AND e.deleted_at IS NULL
+            AND EXISTS (
+                SELECT 1 FROM event_attendees ea
+                WHERE ea.event_id = e.id AND ea.user_id = $2
+            )
+        "#,
+        event_id,
+        user_id
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| DbError::NotFound("Event not found".into()))?;
+
+    let recurrence_id = match (occurrence, event.rrule) {
+        (Some(occurrence), _) => Some(occurrence),
+        (None, Some(_)) => Some(event.dtstart_time),
+        (None, None) => None,
+    };
+
+    let id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO event_counter_proposals (
+            id, event_id, proposer, recurrence_id,
+            proposed_start, proposed_end, proposed_location, comment
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING id
+        "#,
+        nanoid::nanoid!(),
+        event_id,
+        user_id,
+        recurrence_id,
+        create.proposed_start,
+        create.proposed_end,
+        create.proposed_location,
+        create.comment
+    )
+    .fetch_one(db)
+    .await
+    .map_err(|e| match e {
+        sqlx::Error::Database(dbe) if dbe.constraint() == Some("unique_pending_proposal") => {
+            DbError::AlreadyExists("A proposal for this event is already pending".into())
+        }
+        other => other.into(),
+    })?;
+
+    Ok(id)
+}
+
+/// Reads the proposals the user made or received on their calendar
+pub async fn read_all(
+    db: &PgPool,
+    user_id: &str,
+    status: Option<ProposalStatus>,
+) -> Result<Vec<EventProposal>, DbError> {
+    let mut proposals = sqlx::query_as!(
+        EventProposal,
+        r#"
+        SELECT
+            p.id,
+            p.event_id,
+            e.summary,
+            p.proposer,
+            u.name AS proposer_name,
+            p.recurrence_id,
+            p.proposed_start,
+            p.proposed_end,
+            p.proposed_location,
+            p.comment,
+            p.status AS "status!: ProposalStatus",
+            p.created_at AS "created_at!",
+            p.responded_at
+        FROM event_counter_proposals p
+        JOIN calendar_events e ON e.id = p.event_id
+        JOIN calendars c ON c.id = e.calendar_id
+        JOIN "user" u ON u.id = p.proposer
+        WHERE (c.owner_id = $1 OR p.proposer = $1)
+            AND p.status = $2
+            AND e.deleted_at IS NULL
+        ORDER BY p.created_at DESC
+        "#,
+        user_id,
+        status.unwrap_or_default() as _
+    )
+    .fetch_all(db)
+    .await?;
+
+    for proposal in &mut proposals {
+        proposal.event_id = occurrence_id(&proposal.event_id, proposal.recurrence_id);
+    }
+
+    Ok(proposals)
+}
+
+/// Accepts a pending proposal on the owner's calendar and moves the event, or its
+/// occurrence, accordingly. Returns the proposal as answered.
+pub async fn accept(
+    db: &PgPool,
+    user_id: &str,
+    proposal_id: &str,
+) -> Result<EventProposal, DbError> {
+    let mut tx = db.begin().await?;
+
+    let proposal = sqlx::query!(
+        r#"
+        SELECT
+            p.event_id,
+            p.recurrence_id,
+            p.proposed_start,
+            p.proposed_end,
+            p.proposed_location,
+            e.dtstart_time,
+            e.dtend_time
+        FROM event_counter_proposals p
+        JOIN calendar_events e ON e.id = p.event_id
+        JOIN calendars c ON c.id = e.calendar_id
+        WHERE p.id = $1 AND c.owner_id = $2 AND p.status = 'pending'
+            AND e.deleted_at IS NULL
+        FOR UPDATE OF p
+        "#,
+        proposal_id,
+        user_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| DbError::NotFound("Pending proposal not found".into()))?;
+
+    // A new start without an end keeps the event's length
+    let dtend_time = proposal.proposed_end.or_else(|| {
+        let start = proposal.proposed_start?;
+        Some(start + (proposal.dtend_time? - proposal.dtstart_time))
+    });
+    let request = EventUpdateRequest {
+        scope: EditScope::ThisOnly,
+        // The owner accepts knowing their calendar
+        allow_conflicts: true,
+        updates: EventUpdate {
+            description: None,
+            attendee: None,
+            location: proposal.proposed_location,
+            dtstart_time: proposal.proposed_start,
+            dtend_time,
+            dtstart_tz: None,
+            dtend_tz: None,
+            rrule: None,
+        },
+    };
+    update_in(
+        &mut tx,
+        occurrence_id(&proposal.event_id, proposal.recurrence_id),
+        &request,
+    )
+    .await?;
+
+    let proposal = respond(&mut tx, proposal_id, ProposalStatus::Accepted).await?;
+    tx.commit().await?;
+    Ok(proposal)
+}
+
+/// Declines a pending proposal on the owner's calendar, the event stays as it is
+pub async fn decline(
+    db: &PgPool,
+    user_id: &str,
+    proposal_id: &str,
+) -> Result<EventProposal, DbError> {
+    let mut tx = db.begin().await?;
+
+    sqlx::query_scalar!(
+        r#"
+        SELECT p.id
+        FROM event_counter_proposals p
+        JOIN calendar_events e ON e.id = p.event_id
+        JOIN calendars c ON c.id = e.calendar_id
+        WHERE p.id = $1 AND c.owner_id = $2 AND p.status = 'pending'
+        FOR UPDATE OF p
+        "#,
+        proposal_id,
+        user_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| DbError::NotFound("Pending proposal not found".into()))?;
+
+    let proposal = respond(&mut tx, proposal_id, ProposalStatus::Declined).await?;
+    tx.commit().await?;
+    Ok(proposal)
+}
+
+async fn respond(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    proposal_id: &str,
+    status: ProposalStatus,
+) -> Result<EventProposal, DbError> {
+    let mut proposal = sqlx::query_as!(
+        EventProposal,
+        r#"
+        UPDATE event_counter_proposals p
+        SET status = $2, responded_at = NOW()
+        FROM calendar_events e, "user" u
+        WHERE p.id = $1 AND e.id = p.event_id AND u.id = p.proposer
+        RETURNING
+            p.id,
+            p.event_id,
+            e.summary,
+            p.proposer,
+            u.name AS proposer_name,
+            p.recurrence_id,
+            p.proposed_start,
+            p.proposed_end,
+            p.proposed_location,
+            p.comment,
+            p.status AS "status!: ProposalStatus",
+            p.created_at AS "created_at!",
+            p.responded_at
+        "#,
+        proposal_id,
+        status as _
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+
+    proposal.event_id = occurrence_id(&proposal.event_id, proposal.recurrence_id);
+    Ok(proposal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{core::calendar::event::import, tests::create_test_user};
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+    use ogonek_types::{
+        AttendeeImport, EventAttendeeRole, EventAttendeeStatus, EventClass, EventImport,
+        EventStatus, EventTransp,
+    };
+
+    fn lesson(start: DateTime<Utc>) -> EventImport {
+        EventImport {
+            uid: "weekly@example.com".to_string(),
+            summary: "Spanish".to_string(),
+            description: None,
+            location: None,
+            url: None,
+            dtstart_time: start,
+            dtend_time: Some(start + Duration::hours(1)),
+            dtstart_tz: None,
+            rrule: Some("FREQ=WEEKLY;COUNT=3".to_string()),
+            rdate: None,
+            exdate: None,
+            recurrence_id: None,
+            status: EventStatus::Confirmed,
+            class: EventClass::Public,
+            transp: EventTransp::Opaque,
+            priority: None,
+            categories: None,
+            sequence: 0,
+            attendees: vec![AttendeeImport {
+                email: "student@example.com".to_string(),
+                name: None,
+                role: EventAttendeeRole::ReqParticipant,
+                status: EventAttendeeStatus::Accepted,
+            }],
+        }
+    }
+
+    async fn master_id(db: &PgPool) -> String {
+        sqlx::query_scalar!(
+            "SELECT id FROM calendar_events WHERE uid = 'weekly@example.com' AND recurrence_id IS NULL"
+        )
+        .fetch_one(db)
+        .await
+        .unwrap()
+    }
+
+    fn new_time(start: DateTime<Utc>) -> EventProposalCreate {
+        EventProposalCreate {
+            proposed_start: Some(start),
+            proposed_end: None,
+            proposed_location: None,
+            comment: Some("Can we move it?".to_string()),
+        }
+    }
+
+    #[sqlx::test]
+    async fn test_accept_moves_occurrence(db: PgPool) {
+        let teacher = create_test_user(&db, "teacher", "teacher@example.com").await;
+        let student = create_test_user(&db, "student", "student@example.com").await;
+        let start = Utc.with_ymd_and_hms(2025, 3, 3, 15, 0, 0).unwrap();
+        import(&db, &teacher, &[lesson(start)]).await.unwrap();
+        let master = master_id(&db).await;
+
+        let occurrence = start + Duration::weeks(1);
+        let occurrence_id = occurrence_id(&master, Some(occurrence));
+        let moved = occurrence + Duration::hours(2);
+        create(&db, &student, &occurrence_id, &new_time(moved))
+            .await
+            .unwrap();
+
+        // One pending proposal per occurrence
+        let twice = create(&db, &student, &occurrence_id, &new_time(moved)).await;
+        assert!(matches!(twice, Err(DbError::AlreadyExists(_))));
+
+        let pending = read_all(&db, &teacher, None).await.unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].event_id, occurrence_id);
+        assert_eq!(read_all(&db, &student, None).await.unwrap().len(), 1);
+
+        // Only the calendar owner answers
+        let by_student = accept(&db, &student, &pending[0].id).await;
+        assert!(matches!(by_student, Err(DbError::NotFound(_))));
+
+        let accepted = accept(&db, &teacher, &pending[0].id).await.unwrap();
+        assert_eq!(accepted.status, ProposalStatus::Accepted);
+        assert_eq!(accepted.proposer, student);
+        assert!(accepted.responded_at.is_some());
+
+        let exception = sqlx::query!(
+            r#"
+            SELECT dtstart_time, dtend_time, sequence
+            FROM calendar_events
+            WHERE recurrence_id = $1
+            "#,
+            occurrence
+        )
+        .fetch_one(&db)
+        .await
+        .unwrap();
+        assert_eq!(exception.dtstart_time, moved);
+        assert_eq!(exception.dtend_time, Some(moved + Duration::hours(1)));
+        assert_eq!(exception.sequence, 1);
+
+        let again = accept(&db, &teacher, &accepted.id).await;
+        assert!(matches!(again, Err(DbError::NotFound(_))));
+        assert!(read_all(&db, &teacher, None).await.unwrap().is_empty());
+    }
+
+    #[sqlx::test]
+    async fn test_decline_keeps_event(db: PgPool) {
+        let teacher = create_test_user(&db, "teacher", "teacher@example.com").await;
+        let student = create_test_user(&db, "student", "student@example.com").await;
+        let stranger = create_test_user(&db, "stranger", "stranger@example.com").await;
+        let start = Utc.with_ymd_and_hms(2025, 3, 3, 15, 0, 0).unwrap();
+        import(&db, &teacher, &[lesson(start)]).await.unwrap();
+        let master = master_id(&db).await;
+
+        // Only attendees propose
+        let by_stranger = create(&db, &stranger, &master, &new_time(start)).await;
+        assert!(matches!(by_stranger, Err(DbError::NotFound(_))));
+
+        // A proposal on the series targets its first occurrence
+        let proposal_id = create(&db, &student, &master, &new_time(start + Duration::days(1)))
+            .await
+            .unwrap();
+        let declined = decline(&db, &teacher, &proposal_id).await.unwrap();
+        assert_eq!(declined.status, ProposalStatus::Declined);
+        assert_eq!(declined.recurrence_id, Some(start));
+
+        let events = sqlx::query_scalar!("SELECT COUNT(*) FROM calendar_events")
+            .fetch_one(&db)
+            .await
+            .unwrap();
+        assert_eq!(events, Some(1));
+
+        let history = read_all(&db, &student, Some(ProposalStatus::Declined))
+            .await
+            .unwrap();
+        assert_eq!(history.len(), 1);
+    }
+}
@@ -0,0 +1,451 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, Duration, Utc};
+use sqlx::{PgConnection, PgPool};
+
+use crate::{DbError, crud::core::calendar::event::expand_master, helpers::OCCURRENCE_SEPARATOR};
+
+use ogonek_types::{BusyPeriod, EventDB, EventStatus};
+
+/// A span of time, start included and end excluded
+pub(in crate::crud::core::calendar) type Period = (DateTime<Utc>, DateTime<Utc>);
+
+/// Reads the busy time across the user's calendars within the range, sorted by start
+pub async fn read(
+    db: &PgPool,
+    user_id: &str,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Vec<BusyPeriod>, DbError> {
+    let mut conn = db.acquire().await?;
+    let mut busy = read_busy(&mut conn, user_id, start, end).await?;
+    busy.sort_by_key(|period| period.start);
+    Ok(busy)
+}
+
+/// The event being moved on the calendar checked, so that it never clashes with itself
+pub(in crate::crud::core::calendar) struct Moving<'a> {
+    pub uid: &'a str,
+    /// Only this occurrence moves, the rest of the series still takes its time
+    pub occurrence: Option<DateTime<Utc>>,
+}
+
+impl Moving<'_> {
+    fn is(&self, calendar_id: &str, period: &BusyPeriod) -> bool {
+        period.calendar_id == calendar_id
+            && period.uid == self.uid
+            && self
+                .occurrence
+                .is_none_or(|occurrence| period.recurrence_id == Some(occurrence))
+    }
+}
+
+/// Fails with the busy time of the calendar's owner overlapping any of the spans, leaving
+/// out the event being moved
+pub(in crate::crud::core::calendar) async fn check_conflicts(
+    conn: &mut PgConnection,
+    calendar_id: &str,
+    spans: &[Period],
+    moving: Option<Moving<'_>>,
+) -> Result<(), DbError> {
+    let (Some(start), Some(end)) = (
+        spans.iter().map(|span| span.0).min(),
+        spans.iter().map(|span| span.1).max(),
+    ) else {
+        return Ok(());
+    };
+
+    let owner_id = sqlx::query_scalar!("SELECT owner_id FROM calendars WHERE id = $1", calendar_id)
+        .fetch_one(&mut *conn)
+        .await?;
+    let mut conflicts: Vec<_> = read_busy(conn, &owner_id, start, end)
+        .await?
+        .into_iter()
+        .filter(|period| {
+            !moving
+                .as_ref()
+                .is_some_and(|moving| moving.is(calendar_id, period))
+        })
+        .filter(|period| {
+            spans
+                .iter()
+                .any(|&(start, end)| period.overlaps(start, end))
+        })
+        .collect();
+    if conflicts.is_empty() {
+        return Ok(());
+    }
+    conflicts.sort_by_key(|period| period.start);
+    Err(DbError::Conflict(conflicts))
+}
+
+/// Opaque time on every calendar the user owns within the range. Masters are expanded
+/// like in `event::read_all`, their exceptions standing in for the occurrences they replace.
+pub(in crate::crud::core::calendar) async fn read_busy(
+    conn: &mut PgConnection,
+    owner_id: &str,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Vec<BusyPeriod>, DbError> {
+    let events = sqlx::query_as!(
+        EventDB,
+        r#"
+        SELECT
+            id,
+            uid,
+            calendar_id,
+            summary,
+            location,
+            dtstart_time,
+            dtend_time,
+            dtstart_tz,
+            rdate,
+            status AS "status!: EventStatus",
+            exdate,
+            recurrence_id,
+            rrule
+        FROM calendar_events
+        WHERE calendar_id IN (SELECT id FROM calendars WHERE owner_id = $1)
+            AND deleted_at IS NULL
+            AND transp = 'opaque'
+            AND status != 'cancelled'
+            AND (
+                (rrule IS NOT NULL AND recurrence_id IS NULL)
+            OR (dtstart_time < $3 AND COALESCE(dtend_time, dtstart_time) >= $2)
+            )
+        "#,
+        owner_id,
+        start,
+        end
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    // Moved or cancelled occurrences are replaced by their exception, whatever it says.
+    // UIDs are only unique within a calendar.
+    let exceptions: HashSet<(String, String, DateTime<Utc>)> = sqlx::query!(
+        r#"
+        SELECT calendar_id, uid, recurrence_id AS "recurrence_id!"
+        FROM calendar_events
+        WHERE calendar_id IN (SELECT id FROM calendars WHERE owner_id = $1)
+            AND recurrence_id IS NOT NULL
+            AND deleted_at IS NULL
+        "#,
+        owner_id
+    )
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .map(|row| (row.calendar_id, row.uid, row.recurrence_id))
+    .collect();
+
+    let mut busy = Vec::new();
+    for event in &events {
+        let duration = event
+            .dtend_time
+            .map_or(Duration::zero(), |end| end - event.dtstart_time);
+        if event.recurrence_id.is_none()
+            && let Some(occurrences) = expand_master(event, start - duration, end)?
+        {
+            busy.extend(
+                occurrences
+                    .into_iter()
+                    .filter(|occurrence| {
+                        !exceptions.contains(&(
+                            event.calendar_id.clone(),
+                            event.uid.clone(),
+                            *occurrence,
+                        ))
+                    })
+                    .map(|occurrence| BusyPeriod {
+                        event_id: format!(
+                            "{}{}{}",
+                            event.id,
+                            OCCURRENCE_SEPARATOR,
+                            occurrence.timestamp()
+                        ),
+                        uid: event.uid.clone(),
+                        calendar_id: event.calendar_id.clone(),
+                        recurrence_id: Some(occurrence),
+                        summary: event.summary.clone(),
+                        start: occurrence,
+                        end: occurrence + duration,
+                    }),
+            );
+        } else {
+            busy.push(BusyPeriod {
+                event_id: event.id.clone(),
+                uid: event.uid.clone(),
+                calendar_id: event.calendar_id.clone(),
+                recurrence_id: event.recurrence_id,
+                summary: event.summary.clone(),
+                start: event.dtstart_time,
+                end: event.dtstart_time + duration,
+            });
+        }
+    }
+
+    Ok(busy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::calendar::{
+            cal,
+            event::{create, import, update},
+        },
+        tests::create_test_user,
+    };
+    use chrono::TimeZone;
+    use ogonek_types::{
+        CalendarCreate, EditScope, EventClass, EventCreate, EventImport, EventTransp, EventUpdate,
+        EventUpdateRequest,
+    };
+
+    fn event(uid: &str, start: DateTime<Utc>) -> EventImport {
+        EventImport {
+            uid: uid.to_string(),
+            summary: "Spanish".to_string(),
+            description: None,
+            location: None,
+            url: None,
+            dtstart_time: start,
+            dtend_time: Some(start + Duration::hours(1)),
+            dtstart_tz: None,
+            rrule: None,
+            rdate: None,
+            exdate: None,
+            recurrence_id: None,
+            status: EventStatus::Confirmed,
+            class: EventClass::Public,
+            transp: EventTransp::Opaque,
+            priority: None,
+            categories: None,
+            sequence: 0,
+            attendees: vec![],
+        }
+    }
+
+    fn move_to(start: DateTime<Utc>, allow_conflicts: bool) -> EventUpdateRequest {
+        EventUpdateRequest {
+            scope: EditScope::ThisOnly,
+            allow_conflicts,
+            updates: EventUpdate {
+                description: None,
+                attendee: None,
+                location: None,
+                dtstart_time: Some(start),
+                dtend_time: Some(start + Duration::hours(1)),
+                dtstart_tz: None,
+                dtend_tz: None,
+                rrule: None,
+            },
+        }
+    }
+
+    #[sqlx::test]
+    async fn test_busy_time_follows_exceptions(db: PgPool) {
+        let user_id = create_test_user(&db, "teacher", "teacher@example.com").await;
+        let start = Utc.with_ymd_and_hms(2025, 3, 3, 15, 0, 0).unwrap();
+
+        let mut weekly = event("weekly@example.com", start);
+        weekly.rrule = Some("FREQ=WEEKLY;COUNT=3".to_string());
+        let mut moved = event(
+            "weekly@example.com",
+            start + Duration::weeks(1) + Duration::hours(2),
+        );
+        moved.recurrence_id = Some(start + Duration::weeks(1));
+        let mut free = event("free@example.com", start + Duration::days(1));
+        free.transp = EventTransp::Transparent;
+        import(&db, &user_id, &[weekly, moved, free]).await.unwrap();
+
+        let busy = read(&db, &user_id, start, start + Duration::weeks(3))
+            .await
+            .unwrap();
+        let starts: Vec<_> = busy.iter().map(|period| period.start).collect();
+        assert_eq!(
+            starts,
+            [
+                start,
+                start + Duration::weeks(1) + Duration::hours(2),
+                start + Duration::weeks(2)
+            ]
+        );
+        assert!(busy[0].event_id.contains(OCCURRENCE_SEPARATOR));
+    }
+
+    #[sqlx::test]
+    async fn test_conflicts_on_create_and_update(db: PgPool) {
+        let user_id = create_test_user(&db, "teacher", "teacher@example.com").await;
+        let student = create_test_user(&db, "student", "student@example.com").await;
+        let start = Utc.with_ymd_and_hms(2025, 3, 3, 15, 0, 0).unwrap();
+        import(&db, &user_id, &[event("lesson@example.com", start)])
+            .await
+            .unwrap();
+
+        let clashing = |allow_conflicts| EventCreate {
+            attendee: student.clone(),
+            calendar_id: None,
+            dtstart_time: start + Duration::minutes(30),
+            dtend_time: Some(start + Duration::minutes(90)),
+            allow_conflicts,
+        };
+        match create(&db, &user_id, clashing(false)).await {
+            Err(DbError::Conflict(conflicts)) => {
+                assert_eq!(conflicts.len(), 1);
+                assert_eq!(conflicts[0].start, start);
+            }
+            other => panic!("expected a conflict, got {other:?}"),
+        }
+        create(&db, &user_id, clashing(true)).await.unwrap();
+
+        // Back to back is fine
+        let after = EventCreate {
+            attendee: student.clone(),
+            calendar_id: None,
+            dtstart_time: start + Duration::hours(2),
+            dtend_time: Some(start + Duration::hours(3)),
+            allow_conflicts: false,
+        };
+        create(&db, &user_id, after).await.unwrap();
+
+        let later_id = sqlx::query_scalar!(
+            "SELECT id FROM calendar_events WHERE dtstart_time = $1",
+            start + Duration::hours(2)
+        )
+        .fetch_one(&db)
+        .await
+        .unwrap();
+        let moved = update(&db, &user_id, later_id.clone(), move_to(start, false)).await;
+        // Both the imported lesson and the forced one are in the way
+        assert!(matches!(moved, Err(DbError::Conflict(conflicts)) if conflicts.len() == 2));
+        update(
+            &db,
+            &user_id,
+            later_id.clone(),
+            move_to(start + Duration::minutes(90), false),
+        )
+        .await
+        .unwrap();
+        update(&db, &user_id, later_id, move_to(start, true))
+            .await
+            .unwrap();
+    }
+
+    #[sqlx::test]
+    async fn test_busy_time_across_calendars(db: PgPool) {
+        let user_id = create_test_user(&db, "teacher", "teacher@example.com").await;
+        let student = create_test_user(&db, "student", "student@example.com").await;
+        let group = cal::create(
+            &db,
+            &user_id,
+            &CalendarCreate {
+                name: "Group classes".to_string(),
+                description: None,
+                colour: None,
+                timezone: None,
+            },
+        )
+        .await
+        .unwrap();
+
+        let start = Utc.with_ymd_and_hms(2025, 3, 3, 15, 0, 0).unwrap();
+        let lesson = |calendar_id: Option<&str>| EventCreate {
+            attendee: student.clone(),
+            calendar_id: calendar_id.map(str::to_string),
+            dtstart_time: start,
+            dtend_time: Some(start + Duration::hours(1)),
+            allow_conflicts: false,
+        };
+        create(&db, &user_id, lesson(Some(&group))).await.unwrap();
+
+        let busy = read(&db, &user_id, start, start + Duration::days(1))
+            .await
+            .unwrap();
+        assert_eq!(busy.len(), 1);
+        // A lesson on another calendar is in the way just the same
+        let clash = create(&db, &user_id, lesson(None)).await;
+        assert!(matches!(clash, Err(DbError::Conflict(_))));
+    }
+
+    #[sqlx::test]
+    async fn test_moved_occurrence_clashes_with_its_series(db: PgPool) {
+        let user_id = create_test_user(&db, "teacher", "teacher@example.com").await;
+        let student = create_test_user(&db, "student", "student@example.com").await;
+        let start = Utc.with_ymd_and_hms(2025, 3, 3, 15, 0, 0).unwrap();
+        let mut weekly = event("weekly@example.com", start);
+        weekly.rrule = Some("FREQ=WEEKLY;COUNT=3".to_string());
+        import(&db, &user_id, &[weekly]).await.unwrap();
+        let master_id =
+            sqlx::query_scalar!("SELECT id FROM calendar_events WHERE uid = 'weekly@example.com'")
+                .fetch_one(&db)
+                .await
+                .unwrap();
+        let occurrence = |week| {
+            let at: DateTime<Utc> = start + Duration::weeks(week);
+            format!("{master_id}{OCCURRENCE_SEPARATOR}{}", at.timestamp())
+        };
+
+        // The second lesson lands on the third
+        let moved = update(
+            &db,
+            &user_id,
+            occurrence(1),
+            move_to(start + Duration::weeks(2), false),
+        )
+        .await;
+        assert!(matches!(
+            moved,
+            Err(DbError::Conflict(conflicts))
+                if conflicts.len() == 1 && conflicts[0].start == start + Duration::weeks(2)
+        ));
+        // Moving within its own slot is fine
+        update(
+            &db,
+            &user_id,
+            occurrence(1),
+            move_to(start + Duration::weeks(1) + Duration::minutes(30), false),
+        )
+        .await
+        .unwrap();
+
+        // A copy of the lesson on another calendar is still in the way
+        let group = cal::create(
+            &db,
+            &user_id,
+            &CalendarCreate {
+                name: "Group classes".to_string(),
+                description: None,
+                colour: None,
+                timezone: None,
+            },
+        )
+        .await
+        .unwrap();
+        let later = start + Duration::days(1);
+        create(
+            &db,
+            &user_id,
+            EventCreate {
+                attendee: student,
+                calendar_id: Some(group.clone()),
+                dtstart_time: later,
+                dtend_time: Some(later + Duration::hours(1)),
+                allow_conflicts: false,
+            },
+        )
+        .await
+        .unwrap();
+        sqlx::query!(
+            "UPDATE calendar_events SET uid = 'weekly@example.com' WHERE calendar_id = $1",
+            group
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+        let moved = update(&db, &user_id, occurrence(0), move_to(later, false)).await;
+        assert!(matches!(moved, Err(DbError::Conflict(conflicts)) if conflicts.len() == 1));
+    }
+}
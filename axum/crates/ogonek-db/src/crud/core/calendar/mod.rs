@@ -1,3 +1,10 @@
+pub mod attendance;
+pub mod availability;
 pub mod cal;
+pub mod calendar_share;
+pub mod dav;
 pub mod event;
+pub mod event_alarm;
 pub mod event_attendee;
+pub mod event_proposal;
+pub mod freebusy;
@@ -9,7 +9,7 @@ pub async fn find_all(
     let cards = sqlx::query_as!(
         Card,
         r#"
-        SELECT id, front, back, media_url FROM cards
+        SELECT id, front, back, media_url, card_type, tags FROM cards
         WHERE deck_id = $1
         ORDER BY created_at DESC
         "#,
@@ -35,34 +35,40 @@ pub async fn batch_upsert(
     let mut fronts = Vec::with_capacity(cards.len());
     let mut backs = Vec::with_capacity(cards.len());
     let mut media_urls = Vec::with_capacity(cards.len());
+    let mut card_types = Vec::with_capacity(cards.len());
+    // Tags never contain spaces, so each card's tags travel as one space-separated string
+    let mut tags = Vec::with_capacity(cards.len());
 
     for card in cards {
         card_ids.push(card.id.unwrap_or_else(|| nanoid::nanoid!()));
         fronts.push(card.front);
         backs.push(card.back);
         media_urls.push(card.media_url);
+        card_types.push(card.card_type.to_string());
+        tags.push(normalize_tags(&card.tags).join(" "));
     }
 
     sqlx::query!(
         r#"
-        INSERT INTO cards (id, deck_id, front, back, media_url)
-        SELECT 
-            UNNEST($1::text[]),
-            $2,
-            UNNEST($3::text[]),
-            UNNEST($4::text[]),
-            UNNEST($5::text[])
+        INSERT INTO cards (id, deck_id, front, back, media_url, card_type, tags)
+        SELECT c.id, $2, c.front, c.back, c.media_url, c.card_type, string_to_array(c.tags, ' ')
+        FROM UNNEST($1::text[], $3::text[], $4::text[], $5::text[], $6::text[], $7::text[])
+            AS c(id, front, back, media_url, card_type, tags)
         ON CONFLICT (id) DO UPDATE SET
             front = EXCLUDED.front,
             back = EXCLUDED.back,
             deck_id = EXCLUDED.deck_id,
-            media_url = EXCLUDED.media_url
+            media_url = EXCLUDED.media_url,
+            card_type = EXCLUDED.card_type,
+            tags = EXCLUDED.tags
         "#,
         &card_ids,
         deck_id,
         &fronts,
         &backs,
-        &media_urls as &[Option<String>]
+        &media_urls as &[Option<String>],
+        &card_types,
+        &tags
     )
     .execute(executor)
     .await?;
@@ -70,6 +76,23 @@ pub async fn batch_upsert(
     Ok(())
 }
 
+/// Lowercased and deduplicated, with inner whitespace turned into underscores
+pub fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = tags
+        .iter()
+        .map(|tag| {
+            tag.split_whitespace()
+                .collect::<Vec<_>>()
+                .join("_")
+                .to_lowercase()
+        })
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    normalized.sort();
+    normalized.dedup();
+    normalized
+}
+
 /// Deletes cards by deck ID. Used in the update deck implementation
 pub async fn delete_cards(
     executor: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
@@ -31,6 +31,23 @@ pub(crate) async fn create(
     Ok(id)
 }
 
+/// Creates a deck together with its cards, used by imports
+pub async fn create_with_cards(
+    db: &PgPool,
+    user_id: &str,
+    deck: DeckCreate,
+    cards: Vec<CardUpsert>,
+) -> Result<String, DbError> {
+    let mut tx = db.begin().await?;
+
+    let id = create(&mut *tx, user_id, deck).await?;
+    card::batch_upsert(&mut *tx, &id, cards).await?;
+
+    tx.commit().await?;
+
+    Ok(id)
+}
+
 /// Creates a copy of a deck
 pub async fn duplicate(db: &PgPool, user_id: &str, deck_id: &str) -> Result<String, DbError> {
     let mut tx = db.begin().await?;
@@ -53,6 +70,8 @@ pub async fn duplicate(db: &PgPool, user_id: &str, deck_id: &str) -> Result<Stri
             front: card.front,
             back: card.back,
             media_url: card.media_url,
+            card_type: card.card_type,
+            tags: card.tags,
         })
         .collect();
 
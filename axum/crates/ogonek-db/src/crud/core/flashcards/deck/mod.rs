@@ -4,6 +4,8 @@ pub use create::*;
 pub use read::*;
 mod delete;
 pub use delete::*;
+mod search;
+pub use search::*;
 mod update;
 pub use update::*;
 
@@ -15,7 +17,9 @@ mod tests {
         core::flashcards::card::{self, batch_upsert},
         tests::create_test_user,
     };
-    use ogonek_types::{CardUpsert, DeckCreate, DeckUpdate, DeckWithCardsUpdate, Visibility};
+    use ogonek_types::{
+        CardFilter, CardType, CardUpsert, DeckCreate, DeckUpdate, DeckWithCardsUpdate, Visibility,
+    };
     use sqlx::PgPool;
 
     // Helper function to create a test deck
@@ -123,6 +127,46 @@ mod tests {
         }
     }
 
+    #[sqlx::test]
+    async fn test_create_with_cards(db: PgPool) {
+        let user_id = create_test_user(&db, "testuser", "test@example.com").await;
+
+        let deck_create = DeckCreate {
+            title: "Imported Deck".to_string(),
+            description: None,
+            visibility: None,
+            assignee: None,
+        };
+        let cards = vec![
+            CardUpsert {
+                id: None,
+                front: "Hola".to_string(),
+                back: "Hello".to_string(),
+                media_url: None,
+                card_type: CardType::Basic,
+                tags: vec![],
+            },
+            CardUpsert {
+                id: None,
+                front: "Gato".to_string(),
+                back: "Cat".to_string(),
+                media_url: Some("https://scaleway.bucket.com/images/cat.jpg".to_string()),
+                card_type: CardType::Basic,
+                tags: vec![],
+            },
+        ];
+
+        let deck_id = create_with_cards(&db, &user_id, deck_create, cards)
+            .await
+            .unwrap();
+
+        let deck = read_deck_with_cards(&db, &deck_id, &user_id).await.unwrap();
+        assert_eq!(deck.deck.title, "Imported Deck");
+        assert_eq!(deck.cards.len(), 2);
+        assert!(deck.cards.iter().any(|c| c.front == "Gato"
+            && c.media_url.as_deref() == Some("https://scaleway.bucket.com/images/cat.jpg")));
+    }
+
     #[sqlx::test]
     async fn test_duplicate_preserves_all_card_properties(db: PgPool) {
         let user_id = create_test_user(&db, "testuser", "test@example.com").await;
@@ -145,18 +189,24 @@ mod tests {
                 front: "Simple text front".to_string(),
                 back: "Simple text back".to_string(),
                 media_url: None,
+                card_type: CardType::Basic,
+                tags: vec![],
             },
             CardUpsert {
                 id: None,
                 front: "Front with <b>HTML</b>".to_string(),
                 back: "Back with **markdown**".to_string(),
                 media_url: Some("https://scaleway.bucket.com/images/card.jpg".to_string()),
+                card_type: CardType::Basic,
+                tags: vec![],
             },
             CardUpsert {
                 id: None,
                 front: "Special chars: 'quotes' & symbols!".to_string(),
                 back: "Unicode: こんにちは 🚀".to_string(),
                 media_url: Some("https://scaleway.bucket.com/audio/pronunciation.mp3".to_string()),
+                card_type: CardType::Basic,
+                tags: vec![],
             },
         ];
 
@@ -320,6 +370,9 @@ mod tests {
                 unassign: None,
                 visibility: Some(Visibility::Public),
                 assignee: None,
+                scheduler: None,
+                new_cards_per_day: None,
+                reviews_per_day: None,
             },
             cards: vec![], // No cards to update
         };
@@ -350,6 +403,9 @@ mod tests {
                 visibility: None,
                 unassign: None,
                 assignee: None,
+                scheduler: None,
+                new_cards_per_day: None,
+                reviews_per_day: None,
             },
             cards: vec![
                 CardUpsert {
@@ -357,12 +413,16 @@ mod tests {
                     front: "Question 1".to_string(),
                     back: "Answer 1".to_string(),
                     media_url: None,
+                    card_type: CardType::Basic,
+                    tags: vec![],
                 },
                 CardUpsert {
                     id: None, // New card
                     front: "Question 2".to_string(),
                     back: "Answer 2".to_string(),
                     media_url: Some("http://example.com/image.jpg".to_string()),
+                    card_type: CardType::Basic,
+                    tags: vec![],
                 },
             ],
         };
@@ -398,12 +458,17 @@ mod tests {
                 unassign: None,
                 visibility: None,
                 assignee: None,
+                scheduler: None,
+                new_cards_per_day: None,
+                reviews_per_day: None,
             },
             cards: vec![CardUpsert {
                 id: Some(card_ids[0].clone()),
                 front: "Updated Q1".to_string(),
                 back: "Updated A1".to_string(),
                 media_url: None,
+                card_type: CardType::Basic,
+                tags: vec![],
             }],
         };
 
@@ -413,4 +478,112 @@ mod tests {
         let card_count = count_cards_in_deck(&db, &deck_id).await.unwrap();
         assert_eq!(card_count, 1);
     }
+
+    fn tagged_card(front: &str, back: &str, tags: &[&str]) -> CardUpsert {
+        CardUpsert {
+            id: None,
+            front: front.to_string(),
+            back: back.to_string(),
+            media_url: None,
+            card_type: CardType::Basic,
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        }
+    }
+
+    #[sqlx::test]
+    async fn test_batch_upsert_normalizes_tags(db: PgPool) {
+        let user_id = create_test_user(&db, "testuser", "test@example.com").await;
+        let deck_id = create_test_deck(&db, &user_id, "Deck", None, None, None)
+            .await
+            .unwrap();
+
+        batch_upsert(
+            &db,
+            &deck_id,
+            vec![
+                tagged_card(
+                    "Hablar",
+                    "To speak",
+                    &["Verbs", "verbs", " irregular past "],
+                ),
+                tagged_card("Casa", "House", &[]),
+            ],
+        )
+        .await
+        .unwrap();
+
+        let cards = card::find_all(&db, &deck_id).await.unwrap();
+        let hablar = cards.iter().find(|c| c.front == "Hablar").unwrap();
+        let casa = cards.iter().find(|c| c.front == "Casa").unwrap();
+        assert_eq!(hablar.tags, ["irregular_past", "verbs"]);
+        assert!(casa.tags.is_empty());
+    }
+
+    #[sqlx::test]
+    async fn test_search_cards(db: PgPool) {
+        let user_id = create_test_user(&db, "testuser", "test@example.com").await;
+        let other_id = create_test_user(&db, "other", "other@example.com").await;
+        let deck_id = create_test_deck(&db, &user_id, "Spanish", None, None, None)
+            .await
+            .unwrap();
+        let foreign_deck_id = create_test_deck(&db, &other_id, "Other", None, None, None)
+            .await
+            .unwrap();
+
+        batch_upsert(
+            &db,
+            &deck_id,
+            vec![
+                tagged_card("Hablar", "To speak", &["verbs"]),
+                tagged_card("Comer", "To eat", &["verbs", "food"]),
+                tagged_card("Casa", "House", &["nouns"]),
+            ],
+        )
+        .await
+        .unwrap();
+        batch_upsert(
+            &db,
+            &foreign_deck_id,
+            vec![tagged_card("Beber", "To drink", &["verbs"])],
+        )
+        .await
+        .unwrap();
+
+        let filter = CardFilter {
+            tags: vec!["verbs".into()],
+            ..Default::default()
+        };
+        let (cards, count) = search_cards(&db, &user_id, &filter, 20, 0).await.unwrap();
+        assert_eq!(count, 2);
+        assert!(cards.iter().all(|c| c.deck_title == "Spanish"));
+
+        let filter = CardFilter {
+            text: Some("eat".into()),
+            ..Default::default()
+        };
+        let (cards, count) = search_cards(&db, &user_id, &filter, 20, 0).await.unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(cards[0].front, "Comer");
+
+        // Subscribing brings another deck's cards into reach
+        sqlx::query!(
+            "INSERT INTO deck_subscriptions (deck_id, user_id) VALUES ($1, $2)",
+            foreign_deck_id,
+            user_id
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+
+        let filter = CardFilter {
+            tags: vec!["verbs".into()],
+            ..Default::default()
+        };
+        let (_, count) = search_cards(&db, &user_id, &filter, 20, 0).await.unwrap();
+        assert_eq!(count, 3);
+
+        let (cards, count) = search_cards(&db, &user_id, &filter, 1, 1).await.unwrap();
+        assert_eq!(count, 3);
+        assert_eq!(cards.len(), 1);
+    }
 }
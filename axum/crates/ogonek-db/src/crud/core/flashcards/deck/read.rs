@@ -1,6 +1,8 @@
 use crate::{DbError, core::flashcards::card};
 
-use ogonek_types::{DeckFull, DeckPaginationParams, DeckPublic, DeckSmall, DeckWithCards};
+use ogonek_types::{
+    DeckFull, DeckPaginationParams, DeckPublic, DeckSmall, DeckWithCards, SchedulerKind,
+};
 use sqlx::PgPool;
 /// Builds a query based on page number, size, assignee ID
 pub async fn read_all(
@@ -156,6 +158,9 @@ pub async fn read_deck(
             d.created_by,
             d.created_at,
             d.card_count,
+            d.scheduler as "scheduler: SchedulerKind",
+            d.new_cards_per_day,
+            d.reviews_per_day,
             EXISTS (
                 SELECT 1 FROM deck_subscriptions
                 WHERE deck_id = d.id AND user_id = $2
@@ -0,0 +1,107 @@
+use crate::DbError;
+
+use ogonek_types::{CardFilter, CardSearchResult};
+use sqlx::PgPool;
+
+/// Searches cards across the decks a user owns, is assigned or subscribed to.
+/// Text matches are ranked first, otherwise the newest cards come first
+pub async fn search_cards(
+    db: &PgPool,
+    user_id: &str,
+    filter: &CardFilter,
+    limit: i64,
+    offset: i64,
+) -> Result<(Vec<CardSearchResult>, i64), DbError> {
+    let mut query_builder = sqlx::QueryBuilder::new(
+        r#"SELECT
+            c.id,
+            c.deck_id,
+            d.title AS deck_title,
+            c.front,
+            c.back,
+            c.media_url,
+            c.card_type,
+            c.tags
+        FROM cards c
+        JOIN decks d ON d.id = c.deck_id"#,
+    );
+    push_filters(&mut query_builder, user_id, filter);
+
+    query_builder.push(" ORDER BY ");
+    if let Some(text) = &filter.text {
+        query_builder.push("ts_rank(c.search_vector, websearch_to_tsquery('simple', ");
+        query_builder.push_bind(text);
+        query_builder.push(")) DESC, ");
+    }
+    query_builder.push("c.created_at DESC, c.id");
+    query_builder.push(" LIMIT ");
+    query_builder.push_bind(limit);
+    query_builder.push(" OFFSET ");
+    query_builder.push_bind(offset);
+
+    let cards = query_builder
+        .build_query_as::<CardSearchResult>()
+        .fetch_all(db)
+        .await?;
+
+    let mut count_query =
+        sqlx::QueryBuilder::new("SELECT COUNT(*) FROM cards c JOIN decks d ON d.id = c.deck_id");
+    push_filters(&mut count_query, user_id, filter);
+
+    let total: (i64,) = count_query.build_query_as().fetch_one(db).await?;
+    Ok((cards, total.0))
+}
+
+fn push_filters<'a>(
+    query_builder: &mut sqlx::QueryBuilder<'a, sqlx::Postgres>,
+    user_id: &'a str,
+    filter: &'a CardFilter,
+) {
+    query_builder.push(" WHERE (d.created_by = ");
+    query_builder.push_bind(user_id);
+    query_builder.push(" OR d.assignee = ");
+    query_builder.push_bind(user_id);
+    query_builder.push(
+        " OR EXISTS (SELECT 1 FROM deck_subscriptions ds WHERE ds.deck_id = d.id AND ds.user_id = ",
+    );
+    query_builder.push_bind(user_id);
+    query_builder.push("))");
+
+    if let Some(deck_id) = &filter.deck_id {
+        query_builder.push(" AND c.deck_id = ");
+        query_builder.push_bind(deck_id);
+    }
+
+    if !filter.tags.is_empty() {
+        query_builder.push(" AND c.tags @> ");
+        query_builder.push_bind(&filter.tags);
+    }
+
+    if let Some(text) = &filter.text {
+        query_builder.push(" AND c.search_vector @@ websearch_to_tsquery('simple', ");
+        query_builder.push_bind(text);
+        query_builder.push(")");
+    }
+
+    if let Some(days) = filter.failed_within_days {
+        query_builder.push(
+            " AND EXISTS (SELECT 1 FROM card_reviews cr WHERE cr.card_id = c.id AND cr.user_id = ",
+        );
+        query_builder.push_bind(user_id);
+        query_builder.push(
+            " AND cr.quality < 3 AND cr.reviewed_at >= CURRENT_TIMESTAMP - make_interval(days => ",
+        );
+        query_builder.push_bind(days);
+        query_builder.push("))");
+    }
+
+    if let Some(state) = filter.state {
+        query_builder.push(
+            " AND EXISTS (SELECT 1 FROM card_progress cp WHERE cp.card_id = c.id AND cp.user_id = ",
+        );
+        query_builder.push_bind(user_id);
+        query_builder.push(" AND cp.state = ");
+        query_builder.push_bind(state.to_string());
+        query_builder.push(")");
+    }
+}
@@ -49,7 +49,10 @@ async fn update_deck_solo(
             WHEN $7 = true THEN NULL
             ELSE
             COALESCE($4, assignee)
-            END
+            END,
+            scheduler = COALESCE($8, scheduler),
+            new_cards_per_day = COALESCE($9, new_cards_per_day),
+            reviews_per_day = COALESCE($10, reviews_per_day)
          WHERE id = $5 AND created_by = $6",
         update.deck.title,
         update.deck.description,
@@ -57,7 +60,10 @@ async fn update_deck_solo(
         update.deck.assignee,
         deck_id,
         user_id,
-        update.deck.unassign
+        update.deck.unassign,
+        update.deck.scheduler.map(|s| s.to_string()),
+        update.deck.new_cards_per_day,
+        update.deck.reviews_per_day
     )
     .execute(executor)
     .await?;
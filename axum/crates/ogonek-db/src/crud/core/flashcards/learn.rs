@@ -1,55 +1,198 @@
-use crate::DbError;
+use crate::{DbError, core::flashcards::review};
 use sqlx::PgPool;
 
-use ogonek_types::{CardProgress, CardProgressWithFields, UpdateCardProgress};
-pub async fn fetch_due(db: &PgPool, user_id: &str) -> Result<Vec<CardProgressWithFields>, DbError> {
-    let due = sqlx::query_as!(
-        CardProgressWithFields,
+use ogonek_types::{
+    CardFilter, CardProgress, CardProgressWithFields, CardReviewCreate, SchedulerSettings,
+    UpdateCardProgress,
+};
+
+/// The study queue as a `queue` CTE, shared by `fetch_due` and `fetch_due_count` so the
+/// daily limits are only spelled out once. `query!` only takes literals, hence the
+/// runtime queries. Binds: user id, deck id, custom filter, tags, failed within days,
+/// state, search text.
+const DUE_QUEUE: &str = r#"
+WITH limits AS (
+    SELECT
+        COALESCE(up.new_cards_per_day, 20) AS new_limit,
+        COALESCE(up.reviews_per_day, 200) AS review_limit,
+        -- Limits roll over at midnight where the user lives
+        date_trunc('day', CURRENT_TIMESTAMP AT TIME ZONE user_timezone(u.user_id))
+            AT TIME ZONE user_timezone(u.user_id) AS day_start
+    FROM (SELECT $1::VARCHAR AS user_id) u
+    LEFT JOIN user_preferences up ON up.user_id = u.user_id
+),
+today AS (
+    SELECT
+        c.deck_id,
+        COUNT(*) FILTER (
+            WHERE cp.introduced_at >= l.day_start
+        ) AS new_done,
+        COUNT(*) FILTER (
+            WHERE cp.introduced_at < l.day_start
+        ) AS reviews_done
+    FROM card_progress cp
+    JOIN cards c ON c.id = cp.card_id
+    CROSS JOIN limits l
+    WHERE cp.user_id = $1
+        AND cp.last_reviewed >= l.day_start
+    GROUP BY c.deck_id
+),
+due AS (
+    SELECT
+        cp.id,
+        c.front,
+        c.back,
+        c.media_url,
+        c.card_type,
+        cp.ordinal,
+        cp.due_date,
+        cp.state,
+        c.deck_id,
+        ROW_NUMBER() OVER (
+            PARTITION BY c.deck_id, cp.state ORDER BY cp.due_date, cp.id
+        ) AS deck_rank
+    FROM card_progress cp
+    JOIN cards c ON c.id = cp.card_id
+    WHERE cp.user_id = $1
+        AND (cp.due_date <= CURRENT_TIMESTAMP OR cp.due_date IS NULL OR $3)
+        AND ($2::VARCHAR IS NULL OR c.deck_id = $2)
+        AND c.tags @> $4::TEXT[]
+        AND ($5::INT IS NULL OR EXISTS (
+            SELECT 1 FROM card_reviews cr
+            WHERE cr.user_id = $1 AND cr.card_id = c.id AND cr.quality < 3
+                AND cr.reviewed_at >= CURRENT_TIMESTAMP - make_interval(days => $5)
+        ))
+        AND ($6::VARCHAR IS NULL OR cp.state = $6)
+        AND ($7::TEXT IS NULL OR c.search_vector @@ websearch_to_tsquery('simple', $7))
+),
+deck_capped AS (
+    SELECT
+        due.*,
+        ROW_NUMBER() OVER (
+            PARTITION BY due.state ORDER BY due.due_date, due.id
+        ) AS user_rank
+    FROM due
+    JOIN decks d ON d.id = due.deck_id
+    CROSS JOIN limits l
+    LEFT JOIN today t ON t.deck_id = due.deck_id
+    WHERE $3 OR due.state IN ('learning', 'relearning')
+        OR (due.state = 'new' AND due.deck_rank
+            <= COALESCE(d.new_cards_per_day, l.new_limit) - COALESCE(t.new_done, 0))
+        OR (due.state = 'review' AND due.deck_rank
+            <= COALESCE(d.reviews_per_day, l.review_limit) - COALESCE(t.reviews_done, 0))
+),
+queue AS (
+    SELECT dc.*
+    FROM deck_capped dc
+    CROSS JOIN limits l
+    WHERE $3 OR dc.state IN ('learning', 'relearning')
+        OR (dc.state = 'new' AND dc.user_rank
+            <= l.new_limit - (SELECT COALESCE(SUM(new_done), 0) FROM today))
+        OR (dc.state = 'review' AND dc.user_rank
+            <= l.review_limit - (SELECT COALESCE(SUM(reviews_done), 0) FROM today))
+)
+"#;
+
+/// Due cards, capped by the daily new card and review limits.
+/// A deck's own limits apply first, then the user's limits across all decks.
+/// Cards in their learning steps are never held back.
+/// Custom filters (tags, search, failed cards) pull in matching cards whether due or not, without limits
+pub async fn fetch_due(
+    db: &PgPool,
+    user_id: &str,
+    filter: &CardFilter,
+) -> Result<Vec<CardProgressWithFields>, DbError> {
+    let due = sqlx::query_as::<_, CardProgressWithFields>(&format!(
         r#"
-        SELECT
-            cp.id,
-            c.front,
-            c.back,
-            c.media_url
-        FROM card_progress cp
-        JOIN cards c ON c.id = cp.card_id
-        WHERE cp.user_id = $1
-            AND (cp.due_date <= CURRENT_TIMESTAMP OR cp.review_count = 0)
-        ORDER BY cp.due_date ASC
-        "#,
-        user_id,
-    )
+        {DUE_QUEUE}
+        SELECT id, front, back, media_url, card_type, state, ordinal
+        FROM queue
+        ORDER BY due_date ASC
+        "#
+    ))
+    .bind(user_id)
+    .bind(&filter.deck_id)
+    .bind(filter.is_custom())
+    .bind(&filter.tags)
+    .bind(filter.failed_within_days)
+    .bind(filter.state.map(|state| state.to_string()))
+    .bind(&filter.text)
     .fetch_all(db)
     .await?;
 
     Ok(due)
 }
 
+/// Size of the queue `fetch_due` would return
 pub async fn fetch_due_count(db: &PgPool, user_id: &str) -> Result<Option<i64>, DbError> {
-    let due = sqlx::query_scalar!(
+    let filter = CardFilter::default();
+    let count = sqlx::query_scalar::<_, i64>(&format!("{DUE_QUEUE} SELECT COUNT(*) FROM queue"))
+        .bind(user_id)
+        .bind(&filter.deck_id)
+        .bind(filter.is_custom())
+        .bind(&filter.tags)
+        .bind(filter.failed_within_days)
+        .bind(filter.state.map(|state| state.to_string()))
+        .bind(&filter.text)
+        .fetch_one(db)
+        .await?;
+
+    Ok(Some(count))
+}
+
+pub async fn find_by_id(
+    db: &PgPool,
+    progress_id: &str,
+    user_id: &str,
+) -> Result<CardProgress, DbError> {
+    let progress = sqlx::query_as!(
+        CardProgress,
         r#"
-        SELECT COUNT(*)
+        SELECT
+            cp.id,
+            cp.user_id,
+            cp.card_id,
+            cp.review_count,
+            cp.last_reviewed,
+            cp.due_date,
+            cp.ease_factor,
+            cp.interval,
+            cp.stability,
+            cp.difficulty,
+            cp.state,
+            cp.step,
+            cp.lapses
         FROM card_progress cp
-        WHERE cp.user_id = $1
-            AND (cp.due_date <= CURRENT_TIMESTAMP OR cp.review_count = 0)
+        WHERE cp.user_id = $1 AND cp.id = $2
         "#,
         user_id,
+        progress_id
     )
     .fetch_one(db)
     .await?;
 
-    Ok(due)
+    Ok(progress)
 }
 
-pub async fn find_by_id(
+/// The card behind a progress row, as it is shown to the learner
+pub async fn find_fields_by_id(
     db: &PgPool,
     progress_id: &str,
     user_id: &str,
-) -> Result<CardProgress, DbError> {
-    let progress = sqlx::query_as!(
-        CardProgress,
+) -> Result<CardProgressWithFields, DbError> {
+    let card = sqlx::query_as!(
+        CardProgressWithFields,
         r#"
-        SELECT cp.* FROM card_progress cp
+        SELECT
+            cp.id,
+            c.front,
+            c.back,
+            c.media_url,
+            c.card_type,
+            cp.state,
+            cp.ordinal
+        FROM card_progress cp
+        JOIN cards c ON c.id = cp.card_id
         WHERE cp.user_id = $1 AND cp.id = $2
         "#,
         user_id,
@@ -58,11 +201,11 @@ pub async fn find_by_id(
     .fetch_one(db)
     .await?;
 
-    Ok(progress)
+    Ok(card)
 }
 
 pub async fn update(
-    db: &PgPool,
+    executor: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
     card_id: &str,
     user_id: &str,
     update: UpdateCardProgress,
@@ -74,7 +217,13 @@ pub async fn update(
             ease_factor = $2,
             interval = $3,
             last_reviewed = $4,
-            due_date = $5
+            due_date = $5,
+            stability = $8,
+            difficulty = $9,
+            state = $10,
+            step = $11,
+            lapses = $12,
+            introduced_at = COALESCE(introduced_at, $4)
         WHERE user_id = $6 AND id = $7
         "#,
         update.review_count,
@@ -83,14 +232,68 @@ pub async fn update(
         update.last_reviewed,
         update.due_date,
         user_id,
-        card_id
+        card_id,
+        update.stability,
+        update.difficulty,
+        update.state.to_string(),
+        update.step,
+        update.lapses
     )
-    .execute(db)
+    .execute(executor)
     .await?;
 
     Ok(())
 }
 
+/// Resolves how a card gets scheduled: the deck's choice of algorithm wins over the user's preference
+pub async fn read_scheduler_settings(
+    db: &PgPool,
+    progress_id: &str,
+    user_id: &str,
+) -> Result<SchedulerSettings, DbError> {
+    let settings = sqlx::query!(
+        r#"
+        SELECT
+            COALESCE(d.scheduler, up.scheduler, 'sm2') AS "scheduler!",
+            COALESCE(up.learning_steps, '{1, 10}') AS "learning_steps!",
+            COALESCE(up.relearning_steps, '{10}') AS "relearning_steps!"
+        FROM card_progress cp
+        JOIN cards c ON c.id = cp.card_id
+        JOIN decks d ON d.id = c.deck_id
+        LEFT JOIN user_preferences up ON up.user_id = cp.user_id
+        WHERE cp.id = $1 AND cp.user_id = $2
+        "#,
+        progress_id,
+        user_id
+    )
+    .fetch_one(db)
+    .await?;
+
+    Ok(SchedulerSettings {
+        scheduler: settings.scheduler.into(),
+        learning_steps: settings.learning_steps,
+        relearning_steps: settings.relearning_steps,
+    })
+}
+
+/// Updates the progress and appends the answer to the review log in one transaction
+pub async fn update_with_review(
+    db: &PgPool,
+    progress_id: &str,
+    user_id: &str,
+    update: UpdateCardProgress,
+    review: &CardReviewCreate,
+) -> Result<(), DbError> {
+    let mut tx = db.begin().await?;
+
+    self::update(&mut *tx, progress_id, user_id, update).await?;
+    review::create(&mut *tx, progress_id, user_id, review).await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
 pub async fn reset(db: &PgPool, deck_id: &str, user_id: &str) -> Result<(), DbError> {
     let mut tx = db.begin().await?;
 
@@ -102,7 +305,13 @@ pub async fn reset(db: &PgPool, deck_id: &str, user_id: &str) -> Result<(), DbEr
             ease_factor = 2.5,
             interval = 1,
             last_reviewed = NULL,
-            due_date = CURRENT_TIMESTAMP
+            due_date = CURRENT_TIMESTAMP,
+            stability = NULL,
+            difficulty = NULL,
+            state = 'new',
+            step = 0,
+            lapses = 0,
+            introduced_at = NULL
         FROM cards c
         WHERE cp.card_id = c.id
         AND c.deck_id = $1
@@ -121,8 +330,9 @@ pub async fn reset(db: &PgPool, deck_id: &str, user_id: &str) -> Result<(), DbEr
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::tests::create_test_user;
+    use crate::tests::{create_test_user, set_test_timezone};
     use chrono::{DateTime, Utc};
+    use ogonek_types::CardState;
     use sqlx::PgPool;
 
     // Helper function to create a test deck
@@ -200,7 +410,9 @@ mod tests {
         create_card_progress(&db, &user_id, &card2_id, 1, Some(future_date)).await;
 
         // Test
-        let result = fetch_due(&db, &user_id).await.unwrap();
+        let result = fetch_due(&db, &user_id, &CardFilter::default())
+            .await
+            .unwrap();
 
         // Assert
         assert_eq!(result.len(), 1);
@@ -219,7 +431,9 @@ mod tests {
         create_card_progress(&db, &user_id, &card_id, 0, None).await;
 
         // Test
-        let result = fetch_due(&db, &user_id).await.unwrap();
+        let result = fetch_due(&db, &user_id, &CardFilter::default())
+            .await
+            .unwrap();
 
         // Assert
         assert_eq!(result.len(), 1);
@@ -242,7 +456,9 @@ mod tests {
         create_card_progress(&db, &user_id, &card2_id, 1, Some(earlier_date)).await;
 
         // Test
-        let result = fetch_due(&db, &user_id).await.unwrap();
+        let result = fetch_due(&db, &user_id, &CardFilter::default())
+            .await
+            .unwrap();
 
         // Assert - should be ordered by due_date ASC (earlier first)
         assert_eq!(result.len(), 2);
@@ -260,12 +476,238 @@ mod tests {
         create_card_progress(&db, &user_id, &card_id, 1, Some(future_date)).await;
 
         // Test
-        let result = fetch_due(&db, &user_id).await.unwrap();
+        let result = fetch_due(&db, &user_id, &CardFilter::default())
+            .await
+            .unwrap();
 
         // Assert
         assert_eq!(result.len(), 0);
     }
 
+    #[sqlx::test]
+    async fn test_fetch_due_waits_for_learning_step(db: PgPool) {
+        // Setup
+        let user_id = create_test_user(&db, "testuser", "test@example.com").await;
+        let deck_id = create_test_deck(&db, &user_id, "Test Deck").await;
+        let card_id = create_test_card(&db, &deck_id, "Learning Card", "Back").await;
+
+        // Answered once, next learning step is ten minutes away
+        let step_due = Utc::now() + chrono::Duration::minutes(10);
+        create_card_progress(&db, &user_id, &card_id, 0, Some(step_due)).await;
+
+        // Test
+        let result = fetch_due(&db, &user_id, &CardFilter::default())
+            .await
+            .unwrap();
+        let count = fetch_due_count(&db, &user_id).await.unwrap();
+
+        // Assert
+        assert!(result.is_empty());
+        assert_eq!(count, Some(0));
+    }
+
+    #[sqlx::test]
+    async fn test_fetch_due_caps_new_cards_per_user(db: PgPool) {
+        // Setup
+        let user_id = create_test_user(&db, "testuser", "test@example.com").await;
+        let deck1_id = create_test_deck(&db, &user_id, "Deck 1").await;
+        let deck2_id = create_test_deck(&db, &user_id, "Deck 2").await;
+        for deck_id in [&deck1_id, &deck2_id] {
+            for i in 0..3 {
+                let card_id = create_test_card(&db, deck_id, &format!("Card {i}"), "Back").await;
+                create_card_progress(&db, &user_id, &card_id, 0, None).await;
+            }
+        }
+
+        sqlx::query!(
+            "INSERT INTO user_preferences (user_id, new_cards_per_day) VALUES ($1, 4)",
+            user_id
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+
+        // Test
+        let result = fetch_due(&db, &user_id, &CardFilter::default())
+            .await
+            .unwrap();
+        let count = fetch_due_count(&db, &user_id).await.unwrap();
+
+        // Assert
+        assert_eq!(result.len(), 4);
+        assert_eq!(count, Some(4));
+    }
+
+    #[sqlx::test]
+    async fn test_fetch_due_deck_limit_counts_cards_introduced_today(db: PgPool) {
+        // Setup
+        let user_id = create_test_user(&db, "testuser", "test@example.com").await;
+        let deck_id = create_test_deck(&db, &user_id, "Test Deck").await;
+        let mut progress_ids = Vec::new();
+        for i in 0..4 {
+            let card_id = create_test_card(&db, &deck_id, &format!("Card {i}"), "Back").await;
+            progress_ids.push(create_card_progress(&db, &user_id, &card_id, 0, None).await);
+        }
+
+        sqlx::query!(
+            "UPDATE decks SET new_cards_per_day = 2 WHERE id = $1",
+            deck_id
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+
+        // First card answered, waiting for its next learning step
+        let now = Utc::now();
+        let answered = UpdateCardProgress {
+            review_count: 0,
+            ease_factor: 2.5,
+            interval: 1,
+            last_reviewed: now,
+            due_date: now + chrono::Duration::minutes(10),
+            stability: None,
+            difficulty: None,
+            state: CardState::Learning,
+            step: 1,
+            lapses: 0,
+        };
+        update(&db, &progress_ids[0], &user_id, answered)
+            .await
+            .unwrap();
+
+        // Test
+        let result = fetch_due(&db, &user_id, &CardFilter::default())
+            .await
+            .unwrap();
+        let count = fetch_due_count(&db, &user_id).await.unwrap();
+
+        // Assert - one of the two new cards for today is already used up
+        assert_eq!(result.len(), 1);
+        assert_eq!(count, Some(1));
+    }
+
+    #[sqlx::test]
+    async fn test_fetch_due_caps_reviews_but_not_learning_cards(db: PgPool) {
+        // Setup
+        let user_id = create_test_user(&db, "testuser", "test@example.com").await;
+        let deck_id = create_test_deck(&db, &user_id, "Test Deck").await;
+        let past_date = Utc::now() - chrono::Duration::hours(1);
+        for (i, state) in ["review", "review", "review", "relearning"]
+            .iter()
+            .enumerate()
+        {
+            let card_id = create_test_card(&db, &deck_id, &format!("Card {i}"), "Back").await;
+            let progress_id =
+                create_card_progress(&db, &user_id, &card_id, 3, Some(past_date)).await;
+            sqlx::query!(
+                "UPDATE card_progress SET state = $1 WHERE id = $2",
+                state,
+                progress_id
+            )
+            .execute(&db)
+            .await
+            .unwrap();
+        }
+
+        sqlx::query!(
+            "INSERT INTO user_preferences (user_id, reviews_per_day) VALUES ($1, 2)",
+            user_id
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+
+        // Test
+        let result = fetch_due(&db, &user_id, &CardFilter::default())
+            .await
+            .unwrap();
+
+        // Assert - two reviews plus the relearning card
+        assert_eq!(result.len(), 3);
+    }
+
+    #[sqlx::test]
+    async fn test_fetch_due_limits_roll_over_at_local_midnight(db: PgPool) {
+        // Setup
+        let user_id = create_test_user(&db, "testuser", "test@example.com").await;
+        let deck_id = create_test_deck(&db, &user_id, "Test Deck").await;
+        let midnight = set_test_timezone(&db, &user_id).await;
+        let mut progress_ids = Vec::new();
+        for i in 0..3 {
+            let card_id = create_test_card(&db, &deck_id, &format!("Card {i}"), "Back").await;
+            progress_ids.push(create_card_progress(&db, &user_id, &card_id, 0, None).await);
+        }
+
+        sqlx::query!(
+            "INSERT INTO user_preferences (user_id, new_cards_per_day) VALUES ($1, 2)",
+            user_id
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+
+        // Introduced a minute before the user's day started, still learning
+        sqlx::query!(
+            r#"
+            UPDATE card_progress
+            SET introduced_at = $2, last_reviewed = $2, state = 'learning',
+                due_date = CURRENT_TIMESTAMP + INTERVAL '10 minutes'
+            WHERE id = $1
+            "#,
+            progress_ids[0],
+            midnight - chrono::Duration::minutes(1)
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+
+        // Test
+        let count = fetch_due_count(&db, &user_id).await.unwrap();
+
+        // Assert - yesterday's card leaves today's two new cards
+        assert_eq!(count, Some(2));
+    }
+
+    #[sqlx::test]
+    async fn test_fetch_due_custom_filter_ignores_due_dates(db: PgPool) {
+        // Setup
+        let user_id = create_test_user(&db, "testuser", "test@example.com").await;
+        let deck_id = create_test_deck(&db, &user_id, "Test Deck").await;
+        let future_date = Utc::now() + chrono::Duration::days(5);
+
+        let verb_id = create_test_card(&db, &deck_id, "Hablar", "To speak").await;
+        let noun_id = create_test_card(&db, &deck_id, "Casa", "House").await;
+        sqlx::query!("UPDATE cards SET tags = '{verbs}' WHERE id = $1", verb_id)
+            .execute(&db)
+            .await
+            .unwrap();
+        let verb_progress =
+            create_card_progress(&db, &user_id, &verb_id, 3, Some(future_date)).await;
+        create_card_progress(&db, &user_id, &noun_id, 3, Some(future_date)).await;
+
+        // Test
+        let due = fetch_due(&db, &user_id, &CardFilter::default())
+            .await
+            .unwrap();
+        assert!(due.is_empty());
+
+        let tagged = CardFilter {
+            tags: vec!["verbs".into()],
+            ..Default::default()
+        };
+        let result = fetch_due(&db, &user_id, &tagged).await.unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].id, verb_progress);
+
+        let searched = CardFilter {
+            text: Some("house".into()),
+            ..Default::default()
+        };
+        let result = fetch_due(&db, &user_id, &searched).await.unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].front, "Casa");
+    }
+
     #[sqlx::test]
     async fn test_find_by_id_returns_correct_progress(db: PgPool) {
         // Setup
@@ -316,6 +758,11 @@ mod tests {
             interval: 7,
             last_reviewed,
             due_date: new_due_date,
+            stability: Some(7.5),
+            difficulty: Some(4.2),
+            state: CardState::Review,
+            step: 0,
+            lapses: 1,
         };
 
         // Test
@@ -327,6 +774,10 @@ mod tests {
         assert_eq!(updated_progress.review_count, 3);
         assert_eq!(updated_progress.ease_factor, 2.8);
         assert_eq!(updated_progress.interval, 7);
+        assert_eq!(updated_progress.stability, Some(7.5));
+        assert_eq!(updated_progress.difficulty, Some(4.2));
+        assert_eq!(updated_progress.state, CardState::Review);
+        assert_eq!(updated_progress.lapses, 1);
         assert!(updated_progress.last_reviewed.is_some());
         assert_eq!(
             updated_progress
@@ -469,7 +920,9 @@ mod tests {
         create_card_progress(&db, &user_id, &card_id, 0, None).await;
 
         // Test
-        let result = fetch_due(&db, &user_id).await.unwrap();
+        let result = fetch_due(&db, &user_id, &CardFilter::default())
+            .await
+            .unwrap();
 
         // Assert
         assert_eq!(result.len(), 1);
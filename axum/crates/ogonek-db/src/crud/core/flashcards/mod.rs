@@ -1,4 +1,7 @@
 pub mod card;
 pub mod deck;
 pub mod learn;
+pub mod review;
+pub mod session;
+pub mod stats;
 pub mod subscribe;
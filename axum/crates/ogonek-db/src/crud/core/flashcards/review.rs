@@ -0,0 +1,300 @@
+use crate::DbError;
+use ogonek_types::{CardReview, CardReviewCreate, ReviewPaginationParams};
+use sqlx::PgPool;
+
+/// Appends an answer to the review log and returns its id. Card and deck are resolved from the progress row
+pub async fn create(
+    executor: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    progress_id: &str,
+    user_id: &str,
+    review: &CardReviewCreate,
+) -> Result<String, DbError> {
+    let id = nanoid::nanoid!();
+    sqlx::query!(
+        r#"
+        INSERT INTO card_reviews (
+            id, user_id, card_id, deck_id, quality,
+            ease_before, ease_after, interval_before, interval_after,
+            elapsed_seconds, reviewed_at
+        )
+        SELECT $1, cp.user_id, cp.card_id, c.deck_id, $4, $5, $6, $7, $8, $9, $10
+        FROM card_progress cp
+        JOIN cards c ON c.id = cp.card_id
+        WHERE cp.id = $2 AND cp.user_id = $3
+        "#,
+        id,
+        progress_id,
+        user_id,
+        review.quality,
+        review.ease_before,
+        review.ease_after,
+        review.interval_before,
+        review.interval_after,
+        review.elapsed_seconds,
+        review.reviewed_at
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(id)
+}
+
+/// Pages through the review history, newest first.
+/// Without a student the viewer's own history is returned; teachers may pass one of their students.
+pub async fn read_all(
+    db: &PgPool,
+    user_id: &str,
+    deck_id: Option<&str>,
+    params: &ReviewPaginationParams,
+) -> Result<(Vec<CardReview>, i64), DbError> {
+    let reviewer_id = params.student_id.as_deref().unwrap_or(user_id);
+
+    if reviewer_id != user_id {
+        let teaches = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM teacher_student
+                WHERE teacher_id = $1 AND student_id = $2 AND status = 'active'
+            ) AS "exists!"
+            "#,
+            user_id,
+            reviewer_id
+        )
+        .fetch_one(db)
+        .await?;
+
+        if !teaches {
+            return Err(DbError::NotFound("Student not found".into()));
+        }
+    }
+
+    let mut query_builder = sqlx::QueryBuilder::new(
+        r#"SELECT
+            cr.id,
+            cr.user_id,
+            cr.card_id,
+            cr.deck_id,
+            c.front,
+            c.back,
+            cr.quality,
+            cr.ease_before,
+            cr.ease_after,
+            cr.interval_before,
+            cr.interval_after,
+            cr.elapsed_seconds,
+            cr.reviewed_at
+        FROM card_reviews cr
+        JOIN cards c ON c.id = cr.card_id"#,
+    );
+    push_filters(&mut query_builder, reviewer_id, deck_id);
+
+    query_builder.push(" ORDER BY cr.reviewed_at DESC");
+    query_builder.push(" LIMIT ");
+    query_builder.push_bind(params.limit());
+    query_builder.push(" OFFSET ");
+    query_builder.push_bind(params.offset());
+
+    let reviews = query_builder
+        .build_query_as::<CardReview>()
+        .fetch_all(db)
+        .await?;
+
+    let mut count_query = sqlx::QueryBuilder::new("SELECT COUNT(*) FROM card_reviews cr");
+    push_filters(&mut count_query, reviewer_id, deck_id);
+
+    let total: (i64,) = count_query.build_query_as().fetch_one(db).await?;
+    Ok((reviews, total.0))
+}
+
+fn push_filters<'a>(
+    query_builder: &mut sqlx::QueryBuilder<'a, sqlx::Postgres>,
+    reviewer_id: &'a str,
+    deck_id: Option<&'a str>,
+) {
+    query_builder.push(" WHERE cr.user_id = ");
+    query_builder.push_bind(reviewer_id);
+
+    if let Some(deck_id) = deck_id {
+        query_builder.push(" AND cr.deck_id = ");
+        query_builder.push_bind(deck_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::flashcards::learn::{self, find_by_id},
+        tests::create_test_user,
+    };
+    use chrono::Utc;
+    use ogonek_types::{CardState, UpdateCardProgress};
+
+    async fn create_test_deck(db: &PgPool, user_id: &str, name: &str) -> String {
+        let deck_id = nanoid::nanoid!();
+        sqlx::query!(
+            "INSERT INTO decks (id, title, created_by) VALUES ($1, $2, $3)",
+            deck_id,
+            name,
+            user_id
+        )
+        .execute(db)
+        .await
+        .unwrap();
+        deck_id
+    }
+
+    // Creates a card and the progress row for the user, returns the progress id
+    async fn create_test_progress(db: &PgPool, user_id: &str, deck_id: &str) -> String {
+        let card_id = nanoid::nanoid!();
+        sqlx::query!(
+            "INSERT INTO cards (id, front, back, deck_id) VALUES ($1, $2, $3, $4)",
+            card_id,
+            "Front",
+            "Back",
+            deck_id
+        )
+        .execute(db)
+        .await
+        .unwrap();
+
+        let progress_id = nanoid::nanoid!();
+        sqlx::query!(
+            "INSERT INTO card_progress (id, user_id, card_id, due_date) VALUES ($1, $2, $3, NOW())",
+            progress_id,
+            user_id,
+            card_id
+        )
+        .execute(db)
+        .await
+        .unwrap();
+        progress_id
+    }
+
+    fn test_review(quality: i32) -> CardReviewCreate {
+        CardReviewCreate {
+            quality,
+            ease_before: 2.5,
+            ease_after: 2.6,
+            interval_before: 1,
+            interval_after: 6,
+            elapsed_seconds: Some(86400),
+            reviewed_at: Utc::now(),
+        }
+    }
+
+    fn params(student_id: Option<String>) -> ReviewPaginationParams {
+        ReviewPaginationParams {
+            page: 1,
+            per_page: 20,
+            student_id,
+        }
+    }
+
+    #[sqlx::test]
+    async fn test_update_with_review_logs_answer(db: PgPool) {
+        let user_id = create_test_user(&db, "testuser", "test@example.com").await;
+        let deck_id = create_test_deck(&db, &user_id, "Deck").await;
+        let progress_id = create_test_progress(&db, &user_id, &deck_id).await;
+
+        let update = UpdateCardProgress {
+            review_count: 1,
+            ease_factor: 2.6,
+            interval: 6,
+            last_reviewed: Utc::now(),
+            due_date: Utc::now() + chrono::Duration::days(6),
+            stability: None,
+            difficulty: None,
+            state: CardState::Review,
+            step: 0,
+            lapses: 0,
+        };
+        learn::update_with_review(&db, &progress_id, &user_id, update, &test_review(4))
+            .await
+            .unwrap();
+
+        let progress = find_by_id(&db, &progress_id, &user_id).await.unwrap();
+        assert_eq!(progress.interval, 6);
+
+        let (reviews, count) = read_all(&db, &user_id, None, &params(None)).await.unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(reviews[0].quality, 4);
+        assert_eq!(reviews[0].deck_id, deck_id);
+        assert_eq!(reviews[0].card_id, progress.card_id);
+        assert_eq!(reviews[0].interval_before, 1);
+        assert_eq!(reviews[0].interval_after, 6);
+    }
+
+    #[sqlx::test]
+    async fn test_read_all_filters_by_deck(db: PgPool) {
+        let user_id = create_test_user(&db, "testuser", "test@example.com").await;
+        let deck1_id = create_test_deck(&db, &user_id, "Deck 1").await;
+        let deck2_id = create_test_deck(&db, &user_id, "Deck 2").await;
+        let progress1_id = create_test_progress(&db, &user_id, &deck1_id).await;
+        let progress2_id = create_test_progress(&db, &user_id, &deck2_id).await;
+
+        create(&db, &progress1_id, &user_id, &test_review(5))
+            .await
+            .unwrap();
+        create(&db, &progress1_id, &user_id, &test_review(2))
+            .await
+            .unwrap();
+        create(&db, &progress2_id, &user_id, &test_review(3))
+            .await
+            .unwrap();
+
+        let (reviews, count) = read_all(&db, &user_id, Some(&deck1_id), &params(None))
+            .await
+            .unwrap();
+        assert_eq!(count, 2);
+        assert!(reviews.iter().all(|r| r.deck_id == deck1_id));
+
+        let (_, count) = read_all(&db, &user_id, None, &params(None)).await.unwrap();
+        assert_eq!(count, 3);
+    }
+
+    #[sqlx::test]
+    async fn test_read_all_student_history_requires_teacher(db: PgPool) {
+        let teacher_id = create_test_user(&db, "teacher", "teacher@example.com").await;
+        let student_id = create_test_user(&db, "student", "student@example.com").await;
+        let stranger_id = create_test_user(&db, "stranger", "stranger@example.com").await;
+        let deck_id = create_test_deck(&db, &teacher_id, "Deck").await;
+        let progress_id = create_test_progress(&db, &student_id, &deck_id).await;
+
+        sqlx::query!(
+            "INSERT INTO teacher_student (teacher_id, student_id) VALUES ($1, $2)",
+            teacher_id,
+            student_id
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+
+        create(&db, &progress_id, &student_id, &test_review(4))
+            .await
+            .unwrap();
+
+        let (_, count) = read_all(&db, &teacher_id, None, &params(Some(student_id.clone())))
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+
+        let result = read_all(&db, &stranger_id, None, &params(Some(student_id))).await;
+        assert!(matches!(result, Err(DbError::NotFound(_))));
+    }
+
+    #[sqlx::test]
+    async fn test_create_ignores_foreign_progress(db: PgPool) {
+        let user1_id = create_test_user(&db, "user1", "user1@example.com").await;
+        let user2_id = create_test_user(&db, "user2", "user2@example.com").await;
+        let deck_id = create_test_deck(&db, &user1_id, "Deck").await;
+        let progress_id = create_test_progress(&db, &user1_id, &deck_id).await;
+
+        create(&db, &progress_id, &user2_id, &test_review(4))
+            .await
+            .unwrap();
+
+        let (_, count) = read_all(&db, &user2_id, None, &params(None)).await.unwrap();
+        assert_eq!(count, 0);
+    }
+}
@@ -0,0 +1,577 @@
+use crate::{
+    DbError,
+    core::flashcards::{learn, review},
+};
+use ogonek_types::{
+    CardProgressWithFields, CardReviewCreate, CardState, StudySession, StudySessionSummary,
+    UpdateCardProgress,
+};
+use sqlx::PgPool;
+
+/// The card handed out next, with its place in the queue
+pub struct SessionCard {
+    pub position: i32,
+    pub card: CardProgressWithFields,
+}
+
+/// Starts a session over an already ordered queue of progress ids
+pub async fn create(
+    db: &PgPool,
+    user_id: &str,
+    deck_id: Option<&str>,
+    filter: Option<&str>,
+    queue: &[String],
+) -> Result<String, DbError> {
+    let mut tx = db.begin().await?;
+    let id = nanoid::nanoid!();
+
+    sqlx::query!(
+        "INSERT INTO study_sessions (id, user_id, deck_id, filter) VALUES ($1, $2, $3, $4)",
+        id,
+        user_id,
+        deck_id,
+        filter
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO study_session_cards (session_id, position, progress_id)
+        SELECT $1, q.position::INT, q.progress_id
+        FROM UNNEST($2::VARCHAR[]) WITH ORDINALITY AS q(progress_id, position)
+        "#,
+        id,
+        queue
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(id)
+}
+
+pub async fn read_by_id(
+    db: &PgPool,
+    session_id: &str,
+    user_id: &str,
+) -> Result<StudySession, DbError> {
+    let session = sqlx::query_as!(
+        StudySession,
+        r#"
+        SELECT
+            s.id,
+            s.deck_id,
+            s.filter,
+            COUNT(sc.position) FILTER (WHERE sc.answered_at IS NULL) AS "remaining!",
+            COUNT(sc.position) FILTER (WHERE sc.answered_at IS NOT NULL) AS "answered!",
+            s.started_at,
+            s.finished_at
+        FROM study_sessions s
+        LEFT JOIN study_session_cards sc ON sc.session_id = s.id
+        WHERE s.id = $1 AND s.user_id = $2
+        GROUP BY s.id
+        "#,
+        session_id,
+        user_id
+    )
+    .fetch_one(db)
+    .await?;
+
+    Ok(session)
+}
+
+/// Hands out the first unanswered card of a running session, or None once the queue is empty
+pub async fn next_card(
+    db: &PgPool,
+    session_id: &str,
+    user_id: &str,
+) -> Result<Option<SessionCard>, DbError> {
+    let mut tx = db.begin().await?;
+
+    let next = sqlx::query!(
+        r#"
+        SELECT sc.position, sc.progress_id
+        FROM study_session_cards sc
+        JOIN study_sessions s ON s.id = sc.session_id
+        WHERE s.id = $1 AND s.user_id = $2
+            AND s.finished_at IS NULL
+            AND sc.answered_at IS NULL
+        ORDER BY sc.position
+        LIMIT 1
+        "#,
+        session_id,
+        user_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(next) = next else {
+        // Tell a missing or finished session apart from an exhausted queue
+        ensure_running(&mut *tx, session_id, user_id).await?;
+        return Ok(None);
+    };
+
+    // Time spent on a card is counted from the first time it was handed out
+    sqlx::query!(
+        r#"
+        UPDATE study_session_cards
+        SET shown_at = COALESCE(shown_at, CURRENT_TIMESTAMP)
+        WHERE session_id = $1 AND position = $2
+        "#,
+        session_id,
+        next.position
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    let card = learn::find_fields_by_id(db, &next.progress_id, user_id).await?;
+
+    Ok(Some(SessionCard {
+        position: next.position,
+        card,
+    }))
+}
+
+/// Records the answer to a queued card. The progress it replaces is kept for undo,
+/// and cards left in their learning steps are queued again
+pub async fn answer(
+    db: &PgPool,
+    session_id: &str,
+    user_id: &str,
+    position: i32,
+    update: UpdateCardProgress,
+    review: &CardReviewCreate,
+) -> Result<(), DbError> {
+    let mut tx = db.begin().await?;
+
+    let progress_id = sqlx::query_scalar!(
+        r#"
+        UPDATE study_session_cards sc
+        SET
+            answered_at = $4,
+            quality = $5,
+            shown_at = COALESCE(sc.shown_at, $4),
+            previous_progress = (SELECT to_jsonb(cp) FROM card_progress cp WHERE cp.id = sc.progress_id)
+        FROM study_sessions s
+        WHERE s.id = sc.session_id
+            AND sc.session_id = $1 AND sc.position = $2 AND s.user_id = $3
+            AND s.finished_at IS NULL
+            AND sc.answered_at IS NULL
+        RETURNING sc.progress_id
+        "#,
+        session_id,
+        position,
+        user_id,
+        review.reviewed_at,
+        review.quality
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| DbError::NotFound("Queued card not found".into()))?;
+
+    let requeue = matches!(update.state, CardState::Learning | CardState::Relearning);
+    learn::update(&mut *tx, &progress_id, user_id, update).await?;
+    let review_id = review::create(&mut *tx, &progress_id, user_id, review).await?;
+
+    let requeued_position = if requeue {
+        let position = sqlx::query_scalar!(
+            r#"
+            INSERT INTO study_session_cards (session_id, position, progress_id)
+            SELECT session_id, MAX(position) + 1, $2
+            FROM study_session_cards
+            WHERE session_id = $1
+            GROUP BY session_id
+            RETURNING position
+            "#,
+            session_id,
+            progress_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        Some(position)
+    } else {
+        None
+    };
+
+    sqlx::query!(
+        r#"
+        UPDATE study_session_cards
+        SET review_id = $3, requeued_position = $4
+        WHERE session_id = $1 AND position = $2
+        "#,
+        session_id,
+        position,
+        review_id,
+        requeued_position
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Takes back the last answer of a running session: the card progress is restored,
+/// the review is dropped from the log and the card goes back to the front of the queue
+pub async fn undo(db: &PgPool, session_id: &str, user_id: &str) -> Result<(), DbError> {
+    let mut tx = db.begin().await?;
+
+    let last = sqlx::query!(
+        r#"
+        SELECT sc.position, sc.progress_id, sc.previous_progress, sc.review_id, sc.requeued_position
+        FROM study_session_cards sc
+        JOIN study_sessions s ON s.id = sc.session_id
+        WHERE s.id = $1 AND s.user_id = $2
+            AND s.finished_at IS NULL
+            AND sc.answered_at IS NOT NULL
+        ORDER BY sc.answered_at DESC, sc.position DESC
+        LIMIT 1
+        FOR UPDATE OF sc
+        "#,
+        session_id,
+        user_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| DbError::NotFound("Nothing to undo".into()))?;
+
+    sqlx::query!(
+        r#"
+        UPDATE card_progress cp
+        SET
+            review_count = p.review_count,
+            ease_factor = p.ease_factor,
+            interval = p.interval,
+            last_reviewed = p.last_reviewed,
+            due_date = p.due_date,
+            stability = p.stability,
+            difficulty = p.difficulty,
+            state = p.state,
+            step = p.step,
+            lapses = p.lapses,
+            introduced_at = p.introduced_at
+        FROM jsonb_populate_record(NULL::card_progress, $3) p
+        WHERE cp.id = $1 AND cp.user_id = $2
+        "#,
+        last.progress_id,
+        user_id,
+        last.previous_progress
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    if let Some(review_id) = last.review_id {
+        sqlx::query!("DELETE FROM card_reviews WHERE id = $1", review_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    if let Some(requeued) = last.requeued_position {
+        sqlx::query!(
+            "DELETE FROM study_session_cards WHERE session_id = $1 AND position = $2",
+            session_id,
+            requeued
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    sqlx::query!(
+        r#"
+        UPDATE study_session_cards
+        SET answered_at = NULL, quality = NULL, previous_progress = NULL,
+            review_id = NULL, requeued_position = NULL
+        WHERE session_id = $1 AND position = $2
+        "#,
+        session_id,
+        last.position
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Closes the session and sums it up, along with whether this call is the one that
+/// closed it. Finishing twice returns the same summary
+pub async fn finish(
+    db: &PgPool,
+    session_id: &str,
+    user_id: &str,
+) -> Result<(StudySessionSummary, bool), DbError> {
+    let mut tx = db.begin().await?;
+
+    // A concurrent finish waits on the row and then finds it already closed
+    let finished = sqlx::query!(
+        r#"
+        UPDATE study_sessions
+        SET finished_at = CURRENT_TIMESTAMP
+        WHERE id = $1 AND user_id = $2 AND finished_at IS NULL
+        RETURNING id
+        "#,
+        session_id,
+        user_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .is_some();
+    if !finished {
+        sqlx::query!(
+            "SELECT id FROM study_sessions WHERE id = $1 AND user_id = $2",
+            session_id,
+            user_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+    }
+
+    let summary = sqlx::query!(
+        r#"
+        SELECT
+            COUNT(DISTINCT progress_id) FILTER (WHERE answered_at IS NOT NULL) AS "cards_seen!",
+            COUNT(*) FILTER (WHERE answered_at IS NOT NULL) AS "answers!",
+            COUNT(*) FILTER (WHERE quality >= 3) AS "correct!",
+            COALESCE(SUM(EXTRACT(EPOCH FROM answered_at - shown_at)), 0)::BIGINT AS "time_spent_seconds!"
+        FROM study_session_cards
+        WHERE session_id = $1
+        "#,
+        session_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    let summary = StudySessionSummary {
+        cards_seen: summary.cards_seen,
+        answers: summary.answers,
+        correct: summary.correct,
+        accuracy: (summary.answers > 0).then(|| summary.correct as f64 / summary.answers as f64),
+        time_spent_seconds: summary.time_spent_seconds,
+    };
+    Ok((summary, finished))
+}
+
+async fn ensure_running(
+    executor: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    session_id: &str,
+    user_id: &str,
+) -> Result<(), DbError> {
+    sqlx::query!(
+        r#"
+        SELECT id FROM study_sessions
+        WHERE id = $1 AND user_id = $2 AND finished_at IS NULL
+        "#,
+        session_id,
+        user_id
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::create_test_user;
+    use chrono::{Duration, Utc};
+
+    async fn create_test_queue(db: &PgPool, user_id: &str, size: usize) -> Vec<String> {
+        let deck_id = nanoid::nanoid!();
+        sqlx::query!(
+            "INSERT INTO decks (id, title, created_by) VALUES ($1, $2, $3)",
+            deck_id,
+            "Deck",
+            user_id
+        )
+        .execute(db)
+        .await
+        .unwrap();
+
+        let mut queue = Vec::new();
+        for i in 0..size {
+            let card_id = nanoid::nanoid!();
+            sqlx::query!(
+                "INSERT INTO cards (id, front, back, deck_id) VALUES ($1, $2, $3, $4)",
+                card_id,
+                format!("Front {i}"),
+                "Back",
+                deck_id
+            )
+            .execute(db)
+            .await
+            .unwrap();
+
+            let progress_id = nanoid::nanoid!();
+            sqlx::query!(
+                "INSERT INTO card_progress (id, user_id, card_id, due_date) VALUES ($1, $2, $3, NOW())",
+                progress_id,
+                user_id,
+                card_id
+            )
+            .execute(db)
+            .await
+            .unwrap();
+            queue.push(progress_id);
+        }
+        queue
+    }
+
+    fn answer_with(state: CardState, quality: i32) -> (UpdateCardProgress, CardReviewCreate) {
+        let now = Utc::now();
+        let update = UpdateCardProgress {
+            review_count: 1,
+            ease_factor: 2.5,
+            interval: 1,
+            last_reviewed: now,
+            due_date: now + Duration::days(1),
+            stability: None,
+            difficulty: None,
+            state,
+            step: 0,
+            lapses: 0,
+        };
+        let review = CardReviewCreate {
+            quality,
+            ease_before: 2.5,
+            ease_after: 2.5,
+            interval_before: 1,
+            interval_after: 1,
+            elapsed_seconds: None,
+            reviewed_at: now,
+        };
+        (update, review)
+    }
+
+    async fn review_count(db: &PgPool, user_id: &str) -> i64 {
+        sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM card_reviews WHERE user_id = $1"#,
+            user_id
+        )
+        .fetch_one(db)
+        .await
+        .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn test_answer_and_undo_restores_progress(db: PgPool) {
+        let user_id = create_test_user(&db, "testuser", "test@example.com").await;
+        let queue = create_test_queue(&db, &user_id, 2).await;
+        let session_id = create(&db, &user_id, None, None, &queue).await.unwrap();
+
+        let first = next_card(&db, &session_id, &user_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(first.card.id, queue[0]);
+
+        let (update, review) = answer_with(CardState::Review, 4);
+        answer(&db, &session_id, &user_id, first.position, update, &review)
+            .await
+            .unwrap();
+
+        let progress = learn::find_by_id(&db, &queue[0], &user_id).await.unwrap();
+        assert_eq!(progress.state, CardState::Review);
+        assert_eq!(review_count(&db, &user_id).await, 1);
+
+        let second = next_card(&db, &session_id, &user_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(second.card.id, queue[1]);
+
+        undo(&db, &session_id, &user_id).await.unwrap();
+
+        let progress = learn::find_by_id(&db, &queue[0], &user_id).await.unwrap();
+        assert_eq!(progress.state, CardState::New);
+        assert_eq!(progress.review_count, 0);
+        assert_eq!(review_count(&db, &user_id).await, 0);
+
+        let again = next_card(&db, &session_id, &user_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(again.card.id, queue[0]);
+
+        let result = undo(&db, &session_id, &user_id).await;
+        assert!(matches!(result, Err(DbError::NotFound(_))));
+    }
+
+    #[sqlx::test]
+    async fn test_learning_cards_are_requeued(db: PgPool) {
+        let user_id = create_test_user(&db, "testuser", "test@example.com").await;
+        let queue = create_test_queue(&db, &user_id, 2).await;
+        let session_id = create(&db, &user_id, None, None, &queue).await.unwrap();
+
+        let first = next_card(&db, &session_id, &user_id)
+            .await
+            .unwrap()
+            .unwrap();
+        let (update, review) = answer_with(CardState::Learning, 1);
+        answer(&db, &session_id, &user_id, first.position, update, &review)
+            .await
+            .unwrap();
+
+        let session = read_by_id(&db, &session_id, &user_id).await.unwrap();
+        assert_eq!(session.remaining, 2);
+        assert_eq!(session.answered, 1);
+
+        undo(&db, &session_id, &user_id).await.unwrap();
+
+        let session = read_by_id(&db, &session_id, &user_id).await.unwrap();
+        assert_eq!(session.remaining, 2);
+        assert_eq!(session.answered, 0);
+    }
+
+    #[sqlx::test]
+    async fn test_finish_summarizes_session(db: PgPool) {
+        let user_id = create_test_user(&db, "testuser", "test@example.com").await;
+        let other_id = create_test_user(&db, "other", "other@example.com").await;
+        let queue = create_test_queue(&db, &user_id, 2).await;
+        let session_id = create(&db, &user_id, None, None, &queue).await.unwrap();
+
+        for quality in [4, 1] {
+            let card = next_card(&db, &session_id, &user_id)
+                .await
+                .unwrap()
+                .unwrap();
+            let (update, review) = answer_with(CardState::Review, quality);
+            answer(&db, &session_id, &user_id, card.position, update, &review)
+                .await
+                .unwrap();
+        }
+        assert!(
+            next_card(&db, &session_id, &user_id)
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        let result = finish(&db, &session_id, &other_id).await;
+        assert!(matches!(result, Err(DbError::NotFound(_))));
+
+        let (summary, finished) = finish(&db, &session_id, &user_id).await.unwrap();
+        assert!(finished);
+        assert_eq!(summary.cards_seen, 2);
+        assert_eq!(summary.answers, 2);
+        assert_eq!(summary.correct, 1);
+        assert_eq!(summary.accuracy, Some(0.5));
+
+        // Finishing again does not close it a second time
+        let (again, finished) = finish(&db, &session_id, &user_id).await.unwrap();
+        assert!(!finished);
+        assert_eq!(again.answers, 2);
+
+        let result = next_card(&db, &session_id, &user_id).await;
+        assert!(matches!(result, Err(DbError::NotFound(_))));
+    }
+}
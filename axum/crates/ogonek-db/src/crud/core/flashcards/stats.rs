@@ -0,0 +1,306 @@
+use crate::DbError;
+use ogonek_types::{CardCounts, DailyReviews, DueForecast, LearnStats};
+use sqlx::PgPool;
+
+/// Aggregates the learner's progress, across all decks unless one is given.
+/// Without a student the viewer's own statistics are returned; teachers may pass one of their students.
+pub async fn read(
+    db: &PgPool,
+    user_id: &str,
+    student_id: Option<&str>,
+    deck_id: Option<&str>,
+) -> Result<LearnStats, DbError> {
+    let learner_id = student_id.unwrap_or(user_id);
+
+    if learner_id != user_id {
+        let teaches = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM teacher_student
+                WHERE teacher_id = $1 AND student_id = $2 AND status = 'active'
+            ) AS "exists!"
+            "#,
+            user_id,
+            learner_id
+        )
+        .fetch_one(db)
+        .await?;
+
+        if !teaches {
+            return Err(DbError::NotFound("Student not found".into()));
+        }
+    }
+
+    let progress = sqlx::query!(
+        r#"
+        SELECT
+            COUNT(*) FILTER (WHERE cp.state = 'new') AS "new!",
+            COUNT(*) FILTER (WHERE cp.state IN ('learning', 'relearning')) AS "learning!",
+            COUNT(*) FILTER (WHERE cp.state = 'review' AND cp.interval < 21) AS "young!",
+            COUNT(*) FILTER (WHERE cp.state = 'review' AND cp.interval >= 21) AS "mature!",
+            COUNT(*) AS "total!",
+            AVG(cp.ease_factor) FILTER (WHERE cp.state <> 'new') AS average_ease
+        FROM card_progress cp
+        JOIN cards c ON c.id = cp.card_id
+        WHERE cp.user_id = $1 AND ($2::VARCHAR IS NULL OR c.deck_id = $2)
+        "#,
+        learner_id,
+        deck_id
+    )
+    .fetch_one(db)
+    .await?;
+
+    // Answers given during the (re)learning steps would inflate retention,
+    // so only cards last seen on an earlier day count
+    let retention = sqlx::query!(
+        r#"
+        SELECT
+            AVG((quality >= 3)::INT::FLOAT8) FILTER (
+                WHERE reviewed_at >= CURRENT_TIMESTAMP - INTERVAL '7 days'
+            ) AS retention_7d,
+            AVG((quality >= 3)::INT::FLOAT8) AS retention_30d
+        FROM card_reviews
+        WHERE user_id = $1
+            AND ($2::VARCHAR IS NULL OR deck_id = $2)
+            AND reviewed_at >= CURRENT_TIMESTAMP - INTERVAL '30 days'
+            AND date_trunc('day', reviewed_at - make_interval(secs => elapsed_seconds))
+                < date_trunc('day', reviewed_at)
+        "#,
+        learner_id,
+        deck_id
+    )
+    .fetch_one(db)
+    .await?;
+
+    let reviews_per_day = sqlx::query_as!(
+        DailyReviews,
+        r#"
+        SELECT
+            d.day::DATE AS "date!",
+            COUNT(cr.id) AS "reviews!",
+            COUNT(cr.id) FILTER (WHERE cr.quality >= 3) AS "correct!"
+        FROM generate_series(
+            date_trunc('day', CURRENT_TIMESTAMP) - INTERVAL '29 days',
+            date_trunc('day', CURRENT_TIMESTAMP),
+            INTERVAL '1 day'
+        ) AS d(day)
+        LEFT JOIN card_reviews cr
+            ON cr.user_id = $1
+            AND ($2::VARCHAR IS NULL OR cr.deck_id = $2)
+            AND date_trunc('day', cr.reviewed_at) = d.day
+        GROUP BY d.day
+        ORDER BY d.day
+        "#,
+        learner_id,
+        deck_id
+    )
+    .fetch_all(db)
+    .await?;
+
+    let forecast = sqlx::query_as!(
+        DueForecast,
+        r#"
+        WITH scheduled AS (
+            SELECT GREATEST(
+                date_trunc('day', cp.due_date),
+                date_trunc('day', CURRENT_TIMESTAMP)
+            ) AS day
+            FROM card_progress cp
+            JOIN cards c ON c.id = cp.card_id
+            WHERE cp.user_id = $1
+                AND ($2::VARCHAR IS NULL OR c.deck_id = $2)
+                AND cp.state <> 'new'
+                AND cp.due_date IS NOT NULL
+        )
+        SELECT d.day::DATE AS "date!", COUNT(s.day) AS "due!"
+        FROM generate_series(
+            date_trunc('day', CURRENT_TIMESTAMP),
+            date_trunc('day', CURRENT_TIMESTAMP) + INTERVAL '29 days',
+            INTERVAL '1 day'
+        ) AS d(day)
+        LEFT JOIN scheduled s ON s.day = d.day
+        GROUP BY d.day
+        ORDER BY d.day
+        "#,
+        learner_id,
+        deck_id
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(LearnStats {
+        cards: CardCounts {
+            new: progress.new,
+            learning: progress.learning,
+            young: progress.young,
+            mature: progress.mature,
+            total: progress.total,
+        },
+        retention_7d: retention.retention_7d,
+        retention_30d: retention.retention_30d,
+        average_ease: progress.average_ease,
+        reviews_per_day,
+        forecast,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{core::flashcards::review, tests::create_test_user};
+    use chrono::{Duration, Utc};
+    use ogonek_types::CardReviewCreate;
+
+    async fn create_test_deck(db: &PgPool, user_id: &str) -> String {
+        let deck_id = nanoid::nanoid!();
+        sqlx::query!(
+            "INSERT INTO decks (id, title, created_by) VALUES ($1, $2, $3)",
+            deck_id,
+            "Deck",
+            user_id
+        )
+        .execute(db)
+        .await
+        .unwrap();
+        deck_id
+    }
+
+    // Creates a card with progress for the user in the given state, returns the progress id
+    async fn create_test_progress(
+        db: &PgPool,
+        user_id: &str,
+        deck_id: &str,
+        state: &str,
+        interval: i32,
+        due_in_days: i64,
+    ) -> String {
+        let card_id = nanoid::nanoid!();
+        sqlx::query!(
+            "INSERT INTO cards (id, front, back, deck_id) VALUES ($1, $2, $3, $4)",
+            card_id,
+            "Front",
+            "Back",
+            deck_id
+        )
+        .execute(db)
+        .await
+        .unwrap();
+
+        let progress_id = nanoid::nanoid!();
+        sqlx::query!(
+            r#"
+            INSERT INTO card_progress (id, user_id, card_id, state, interval, due_date)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            progress_id,
+            user_id,
+            card_id,
+            state,
+            interval,
+            Utc::now() + Duration::days(due_in_days)
+        )
+        .execute(db)
+        .await
+        .unwrap();
+        progress_id
+    }
+
+    fn test_review(quality: i32, elapsed_seconds: Option<i64>) -> CardReviewCreate {
+        CardReviewCreate {
+            quality,
+            ease_before: 2.5,
+            ease_after: 2.5,
+            interval_before: 1,
+            interval_after: 1,
+            elapsed_seconds,
+            reviewed_at: Utc::now(),
+        }
+    }
+
+    #[sqlx::test]
+    async fn test_read_counts_cards_by_state(db: PgPool) {
+        let user_id = create_test_user(&db, "testuser", "test@example.com").await;
+        let deck1_id = create_test_deck(&db, &user_id).await;
+        let deck2_id = create_test_deck(&db, &user_id).await;
+
+        create_test_progress(&db, &user_id, &deck1_id, "new", 1, 0).await;
+        create_test_progress(&db, &user_id, &deck1_id, "learning", 1, 0).await;
+        create_test_progress(&db, &user_id, &deck1_id, "review", 5, 3).await;
+        create_test_progress(&db, &user_id, &deck1_id, "review", 40, -2).await;
+        create_test_progress(&db, &user_id, &deck2_id, "relearning", 1, 0).await;
+
+        let stats = read(&db, &user_id, None, Some(&deck1_id)).await.unwrap();
+        assert_eq!(stats.cards.new, 1);
+        assert_eq!(stats.cards.learning, 1);
+        assert_eq!(stats.cards.young, 1);
+        assert_eq!(stats.cards.mature, 1);
+        assert_eq!(stats.cards.total, 4);
+        assert_eq!(stats.average_ease, Some(2.5));
+
+        // The overdue card and the learning card are due today
+        assert_eq!(stats.forecast.len(), 30);
+        assert_eq!(stats.forecast[0].due, 2);
+        assert_eq!(stats.forecast[3].due, 1);
+
+        let all = read(&db, &user_id, None, None).await.unwrap();
+        assert_eq!(all.cards.learning, 2);
+        assert_eq!(all.cards.total, 5);
+    }
+
+    #[sqlx::test]
+    async fn test_read_retention_skips_learning_answers(db: PgPool) {
+        let user_id = create_test_user(&db, "testuser", "test@example.com").await;
+        let deck_id = create_test_deck(&db, &user_id).await;
+        let progress_id = create_test_progress(&db, &user_id, &deck_id, "review", 5, 0).await;
+
+        let day = Some(2 * 86400);
+        for quality in [5, 4, 4, 1] {
+            review::create(&db, &progress_id, &user_id, &test_review(quality, day))
+                .await
+                .unwrap();
+        }
+        // Intra-day and first answers are left out of retention
+        review::create(&db, &progress_id, &user_id, &test_review(1, Some(60)))
+            .await
+            .unwrap();
+        review::create(&db, &progress_id, &user_id, &test_review(1, None))
+            .await
+            .unwrap();
+
+        let stats = read(&db, &user_id, None, Some(&deck_id)).await.unwrap();
+        assert_eq!(stats.retention_7d, Some(0.75));
+        assert_eq!(stats.retention_30d, Some(0.75));
+
+        assert_eq!(stats.reviews_per_day.len(), 30);
+        let today = stats.reviews_per_day.last().unwrap();
+        assert_eq!(today.reviews, 6);
+        assert_eq!(today.correct, 3);
+    }
+
+    #[sqlx::test]
+    async fn test_read_student_stats_requires_teacher(db: PgPool) {
+        let teacher_id = create_test_user(&db, "teacher", "teacher@example.com").await;
+        let student_id = create_test_user(&db, "student", "student@example.com").await;
+        let stranger_id = create_test_user(&db, "stranger", "stranger@example.com").await;
+        let deck_id = create_test_deck(&db, &teacher_id).await;
+        create_test_progress(&db, &student_id, &deck_id, "new", 1, 0).await;
+
+        sqlx::query!(
+            "INSERT INTO teacher_student (teacher_id, student_id) VALUES ($1, $2)",
+            teacher_id,
+            student_id
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+
+        let stats = read(&db, &teacher_id, Some(&student_id), Some(&deck_id))
+            .await
+            .unwrap();
+        assert_eq!(stats.cards.new, 1);
+        assert_eq!(stats.retention_7d, None);
+
+        let result = read(&db, &stranger_id, Some(&student_id), None).await;
+        assert!(matches!(result, Err(DbError::NotFound(_))));
+    }
+}
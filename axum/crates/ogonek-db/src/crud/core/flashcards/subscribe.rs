@@ -16,38 +16,23 @@ pub async fn subscribe(db: &PgPool, deck_id: &str, user_id: &str) -> Result<(),
     .execute(&mut *tx)
     .await?;
 
-    let cards = sqlx::query!(
+    // One row per side of every card, reversed and cloze cards have several
+    sqlx::query!(
         r#"
-    SELECT c.id FROM cards c
-    LEFT JOIN card_progress cp
-        ON cp.card_id = c.id
-        AND cp.user_id = $1
+    INSERT INTO card_progress
+        (id, user_id, card_id, ordinal, review_count, due_date, ease_factor, interval)
+    SELECT nanoid(), $1, c.id, o.ordinal, 0, CURRENT_TIMESTAMP, 2.5, 1
+    FROM cards c
+    CROSS JOIN card_ordinals(c.card_type, c.front) AS o(ordinal)
     WHERE c.deck_id = $2
-    AND cp.id IS NULL
+    ON CONFLICT (user_id, card_id, ordinal) DO NOTHING
     "#,
         user_id,
         deck_id
     )
-    .fetch_all(&mut *tx)
+    .execute(&mut *tx)
     .await?;
 
-    for card in cards {
-        sqlx::query!(
-            r#"
-        INSERT INTO card_progress
-            (id, user_id, card_id, review_count, due_date, ease_factor, interval)
-        VALUES
-            ($1, $2, $3, 0, CURRENT_TIMESTAMP, 2.5, 1)
-        ON CONFLICT (user_id, card_id) DO NOTHING
-        "#,
-            nanoid::nanoid!(),
-            user_id,
-            card.id,
-        )
-        .execute(&mut *tx)
-        .await?;
-    }
-
     tx.commit().await?;
 
     Ok(())
@@ -203,6 +188,77 @@ mod tests {
         assert_eq!(progress.interval, 1);
     }
 
+    async fn create_typed_card(db: &PgPool, deck_id: &str, front: &str, card_type: &str) -> String {
+        let card_id = nanoid::nanoid!();
+        sqlx::query!(
+            r#"
+            INSERT INTO cards (id, front, back, deck_id, card_type)
+            VALUES ($1, $2, 'Back', $3, $4)
+            "#,
+            card_id,
+            front,
+            deck_id,
+            card_type
+        )
+        .execute(db)
+        .await
+        .unwrap();
+        card_id
+    }
+
+    #[sqlx::test]
+    async fn test_subscribe_creates_progress_per_card_side(db: PgPool) {
+        // Setup
+        let user_id = create_test_user(&db, "testuser", "test@example.com").await;
+        let deck_id = create_test_deck(&db, "Test Deck", &user_id).await;
+        create_typed_card(&db, &deck_id, "Perro", "reversed").await;
+        create_typed_card(
+            &db,
+            &deck_id,
+            "{{c1::El}} {{c2::perro}} {{c1::ladra}}",
+            "cloze",
+        )
+        .await;
+        create_typed_card(&db, &deck_id, "Gato", "typed").await;
+
+        // Test
+        subscribe(&db, &deck_id, &user_id).await.unwrap();
+
+        // Verify - two directions, two clozes and one typed card
+        let progress_count = get_card_progress_count(&db, &user_id, &deck_id).await;
+        assert_eq!(progress_count, 5);
+    }
+
+    #[sqlx::test]
+    async fn test_card_edits_sync_subscriber_progress(db: PgPool) {
+        // Setup
+        let user_id = create_test_user(&db, "testuser", "test@example.com").await;
+        let deck_id = create_test_deck(&db, "Test Deck", &user_id).await;
+        subscribe(&db, &deck_id, &user_id).await.unwrap();
+
+        // Cards added after subscribing go through the trigger
+        let card_id = create_typed_card(&db, &deck_id, "Perro", "reversed").await;
+        assert_eq!(get_card_progress_count(&db, &user_id, &deck_id).await, 2);
+
+        sqlx::query!(
+            "UPDATE cards SET card_type = 'cloze', front = '{{c1::Un}} {{c2::perro}} {{c3::ladra}}' WHERE id = $1",
+            card_id
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+        assert_eq!(get_card_progress_count(&db, &user_id, &deck_id).await, 3);
+
+        sqlx::query!(
+            "UPDATE cards SET card_type = 'basic' WHERE id = $1",
+            card_id
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+        assert_eq!(get_card_progress_count(&db, &user_id, &deck_id).await, 1);
+    }
+
     #[sqlx::test]
     async fn test_subscribe_handles_duplicate_subscription(db: PgPool) {
         // Setup
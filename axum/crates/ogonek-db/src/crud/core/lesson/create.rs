@@ -3,6 +3,7 @@ use sqlx::PgPool;
 
 use crate::DbError;
 
+#[allow(clippy::unnecessary_unwrap)]
 pub async fn create(db: &PgPool, user_id: &str, create: LessonCreate) -> Result<String, DbError> {
     let mut assignee = user_id;
 
@@ -10,17 +10,31 @@ pub async fn log_activity(
     model_type: ModelType,
     action: ActionType,
     target_id: Option<&str>,
+) -> Result<(), DbError> {
+    log_activity_with_metadata(db, user_id, model_id, model_type, action, target_id, None).await
+}
+
+/// Same as `log_activity`, keeping extra details about the action with the entry
+pub async fn log_activity_with_metadata(
+    db: &PgPool,
+    user_id: &str,
+    model_id: &str,
+    model_type: ModelType,
+    action: ActionType,
+    target_id: Option<&str>,
+    metadata: Option<serde_json::Value>,
 ) -> Result<(), DbError> {
     sqlx::query!(
         r#"
-        INSERT INTO activity_logs (user_id, model_type, model_id, action, target_user_id)
-        VALUES ($1, $2, $3, $4, $5)
+        INSERT INTO activity_logs (user_id, model_type, model_id, action, target_user_id, metadata)
+        VALUES ($1, $2, $3, $4, $5, $6)
         "#,
         user_id,             // who did it
         model_type.as_str(), // what type
         model_id,            // which entity
         action.as_str(),     // what action
-        target_id            // who it affects
+        target_id,           // who it affects
+        metadata             // anything else worth keeping
     )
     .execute(db)
     .await?;
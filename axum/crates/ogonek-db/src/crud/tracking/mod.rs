@@ -1,6 +1,8 @@
 pub mod activity;
 pub mod audit;
 pub mod seen;
+pub mod study;
 pub use activity::*;
 pub use audit::*;
 pub use seen::*;
+pub use study::*;
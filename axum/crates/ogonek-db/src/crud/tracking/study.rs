@@ -0,0 +1,345 @@
+use crate::DbError;
+use chrono::NaiveDate;
+use ogonek_types::{ActionType, DailyGoals, HeatmapDay, ModelType, StudyStreak};
+use sqlx::PgPool;
+
+/// Current and longest run of days with a card reviewed or a task completed.
+/// Days are the user's own, see `user_timezone`
+pub async fn read_streak(db: &PgPool, user_id: &str) -> Result<StudyStreak, DbError> {
+    let streak = sqlx::query_as!(
+        StudyStreak,
+        r#"
+        WITH zone AS (
+            SELECT
+                user_timezone($1) AS tz,
+                (CURRENT_TIMESTAMP AT TIME ZONE user_timezone($1))::DATE AS today
+        ),
+        days AS (
+            SELECT (reviewed_at AT TIME ZONE z.tz)::DATE AS day
+            FROM card_reviews
+            CROSS JOIN zone z
+            WHERE user_id = $1
+            UNION
+            SELECT (created_at AT TIME ZONE z.tz)::DATE
+            FROM activity_logs
+            CROSS JOIN zone z
+            WHERE user_id = $1 AND model_type = $2 AND action = $3
+        ),
+        -- Consecutive days share the same offset from their row number
+        islands AS (
+            SELECT day, day - (ROW_NUMBER() OVER (ORDER BY day))::INT AS island
+            FROM days
+            WHERE day IS NOT NULL
+        ),
+        streaks AS (
+            SELECT MAX(day) AS last_day, COUNT(*) AS length
+            FROM islands
+            GROUP BY island
+        )
+        SELECT
+            COALESCE(
+                MAX(length) FILTER (WHERE last_day >= (SELECT today FROM zone) - 1),
+                0
+            ) AS "current!",
+            COALESCE(MAX(length), 0) AS "longest!",
+            COALESCE(MAX(last_day) = (SELECT today FROM zone), false) AS "studied_today!"
+        FROM streaks
+        "#,
+        user_id,
+        ModelType::Task.as_str(),
+        ActionType::Complete.as_str()
+    )
+    .fetch_one(db)
+    .await?;
+
+    Ok(streak)
+}
+
+/// Reviews and completed tasks so far today, against the goals in the user's preferences
+pub async fn read_daily_goals(db: &PgPool, user_id: &str) -> Result<DailyGoals, DbError> {
+    let goals = sqlx::query_as!(
+        DailyGoals,
+        r#"
+        WITH zone AS (
+            SELECT date_trunc('day', CURRENT_TIMESTAMP AT TIME ZONE user_timezone($1))
+                AT TIME ZONE user_timezone($1) AS day_start
+        )
+        SELECT
+            (
+                SELECT COUNT(*) FROM card_reviews
+                WHERE user_id = $1 AND reviewed_at >= (SELECT day_start FROM zone)
+            ) AS "reviews_done!",
+            COALESCE(up.daily_review_goal, 20) AS "review_goal!",
+            (
+                SELECT COUNT(*) FROM activity_logs
+                WHERE user_id = $1 AND model_type = $2 AND action = $3
+                    AND created_at >= (SELECT day_start FROM zone)
+            ) AS "tasks_done!",
+            COALESCE(up.daily_task_goal, 1) AS "task_goal!"
+        FROM (SELECT $1::VARCHAR AS user_id) u
+        LEFT JOIN user_preferences up ON up.user_id = u.user_id
+        "#,
+        user_id,
+        ModelType::Task.as_str(),
+        ActionType::Complete.as_str()
+    )
+    .fetch_one(db)
+    .await?;
+
+    Ok(goals)
+}
+
+/// The date where the user is, see `user_timezone`
+pub async fn read_today(db: &PgPool, user_id: &str) -> Result<NaiveDate, DbError> {
+    let today = sqlx::query_scalar!(
+        r#"SELECT (CURRENT_TIMESTAMP AT TIME ZONE user_timezone($1))::DATE AS "today!""#,
+        user_id
+    )
+    .fetch_one(db)
+    .await?;
+
+    Ok(today)
+}
+
+/// Activity per day between two dates, both included, in the user's timezone
+pub async fn read_heatmap(
+    db: &PgPool,
+    user_id: &str,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<HeatmapDay>, DbError> {
+    let days = sqlx::query_as!(
+        HeatmapDay,
+        r#"
+        WITH zone AS (
+            SELECT
+                user_timezone($1) AS tz,
+                $2::DATE::TIMESTAMP AT TIME ZONE user_timezone($1) AS starts,
+                ($3::DATE + 1)::TIMESTAMP AT TIME ZONE user_timezone($1) AS ends
+        ),
+        reviews AS (
+            SELECT (reviewed_at AT TIME ZONE z.tz)::DATE AS day, COUNT(*) AS reviews
+            FROM card_reviews
+            CROSS JOIN zone z
+            WHERE user_id = $1 AND reviewed_at >= z.starts AND reviewed_at < z.ends
+            GROUP BY 1
+        ),
+        activities AS (
+            SELECT
+                (created_at AT TIME ZONE z.tz)::DATE AS day,
+                COUNT(*) AS activities,
+                COUNT(*) FILTER (WHERE model_type = $4 AND action = $5) AS tasks_completed
+            FROM activity_logs
+            CROSS JOIN zone z
+            WHERE user_id = $1 AND created_at >= z.starts AND created_at < z.ends
+            GROUP BY 1
+        )
+        SELECT
+            COALESCE(r.day, a.day) AS "date!",
+            COALESCE(r.reviews, 0) AS "reviews!",
+            COALESCE(a.activities, 0) AS "activities!",
+            COALESCE(a.tasks_completed, 0) AS "tasks_completed!"
+        FROM reviews r
+        FULL JOIN activities a ON a.day = r.day
+        ORDER BY 1
+        "#,
+        user_id,
+        from,
+        to,
+        ModelType::Task.as_str(),
+        ActionType::Complete.as_str()
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(days)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        tests::{create_test_user, set_test_timezone},
+        tracking::log_activity,
+    };
+    use chrono::{DateTime, Duration, Utc};
+
+    // Logs a review of a fresh card `days_ago` days back
+    async fn create_test_review(db: &PgPool, user_id: &str, days_ago: i64) {
+        create_test_review_at(db, user_id, Utc::now() - Duration::days(days_ago)).await;
+    }
+
+    async fn create_test_review_at(db: &PgPool, user_id: &str, reviewed_at: DateTime<Utc>) {
+        let deck_id = nanoid::nanoid!();
+        let card_id = nanoid::nanoid!();
+        sqlx::query!(
+            "INSERT INTO decks (id, title, created_by) VALUES ($1, $2, $3)",
+            deck_id,
+            "Deck",
+            user_id
+        )
+        .execute(db)
+        .await
+        .unwrap();
+        sqlx::query!(
+            "INSERT INTO cards (id, front, back, deck_id) VALUES ($1, $2, $3, $4)",
+            card_id,
+            "Front",
+            "Back",
+            deck_id
+        )
+        .execute(db)
+        .await
+        .unwrap();
+        sqlx::query!(
+            r#"
+            INSERT INTO card_reviews (
+                id, user_id, card_id, deck_id, quality,
+                ease_before, ease_after, interval_before, interval_after, reviewed_at
+            )
+            VALUES ($1, $2, $3, $4, 4, 2.5, 2.5, 1, 1, $5)
+            "#,
+            nanoid::nanoid!(),
+            user_id,
+            card_id,
+            deck_id,
+            reviewed_at
+        )
+        .execute(db)
+        .await
+        .unwrap();
+    }
+
+    #[sqlx::test]
+    async fn test_read_streak(db: PgPool) {
+        let user_id = create_test_user(&db, "testuser", "test@example.com").await;
+
+        let streak = read_streak(&db, &user_id).await.unwrap();
+        assert_eq!(streak.current, 0);
+        assert_eq!(streak.longest, 0);
+        assert!(!streak.studied_today);
+
+        // A three day run that ended a week ago, then yesterday and the day before
+        for days_ago in [7, 8, 9, 1, 2] {
+            create_test_review(&db, &user_id, days_ago).await;
+        }
+
+        let streak = read_streak(&db, &user_id).await.unwrap();
+        assert_eq!(streak.current, 2);
+        assert_eq!(streak.longest, 3);
+        assert!(!streak.studied_today);
+
+        // Completing a task counts as studying
+        log_activity(
+            &db,
+            &user_id,
+            "task",
+            ModelType::Task,
+            ActionType::Complete,
+            None,
+        )
+        .await
+        .unwrap();
+
+        let streak = read_streak(&db, &user_id).await.unwrap();
+        assert_eq!(streak.current, 3);
+        assert_eq!(streak.longest, 3);
+        assert!(streak.studied_today);
+    }
+
+    #[sqlx::test]
+    async fn test_read_daily_goals(db: PgPool) {
+        let user_id = create_test_user(&db, "testuser", "test@example.com").await;
+
+        create_test_review(&db, &user_id, 0).await;
+        create_test_review(&db, &user_id, 0).await;
+        create_test_review(&db, &user_id, 1).await;
+        log_activity(
+            &db,
+            &user_id,
+            "task",
+            ModelType::Task,
+            ActionType::Complete,
+            None,
+        )
+        .await
+        .unwrap();
+
+        let goals = read_daily_goals(&db, &user_id).await.unwrap();
+        assert_eq!(goals.reviews_done, 2);
+        assert_eq!(goals.review_goal, 20);
+        assert_eq!(goals.tasks_done, 1);
+        assert!(!goals.is_met());
+
+        sqlx::query!(
+            "INSERT INTO user_preferences (user_id, daily_review_goal) VALUES ($1, 2)",
+            user_id
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+
+        let goals = read_daily_goals(&db, &user_id).await.unwrap();
+        assert!(goals.is_met());
+    }
+
+    #[sqlx::test]
+    async fn test_read_heatmap(db: PgPool) {
+        let user_id = create_test_user(&db, "testuser", "test@example.com").await;
+
+        create_test_review(&db, &user_id, 0).await;
+        create_test_review(&db, &user_id, 3).await;
+        create_test_review(&db, &user_id, 3).await;
+        create_test_review(&db, &user_id, 40).await;
+        log_activity(
+            &db,
+            &user_id,
+            "task",
+            ModelType::Task,
+            ActionType::Complete,
+            None,
+        )
+        .await
+        .unwrap();
+
+        let today = Utc::now().date_naive();
+        let days = read_heatmap(&db, &user_id, today - Duration::days(30), today)
+            .await
+            .unwrap();
+
+        assert_eq!(days.len(), 2);
+        assert_eq!(days[0].date, today - Duration::days(3));
+        assert_eq!(days[0].reviews, 2);
+        assert_eq!(days[1].date, today);
+        assert_eq!(days[1].reviews, 1);
+        assert_eq!(days[1].activities, 1);
+        assert_eq!(days[1].tasks_completed, 1);
+    }
+
+    #[sqlx::test]
+    async fn test_days_follow_user_timezone(db: PgPool) {
+        let user_id = create_test_user(&db, "testuser", "test@example.com").await;
+        let midnight = set_test_timezone(&db, &user_id).await;
+
+        // Either side of the user's midnight
+        create_test_review_at(&db, &user_id, midnight - Duration::minutes(1)).await;
+        create_test_review_at(&db, &user_id, midnight + Duration::minutes(1)).await;
+
+        let goals = read_daily_goals(&db, &user_id).await.unwrap();
+        assert_eq!(goals.reviews_done, 1);
+
+        let streak = read_streak(&db, &user_id).await.unwrap();
+        assert_eq!(streak.current, 2);
+        assert!(streak.studied_today);
+
+        let today = read_today(&db, &user_id).await.unwrap();
+        let days = read_heatmap(&db, &user_id, today - Duration::days(1), today)
+            .await
+            .unwrap();
+        assert_eq!(days.len(), 2);
+        assert_eq!(days[0].date, today - Duration::days(1));
+        assert_eq!(days[0].reviews, 1);
+        assert_eq!(days[1].date, today);
+        assert_eq!(days[1].reviews, 1);
+    }
+}
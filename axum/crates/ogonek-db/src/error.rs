@@ -1,4 +1,5 @@
 use crate::helpers::RRuleError;
+use ogonek_types::BusyPeriod;
 use sqlx::error::Error as SqlxError;
 use thiserror::Error;
 
@@ -12,6 +13,10 @@ pub enum DbError {
     TransactionFailed,
     #[error("Already exists: {0}")]
     AlreadyExists(String),
+    #[error("Precondition failed: {0}")]
+    PreconditionFailed(String),
+    #[error("Conflicts with {} events", .0.len())]
+    Conflict(Vec<BusyPeriod>),
     #[error("The event is not recurring")]
     NotRecurring,
     #[error("Invalid recurrence id")]
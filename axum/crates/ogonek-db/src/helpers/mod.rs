@@ -1,7 +1,5 @@
-mod parsers;
-mod rrule;
+pub mod rrule;
 use chrono::{DateTime, TimeZone, Utc};
-pub use parsers::*;
 pub use rrule::*;
 
 const NANOID: usize = 21;
@@ -37,6 +35,19 @@ pub fn extract_id_and_occurence(event_id: String) -> (String, Option<DateTime<Ut
     (event_id, None)
 }
 
+/// Builds the id of an occurrence, or keeps the event id when there is no occurrence
+pub fn occurrence_id(event_id: &str, occurrence: Option<DateTime<Utc>>) -> String {
+    match occurrence {
+        Some(occurrence) => format!(
+            "{}{}{}",
+            event_id,
+            OCCURRENCE_SEPARATOR,
+            occurrence.timestamp()
+        ),
+        None => event_id.to_string(),
+    }
+}
+
 /// Remove the UNTIL part from an RRULE string according to RFC 5545
 pub fn remove_until_from_rrule(rrule: &str) -> String {
     rrule
@@ -1,22 +1,56 @@
-use chrono::{DateTime, Datelike, Duration, Utc, Weekday};
+use std::{collections::HashMap, fmt};
 
-#[derive(Debug, Clone)]
+use chrono::{
+    DateTime, Datelike, Duration, LocalResult, NaiveDate, NaiveDateTime, Offset, TimeZone, Utc,
+    Weekday,
+};
+use chrono_tz::Tz;
+use thiserror::Error;
+
+/// Upper bound on the periods walked through by one expansion
+const MAX_PERIODS: i64 = 10_000;
+const ICAL_DATETIME: &str = "%Y%m%dT%H%M%S";
+const ICAL_DATE: &str = "%Y%m%d";
+
+/// A recurrence rule as defined by RFC 5545, section 3.3.10.
+/// Sub-daily frequencies and BYWEEKNO are not supported.
+#[derive(Debug, Clone, PartialEq)]
 pub struct RRule {
     pub freq: Frequency,
     pub interval: i32,
-    pub by_day: Option<Vec<Weekday>>,
+    pub by_day: Option<Vec<WeekdayNum>>,
+    pub by_month_day: Option<Vec<i32>>,
+    pub by_year_day: Option<Vec<i32>>,
+    pub by_month: Option<Vec<u32>>,
+    pub by_set_pos: Option<Vec<i32>>,
+    pub week_start: Weekday,
     pub count: Option<i32>,
-    pub until: Option<DateTime<Utc>>,
+    pub until: Option<Until>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Frequency {
     Daily,
     Weekly,
     Monthly,
     Yearly,
 }
-use thiserror::Error;
+
+/// A BYDAY entry, `1MO` is the first Monday of the month or year and `-1FR` the last Friday
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WeekdayNum {
+    pub ordinal: Option<i32>,
+    pub weekday: Weekday,
+}
+
+/// The end of a rule. A UTC value is an instant, floating and date-only values are
+/// wall-clock times in the event's zone
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Until {
+    Utc(DateTime<Utc>),
+    Local(NaiveDateTime),
+    Date(NaiveDate),
+}
 
 #[derive(Error, Debug)]
 pub enum RRuleError {
@@ -26,15 +60,28 @@ pub enum RRuleError {
     MissingField(String),
     #[error("Unsupported frequency: {0}")]
     UnsupportedFrequency(String),
+    #[error("Unsupported rule part: {0}")]
+    UnsupportedPart(String),
     #[error("Invalid interval: {0}")]
     InvalidInterval(String),
     #[error("Invalid day: {0}")]
     InvalidDay(String),
+    #[error("Invalid month day: {0}")]
+    InvalidMonthDay(String),
+    #[error("Invalid year day: {0}")]
+    InvalidYearDay(String),
+    #[error("Invalid month: {0}")]
+    InvalidMonth(String),
+    #[error("Invalid set position: {0}")]
+    InvalidSetPos(String),
     #[error("Invalid count: {0}")]
     InvalidCount(String),
     #[error("Invalid until date: {0}")]
     InvalidUntilDate(String),
+    #[error("Invalid date: {0}")]
+    InvalidDate(String),
 }
+
 impl RRule {
     pub fn parse(rrule_str: Option<String>) -> Result<Option<Self>, RRuleError> {
         let Some(rrule) = rrule_str else {
@@ -48,16 +95,17 @@ impl RRule {
         let mut freq = None;
         let mut interval = 1;
         let mut by_day = None;
+        let mut by_month_day = None;
+        let mut by_year_day = None;
+        let mut by_month = None;
+        let mut by_set_pos = None;
+        let mut week_start = Weekday::Mon;
         let mut count = None;
         let mut until = None;
 
         for part in rrule.split(';') {
-            let mut split = part.split('=');
-            let key = split.next().ok_or_else(|| {
-                RRuleError::InvalidFormat("Missing key in RRULE part".to_string())
-            })?;
-            let value = split.next().ok_or_else(|| {
-                RRuleError::InvalidFormat(format!("Missing value for key: {}", key))
+            let (key, value) = part.split_once('=').ok_or_else(|| {
+                RRuleError::InvalidFormat(format!("Missing value for key: {part}"))
             })?;
 
             match key {
@@ -73,23 +121,37 @@ impl RRule {
                 "INTERVAL" => {
                     interval = value
                         .parse()
-                        .map_err(|_| RRuleError::InvalidInterval(value.to_string()))?;
+                        .ok()
+                        .filter(|interval| *interval > 0)
+                        .ok_or_else(|| RRuleError::InvalidInterval(value.to_string()))?;
                 }
                 "BYDAY" => {
-                    let days = value
-                        .split(',')
-                        .map(|day| match day {
-                            "MO" => Ok(Weekday::Mon),
-                            "TU" => Ok(Weekday::Tue),
-                            "WE" => Ok(Weekday::Wed),
-                            "TH" => Ok(Weekday::Thu),
-                            "FR" => Ok(Weekday::Fri),
-                            "SA" => Ok(Weekday::Sat),
-                            "SU" => Ok(Weekday::Sun),
-                            _ => Err(RRuleError::InvalidDay(day.to_string())),
-                        })
-                        .collect::<Result<Vec<_>, _>>()?;
-                    by_day = Some(days);
+                    by_day = Some(
+                        value
+                            .split(',')
+                            .map(WeekdayNum::parse)
+                            .collect::<Result<Vec<_>, _>>()?,
+                    );
+                }
+                "BYMONTHDAY" => {
+                    by_month_day = Some(parse_list(value, 31, RRuleError::InvalidMonthDay)?);
+                }
+                "BYYEARDAY" => {
+                    by_year_day = Some(parse_list(value, 366, RRuleError::InvalidYearDay)?);
+                }
+                "BYMONTH" => {
+                    let months = parse_list(value, 12, RRuleError::InvalidMonth)?;
+                    if months.iter().any(|month| *month < 0) {
+                        return Err(RRuleError::InvalidMonth(value.to_string()));
+                    }
+                    by_month = Some(months.into_iter().map(|month| month as u32).collect());
+                }
+                "BYSETPOS" => {
+                    by_set_pos = Some(parse_list(value, 366, RRuleError::InvalidSetPos)?);
+                }
+                "WKST" => {
+                    week_start = parse_weekday(value)
+                        .ok_or_else(|| RRuleError::InvalidDay(value.to_string()))?;
                 }
                 "COUNT" => {
                     count = Some(
@@ -98,17 +160,9 @@ impl RRule {
                             .map_err(|_| RRuleError::InvalidCount(value.to_string()))?,
                     );
                 }
-                "UNTIL" => {
-                    // Support du format iCal compact: 20250925T000000Z
-                    until = Some(
-                        DateTime::parse_from_str(&format!("{}+00:00", value), "%Y%m%dT%H%M%SZ%z")
-                            .or_else(|_| {
-                                // Fallback vers RFC3339 pour rétrocompatibilité
-                                DateTime::parse_from_rfc3339(value)
-                            })
-                            .map_err(|_| RRuleError::InvalidUntilDate(value.to_string()))?
-                            .with_timezone(&Utc),
-                    );
+                "UNTIL" => until = Some(Until::parse(value)?),
+                "BYSECOND" | "BYMINUTE" | "BYHOUR" | "BYWEEKNO" => {
+                    return Err(RRuleError::UnsupportedPart(key.to_string()));
                 }
                 _ => {} // Ignore unknown fields for now
             }
@@ -116,152 +170,103 @@ impl RRule {
 
         let freq = freq.ok_or_else(|| RRuleError::MissingField("FREQ".to_string()))?;
 
-        Ok(Some(RRule {
+        let rule = RRule {
             freq,
             interval,
             by_day,
+            by_month_day,
+            by_year_day,
+            by_month,
+            by_set_pos,
+            week_start,
             count,
             until,
-        }))
+        };
+        rule.validate()?;
+        Ok(Some(rule))
+    }
+
+    /// Rejects the combinations RFC 5545 forbids
+    fn validate(&self) -> Result<(), RRuleError> {
+        if self.by_month_day.is_some() && self.freq == Frequency::Weekly {
+            return Err(RRuleError::InvalidFormat(
+                "BYMONTHDAY cannot be used with FREQ=WEEKLY".to_string(),
+            ));
+        }
+        if self.by_year_day.is_some() && self.freq != Frequency::Yearly {
+            return Err(RRuleError::InvalidFormat(
+                "BYYEARDAY can only be used with FREQ=YEARLY".to_string(),
+            ));
+        }
+        // Ordinal weekdays count within a month or a year
+        if matches!(self.freq, Frequency::Daily | Frequency::Weekly)
+            && let Some(day) = self
+                .by_day
+                .iter()
+                .flatten()
+                .find(|day| day.ordinal.is_some())
+        {
+            return Err(RRuleError::InvalidDay(day.to_string()));
+        }
+        if self.by_set_pos.is_some()
+            && self.by_day.is_none()
+            && self.by_month_day.is_none()
+            && self.by_year_day.is_none()
+            && self.by_month.is_none()
+        {
+            return Err(RRuleError::InvalidFormat(
+                "BYSETPOS needs another BYxxx part".to_string(),
+            ));
+        }
+        Ok(())
     }
 
+    /// Expands the rule from `dtstart` and returns the occurrences within the range.
+    /// Recurrence follows the wall clock of `tz`: a weekly 18:00 lesson stays at 18:00
+    /// local time across DST changes while its UTC time moves.
     pub fn generate_occurrences(
         &self,
         dtstart: DateTime<Utc>,
+        tz: Tz,
         range_start: DateTime<Utc>,
         range_end: DateTime<Utc>,
     ) -> Vec<DateTime<Utc>> {
-        let mut occurrences = Vec::new();
+        let start = dtstart.with_timezone(&tz).naive_local();
+        let last_day = range_end.with_timezone(&tz).date_naive();
 
-        match &self.freq {
-            Frequency::Weekly if self.by_day.is_some() => {
-                // Special handling for weekly events with BYDAY
-                if let Some(ref days) = self.by_day {
-                    let mut week_start =
-                        dtstart - Duration::days(dtstart.weekday().num_days_from_monday() as i64);
-                    let mut week_count = 0;
-                    let mut occurrence_count = 0;
-
-                    // Fast-forward to the first week that could contain occurrences in range
-                    if week_start < range_start {
-                        let weeks_diff = (range_start - week_start).num_weeks();
-                        let intervals_to_skip = weeks_diff / self.interval as i64;
-                        week_start += Duration::weeks(intervals_to_skip * self.interval as i64);
-                        week_count = intervals_to_skip;
-                    }
+        // COUNT is counted from DTSTART, so only rules without it may skip ahead
+        let first_period = match self.count {
+            Some(_) => 0,
+            None => self.first_period(start, range_start.with_timezone(&tz).naive_local()),
+        };
 
-                    while week_start <= range_end {
-                        // Check count limit
-                        if let Some(max_count) = self.count
-                            && occurrence_count >= max_count
-                        {
-                            break;
-                        }
-
-                        // Check until limit
-                        if let Some(until) = self.until
-                            && week_start > until
-                        {
-                            break;
-                        }
-
-                        // Generate occurrences for this week
-                        for &day in days {
-                            let occurrence =
-                                week_start + Duration::days(day.num_days_from_monday() as i64);
-
-                            // Check all constraints
-                            if occurrence >= dtstart
-                                && occurrence >= range_start
-                                && occurrence <= range_end
-                            {
-                                if let Some(until) = self.until
-                                    && occurrence > until
-                                {
-                                    continue;
-                                }
-
-                                if let Some(max_count) = self.count
-                                    && occurrence_count >= max_count
-                                {
-                                    break;
-                                }
-
-                                occurrences.push(occurrence);
-                                occurrence_count += 1;
-                            }
-                        }
-
-                        // Move to next week
-                        week_start += Duration::weeks(self.interval as i64);
-                        week_count += 1;
-
-                        // Safety break
-                        if week_count > 1000 {
-                            break;
-                        }
-                    }
-                }
+        let mut occurrences = Vec::new();
+        let mut count = 0;
+        for period in first_period..first_period + MAX_PERIODS {
+            let span = self.period_span(start.date(), period);
+            // Rules matching nothing for a while must not walk every period
+            if span.first().is_none_or(|first| *first > last_day) {
+                break;
             }
-            _ => {
-                // Original logic for non-weekly-BYDAY events
-                let mut current = dtstart;
-                let mut count = 0;
-
-                // Fast-forward to the first occurrence that could be in range
-                if current < range_start {
-                    current = self.fast_forward_to_range(dtstart, range_start);
-                }
 
-                while current <= range_end {
-                    // Check count limit
-                    if let Some(max_count) = self.count
-                        && count >= max_count
-                    {
-                        break;
-                    }
-
-                    // Check until limit
-                    if let Some(until) = self.until
-                        && current > until
-                    {
-                        break;
-                    }
+            for date in self.period_dates(start.date(), span) {
+                let local = date.and_time(start.time());
+                if local < start {
+                    continue;
+                }
 
-                    // If in range, add occurrence
-                    if current >= range_start {
-                        occurrences.push(current);
-                    }
+                let occurrence = local_to_utc(&tz, local);
+                let past_until = self
+                    .until
+                    .is_some_and(|until| !until.includes(local, occurrence));
+                let past_count = self.count.is_some_and(|max| count >= max);
+                if past_until || past_count || occurrence > range_end {
+                    return occurrences;
+                }
 
-                    // Move to next occurrence
-                    current = match self.freq {
-                        Frequency::Daily => current + Duration::days(self.interval as i64),
-                        Frequency::Weekly => current + Duration::weeks(self.interval as i64),
-                        Frequency::Monthly => {
-                            // This is tricky - month arithmetic
-                            let mut year = current.year();
-                            let mut month = current.month() as i32 + self.interval;
-
-                            while month > 12 {
-                                year += 1;
-                                month -= 12;
-                            }
-
-                            current
-                                .with_year(year)
-                                .unwrap()
-                                .with_month(month as u32)
-                                .unwrap()
-                        }
-                        Frequency::Yearly => current + Duration::days(365 * self.interval as i64), /* Rough */
-                    };
-
-                    count += 1;
-
-                    // Safety break
-                    if count > 1000 {
-                        break;
-                    }
+                count += 1;
+                if occurrence >= range_start {
+                    occurrences.push(occurrence);
                 }
             }
         }
@@ -269,56 +274,723 @@ impl RRule {
         occurrences
     }
 
-    fn fast_forward_to_range(
-        &self,
-        dtstart: DateTime<Utc>,
-        range_start: DateTime<Utc>,
-    ) -> DateTime<Utc> {
-        if dtstart >= range_start {
-            return dtstart;
+    /// Every day of a period of the rule: a day, a week starting on WKST, a month or a year
+    fn period_span(&self, start: NaiveDate, period: i64) -> Vec<NaiveDate> {
+        let step = period * self.interval as i64;
+        let (first, days) = match self.freq {
+            Frequency::Daily => (start.checked_add_signed(Duration::days(step)), 1),
+            Frequency::Weekly => {
+                let offset = start.weekday().days_since(self.week_start) as i64;
+                let first = start
+                    .checked_sub_signed(Duration::days(offset))
+                    .and_then(|monday| monday.checked_add_signed(Duration::weeks(step)));
+                (first, 7)
+            }
+            Frequency::Monthly => {
+                let first = start.with_day(1).and_then(|first| add_months(first, step));
+                let days = first.map_or(0, |first| days_in_month(first.year(), first.month()));
+                (first, days)
+            }
+            Frequency::Yearly => {
+                let first = i32::try_from(start.year() as i64 + step)
+                    .ok()
+                    .and_then(|year| NaiveDate::from_ymd_opt(year, 1, 1));
+                let days = first.map_or(0, |first| if first.leap_year() { 366 } else { 365 });
+                (first, days)
+            }
+        };
+
+        first
+            .map(|first| first.iter_days().take(days as usize).collect())
+            .unwrap_or_default()
+    }
+
+    /// The days of a period the rule recurs on, in order
+    fn period_dates(&self, start: NaiveDate, span: Vec<NaiveDate>) -> Vec<NaiveDate> {
+        let mut dates = span;
+        if let Some(months) = &self.by_month {
+            dates.retain(|date| months.contains(&date.month()));
+        }
+
+        if let Some(days) = &self.by_day {
+            // Ordinals count within the month unless a yearly rule spans whole years
+            let per_month = self.freq == Frequency::Monthly || self.by_month.is_some();
+            dates = filter_weekdays(dates, days, per_month);
+        }
+        if let Some(month_days) = &self.by_month_day {
+            dates.retain(|date| {
+                let length = days_in_month(date.year(), date.month()) as i32;
+                month_days
+                    .iter()
+                    .any(|day| resolve_ordinal(*day, length) == Some(date.day() as i32))
+            });
+        }
+        if let Some(year_days) = &self.by_year_day {
+            dates.retain(|date| {
+                let length = if date.leap_year() { 366 } else { 365 };
+                year_days
+                    .iter()
+                    .any(|day| resolve_ordinal(*day, length) == Some(date.ordinal() as i32))
+            });
         }
 
-        match self.freq {
-            Frequency::Daily => {
-                let days_diff = (range_start - dtstart).num_days();
-                let intervals_to_skip = days_diff / self.interval as i64;
-                dtstart + Duration::days(intervals_to_skip * self.interval as i64)
+        // Without a day part the rule recurs on the start's day of the period
+        if self.by_day.is_none() && self.by_month_day.is_none() && self.by_year_day.is_none() {
+            match self.freq {
+                Frequency::Daily => {}
+                Frequency::Weekly => dates.retain(|date| date.weekday() == start.weekday()),
+                Frequency::Monthly => dates.retain(|date| date.day() == start.day()),
+                Frequency::Yearly => dates.retain(|date| {
+                    date.day() == start.day()
+                        && (self.by_month.is_some() || date.month() == start.month())
+                }),
             }
-            Frequency::Weekly => {
-                let weeks_diff = (range_start - dtstart).num_weeks();
-                let intervals_to_skip = weeks_diff / self.interval as i64;
-                dtstart + Duration::weeks(intervals_to_skip * self.interval as i64)
+        }
+
+        match &self.by_set_pos {
+            Some(positions) => {
+                let mut selected: Vec<_> = positions
+                    .iter()
+                    .filter_map(|position| resolve_ordinal(*position, dates.len() as i32))
+                    .map(|index| dates[index as usize - 1])
+                    .collect();
+                selected.sort();
+                selected.dedup();
+                selected
             }
+            None => dates,
+        }
+    }
+
+    /// The first period which may recur at or after `from`, one early to stay safe
+    fn first_period(&self, start: NaiveDateTime, from: NaiveDateTime) -> i64 {
+        if from <= start {
+            return 0;
+        }
+
+        let elapsed = match self.freq {
+            Frequency::Daily => (from.date() - start.date()).num_days(),
+            Frequency::Weekly => (from.date() - start.date()).num_weeks(),
             Frequency::Monthly => {
-                let current = dtstart;
-                let months_diff = (range_start.year() - dtstart.year()) * 12
-                    + (range_start.month() as i32 - dtstart.month() as i32);
-                let intervals_to_skip = months_diff / self.interval;
+                (from.year() - start.year()) as i64 * 12 + from.month() as i64
+                    - start.month() as i64
+            }
+            Frequency::Yearly => (from.year() - start.year()) as i64,
+        };
+        (elapsed / self.interval as i64 - 1).max(0)
+    }
+}
+
+/// Writes the rule in canonical form, parts in RFC 5545 order and defaults left out
+impl fmt::Display for RRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FREQ={}", self.freq)?;
+        if let Some(until) = &self.until {
+            write!(f, ";UNTIL={until}")?;
+        }
+        if let Some(count) = self.count {
+            write!(f, ";COUNT={count}")?;
+        }
+        if self.interval != 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        if let Some(days) = &self.by_day {
+            write!(f, ";BYDAY={}", join(days))?;
+        }
+        if let Some(days) = &self.by_month_day {
+            write!(f, ";BYMONTHDAY={}", join(days))?;
+        }
+        if let Some(days) = &self.by_year_day {
+            write!(f, ";BYYEARDAY={}", join(days))?;
+        }
+        if let Some(months) = &self.by_month {
+            write!(f, ";BYMONTH={}", join(months))?;
+        }
+        if let Some(positions) = &self.by_set_pos {
+            write!(f, ";BYSETPOS={}", join(positions))?;
+        }
+        if self.week_start != Weekday::Mon {
+            write!(f, ";WKST={}", weekday_code(self.week_start))?;
+        }
+        Ok(())
+    }
+}
 
-                let mut year = current.year();
-                let mut month = current.month() as i32 + (intervals_to_skip * self.interval);
+impl fmt::Display for Frequency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Daily => "DAILY",
+            Self::Weekly => "WEEKLY",
+            Self::Monthly => "MONTHLY",
+            Self::Yearly => "YEARLY",
+        })
+    }
+}
 
-                while month > 12 {
-                    year += 1;
-                    month -= 12;
-                }
-                while month < 1 {
-                    year -= 1;
-                    month += 12;
-                }
+impl WeekdayNum {
+    fn parse(value: &str) -> Result<Self, RRuleError> {
+        let invalid = || RRuleError::InvalidDay(value.to_string());
+        let split = value.len().checked_sub(2).ok_or_else(invalid)?;
+        let (ordinal, day) = value.split_at_checked(split).ok_or_else(invalid)?;
+        let weekday = parse_weekday(day).ok_or_else(invalid)?;
+
+        let ordinal = match ordinal {
+            "" => None,
+            ordinal => Some(
+                ordinal
+                    .parse::<i32>()
+                    .ok()
+                    .filter(|ordinal| *ordinal != 0 && ordinal.abs() <= 53)
+                    .ok_or_else(invalid)?,
+            ),
+        };
+
+        Ok(Self { ordinal, weekday })
+    }
+}
+
+impl fmt::Display for WeekdayNum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(ordinal) = self.ordinal {
+            write!(f, "{ordinal}")?;
+        }
+        f.write_str(weekday_code(self.weekday))
+    }
+}
 
-                current
-                    .with_year(year)
-                    .unwrap()
-                    .with_month(month as u32)
-                    .unwrap()
+impl Until {
+    fn parse(value: &str) -> Result<Self, RRuleError> {
+        if let Some(utc) = value.strip_suffix('Z')
+            && let Ok(naive) = NaiveDateTime::parse_from_str(utc, ICAL_DATETIME)
+        {
+            return Ok(Self::Utc(naive.and_utc()));
+        }
+        if let Ok(naive) = NaiveDateTime::parse_from_str(value, ICAL_DATETIME) {
+            return Ok(Self::Local(naive));
+        }
+        if let Ok(date) = NaiveDate::parse_from_str(value, ICAL_DATE) {
+            return Ok(Self::Date(date));
+        }
+        // Older rules were written in RFC 3339
+        if let Ok(datetime) = DateTime::parse_from_rfc3339(value) {
+            return Ok(Self::Utc(datetime.with_timezone(&Utc)));
+        }
+
+        Err(RRuleError::InvalidUntilDate(value.to_string()))
+    }
+
+    fn includes(&self, local: NaiveDateTime, occurrence: DateTime<Utc>) -> bool {
+        match self {
+            Self::Utc(until) => occurrence <= *until,
+            Self::Local(until) => local <= *until,
+            // A bare date includes the whole day
+            Self::Date(until) => local.date() <= *until,
+        }
+    }
+}
+
+impl fmt::Display for Until {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Utc(until) => write!(f, "{}Z", until.format(ICAL_DATETIME)),
+            Self::Local(until) => write!(f, "{}", until.format(ICAL_DATETIME)),
+            Self::Date(until) => write!(f, "{}", until.format(ICAL_DATE)),
+        }
+    }
+}
+
+fn parse_weekday(code: &str) -> Option<Weekday> {
+    match code {
+        "MO" => Some(Weekday::Mon),
+        "TU" => Some(Weekday::Tue),
+        "WE" => Some(Weekday::Wed),
+        "TH" => Some(Weekday::Thu),
+        "FR" => Some(Weekday::Fri),
+        "SA" => Some(Weekday::Sat),
+        "SU" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+fn weekday_code(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+/// Parses a comma separated list of non-zero values within `-max..=max`
+fn parse_list(
+    value: &str,
+    max: i32,
+    error: fn(String) -> RRuleError,
+) -> Result<Vec<i32>, RRuleError> {
+    value
+        .split(',')
+        .map(|item| {
+            item.parse::<i32>()
+                .ok()
+                .filter(|item| *item != 0 && item.abs() <= max)
+                .ok_or_else(|| error(item.to_string()))
+        })
+        .collect()
+}
+
+fn join<T: fmt::Display>(values: &[T]) -> String {
+    values
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// The 1-based position an ordinal points to in a list of `length`, negative ordinals
+/// count from the end
+fn resolve_ordinal(ordinal: i32, length: i32) -> Option<i32> {
+    let position = if ordinal < 0 {
+        length + ordinal + 1
+    } else {
+        ordinal
+    };
+    (1..=length).contains(&position).then_some(position)
+}
+
+/// Keeps the dates matching one of the BYDAY entries, ordinals counting within each
+/// month or within the whole list
+fn filter_weekdays(dates: Vec<NaiveDate>, days: &[WeekdayNum], per_month: bool) -> Vec<NaiveDate> {
+    let group = |date: &NaiveDate| (per_month.then(|| date.month()), date.weekday());
+
+    let mut totals = HashMap::new();
+    for date in &dates {
+        *totals.entry(group(date)).or_insert(0) += 1;
+    }
+
+    let mut seen = HashMap::new();
+    dates
+        .into_iter()
+        .filter(|date| {
+            let position = seen.entry(group(date)).or_insert(0);
+            *position += 1;
+            let total = totals[&group(date)];
+
+            days.iter().any(|day| {
+                day.weekday == date.weekday()
+                    && day
+                        .ordinal
+                        .is_none_or(|ordinal| resolve_ordinal(ordinal, total) == Some(*position))
+            })
+        })
+        .collect()
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    let next = match month {
+        12 => NaiveDate::from_ymd_opt(year + 1, 1, 1),
+        _ => NaiveDate::from_ymd_opt(year, month + 1, 1),
+    };
+    next.and_then(|next| next.pred_opt())
+        .map_or(31, |last| last.day())
+}
+
+/// An EXDATE or RDATE entry. Exact times are instants, floating times and bare dates
+/// are read on the wall clock of the event's zone
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RecurrenceDate {
+    Instant(DateTime<Utc>),
+    Day(NaiveDate),
+}
+
+impl RecurrenceDate {
+    pub fn parse(value: &str, tz: &Tz) -> Result<Self, RRuleError> {
+        let value = value.trim();
+
+        if let Ok(datetime) = DateTime::parse_from_rfc3339(value) {
+            return Ok(Self::Instant(datetime.with_timezone(&Utc)));
+        }
+        if let Some(utc) = value.strip_suffix('Z')
+            && let Ok(naive) = NaiveDateTime::parse_from_str(utc, ICAL_DATETIME)
+        {
+            return Ok(Self::Instant(naive.and_utc()));
+        }
+        for format in [ICAL_DATETIME, "%Y-%m-%dT%H:%M:%S"] {
+            if let Ok(naive) = NaiveDateTime::parse_from_str(value, format) {
+                return Ok(Self::Instant(local_to_utc(tz, naive)));
             }
-            Frequency::Yearly => {
-                let years_diff = range_start.year() - dtstart.year();
-                let intervals_to_skip = years_diff / self.interval;
-                let target_year = dtstart.year() + (intervals_to_skip * self.interval);
-                dtstart.with_year(target_year).unwrap()
+        }
+        for format in ["%Y-%m-%d", "%Y%m%d"] {
+            if let Ok(date) = NaiveDate::parse_from_str(value, format) {
+                return Ok(Self::Day(date));
             }
         }
+
+        Err(RRuleError::InvalidDate(value.to_string()))
+    }
+
+    /// Parses a TEXT[] column, skipping blank entries
+    pub fn parse_all(values: &Option<Vec<String>>, tz: &Tz) -> Result<Vec<Self>, RRuleError> {
+        values
+            .iter()
+            .flatten()
+            .filter(|value| !value.trim().is_empty())
+            .map(|value| Self::parse(value, tz))
+            .collect()
+    }
+
+    /// Whether the entry falls on the occurrence
+    pub fn matches(&self, occurrence: DateTime<Utc>, tz: &Tz) -> bool {
+        match self {
+            Self::Instant(instant) => *instant == occurrence,
+            Self::Day(day) => occurrence.with_timezone(tz).date_naive() == *day,
+        }
+    }
+
+    /// The occurrence an RDATE adds, a bare date recurs at the start's wall-clock time
+    pub fn occurrence(&self, dtstart: DateTime<Utc>, tz: &Tz) -> DateTime<Utc> {
+        match self {
+            Self::Instant(instant) => *instant,
+            Self::Day(day) => local_to_utc(tz, day.and_time(dtstart.with_timezone(tz).time())),
+        }
+    }
+}
+
+/// The zone an event recurs in, UTC when it is not a known IANA zone
+pub fn event_zone(tzid: Option<&str>) -> Tz {
+    tzid.and_then(|tzid| tzid.parse().ok()).unwrap_or(Tz::UTC)
+}
+
+/// Converts a wall-clock time of `tz` to UTC, following RFC 5545: an ambiguous time is
+/// its first instance, a time skipped by a DST change takes the offset in force before it
+pub fn local_to_utc(tz: &Tz, local: NaiveDateTime) -> DateTime<Utc> {
+    match tz.from_local_datetime(&local) {
+        LocalResult::Single(datetime) => datetime.with_timezone(&Utc),
+        LocalResult::Ambiguous(first, _) => first.with_timezone(&Utc),
+        LocalResult::None => {
+            let offset = (1..=48)
+                .find_map(|half_hours| {
+                    tz.from_local_datetime(&(local - Duration::minutes(30 * half_hours)))
+                        .latest()
+                })
+                .map(|before| before.offset().fix().local_minus_utc())
+                .unwrap_or(0);
+            (local - Duration::seconds(offset as i64)).and_utc()
+        }
+    }
+}
+
+fn add_months(date: NaiveDate, months: i64) -> Option<NaiveDate> {
+    let total = date.year() as i64 * 12 + date.month0() as i64 + months;
+    NaiveDate::from_ymd_opt(
+        i32::try_from(total.div_euclid(12)).ok()?,
+        total.rem_euclid(12) as u32 + 1,
+        date.day(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn berlin() -> Tz {
+        "Europe/Berlin".parse().unwrap()
+    }
+
+    fn utc(value: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(value)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn rule(value: &str) -> RRule {
+        RRule::parse(Some(value.to_string())).unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_weekly_keeps_local_time_across_dst() {
+        // 18:00 in Berlin, a week before clocks go forward on March 30th
+        let dtstart = utc("2025-03-24T17:00:00Z");
+        let occurrences = rule("FREQ=WEEKLY;COUNT=3").generate_occurrences(
+            dtstart,
+            berlin(),
+            dtstart,
+            utc("2025-12-31T00:00:00Z"),
+        );
+
+        assert_eq!(
+            occurrences,
+            [
+                utc("2025-03-24T17:00:00Z"),
+                utc("2025-03-31T16:00:00Z"),
+                utc("2025-04-07T16:00:00Z"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_weekly_by_day_across_dst_end() {
+        // Mondays and Thursdays at 18:00 around October 26th, when clocks go back
+        let dtstart = utc("2025-10-20T16:00:00Z");
+        let occurrences = rule("FREQ=WEEKLY;BYDAY=TH,MO").generate_occurrences(
+            dtstart,
+            berlin(),
+            utc("2025-10-22T00:00:00Z"),
+            utc("2025-10-31T00:00:00Z"),
+        );
+
+        assert_eq!(
+            occurrences,
+            [
+                utc("2025-10-23T16:00:00Z"),
+                utc("2025-10-27T17:00:00Z"),
+                utc("2025-10-30T17:00:00Z"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_count_is_kept_when_skipping_ahead() {
+        let dtstart = utc("2025-01-01T09:00:00Z");
+        let daily = rule("FREQ=DAILY;INTERVAL=2;COUNT=5");
+        let occurrences = daily.generate_occurrences(
+            dtstart,
+            Tz::UTC,
+            utc("2025-01-06T00:00:00Z"),
+            utc("2025-02-01T00:00:00Z"),
+        );
+        // The 1st, 3rd and 5th are before the range, the rule ends on the 9th
+        assert_eq!(
+            occurrences,
+            [utc("2025-01-07T09:00:00Z"), utc("2025-01-09T09:00:00Z")]
+        );
+
+        let open = rule("FREQ=DAILY;INTERVAL=2");
+        let occurrences = open.generate_occurrences(
+            dtstart,
+            Tz::UTC,
+            utc("2026-01-02T00:00:00Z"),
+            utc("2026-01-06T00:00:00Z"),
+        );
+        assert_eq!(
+            occurrences,
+            [utc("2026-01-02T09:00:00Z"), utc("2026-01-04T09:00:00Z")]
+        );
+    }
+
+    #[test]
+    fn test_monthly_skips_missing_days() {
+        let dtstart = utc("2025-01-31T10:00:00Z");
+        let occurrences = rule("FREQ=MONTHLY;COUNT=3").generate_occurrences(
+            dtstart,
+            Tz::UTC,
+            dtstart,
+            utc("2026-01-01T00:00:00Z"),
+        );
+        assert_eq!(
+            occurrences,
+            [
+                utc("2025-01-31T10:00:00Z"),
+                utc("2025-03-31T10:00:00Z"),
+                utc("2025-05-31T10:00:00Z"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_until_forms() {
+        let dtstart = utc("2025-03-03T17:00:00Z");
+        let end = utc("2026-01-01T00:00:00Z");
+        let expand = |value: &str| {
+            rule(value)
+                .generate_occurrences(dtstart, berlin(), dtstart, end)
+                .len()
+        };
+
+        assert_eq!(expand("FREQ=WEEKLY;UNTIL=20250317T170000Z"), 3);
+        assert_eq!(expand("FREQ=WEEKLY;UNTIL=20250317"), 3);
+        assert_eq!(expand("FREQ=WEEKLY;UNTIL=20250317T175959"), 2);
+        assert_eq!(expand("FREQ=WEEKLY;UNTIL=2025-03-17T17:00:00+00:00"), 3);
+        assert!(RRule::parse(Some("FREQ=WEEKLY;UNTIL=soon".into())).is_err());
+        assert!(RRule::parse(Some("FREQ=WEEKLY;INTERVAL=0".into())).is_err());
+    }
+
+    /// The first occurrences of a rule in UTC, as dates
+    fn expand(value: &str, dtstart: &str) -> Vec<NaiveDate> {
+        let dtstart = utc(dtstart);
+        rule(value)
+            .generate_occurrences(dtstart, Tz::UTC, dtstart, utc("2030-01-01T00:00:00Z"))
+            .into_iter()
+            .map(|occurrence| occurrence.date_naive())
+            .collect()
+    }
+
+    fn dates(values: &[&str]) -> Vec<NaiveDate> {
+        values
+            .iter()
+            .map(|value| NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_ordinal_weekdays() {
+        assert_eq!(
+            expand("FREQ=MONTHLY;BYDAY=-1FR;COUNT=3", "2025-01-31T10:00:00Z"),
+            dates(&["2025-01-31", "2025-02-28", "2025-03-28"])
+        );
+        assert_eq!(
+            expand("FREQ=MONTHLY;BYDAY=1MO,3MO;COUNT=4", "2025-01-06T10:00:00Z"),
+            dates(&["2025-01-06", "2025-01-20", "2025-02-03", "2025-02-17"])
+        );
+        // Within a month when BYMONTH is given, within the year otherwise
+        assert_eq!(
+            expand(
+                "FREQ=YEARLY;BYMONTH=11;BYDAY=4TH;COUNT=2",
+                "2025-11-27T10:00:00Z"
+            ),
+            dates(&["2025-11-27", "2026-11-26"])
+        );
+        assert_eq!(
+            expand("FREQ=YEARLY;BYDAY=20MO;COUNT=2", "2025-01-01T10:00:00Z"),
+            dates(&["2025-05-19", "2026-05-18"])
+        );
+    }
+
+    #[test]
+    fn test_month_days_year_days_and_set_positions() {
+        assert_eq!(
+            expand("FREQ=MONTHLY;BYMONTHDAY=-1;COUNT=3", "2025-01-31T10:00:00Z"),
+            dates(&["2025-01-31", "2025-02-28", "2025-03-31"])
+        );
+        assert_eq!(
+            expand("FREQ=YEARLY;BYYEARDAY=1,-1;COUNT=3", "2025-01-01T10:00:00Z"),
+            dates(&["2025-01-01", "2025-12-31", "2026-01-01"])
+        );
+        // The last working day of the month
+        assert_eq!(
+            expand(
+                "FREQ=MONTHLY;BYDAY=MO,TU,WE,TH,FR;BYSETPOS=-1;COUNT=3",
+                "2025-01-31T10:00:00Z"
+            ),
+            dates(&["2025-01-31", "2025-02-28", "2025-03-31"])
+        );
+        assert_eq!(
+            expand("FREQ=YEARLY;BYMONTH=2,3;COUNT=3", "2025-01-15T10:00:00Z"),
+            dates(&["2025-02-15", "2025-03-15", "2026-02-15"])
+        );
+        // Rules which never match stop at the end of the range
+        assert!(
+            expand(
+                "FREQ=YEARLY;BYMONTH=2;BYMONTHDAY=30;COUNT=1",
+                "2025-01-01T10:00:00Z"
+            )
+            .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_week_start() {
+        // The examples of RFC 5545, the week start decides which Sunday follows
+        assert_eq!(
+            expand(
+                "FREQ=WEEKLY;INTERVAL=2;COUNT=4;BYDAY=TU,SU;WKST=MO",
+                "1997-08-05T09:00:00Z"
+            ),
+            dates(&["1997-08-05", "1997-08-10", "1997-08-19", "1997-08-24"])
+        );
+        assert_eq!(
+            expand(
+                "FREQ=WEEKLY;INTERVAL=2;COUNT=4;BYDAY=TU,SU;WKST=SU",
+                "1997-08-05T09:00:00Z"
+            ),
+            dates(&["1997-08-05", "1997-08-17", "1997-08-19", "1997-08-31"])
+        );
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        let canonical = |value: &str| rule(value).to_string();
+
+        assert_eq!(
+            canonical("BYDAY=1MO,-1FR;INTERVAL=2;FREQ=MONTHLY;COUNT=5"),
+            "FREQ=MONTHLY;COUNT=5;INTERVAL=2;BYDAY=1MO,-1FR"
+        );
+        assert_eq!(canonical("FREQ=WEEKLY;INTERVAL=1;WKST=MO"), "FREQ=WEEKLY");
+        assert_eq!(
+            canonical("FREQ=YEARLY;BYSETPOS=-1;BYMONTH=3,9;BYMONTHDAY=-1,-2;WKST=SU"),
+            "FREQ=YEARLY;BYMONTHDAY=-1,-2;BYMONTH=3,9;BYSETPOS=-1;WKST=SU"
+        );
+        assert_eq!(
+            canonical("FREQ=DAILY;UNTIL=2025-03-17T17:00:00+00:00"),
+            "FREQ=DAILY;UNTIL=20250317T170000Z"
+        );
+        for value in [
+            "FREQ=DAILY;UNTIL=20250317",
+            "FREQ=DAILY;UNTIL=20250317T180000",
+            "FREQ=YEARLY;BYYEARDAY=100,-1;BYSETPOS=1",
+        ] {
+            assert_eq!(canonical(value), value);
+            assert_eq!(rule(&canonical(value)), rule(value));
+        }
+    }
+
+    #[test]
+    fn test_invalid_parts() {
+        for value in [
+            "FREQ=WEEKLY;BYDAY=1MO",
+            "FREQ=MONTHLY;BYDAY=0MO",
+            "FREQ=MONTHLY;BYDAY=MON",
+            "FREQ=MONTHLY;BYMONTHDAY=0",
+            "FREQ=MONTHLY;BYMONTHDAY=32",
+            "FREQ=WEEKLY;BYMONTHDAY=1",
+            "FREQ=MONTHLY;BYYEARDAY=1",
+            "FREQ=YEARLY;BYMONTH=13",
+            "FREQ=MONTHLY;BYSETPOS=1",
+            "FREQ=DAILY;BYHOUR=9",
+            "FREQ=WEEKLY;WKST=XX",
+        ] {
+            assert!(RRule::parse(Some(value.into())).is_err(), "{value}");
+        }
+    }
+
+    #[test]
+    fn test_local_to_utc_gaps_and_overlaps() {
+        let tz = berlin();
+        let at = |value: &str| NaiveDateTime::parse_from_str(value, ICAL_DATETIME).unwrap();
+
+        // 02:30 does not exist on March 30th, it is read with the winter offset
+        assert_eq!(
+            local_to_utc(&tz, at("20250330T023000")),
+            utc("2025-03-30T01:30:00Z")
+        );
+        // 02:30 happens twice on October 26th, the first one counts
+        assert_eq!(
+            local_to_utc(&tz, at("20251026T023000")),
+            utc("2025-10-26T00:30:00Z")
+        );
+    }
+
+    #[test]
+    fn test_recurrence_dates_in_zone() {
+        let tz = berlin();
+        let occurrence = utc("2025-03-31T16:00:00Z");
+
+        let exact = RecurrenceDate::parse("2025-03-31T16:00:00+00:00", &tz).unwrap();
+        assert!(exact.matches(occurrence, &tz));
+        let floating = RecurrenceDate::parse("20250331T180000", &tz).unwrap();
+        assert!(floating.matches(occurrence, &tz));
+        let day = RecurrenceDate::parse("20250331", &tz).unwrap();
+        assert!(day.matches(occurrence, &tz));
+        assert!(!day.matches(utc("2025-03-31T22:30:00Z"), &tz));
+
+        // A bare RDATE takes the local time of the start
+        let dtstart = utc("2025-03-24T17:00:00Z");
+        assert_eq!(day.occurrence(dtstart, &tz), occurrence);
+
+        let parsed = RecurrenceDate::parse_all(&Some(vec![" ".into(), "20250331".into()]), &tz);
+        assert_eq!(parsed.unwrap(), [day]);
+        assert!(RecurrenceDate::parse("yesterday", &tz).is_err());
     }
 }
@@ -11,6 +11,7 @@ use anyhow::Context;
 use chrono::Offset;
 use chrono_tz::TZ_VARIANTS;
 use dotenvy::dotenv;
+pub use helpers::rrule;
 use sqlx::{
     migrate::Migrator,
     postgres::{PgPool, PgPoolOptions},
@@ -3,6 +3,9 @@ use redis::{AsyncCommands, aio::MultiplexedConnection};
 pub use error::RedisError;
 mod email;
 mod error;
+mod password;
+mod rate_limit;
+pub use rate_limit::LockoutPolicy;
 
 #[derive(Clone, Debug)]
 pub struct RedisClient {
@@ -0,0 +1,38 @@
+use crate::{RedisClient, RedisError};
+
+impl RedisClient {
+    /// Store password reset token with TTL (default 30 minutes)
+    pub async fn set_password_reset_token(
+        &mut self,
+        user_id: &str,
+        token: &str,
+        ttl_seconds: Option<u64>,
+    ) -> Result<(), RedisError> {
+        let key = format!("password_reset:{}", token);
+        let ttl = ttl_seconds.unwrap_or(1800);
+
+        redis::cmd("SETEX")
+            .arg(&key)
+            .arg(ttl)
+            .arg(user_id)
+            .query_async(&mut self.con)
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Get and delete password reset token, so every link works once
+    /// Returns Some(user_id) if token exists, None if expired/invalid
+    pub async fn consume_password_reset_token(
+        &mut self,
+        token: &str,
+    ) -> Result<Option<String>, RedisError> {
+        let key = format!("password_reset:{}", token);
+
+        let user_id: Option<String> = redis::cmd("GETDEL")
+            .arg(&key)
+            .query_async(&mut self.con)
+            .await?;
+
+        Ok(user_id)
+    }
+}
@@ -0,0 +1,179 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{RedisClient, RedisError};
+
+/// Trims the window, then either counts the hit (unless only peeking) or reports the
+/// milliseconds until the oldest hit in the window falls out of it. Runs atomically so
+/// that concurrent requests can't all slip in under the limit.
+const SLIDING_WINDOW_SCRIPT: &str = r#"
+local key = KEYS[1]
+local now = tonumber(ARGV[1])
+local window = tonumber(ARGV[2])
+local limit = tonumber(ARGV[3])
+local member = ARGV[4]
+local peek = ARGV[5] == '1'
+
+redis.call('ZREMRANGEBYSCORE', key, 0, now - window)
+if redis.call('ZCARD', key) >= limit then
+    local oldest = redis.call('ZRANGE', key, 0, 0, 'WITHSCORES')
+    return tonumber(oldest[2]) + window - now
+end
+if peek then
+    return 0
+end
+redis.call('ZADD', key, now, member)
+redis.call('PEXPIRE', key, window)
+return 0
+"#;
+
+/// How failed sign-ins turn into lockouts
+#[derive(Debug, Clone, Copy)]
+pub struct LockoutPolicy {
+    /// Failures in a row before the first lockout
+    pub threshold: u32,
+    /// The first lockout, doubled for every failure past the threshold
+    pub base_secs: u64,
+    pub max_secs: u64,
+    /// Failures are forgotten after this long without another one
+    pub memory_secs: u64,
+}
+
+impl LockoutPolicy {
+    /// How long to lock out after `failures` failed attempts in a row, if at all
+    pub fn lockout_secs(&self, failures: u32) -> Option<u64> {
+        let past_threshold = failures.checked_sub(self.threshold)?;
+        let factor = 1u64.checked_shl(past_threshold).unwrap_or(u64::MAX);
+        Some(self.base_secs.saturating_mul(factor).min(self.max_secs))
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+impl RedisClient {
+    /// Counts a hit against a sliding window of `window_secs`. Returns how many seconds
+    /// to wait when the window already holds `limit` hits, the hit is not counted then.
+    pub async fn hit_rate_limit(
+        &mut self,
+        key: &str,
+        limit: u32,
+        window_secs: u64,
+    ) -> Result<Option<u64>, RedisError> {
+        self.sliding_window(key, limit, window_secs, false).await
+    }
+
+    /// Like `hit_rate_limit` without counting a hit, for when only failures count
+    pub async fn peek_rate_limit(
+        &mut self,
+        key: &str,
+        limit: u32,
+        window_secs: u64,
+    ) -> Result<Option<u64>, RedisError> {
+        self.sliding_window(key, limit, window_secs, true).await
+    }
+
+    async fn sliding_window(
+        &mut self,
+        key: &str,
+        limit: u32,
+        window_secs: u64,
+        peek: bool,
+    ) -> Result<Option<u64>, RedisError> {
+        let key = format!("rate_limit:{}", key);
+        let now = now_millis();
+        // Hits in the same millisecond still count separately
+        let member = format!("{}-{}", now, nanoid::nanoid!(8));
+
+        let wait_millis: u64 = redis::Script::new(SLIDING_WINDOW_SCRIPT)
+            .key(&key)
+            .arg(now)
+            .arg(window_secs * 1000)
+            .arg(limit)
+            .arg(member)
+            .arg(if peek { "1" } else { "0" })
+            .invoke_async(&mut self.con)
+            .await?;
+
+        Ok((wait_millis > 0).then(|| wait_millis.div_ceil(1000)))
+    }
+
+    /// Seconds left on the subject's lockout, if it's locked out
+    pub async fn lockout_remaining(&mut self, subject: &str) -> Result<Option<u64>, RedisError> {
+        let key = format!("lockout:{}", subject);
+
+        let ttl: i64 = redis::cmd("TTL")
+            .arg(&key)
+            .query_async(&mut self.con)
+            .await?;
+
+        Ok((ttl > 0).then_some(ttl as u64))
+    }
+
+    /// Counts a failed attempt. Returns the lockout it earned, if any.
+    pub async fn record_failure(
+        &mut self,
+        subject: &str,
+        policy: &LockoutPolicy,
+    ) -> Result<Option<u64>, RedisError> {
+        let failures_key = format!("login_failures:{}", subject);
+
+        let (failures,): (u32,) = redis::pipe()
+            .atomic()
+            .cmd("INCR")
+            .arg(&failures_key)
+            .cmd("EXPIRE")
+            .arg(&failures_key)
+            .arg(policy.memory_secs)
+            .ignore()
+            .query_async(&mut self.con)
+            .await?;
+
+        let Some(lockout_secs) = policy.lockout_secs(failures) else {
+            return Ok(None);
+        };
+        redis::cmd("SETEX")
+            .arg(format!("lockout:{}", subject))
+            .arg(lockout_secs)
+            .arg(failures)
+            .query_async::<()>(&mut self.con)
+            .await?;
+
+        Ok(Some(lockout_secs))
+    }
+
+    /// Forgets failed attempts after a successful one
+    pub async fn clear_failures(&mut self, subject: &str) -> Result<(), RedisError> {
+        let _: () = redis::cmd("DEL")
+            .arg(format!("login_failures:{}", subject))
+            .query_async(&mut self.con)
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lockout_backs_off_exponentially() {
+        let policy = LockoutPolicy {
+            threshold: 5,
+            base_secs: 60,
+            max_secs: 3600,
+            memory_secs: 86400,
+        };
+
+        assert_eq!(policy.lockout_secs(4), None);
+        assert_eq!(policy.lockout_secs(5), Some(60));
+        assert_eq!(policy.lockout_secs(6), Some(120));
+        assert_eq!(policy.lockout_secs(8), Some(480));
+        assert_eq!(policy.lockout_secs(11), Some(3600));
+        assert_eq!(policy.lockout_secs(500), Some(3600));
+    }
+}
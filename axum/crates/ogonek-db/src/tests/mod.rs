@@ -29,3 +29,54 @@ pub async fn cleanup_user(db: &PgPool, user_id: &str) {
         .execute(db)
         .await;
 }
+#[allow(dead_code)]
+// Helper function to move a user to a timezone whose day started an hour or two ago,
+// so their day and the UTC one mostly disagree. Returns their last midnight
+pub async fn set_test_timezone(db: &PgPool, user_id: &str) -> chrono::DateTime<chrono::Utc> {
+    use chrono::Timelike;
+
+    let hours_ahead = (1 - chrono::Utc::now().hour() as i32).rem_euclid(24);
+    let offset = if hours_ahead > 12 {
+        hours_ahead - 24
+    } else {
+        hours_ahead
+    };
+    // POSIX signs, Etc/GMT-3 is three hours ahead of UTC
+    let tzid = format!("Etc/GMT{:+}", -offset);
+
+    sqlx::query!(
+        r#"
+        INSERT INTO timezones (tzid, display_name, utc_offset_std)
+        VALUES ($1, $1, $2)
+        ON CONFLICT (tzid) DO NOTHING
+        "#,
+        tzid,
+        offset * 3600
+    )
+    .execute(db)
+    .await
+    .expect("Failed to create test timezone");
+
+    sqlx::query!(
+        r#"
+        INSERT INTO calendars (id, name, owner_id, timezone, is_default)
+        VALUES ($1, 'Default', $2, $3, true)
+        "#,
+        nanoid::nanoid!(),
+        user_id,
+        tzid
+    )
+    .execute(db)
+    .await
+    .expect("Failed to create test calendar");
+
+    sqlx::query_scalar!(
+        r#"
+        SELECT date_trunc('day', CURRENT_TIMESTAMP AT TIME ZONE $1) AT TIME ZONE $1 AS "midnight!"
+        "#,
+        tzid
+    )
+    .fetch_one(db)
+    .await
+    .expect("Failed to read local midnight")
+}
@@ -84,6 +84,47 @@ impl NotificationService {
         Ok(())
     }
 
+    /// Notifies a user on their devices and in Telegram
+    pub async fn notify_user(
+        &self,
+        user_id: &str,
+        notification_type: NotificationType,
+    ) -> Result<(), NotificationError> {
+        info!("Notifying user {}: {:?}", user_id, notification_type);
+
+        self.send_apns_notifications(user_id, &notification_type)
+            .await?;
+
+        if let Ok(Some(telegram_id)) = profile::read_telegram_id(&self.db, user_id).await {
+            self.telegram_provider
+                .send_notification(&telegram_id, &notification_type)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Sends a notification to a Telegram chat
+    pub async fn notify_telegram(
+        &self,
+        telegram_id: &str,
+        notification_type: &NotificationType,
+    ) -> Result<(), NotificationError> {
+        self.telegram_provider
+            .send_notification(telegram_id, notification_type)
+            .await
+    }
+
+    /// Pushes a notification to every device of a user
+    pub async fn notify_devices(
+        &self,
+        user_id: &str,
+        notification_type: &NotificationType,
+    ) -> Result<(), NotificationError> {
+        self.send_apns_notifications(user_id, notification_type)
+            .await
+    }
+
     async fn send_apns_notifications(
         &self,
         recipient_id: &str,
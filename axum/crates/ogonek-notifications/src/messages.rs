@@ -31,6 +31,21 @@ pub enum NotificationType {
         lesson_topic: String,
         lesson_id: String,
     },
+    #[serde(rename = "eventReminder")]
+    EventReminder {
+        summary: String,
+        starts_at: String,
+        event_id: String,
+    },
+    #[serde(rename = "proposalAccepted")]
+    ProposalAccepted { summary: String, event_id: String },
+    #[serde(rename = "proposalDeclined")]
+    ProposalDeclined { summary: String, event_id: String },
+    #[serde(rename = "inviteRedeemed")]
+    InviteRedeemed {
+        student_name: String,
+        student_id: String,
+    },
 }
 
 impl NotificationType {
@@ -99,6 +114,25 @@ impl NotificationType {
                 escape_markdown_v2(lesson_topic),
                 lesson_id
             ),
+            Self::EventReminder {
+                summary, starts_at, ..
+            } => format!(
+                "Reminder: \"{}\" starts {}",
+                escape_markdown_v2(summary),
+                escape_markdown_v2(starts_at)
+            ),
+            Self::ProposalAccepted { summary, .. } => format!(
+                "Your proposal for \"{}\" was accepted, the event has moved",
+                escape_markdown_v2(summary)
+            ),
+            Self::ProposalDeclined { summary, .. } => format!(
+                "Your proposal for \"{}\" was declined",
+                escape_markdown_v2(summary)
+            ),
+            Self::InviteRedeemed { student_name, .. } => format!(
+                "{} joined you through your invite link",
+                escape_markdown_v2(student_name)
+            ),
         }
     }
 
@@ -192,6 +226,53 @@ impl NotificationType {
                     "lesson_id": lesson_id
                 })),
             },
+            Self::EventReminder {
+                summary,
+                starts_at,
+                event_id,
+            } => NotificationPayload {
+                title: "Upcoming Event".to_string(),
+                body: format!("{} starts {}", summary, starts_at),
+                badge: Some(1),
+                sound: Some("default".to_string()),
+                data: Some(serde_json::json!({
+                    "type": "event_reminder",
+                    "event_id": event_id
+                })),
+            },
+            Self::ProposalAccepted { summary, event_id } => NotificationPayload {
+                title: "Proposal Accepted".to_string(),
+                body: format!("Your proposal for {} was accepted", summary),
+                badge: Some(1),
+                sound: Some("default".to_string()),
+                data: Some(serde_json::json!({
+                    "type": "proposal_accepted",
+                    "event_id": event_id
+                })),
+            },
+            Self::ProposalDeclined { summary, event_id } => NotificationPayload {
+                title: "Proposal Declined".to_string(),
+                body: format!("Your proposal for {} was declined", summary),
+                badge: Some(1),
+                sound: Some("default".to_string()),
+                data: Some(serde_json::json!({
+                    "type": "proposal_declined",
+                    "event_id": event_id
+                })),
+            },
+            Self::InviteRedeemed {
+                student_name,
+                student_id,
+            } => NotificationPayload {
+                title: "New Student".to_string(),
+                body: format!("{} joined through your invite", student_name),
+                badge: Some(1),
+                sound: Some("default".to_string()),
+                data: Some(serde_json::json!({
+                    "type": "invite_redeemed",
+                    "student_id": student_id
+                })),
+            },
         }
     }
 }
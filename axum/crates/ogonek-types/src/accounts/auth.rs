@@ -1,4 +1,3 @@
-use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 use validator::Validate;
@@ -49,6 +48,9 @@ pub struct TokenWithExpiry {
 pub struct RefreshTokenResponse {
     #[serde(rename = "accessToken")]
     pub access_token: TokenWithExpiry,
+    /// Replaces the refresh token sent, which stops working
+    #[serde(rename = "refreshToken")]
+    pub refresh_token: TokenWithExpiry,
 }
 
 #[derive(Deserialize, ToSchema)]
@@ -80,26 +82,16 @@ impl TokenPair {
     }
 }
 
-// Simple struct to hold the invite data
+/// The claims of a signed invite token, naming an invite record
 #[derive(Serialize, Deserialize, ToSchema)]
 pub struct InviteToken {
-    pub teacher_id: String,
-    pub created_at: DateTime<Utc>,
-}
-
-impl InviteToken {
-    pub fn new(teacher_id: String) -> Self {
-        Self {
-            teacher_id,
-            created_at: Utc::now(),
-        }
-    }
+    pub invite_id: String,
+    pub exp: usize,
 }
 
 #[derive(Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct BindPayload {
-    pub student_id: String,
     pub invite_token: String,
 }
 
@@ -120,3 +112,22 @@ pub struct InviterQuery {
 pub struct EmailVerificationQuery {
     pub token: String,
 }
+
+#[derive(Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ForgotPasswordPayload {
+    #[validate(email)]
+    #[schema(format = "email", example = "john@example.com")]
+    pub email: String,
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PasswordResetPayload {
+    /// The token from the emailed reset link
+    pub token: String,
+
+    #[validate(length(min = 8, max = 32))]
+    #[schema(min_length = 8, max_length = 32, example = "MyPassword123")]
+    pub pass: String,
+}
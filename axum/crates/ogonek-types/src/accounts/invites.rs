@@ -0,0 +1,61 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+use crate::datetime_serialization;
+
+/// An invite link a teacher handed out
+#[derive(ToSchema, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Invite {
+    pub id: String,
+    #[serde(skip_serializing)]
+    pub teacher_id: String,
+    /// Only the student with this email can use the invite
+    pub email: Option<String>,
+    /// Unlimited when left out
+    pub max_uses: Option<i32>,
+    pub use_count: i32,
+    #[serde(with = "datetime_serialization")]
+    pub expires_at: DateTime<Utc>,
+    #[serde(with = "datetime_serialization::option")]
+    pub revoked_at: Option<DateTime<Utc>>,
+    #[serde(with = "datetime_serialization")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(ToSchema, Deserialize, Validate, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct InviteCreate {
+    #[validate(email)]
+    pub email: Option<String>,
+    #[validate(range(min = 1, max = 1000))]
+    pub max_uses: Option<i32>,
+    /// A week when left out
+    #[validate(range(min = 1, max = 90))]
+    pub expires_in_days: Option<i64>,
+}
+
+/// A freshly created invite with the links to hand out
+#[derive(ToSchema, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct InviteLinks {
+    #[serde(flatten)]
+    pub invite: Invite,
+    pub token: String,
+    /// For students who still have to sign up
+    pub signup_url: String,
+    /// For students who already have an account
+    pub bind_url: String,
+}
+
+/// A student who joined through an invite
+#[derive(ToSchema, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct InviteRedemption {
+    pub student_id: String,
+    pub student_name: String,
+    #[serde(with = "datetime_serialization")]
+    pub redeemed_at: DateTime<Utc>,
+}
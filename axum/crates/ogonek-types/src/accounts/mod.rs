@@ -1,9 +1,15 @@
 pub mod auth;
+pub mod invites;
 pub mod profiles;
+pub mod sessions;
 pub mod students;
+pub mod two_factor;
 pub mod users;
 
 pub use auth::*;
+pub use invites::*;
 pub use profiles::*;
+pub use sessions::*;
 pub use students::*;
+pub use two_factor::*;
 pub use users::*;
@@ -0,0 +1,32 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::types::ipnetwork::IpNetwork;
+use utoipa::ToSchema;
+
+use crate::datetime_serialization;
+
+/// A device the user is signed in on
+#[derive(ToSchema, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RefreshSession {
+    pub id: String,
+    pub device: Option<String>,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    #[serde(with = "datetime_serialization")]
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "datetime_serialization")]
+    pub last_used_at: DateTime<Utc>,
+    #[serde(with = "datetime_serialization")]
+    pub expires_at: DateTime<Utc>,
+    /// The session the request was made from
+    pub is_current: bool,
+}
+
+/// Where a session was started or last refreshed from
+#[derive(Debug, Clone, Default)]
+pub struct SessionOrigin {
+    pub device: Option<String>,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<IpNetwork>,
+}
@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{TokenPair, TokenWithExpiry};
+
+/// What signing in returns: tokens, or a challenge when a second factor is needed
+#[derive(ToSchema, Serialize)]
+#[serde(untagged)]
+pub enum SignInResponse {
+    Tokens(TokenPair),
+    Challenge(TwoFactorChallenge),
+}
+
+/// Exchanged for tokens at `/auth/2fa/verify` together with a code
+#[derive(ToSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TwoFactorChallenge {
+    pub challenge_token: TokenWithExpiry,
+    /// The account has to enrol before it can sign in. The challenge token then stands
+    /// in for the access token at `/auth/2fa/setup` and `/auth/2fa/confirm`.
+    pub setup_required: bool,
+}
+
+/// The claims of a challenge token, only good for finishing the sign in
+#[derive(Serialize, Deserialize)]
+pub struct TwoFactorChallengeClaims {
+    pub sub: String,
+    pub exp: usize,
+    /// `2fa`, or `2fa_setup` when the user has to enrol first. Keeps the token apart
+    /// from access tokens.
+    pub purpose: String,
+}
+
+#[derive(ToSchema, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TwoFactorVerifyPayload {
+    pub challenge_token: String,
+    /// A code from the authenticator or an unused recovery code
+    pub code: String,
+}
+
+/// A secret to scan into an authenticator app
+#[derive(ToSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TotpSetup {
+    pub secret: String,
+    /// `otpauth://` URI to render as a QR code
+    pub provisioning_uri: String,
+}
+
+#[derive(ToSchema, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TotpCodePayload {
+    pub code: String,
+}
+
+/// 2FA is on. Enrolling from a challenge signs the user in as well.
+#[derive(ToSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TwoFactorEnabled {
+    pub recovery_codes: Vec<String>,
+    pub tokens: Option<TokenPair>,
+}
+
+/// Shown once, each code signs in a single time
+#[derive(ToSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveryCodes {
+    pub codes: Vec<String>,
+}
+
+#[derive(ToSchema, Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TwoFactorStatus {
+    pub enabled: bool,
+    /// The account's role can't sign in without 2FA
+    pub required: bool,
+    pub recovery_codes_left: i64,
+}
@@ -0,0 +1,117 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::Type;
+use utoipa::ToSchema;
+use validator::Validate;
+
+use crate::datetime_serialization;
+
+#[derive(ToSchema, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct EventAlarm {
+    pub id: String,
+    pub event_id: String,
+    /// Seconds from the start of the event, negative before it
+    pub trigger_offset_seconds: Option<i64>,
+    #[serde(with = "datetime_serialization::option")]
+    pub trigger_datetime: Option<DateTime<Utc>>,
+    pub action: EventAlarmAction,
+    pub description: Option<String>,
+    pub summary: Option<String>,
+    pub attendee_email: Vec<String>,
+    pub attendee_telegram_id: Vec<String>,
+    /// Users whose devices get a push notification
+    pub attendee_apns: Vec<String>,
+    pub repeat_count: i32,
+    pub repeat_interval_seconds: Option<i64>,
+    /// When the alarm first fires for the requested occurrence
+    #[serde(with = "datetime_serialization::option")]
+    pub fires_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing)]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(ToSchema, Serialize, Deserialize, Type, Debug, Default, PartialEq, Clone)]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum EventAlarmAction {
+    #[default]
+    Display,
+    Audio,
+    Email,
+    Procedure,
+}
+
+/// The channels an alarm is delivered through
+#[derive(Type, Debug, PartialEq, Clone, Copy)]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+pub enum AlarmChannel {
+    Email,
+    Telegram,
+    Apns,
+}
+
+/// Recipients left out follow the event's attendees, including those added later.
+/// Those given are limited to the attendees and the event's owner
+#[derive(Validate, ToSchema, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventAlarmCreate {
+    pub trigger_offset_seconds: Option<i64>,
+    #[serde(default, with = "datetime_serialization::option")]
+    pub trigger_datetime: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub action: EventAlarmAction,
+    pub description: Option<String>,
+    #[validate(length(max = 255))]
+    pub summary: Option<String>,
+    #[validate(length(max = 100))]
+    pub attendee_email: Option<Vec<String>>,
+    #[validate(length(max = 100))]
+    pub attendee_telegram_id: Option<Vec<String>>,
+    #[validate(length(max = 100))]
+    pub attendee_apns: Option<Vec<String>>,
+    #[validate(range(min = 0, max = 10))]
+    #[serde(default)]
+    pub repeat_count: i32,
+    pub repeat_interval_seconds: Option<i64>,
+}
+
+/// Setting one kind of trigger clears the other
+#[derive(Validate, ToSchema, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct EventAlarmUpdate {
+    pub trigger_offset_seconds: Option<i64>,
+    #[serde(default, with = "datetime_serialization::option")]
+    pub trigger_datetime: Option<DateTime<Utc>>,
+    pub action: Option<EventAlarmAction>,
+    pub description: Option<String>,
+    #[validate(length(max = 255))]
+    pub summary: Option<String>,
+    #[validate(length(max = 100))]
+    pub attendee_email: Option<Vec<String>>,
+    #[validate(length(max = 100))]
+    pub attendee_telegram_id: Option<Vec<String>>,
+    #[validate(length(max = 100))]
+    pub attendee_apns: Option<Vec<String>>,
+    #[validate(range(min = 0, max = 10))]
+    pub repeat_count: Option<i32>,
+    pub repeat_interval_seconds: Option<i64>,
+}
+
+/// An alarm due to fire for one occurrence of an event
+#[derive(Debug, Clone, PartialEq)]
+pub struct DueAlarm {
+    pub alarm_id: String,
+    /// The occurrence's id, virtual for recurring events
+    pub event_id: String,
+    pub summary: String,
+    pub description: Option<String>,
+    pub location: Option<String>,
+    pub occurrence: DateTime<Utc>,
+    pub dtstart_tz: Option<String>,
+    pub repeat_index: i32,
+    pub fires_at: DateTime<Utc>,
+    pub attendee_email: Vec<String>,
+    pub attendee_telegram_id: Vec<String>,
+    pub attendee_apns: Vec<String>,
+}
@@ -0,0 +1,90 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::Type;
+use utoipa::ToSchema;
+use validator::Validate;
+
+use crate::datetime_serialization;
+
+#[derive(ToSchema, Serialize, Deserialize, Type, Debug, PartialEq, Clone)]
+#[sqlx(type_name = "attendance_status", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum AttendanceStatus {
+    Attended,
+    CancelledByStudent,
+    CancelledByTeacher,
+    NoShow,
+}
+
+/// What became of an event or occurrence for one student
+#[derive(ToSchema, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct EventAttendance {
+    pub id: String,
+    /// The virtual occurrence ID for occurrences of a series
+    pub event_id: String,
+    #[serde(with = "datetime_serialization::option")]
+    pub recurrence_id: Option<DateTime<Utc>>,
+    pub student_id: String,
+    pub student_name: String,
+    pub status: AttendanceStatus,
+    pub lesson_id: Option<String>,
+    pub lesson_title: Option<String>,
+    pub note: Option<String>,
+    #[serde(with = "datetime_serialization")]
+    pub starts_at: DateTime<Utc>,
+    pub duration_minutes: i32,
+    #[serde(with = "datetime_serialization")]
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Records the outcome, replacing the one already recorded for the student
+#[derive(Validate, ToSchema, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventAttendanceUpsert {
+    pub student_id: String,
+    pub status: AttendanceStatus,
+    /// The lesson covering what was taught
+    pub lesson_id: Option<String>,
+    #[validate(length(max = 2000))]
+    pub note: Option<String>,
+}
+
+#[derive(ToSchema, Serialize, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AttendanceTotals {
+    pub attended: i64,
+    pub cancelled_by_student: i64,
+    pub cancelled_by_teacher: i64,
+    pub no_show: i64,
+    /// Minutes of the lessons attended
+    pub attended_minutes: i64,
+}
+
+#[derive(ToSchema, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StudentAttendanceReport {
+    pub student_id: String,
+    pub student_name: String,
+    #[serde(flatten)]
+    pub totals: AttendanceTotals,
+    pub records: Vec<EventAttendance>,
+}
+
+/// One student's attendance over a month of the teacher's calendar
+#[derive(ToSchema, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MonthlyAttendance {
+    /// As YYYY-MM
+    pub month: String,
+    pub student_id: String,
+    pub student_name: String,
+    #[serde(flatten)]
+    pub totals: AttendanceTotals,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AttendanceReportQuery {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
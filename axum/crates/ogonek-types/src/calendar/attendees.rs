@@ -25,7 +25,7 @@ pub struct EventAttendee {
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(ToSchema, Serialize, Deserialize, Type, Debug, PartialEq)]
+#[derive(ToSchema, Serialize, Deserialize, Type, Debug, PartialEq, Clone)]
 #[sqlx(type_name = "varchar", rename_all = "kebab-case")]
 #[serde(rename_all = "kebab-case")]
 pub enum EventAttendeeRole {
@@ -35,7 +35,7 @@ pub enum EventAttendeeRole {
     NonParticipant,
 }
 
-#[derive(ToSchema, Serialize, Deserialize, Type, Debug, PartialEq)]
+#[derive(ToSchema, Serialize, Deserialize, Type, Debug, PartialEq, Clone)]
 #[sqlx(type_name = "varchar", rename_all = "kebab-case")]
 #[serde(rename_all = "kebab-case")]
 pub enum EventAttendeeStatus {
@@ -63,30 +63,6 @@ pub struct EventAttendeeUpdate {
     pub rsvp: Option<bool>,
 }
 
-#[derive(Validate, ToSchema, Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct EventAlarm {
-    pub id: String,
-    pub event_id: String,
-    pub trigger_datetime: DateTime<Utc>,
-    pub action: EventAlarmAction,
-    pub description: Option<String>,
-    pub summary: Option<String>,
-    pub attendee_email: Option<String>,
-    pub attendee_telegram_id: Option<String>,
-    pub repeat_count: i32,
-    pub created_at: DateTime<Utc>,
-}
-
-#[derive(ToSchema, Serialize)]
-#[serde(rename_all = "kebab-case")]
-pub enum EventAlarmAction {
-    Display,
-    Audio,
-    Email,
-    Procedure,
-}
-
 impl fmt::Display for EventAttendeeRole {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
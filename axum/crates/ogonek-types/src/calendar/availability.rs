@@ -0,0 +1,97 @@
+use chrono::{DateTime, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+use crate::datetime_serialization;
+
+/// A weekly window, in the wall clock of the teacher's calendar
+#[derive(ToSchema, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AvailabilityRule {
+    pub id: String,
+    /// 0 is Monday
+    pub weekday: i16,
+    pub start_time: NaiveTime,
+    pub end_time: NaiveTime,
+}
+
+#[derive(Validate, ToSchema, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AvailabilityRuleCreate {
+    #[validate(range(min = 0, max = 6))]
+    pub weekday: i16,
+    pub start_time: NaiveTime,
+    pub end_time: NaiveTime,
+}
+
+/// Blocked time, or an extra window when `available` is set
+#[derive(ToSchema, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AvailabilityException {
+    pub id: String,
+    #[serde(with = "datetime_serialization")]
+    pub starts_at: DateTime<Utc>,
+    #[serde(with = "datetime_serialization")]
+    pub ends_at: DateTime<Utc>,
+    pub available: bool,
+}
+
+#[derive(Validate, ToSchema, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AvailabilityExceptionCreate {
+    #[serde(with = "datetime_serialization")]
+    pub starts_at: DateTime<Utc>,
+    #[serde(with = "datetime_serialization")]
+    pub ends_at: DateTime<Utc>,
+    #[serde(default)]
+    pub available: bool,
+}
+
+#[derive(Validate, ToSchema, Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AvailabilitySettings {
+    #[validate(range(min = 15, max = 480))]
+    pub lesson_minutes: i32,
+    /// Free time kept around every lesson and event
+    #[validate(range(min = 0, max = 120))]
+    pub buffer_minutes: i32,
+}
+
+impl Default for AvailabilitySettings {
+    fn default() -> Self {
+        Self {
+            lesson_minutes: 60,
+            buffer_minutes: 0,
+        }
+    }
+}
+
+#[derive(ToSchema, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Availability {
+    pub settings: AvailabilitySettings,
+    pub rules: Vec<AvailabilityRule>,
+    pub exceptions: Vec<AvailabilityException>,
+}
+
+#[derive(ToSchema, Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BookableSlot {
+    #[serde(with = "datetime_serialization")]
+    pub start: DateTime<Utc>,
+    #[serde(with = "datetime_serialization")]
+    pub end: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SlotQuery {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+#[derive(ToSchema, Deserialize)]
+pub struct BookingCreate {
+    #[serde(with = "datetime_serialization")]
+    pub start: DateTime<Utc>,
+}
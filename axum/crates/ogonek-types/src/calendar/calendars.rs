@@ -1,5 +1,6 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sqlx::prelude::Type;
 use utoipa::ToSchema;
 use validator::Validate;
 
@@ -17,6 +18,8 @@ pub struct CalendarFull {
     pub timezone: String,
     #[serde(skip_serializing)]
     pub owner_id: String,
+    /// Events land here unless another calendar is picked
+    pub is_default: bool,
 
     #[serde(skip_serializing)]
     pub caldav_url: Option<String>,
@@ -34,6 +37,20 @@ pub struct CalendarFull {
     pub created_at: DateTime<Utc>,
     #[serde(skip_serializing)]
     pub updated_at: DateTime<Utc>,
+
+    #[serde(skip_serializing)]
+    pub feed_token: Option<String>,
+}
+
+#[derive(Validate, ToSchema, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CalendarCreate {
+    #[validate(length(min = 1, max = 255))]
+    pub name: String,
+    pub description: Option<String>,
+    #[validate(length(equal = 7))]
+    pub colour: Option<String>,
+    pub timezone: Option<String>,
 }
 
 #[derive(Validate, ToSchema, Deserialize)]
@@ -46,3 +63,54 @@ pub struct CalendarUpdate {
     pub caldav_url: Option<String>,
     pub sync_token: Option<String>,
 }
+
+/// Subscription links for the calendar's iCalendar feed
+#[derive(ToSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CalendarFeed {
+    pub url: String,
+    pub webcal_url: String,
+}
+
+#[derive(ToSchema, Serialize, Deserialize, Type, Debug, PartialEq, Clone)]
+#[sqlx(type_name = "calendar_permission", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum CalendarPermission {
+    Read,
+    ReadWrite,
+}
+
+/// A calendar the user owns or has been given access to
+#[derive(ToSchema, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CalendarSmall {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub colour: String,
+    pub timezone: String,
+    pub owner_id: String,
+    pub owner_name: String,
+    pub is_default: bool,
+    pub is_owner: bool,
+    /// Owners always have read-write access
+    pub permission: CalendarPermission,
+}
+
+/// A user the calendar is shared with
+#[derive(ToSchema, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CalendarShare {
+    pub user_id: String,
+    pub name: String,
+    pub username: String,
+    pub permission: CalendarPermission,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(ToSchema, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CalendarShareUpsert {
+    pub user_id: String,
+    pub permission: CalendarPermission,
+}
@@ -0,0 +1,21 @@
+use chrono::{DateTime, Utc};
+
+/// A CalDAV resource: one UID with its master event and overrides
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalendarResource {
+    pub href: String,
+    pub uid: String,
+    pub etag: String,
+    pub deleted: bool,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Conditional request headers of a write
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResourcePrecondition {
+    None,
+    /// `If-Match`, `*` matches any existing resource
+    IfMatch(String),
+    /// `If-None-Match: *`, the resource must not exist yet
+    IfNoneMatch,
+}
@@ -1,6 +1,6 @@
 use crate::{
-    CalendarRole, DeleteScope, EditScope, EventAttendee, EventClass, EventStatus, EventTransp,
-    datetime_serialization,
+    CalendarRole, DeleteScope, EditScope, EventAttendee, EventAttendeeRole, EventAttendeeStatus,
+    EventClass, EventStatus, EventTransp, datetime_serialization,
 };
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -12,6 +12,8 @@ pub struct CalendarQuery {
     pub start: DateTime<Utc>,
     pub end: DateTime<Utc>,
     pub role: Option<CalendarRole>,
+    /// Comma-separated calendar IDs, every calendar the user can see when left out
+    pub calendars: Option<String>,
 }
 
 #[derive(Validate, ToSchema, Serialize)]
@@ -29,6 +31,7 @@ pub struct EventSmall {
 pub struct EventDB {
     pub id: String,
     pub uid: String,
+    pub calendar_id: String,
 
     #[serde(rename = "title")]
     pub summary: String,
@@ -39,6 +42,8 @@ pub struct EventDB {
     #[serde(with = "datetime_serialization::option")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub dtend_time: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dtstart_tz: Option<String>,
 
     pub status: EventStatus,
 
@@ -142,10 +147,15 @@ pub struct EventDBFull {
 #[serde(rename_all = "camelCase")]
 pub struct EventCreate {
     pub attendee: String,
+    /// The user's default calendar when left out
+    pub calendar_id: Option<String>,
     #[serde(with = "datetime_serialization")]
     pub dtstart_time: DateTime<Utc>,
     #[serde(with = "datetime_serialization::option")]
     pub dtend_time: Option<DateTime<Utc>>,
+    /// Creates the event even if it overlaps busy time
+    #[serde(default)]
+    pub allow_conflicts: bool,
 }
 
 #[derive(Validate, ToSchema, Deserialize)]
@@ -158,6 +168,9 @@ pub struct EventDelete {
 #[serde(rename_all = "camelCase")]
 pub struct EventUpdateRequest {
     pub scope: EditScope,
+    /// Moves the event even if it then overlaps busy time
+    #[serde(default)]
+    pub allow_conflicts: bool,
     #[serde(flatten)]
     pub updates: EventUpdate,
 }
@@ -181,3 +194,44 @@ pub struct EventWithAttendees {
     pub event: EventFull,
     pub attendees: Vec<EventAttendee>,
 }
+
+/// An event read from an iCalendar file
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventImport {
+    pub uid: String,
+    pub summary: String,
+    pub description: Option<String>,
+    pub location: Option<String>,
+    pub url: Option<String>,
+    pub dtstart_time: DateTime<Utc>,
+    pub dtend_time: Option<DateTime<Utc>>,
+    pub dtstart_tz: Option<String>,
+    pub rrule: Option<String>,
+    pub rdate: Option<Vec<String>>,
+    pub exdate: Option<Vec<String>>,
+    pub recurrence_id: Option<DateTime<Utc>>,
+    pub status: EventStatus,
+    pub class: EventClass,
+    pub transp: EventTransp,
+    pub priority: Option<i32>,
+    pub categories: Option<Vec<String>>,
+    pub sequence: i32,
+    pub attendees: Vec<AttendeeImport>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttendeeImport {
+    pub email: String,
+    pub name: Option<String>,
+    pub role: EventAttendeeRole,
+    pub status: EventAttendeeStatus,
+}
+
+#[derive(ToSchema, Serialize, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EventImportSummary {
+    pub created: usize,
+    pub updated: usize,
+    /// Events that could not be read, e.g. with an unsupported recurrence rule
+    pub skipped: usize,
+}
@@ -0,0 +1,39 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::datetime_serialization;
+
+/// Time taken by an opaque event or one of its occurrences
+#[derive(ToSchema, Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BusyPeriod {
+    /// The virtual occurrence ID for occurrences of a series
+    pub event_id: String,
+    #[serde(skip)]
+    pub uid: String,
+    #[serde(skip)]
+    pub calendar_id: String,
+    /// The occurrence of a series the period stands for
+    #[serde(skip)]
+    pub recurrence_id: Option<DateTime<Utc>>,
+    pub summary: String,
+    #[serde(with = "datetime_serialization")]
+    pub start: DateTime<Utc>,
+    #[serde(with = "datetime_serialization")]
+    pub end: DateTime<Utc>,
+}
+
+impl BusyPeriod {
+    /// Whether the period overlaps the span, events without an end still take their start
+    pub fn overlaps(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> bool {
+        self.start < end.max(start + chrono::Duration::seconds(1))
+            && self.end.max(self.start + chrono::Duration::seconds(1)) > start
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct FreeBusyQuery {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
@@ -1,9 +1,21 @@
+pub mod alarms;
+pub mod attendance;
 pub mod attendees;
+pub mod availability;
 pub mod calendars;
+pub mod dav;
 pub mod events;
+pub mod freebusy;
+pub mod proposals;
 
+pub use alarms::*;
+pub use attendance::*;
 pub use attendees::*;
+pub use availability::*;
 pub use calendars::*;
+pub use dav::*;
 pub use events::*;
+pub use freebusy::*;
+pub use proposals::*;
 pub mod event_enums;
 pub use event_enums::*;
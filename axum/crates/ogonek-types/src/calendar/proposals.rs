@@ -0,0 +1,61 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::Type;
+use utoipa::ToSchema;
+use validator::Validate;
+
+use crate::datetime_serialization;
+
+#[derive(ToSchema, Serialize, Deserialize, Type, Debug, Default, PartialEq, Clone)]
+#[sqlx(type_name = "proposal_status", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ProposalStatus {
+    #[default]
+    Pending,
+    Accepted,
+    Declined,
+}
+
+/// A new time or place proposed by an attendee
+#[derive(ToSchema, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct EventProposal {
+    pub id: String,
+    /// The virtual occurrence ID when the proposal is for one occurrence of a series
+    pub event_id: String,
+    pub summary: String,
+    pub proposer: String,
+    pub proposer_name: String,
+    #[serde(with = "datetime_serialization::option")]
+    pub recurrence_id: Option<DateTime<Utc>>,
+    #[serde(with = "datetime_serialization::option")]
+    pub proposed_start: Option<DateTime<Utc>>,
+    #[serde(with = "datetime_serialization::option")]
+    pub proposed_end: Option<DateTime<Utc>>,
+    pub proposed_location: Option<String>,
+    pub comment: Option<String>,
+    pub status: ProposalStatus,
+    #[serde(with = "datetime_serialization")]
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "datetime_serialization::option")]
+    pub responded_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Validate, ToSchema, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventProposalCreate {
+    #[serde(default, with = "datetime_serialization::option")]
+    pub proposed_start: Option<DateTime<Utc>>,
+    #[serde(default, with = "datetime_serialization::option")]
+    pub proposed_end: Option<DateTime<Utc>>,
+    #[validate(length(max = 255))]
+    pub proposed_location: Option<String>,
+    #[validate(length(max = 1000))]
+    pub comment: Option<String>,
+}
+
+/// Lists pending proposals unless another status is asked for
+#[derive(ToSchema, Deserialize)]
+pub struct ProposalQuery {
+    pub status: Option<ProposalStatus>,
+}
@@ -1,6 +1,7 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
+use core::fmt;
 use serde::{Deserialize, Serialize};
-use sqlx::prelude::FromRow;
+use sqlx::prelude::{FromRow, Type};
 
 use crate::{Visibility, datetime_serialization};
 
@@ -11,6 +12,8 @@ pub struct Card {
     pub front: String,
     pub back: String,
     pub media_url: Option<String>,
+    pub card_type: CardType,
+    pub tags: Vec<String>,
 }
 #[derive(Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
@@ -19,6 +22,91 @@ pub struct CardUpsert {
     pub front: String,
     pub back: String,
     pub media_url: Option<String>,
+    #[serde(default)]
+    pub card_type: CardType,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// A card found by search, with the deck it belongs to
+#[derive(Serialize, ToSchema, FromRow, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CardSearchResult {
+    pub id: String,
+    pub deck_id: String,
+    pub deck_title: String,
+    pub front: String,
+    pub back: String,
+    pub media_url: Option<String>,
+    pub card_type: CardType,
+    pub tags: Vec<String>,
+}
+
+/// Narrows cards down for search and filtered study.
+/// Parsed from expressions like `deck:ID tag:verbs failed:7 is:review perro`
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CardFilter {
+    pub deck_id: Option<String>,
+    /// Cards carrying all of these tags
+    pub tags: Vec<String>,
+    /// Cards answered wrong within this many days
+    pub failed_within_days: Option<i32>,
+    pub state: Option<CardState>,
+    /// Full-text search over front and back
+    pub text: Option<String>,
+}
+
+impl CardFilter {
+    pub fn for_deck(deck_id: Option<String>) -> Self {
+        Self {
+            deck_id,
+            ..Default::default()
+        }
+    }
+
+    /// Anything narrower than a deck. Such filters are studied regardless of due dates and daily limits
+    pub fn is_custom(&self) -> bool {
+        !self.tags.is_empty()
+            || self.failed_within_days.is_some()
+            || self.state.is_some()
+            || self.text.is_some()
+    }
+}
+
+/// How a card is studied. Reversed cards are learnt in both directions,
+/// cloze cards once per `{{c1::...}}` deletion, typed cards by typing the back
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type, ToSchema)]
+#[sqlx(type_name = "varchar")]
+#[sqlx(rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum CardType {
+    #[default]
+    Basic,
+    Reversed,
+    Cloze,
+    Typed,
+}
+
+impl fmt::Display for CardType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CardType::Basic => write!(f, "basic"),
+            CardType::Reversed => write!(f, "reversed"),
+            CardType::Cloze => write!(f, "cloze"),
+            CardType::Typed => write!(f, "typed"),
+        }
+    }
+}
+
+impl From<String> for CardType {
+    fn from(s: String) -> Self {
+        match s.to_lowercase().as_str() {
+            "reversed" => CardType::Reversed,
+            "cloze" => CardType::Cloze,
+            "typed" => CardType::Typed,
+            _ => CardType::Basic,
+        }
+    }
 }
 
 // DECK STRUCTS HERE
@@ -47,6 +135,9 @@ pub struct DeckFull {
     pub is_subscribed: Option<bool>,
     pub created_by: String,
     pub card_count: i32,
+    pub scheduler: Option<SchedulerKind>,
+    pub new_cards_per_day: Option<i32>,
+    pub reviews_per_day: Option<i32>,
 
     #[serde(with = "datetime_serialization")]
     pub created_at: DateTime<Utc>,
@@ -89,18 +180,42 @@ pub struct DeckUpdate {
     pub visibility: Option<Visibility>,
     pub assignee: Option<String>,
     pub unassign: Option<bool>,
+    pub scheduler: Option<SchedulerKind>,
+    pub new_cards_per_day: Option<i32>,
+    pub reviews_per_day: Option<i32>,
+}
+
+#[derive(Deserialize, ToSchema, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DeckExportFormat {
+    #[default]
+    Csv,
+    Apkg,
+}
+
+#[derive(Deserialize, ToSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DeckExportQuery {
+    #[serde(default)]
+    pub format: DeckExportFormat,
 }
 
 use utoipa::ToSchema;
 use validator::Validate;
 
-#[derive(Serialize, Clone, Validate, ToSchema)]
+#[derive(Serialize, Clone, Validate, ToSchema, FromRow)]
 #[serde(rename_all = "camelCase")]
 pub struct CardProgressWithFields {
     pub id: String,
     pub front: String,
+    /// Left empty for typed cards, the grade carries the expected answer
     pub back: String,
     pub media_url: Option<String>,
+    pub card_type: CardType,
+    pub state: CardState,
+    /// Side of the card being learnt: 1 is the reverse of a reversed card,
+    /// for cloze cards it is the number of the deletion
+    pub ordinal: i32,
 }
 
 #[derive(Serialize, Clone, Validate, ToSchema, Debug)]
@@ -119,6 +234,14 @@ pub struct CardProgress {
     pub ease_factor: f64,
     #[validate(range(min = 1))]
     pub interval: i32,
+    pub stability: Option<f64>,
+    #[validate(range(min = 1.0, max = 10.0))]
+    pub difficulty: Option<f64>,
+    pub state: CardState,
+    #[validate(range(min = 0))]
+    pub step: i32,
+    #[validate(range(min = 0))]
+    pub lapses: i32,
 }
 
 #[derive(ToSchema, Deserialize)]
@@ -130,10 +253,219 @@ pub struct UpdateCardProgress {
     pub interval: i32,
     #[serde(with = "datetime_serialization")]
     pub last_reviewed: DateTime<Utc>,
+    pub stability: Option<f64>,
+    pub difficulty: Option<f64>,
+    pub state: CardState,
+    pub step: i32,
+    pub lapses: i32,
+}
+
+/// Where a card is in its life cycle. New and lapsed cards go through
+/// intra-day (re)learning steps before the scheduler takes over
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type, ToSchema)]
+#[sqlx(type_name = "varchar")]
+#[sqlx(rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum CardState {
+    #[default]
+    New,
+    Learning,
+    Review,
+    Relearning,
+}
+
+impl fmt::Display for CardState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CardState::New => write!(f, "new"),
+            CardState::Learning => write!(f, "learning"),
+            CardState::Review => write!(f, "review"),
+            CardState::Relearning => write!(f, "relearning"),
+        }
+    }
+}
+
+impl From<String> for CardState {
+    fn from(s: String) -> Self {
+        match s.to_lowercase().as_str() {
+            "learning" => CardState::Learning,
+            "review" => CardState::Review,
+            "relearning" => CardState::Relearning,
+            _ => CardState::New,
+        }
+    }
+}
+
+/// Everything needed to schedule a card for a particular user
+pub struct SchedulerSettings {
+    pub scheduler: SchedulerKind,
+    /// Minutes
+    pub learning_steps: Vec<i32>,
+    /// Minutes
+    pub relearning_steps: Vec<i32>,
+}
+
+/// The spaced-repetition algorithm used to schedule a card
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type, ToSchema)]
+#[sqlx(type_name = "varchar")]
+#[sqlx(rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum SchedulerKind {
+    #[default]
+    Sm2,
+    Fsrs,
+}
+
+impl fmt::Display for SchedulerKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchedulerKind::Sm2 => write!(f, "sm2"),
+            SchedulerKind::Fsrs => write!(f, "fsrs"),
+        }
+    }
+}
+
+impl From<String> for SchedulerKind {
+    fn from(s: String) -> Self {
+        match s.to_lowercase().as_str() {
+            "fsrs" => SchedulerKind::Fsrs,
+            _ => SchedulerKind::Sm2,
+        }
+    }
 }
 
 #[derive(ToSchema, Deserialize, Validate)]
 pub struct ReviewPayload {
     #[validate(range(min = 0, max = 5))]
     pub quality: i32,
+    /// What the learner typed. Required for typed cards, which are graded from it and ignore `quality`
+    pub answer: Option<String>,
+}
+
+#[derive(ToSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReviewOutcome {
+    /// The quality the card was scheduled with
+    pub quality: i32,
+    /// Only set for typed cards
+    pub correct: Option<bool>,
+    pub expected: Option<String>,
+}
+
+/// A single answer given to a card, as stored in the review log
+#[derive(Serialize, ToSchema, FromRow, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CardReview {
+    pub id: String,
+    pub user_id: String,
+    pub card_id: String,
+    pub deck_id: String,
+    pub front: String,
+    pub back: String,
+    pub quality: i32,
+    pub ease_before: f64,
+    pub ease_after: f64,
+    pub interval_before: i32,
+    pub interval_after: i32,
+    pub elapsed_seconds: Option<i64>,
+    #[serde(with = "datetime_serialization")]
+    pub reviewed_at: DateTime<Utc>,
+}
+
+/// What gets written to the review log on every progress update
+pub struct CardReviewCreate {
+    pub quality: i32,
+    pub ease_before: f64,
+    pub ease_after: f64,
+    pub interval_before: i32,
+    pub interval_after: i32,
+    pub elapsed_seconds: Option<i64>,
+    pub reviewed_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct StatsQuery {
+    /// Teachers can look at the statistics of one of their students
+    #[serde(default)]
+    pub student_id: Option<String>,
+}
+
+/// Learning statistics for a deck, or for all of a user's decks
+#[derive(Serialize, ToSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LearnStats {
+    pub cards: CardCounts,
+    /// Share of answers recalled over the last 7 days, counting only cards last seen on an earlier day
+    pub retention_7d: Option<f64>,
+    pub retention_30d: Option<f64>,
+    /// Mean ease factor of the cards studied so far
+    pub average_ease: Option<f64>,
+    /// The last 30 days, oldest first
+    pub reviews_per_day: Vec<DailyReviews>,
+    /// Cards falling due over the next 30 days, overdue ones are counted today
+    pub forecast: Vec<DueForecast>,
+}
+
+/// Cards by state. Review cards with an interval of 21 days or more are mature
+#[derive(Serialize, ToSchema, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CardCounts {
+    pub new: i64,
+    pub learning: i64,
+    pub young: i64,
+    pub mature: i64,
+    pub total: i64,
+}
+
+#[derive(Serialize, ToSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DailyReviews {
+    pub date: NaiveDate,
+    pub reviews: i64,
+    /// Answers graded 3 or above
+    pub correct: i64,
+}
+
+#[derive(Serialize, ToSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DueForecast {
+    pub date: NaiveDate,
+    pub due: i64,
+}
+
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StudySessionCreate {
+    /// Only study this deck, all subscribed decks otherwise
+    pub deck_id: Option<String>,
+    /// Filter expression such as `tag:verbs` or `failed:7`, see `CardFilter`
+    pub filter: Option<String>,
+}
+
+#[derive(Serialize, ToSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StudySession {
+    pub id: String,
+    pub deck_id: Option<String>,
+    pub filter: Option<String>,
+    /// Cards still waiting in the queue, including learning cards that came back
+    pub remaining: i64,
+    pub answered: i64,
+    #[serde(with = "datetime_serialization")]
+    pub started_at: DateTime<Utc>,
+    #[serde(with = "datetime_serialization::option")]
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// What a finished session amounts to, also stored with its activity log entry
+#[derive(Serialize, ToSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StudySessionSummary {
+    pub cards_seen: i64,
+    pub answers: i64,
+    pub correct: i64,
+    /// Share of answers graded 3 or above
+    pub accuracy: Option<f64>,
+    /// Time between each card being handed out and answered
+    pub time_spent_seconds: i64,
 }
@@ -0,0 +1,45 @@
+use crate::CardSearchResult;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+use super::{default_page, default_per_page};
+
+#[derive(Debug, Deserialize, ToSchema, Validate)]
+pub struct CardSearchParams {
+    #[validate(range(min = 1))]
+    #[serde(default = "default_page")]
+    pub page: u32,
+
+    #[validate(range(min = 1, max = 100))]
+    #[serde(default = "default_per_page")]
+    pub per_page: u32,
+
+    /// Filter expression such as `tag:verbs failed:7 perro`
+    #[serde(default)]
+    pub q: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PaginatedCards {
+    pub data: Vec<CardSearchResult>,
+    pub page: i64,
+    pub count: i64,
+    pub total_pages: i64,
+    pub per_page: i64,
+}
+
+impl CardSearchParams {
+    pub fn offset(&self) -> i64 {
+        ((self.page - 1) * self.per_page) as i64
+    }
+
+    pub fn limit(&self) -> i64 {
+        self.per_page as i64
+    }
+
+    pub fn page(&self) -> i64 {
+        self.page as i64
+    }
+}
@@ -1,9 +1,13 @@
+mod cards;
 mod decks;
 mod lessons;
+mod reviews;
 mod tasks;
 
+pub use cards::*;
 pub use decks::*;
 pub use lessons::*;
+pub use reviews::*;
 use serde::{Deserialize, Serialize};
 pub use tasks::*;
 use utoipa::ToSchema;
@@ -0,0 +1,34 @@
+use serde::Deserialize;
+use utoipa::ToSchema;
+use validator::Validate;
+
+use super::{default_page, default_per_page};
+
+#[derive(Debug, Deserialize, ToSchema, Validate)]
+pub struct ReviewPaginationParams {
+    #[validate(range(min = 1))]
+    #[serde(default = "default_page")]
+    pub page: u32,
+
+    #[validate(range(min = 1, max = 100))]
+    #[serde(default = "default_per_page")]
+    pub per_page: u32,
+
+    /// Teachers can look at the history of one of their students
+    #[serde(default)]
+    pub student_id: Option<String>,
+}
+
+impl ReviewPaginationParams {
+    pub fn offset(&self) -> i64 {
+        ((self.page - 1) * self.per_page) as i64
+    }
+
+    pub fn limit(&self) -> i64 {
+        self.per_page as i64
+    }
+
+    pub fn page(&self) -> i64 {
+        self.page as i64
+    }
+}
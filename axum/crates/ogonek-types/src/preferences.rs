@@ -1,3 +1,4 @@
+use crate::SchedulerKind;
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use utoipa::ToSchema;
@@ -12,6 +13,13 @@ pub struct UserPreferences {
     pub push_notifications: bool,
     pub theme: String,
     pub language: String,
+    pub scheduler: SchedulerKind,
+    pub learning_steps: Vec<i32>,
+    pub relearning_steps: Vec<i32>,
+    pub new_cards_per_day: i32,
+    pub reviews_per_day: i32,
+    pub daily_review_goal: i32,
+    pub daily_task_goal: i32,
 }
 
 #[derive(Deserialize)]
@@ -22,6 +30,13 @@ pub struct UserPreferencesUpdate {
     pub push_notifications: Option<bool>,
     pub theme: Option<String>,
     pub language: Option<String>,
+    pub scheduler: Option<SchedulerKind>,
+    pub learning_steps: Option<Vec<i32>>,
+    pub relearning_steps: Option<Vec<i32>>,
+    pub new_cards_per_day: Option<i32>,
+    pub reviews_per_day: Option<i32>,
+    pub daily_review_goal: Option<i32>,
+    pub daily_task_goal: Option<i32>,
 }
 
 #[derive(Serialize)]
@@ -32,4 +47,11 @@ pub struct UserPreferencesResponse {
     pub push_notifications: bool,
     pub theme: String,
     pub language: String,
+    pub scheduler: SchedulerKind,
+    pub learning_steps: Vec<i32>,
+    pub relearning_steps: Vec<i32>,
+    pub new_cards_per_day: i32,
+    pub reviews_per_day: i32,
+    pub daily_review_goal: i32,
+    pub daily_task_goal: i32,
 }
@@ -1,4 +1,7 @@
-use crate::{EventSmall, LessonSmall, Profile, Student, TaskSmall, User, UserPreferences};
+use crate::{
+    DailyGoals, EventSmall, LessonSmall, Profile, Student, StudyStreak, TaskSmall, User,
+    UserPreferences,
+};
 use serde::Serialize;
 use utoipa::ToSchema;
 
@@ -18,6 +21,8 @@ pub struct DashboardData {
     pub tasks: Vec<TaskSmall>,
     pub events: Vec<EventSmall>,
     pub lessons: Vec<LessonSmall>,
+    pub streak: StudyStreak,
+    pub goals: DailyGoals,
 }
 
 #[derive(Serialize, ToSchema)]
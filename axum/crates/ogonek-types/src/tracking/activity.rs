@@ -10,6 +10,7 @@ pub enum ModelType {
     Task,
     Deck,
     Word,
+    StudySession,
 }
 
 impl ModelType {
@@ -19,6 +20,7 @@ impl ModelType {
             ModelType::Task => "task",
             ModelType::Deck => "deck",
             ModelType::Word => "word",
+            ModelType::StudySession => "study_session",
         }
     }
 }
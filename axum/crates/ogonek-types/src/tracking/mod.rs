@@ -1,5 +1,7 @@
 pub mod activity;
 pub mod audit;
+pub mod study;
 
 pub use activity::*;
 pub use audit::*;
+pub use study::*;
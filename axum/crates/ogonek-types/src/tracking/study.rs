@@ -0,0 +1,48 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Consecutive days with at least one card reviewed or task completed
+#[derive(Serialize, ToSchema, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct StudyStreak {
+    /// Still running when the last study day was yesterday
+    pub current: i64,
+    pub longest: i64,
+    pub studied_today: bool,
+}
+
+/// Today's progress towards the goals set in the user's preferences
+#[derive(Serialize, ToSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DailyGoals {
+    pub reviews_done: i64,
+    pub review_goal: i32,
+    pub tasks_done: i64,
+    pub task_goal: i32,
+}
+
+impl DailyGoals {
+    pub fn is_met(&self) -> bool {
+        self.reviews_done >= self.review_goal.into() && self.tasks_done >= self.task_goal.into()
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct HeatmapQuery {
+    /// Defaults to a year before `to`
+    pub from: Option<NaiveDate>,
+    /// Defaults to today
+    pub to: Option<NaiveDate>,
+}
+
+/// A day with any activity. Days without any are left out
+#[derive(Serialize, ToSchema, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HeatmapDay {
+    pub date: NaiveDate,
+    pub reviews: i64,
+    /// Entries in the activity log, completed tasks among them
+    pub activities: i64,
+    pub tasks_completed: i64,
+}
@@ -2,8 +2,9 @@ use crate::{
     api::{AUTH_TAG, error::APIError},
     app::AppState,
     services::{
-        AuthError, decode_token, generate_secure_token, generate_token, hash_password,
-        verify_password,
+        AuditBuilder, AuthError, ChallengePurpose, RequestMetadata, encode_challenge_token,
+        generate_secure_token, generate_session_token, hash_password, hash_token,
+        two_factor_required, verify_signin_password,
     },
 };
 
@@ -11,12 +12,29 @@ use axum::{
     extract::{Json, State},
     http::StatusCode,
 };
-use ogonek_db::core::account::{auth, user};
+use chrono::{Duration, Utc};
+use ogonek_db::{
+    DbError,
+    core::account::{
+        auth,
+        session::{self, Rotation},
+        two_factor, user,
+    },
+    tracking::audit,
+};
 use ogonek_types::{
-    AuthPayload, RefreshTokenPayload, RefreshTokenResponse, SignUpPayload, TokenPair, UserRole,
+    AuthPayload, RefreshTokenPayload, RefreshTokenResponse, SignInResponse, SignUpPayload,
+    TokenPair, TokenWithExpiry, TwoFactorChallenge, UserRole,
 };
 use validator::Validate;
 
+use super::session_origin;
+
+/// How long an access token lasts
+const ACCESS_TOKEN_SECS: u64 = 60 * 15;
+/// How long a refresh session lasts, rotating its token doesn't extend it
+const REFRESH_TOKEN_SECS: i64 = 60 * 60 * 24 * 30;
+
 /// Registers a new user account with email verification
 ///
 /// Creates a new user account, hashes the password, and sends an email
@@ -29,7 +47,8 @@ use validator::Validate;
         (status = 201, description = "User registered successfully"),
         (status = 400, description = "Invalid registration data"),
         (status = 403, description = "Forbidden"),
-        (status = 409, description = "User already exists")
+        (status = 409, description = "User already exists"),
+        (status = 429, description = "Too many requests, see Retry-After")
     )
 )]
 pub async fn signup(
@@ -78,21 +97,25 @@ pub async fn signup(
 
 /// Authenticates a user and returns access/refresh tokens
 ///
-/// Validates credentials and returns JWT tokens for authenticated access.
+/// Validates credentials and starts a refresh session for the device. The refresh token
+/// is opaque and changes on every refresh. Accounts with 2FA, or whose role requires it,
+/// get a short-lived challenge instead, to be finished at `/2fa/verify`.
 #[utoipa::path(
     post,
     path = "/signin",
     request_body = AuthPayload,
     tag = AUTH_TAG,
     responses(
-        (status = 200, description = "Authentication successful", body = TokenPair),
-        (status = 401, description = "Invalid credentials")
+        (status = 200, description = "Tokens, or a second-factor challenge", body = SignInResponse),
+        (status = 401, description = "Invalid credentials"),
+        (status = 429, description = "Too many attempts or account locked out, see Retry-After")
     )
 )]
 pub async fn signin(
-    State(state): State<AppState>,
+    State(mut state): State<AppState>,
+    metadata: RequestMetadata,
     Json(payload): Json<AuthPayload>,
-) -> Result<Json<TokenPair>, APIError> {
+) -> Result<Json<SignInResponse>, APIError> {
     if payload.username.is_empty() || payload.pass.is_empty() {
         return Err(APIError::AuthError(AuthError::InvalidCredentials));
     }
@@ -103,19 +126,67 @@ pub async fn signin(
 
     let user = auth::read_by_username(&state.db, &payload.username).await?;
 
-    if !verify_password(&user.pass, &payload.pass)? {
-        return Err(APIError::AuthError(AuthError::AuthenticationFailed));
+    verify_signin_password(&mut state, &user, &payload.pass, &metadata).await?;
+
+    if two_factor::is_enabled(&state.db, &user.id).await? {
+        let challenge_token = encode_challenge_token(&user.id, ChallengePurpose::Verify)?;
+        return Ok(Json(SignInResponse::Challenge(TwoFactorChallenge {
+            challenge_token,
+            setup_required: false,
+        })));
+    }
+    if two_factor_required(&user.role) {
+        let challenge_token = encode_challenge_token(&user.id, ChallengePurpose::Setup)?;
+        return Ok(Json(SignInResponse::Challenge(TwoFactorChallenge {
+            challenge_token,
+            setup_required: true,
+        })));
     }
 
-    let access_token = generate_token(&user.id, &user.role, 60 * 15)?;
-    let refresh_token = generate_token(&user.id, &user.role, 60 * 60 * 24 * 30)?;
+    let tokens = start_session(&state, &user.id, &user.role, &metadata).await?;
+    Ok(Json(SignInResponse::Tokens(tokens)))
+}
 
-    Ok(Json(TokenPair::new(access_token, refresh_token)))
+/// Starts a refresh session for a user whose credentials checked out, and audits the
+/// sign in
+pub(super) async fn start_session(
+    state: &AppState,
+    user_id: &str,
+    role: &UserRole,
+    metadata: &RequestMetadata,
+) -> Result<TokenPair, APIError> {
+    let refresh_token = generate_secure_token();
+    let expires_at = Utc::now() + Duration::seconds(REFRESH_TOKEN_SECS);
+    let session_id = session::create(
+        &state.db,
+        user_id,
+        &hash_token(&refresh_token),
+        &session_origin(metadata),
+        expires_at,
+    )
+    .await?;
+    let access_token = generate_session_token(user_id, role, ACCESS_TOKEN_SECS, Some(&session_id))?;
+
+    let email = user::read_email(&state.db, user_id).await?;
+    let signin_audit = AuditBuilder::session_operation("SIGNIN", user_id, role, email)
+        .resource_id(session_id)
+        .with_metadata(metadata)
+        .build();
+    audit::create(&state.db, &signin_audit).await?;
+
+    Ok(TokenPair::new(
+        access_token,
+        TokenWithExpiry {
+            token: refresh_token,
+            expires_at: expires_at.timestamp() as u64,
+        },
+    ))
 }
 
-/// Refreshes an access token using a valid refresh token
+/// Exchanges a refresh token for a new access token and a new refresh token
 ///
-/// Decodes the refresh token and generates a new access token for the user.
+/// The refresh token sent stops working. Sending it again is taken as theft: the whole
+/// session is revoked, including the token that replaced it.
 #[utoipa::path(
     post,
     path = "/refresh",
@@ -123,20 +194,69 @@ pub async fn signin(
     tag = AUTH_TAG,
     responses(
         (status = 200, description = "Token refreshed", body = RefreshTokenResponse),
-        (status = 401, description = "Invalid refresh token")
+        (status = 401, description = "Invalid, expired or reused refresh token")
     )
 )]
 pub async fn refresh(
     State(state): State<AppState>,
+    metadata: RequestMetadata,
     Json(request): Json<RefreshTokenPayload>,
 ) -> Result<Json<RefreshTokenResponse>, APIError> {
-    // Decode the refresh token to get user claims
-    let refresh_claims = decode_token(&request.refresh_token)?;
+    let next_token = generate_secure_token();
+    let rotation = session::rotate(
+        &state.db,
+        &hash_token(&request.refresh_token),
+        &hash_token(&next_token),
+        &session_origin(&metadata),
+    )
+    .await
+    .map_err(|e| match e {
+        DbError::NotFound(_) => APIError::AuthError(AuthError::InvalidToken),
+        other => other.into(),
+    })?;
 
-    let user = user::read_by_id(&state.db, &refresh_claims.sub).await?;
-    let new_access_token = generate_token(&user.id, &user.role, 60 * 15)?;
+    match rotation {
+        Rotation::Reused {
+            session_id,
+            user_id,
+        } => {
+            let user = user::read_by_id(&state.db, &user_id).await?;
+            let reuse_audit =
+                AuditBuilder::session_operation("REFRESH", &user.id, &user.role, user.email)
+                    .failed()
+                    .security_event()
+                    .resource_id(session_id)
+                    .with_metadata(&metadata)
+                    .payload(serde_json::json!({ "reason": "refresh_token_reuse" }))
+                    .build();
+            audit::create(&state.db, &reuse_audit).await?;
 
-    Ok(Json(RefreshTokenResponse {
-        access_token: new_access_token,
-    }))
+            tracing::warn!("Refresh token reused, revoked the session of user {user_id}");
+            Err(APIError::AuthError(AuthError::InvalidToken))
+        }
+        Rotation::Rotated {
+            session_id,
+            user_id,
+            expires_at,
+        } => {
+            let user = user::read_by_id(&state.db, &user_id).await?;
+            let access_token =
+                generate_session_token(&user.id, &user.role, ACCESS_TOKEN_SECS, Some(&session_id))?;
+
+            let refresh_audit =
+                AuditBuilder::session_operation("REFRESH", &user.id, &user.role, user.email)
+                    .resource_id(session_id)
+                    .with_metadata(&metadata)
+                    .build();
+            audit::create(&state.db, &refresh_audit).await?;
+
+            Ok(Json(RefreshTokenResponse {
+                access_token,
+                refresh_token: TokenWithExpiry {
+                    token: next_token,
+                    expires_at: expires_at.timestamp() as u64,
+                },
+            }))
+        }
+    }
 }
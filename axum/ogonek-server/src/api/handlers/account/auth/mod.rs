@@ -1,6 +1,12 @@
 mod basic;
+mod reset;
+mod sessions;
 mod teacher_student;
+mod two_factor;
 mod verification;
 pub use basic::*;
+pub use reset::*;
+pub use sessions::*;
 pub use teacher_student::*;
+pub use two_factor::*;
 pub use verification::*;
@@ -1,37 +1,106 @@
-use axum::extract::State;
-use reqwest::StatusCode;
-use ogonek_db::core::account::user::read_email;
+use axum::{
+    extract::{Json, State},
+    http::StatusCode,
+};
+use ogonek_db::{
+    core::account::{auth, session, user},
+    tracking::audit,
+};
+use ogonek_types::{ForgotPasswordPayload, PasswordResetPayload};
+use validator::Validate;
 
-use crate::{AppError, AppState, Claims, api::AUTH_TAG, services::generate_secure_token};
+use crate::{
+    api::{AUTH_TAG, error::APIError},
+    app::AppState,
+    services::{AuditBuilder, AuthError, RequestMetadata, generate_secure_token, hash_password},
+};
 
-/// Initiates the password reset process for the authenticated user
+/// Emails a password reset link
 ///
-/// Generates a secure token and stores it for password reset verification.
+/// Always accepted, whether or not an account uses the email, so the endpoint can't be
+/// used to find out who is registered. The link works once and expires after 30 minutes.
+#[utoipa::path(
+    post,
+    path = "/forgot_password",
+    request_body = ForgotPasswordPayload,
+    tag = AUTH_TAG,
+    responses(
+        (status = 202, description = "A reset link is sent if the account exists"),
+        (status = 400, description = "Invalid email"),
+        (status = 429, description = "Too many requests, see Retry-After")
+    )
+)]
+pub async fn forgot_password(
+    State(mut state): State<AppState>,
+    Json(payload): Json<ForgotPasswordPayload>,
+) -> Result<StatusCode, APIError> {
+    payload.validate()?;
+
+    if let Some((user_id, name)) = auth::read_by_email(&state.db, &payload.email).await? {
+        let token = generate_secure_token();
+        state
+            .redis
+            .set_password_reset_token(&user_id, &token, None)
+            .await?;
+
+        // Sent in the background so the response time doesn't tell whether the account exists
+        tokio::spawn(async move {
+            if let Err(e) = state
+                .ses
+                .send_password_reset(&payload.email, &name, &token)
+                .await
+            {
+                tracing::error!("Error sending password reset link: {e}")
+            }
+        });
+    }
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// Sets a new password using the emailed reset token
+///
+/// Consumes the token and signs the user out on every device.
 #[utoipa::path(
     post,
     path = "/reset_password",
+    request_body = PasswordResetPayload,
     tag = AUTH_TAG,
     responses(
-        (status = 200, description = "Password reset"),
-        (status = 401, description = "Unauthorized"),
-        (status = 400, description = "Invalid token")
+        (status = 204, description = "Password changed"),
+        (status = 400, description = "Invalid password"),
+        (status = 401, description = "Invalid or expired token"),
+        (status = 429, description = "Too many requests, see Retry-After")
     )
 )]
 pub async fn reset_password(
     State(mut state): State<AppState>,
-    claims: Claims,
-) -> Result<StatusCode, AppError> {
-    let token = generate_secure_token();
-    let email = read_email(&state.db, &claims.sub).await.ok();
-
-    match email {
-        Some(email) => {
-            state
-                .redis
-                .set_verification_token(&email, &token, None)
-                .await?;
-            Ok(StatusCode::ACCEPTED)
-        }
-        None => Ok(StatusCode::NOT_FOUND),
-    }
+    metadata: RequestMetadata,
+    Json(payload): Json<PasswordResetPayload>,
+) -> Result<StatusCode, APIError> {
+    payload.validate()?;
+
+    let user_id = state
+        .redis
+        .consume_password_reset_token(&payload.token)
+        .await?
+        .ok_or(APIError::AuthError(AuthError::InvalidToken))?;
+
+    let hashed_password = hash_password(&payload.pass)?;
+    auth::set_password(&state.db, &user_id, &hashed_password).await?;
+
+    let revoked = session::revoke_all(&state.db, &user_id, "password_reset").await?;
+
+    let user = user::read_by_id(&state.db, &user_id).await?;
+    let reset_audit =
+        AuditBuilder::session_operation("LOGOUT_ALL", &user.id, &user.role, user.email)
+            .with_metadata(&metadata)
+            .payload(serde_json::json!({
+                "reason": "password_reset",
+                "revoked_sessions": revoked
+            }))
+            .build();
+    audit::create(&state.db, &reset_audit).await?;
+
+    Ok(StatusCode::NO_CONTENT)
 }
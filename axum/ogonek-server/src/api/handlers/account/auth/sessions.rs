@@ -0,0 +1,134 @@
+use axum::{
+    extract::{Json, Path, State},
+    http::StatusCode,
+};
+use ogonek_db::{
+    core::account::{session, user},
+    tracking::audit,
+};
+use ogonek_types::{RefreshSession, SessionOrigin};
+
+use crate::{
+    api::{AUTH_TAG, error::APIError},
+    app::AppState,
+    services::{AuditBuilder, Claims, RequestMetadata},
+};
+
+pub(super) fn session_origin(metadata: &RequestMetadata) -> SessionOrigin {
+    SessionOrigin {
+        device: metadata.device.clone(),
+        user_agent: Some(metadata.user_agent.clone()),
+        ip_address: metadata.ip_address,
+    }
+}
+
+/// Lists the devices the user is signed in on
+#[utoipa::path(
+    get,
+    path = "/sessions",
+    tag = AUTH_TAG,
+    responses(
+        (status = 200, description = "Active sessions", body = Vec<RefreshSession>),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn list_sessions(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<Vec<RefreshSession>>, APIError> {
+    let sessions = session::read_all(&state.db, &claims.sub, claims.sid.as_deref()).await?;
+    Ok(Json(sessions))
+}
+
+/// Signs a device out
+///
+/// Its refresh token stops working, access tokens already issued run out on their own.
+#[utoipa::path(
+    delete,
+    path = "/sessions/{id}",
+    tag = AUTH_TAG,
+    params(
+        ("id" = String, Path, description = "Session ID")
+    ),
+    responses(
+        (status = 204, description = "Session revoked"),
+        (status = 404, description = "Session not found"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn revoke_session(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    claims: Claims,
+    metadata: RequestMetadata,
+) -> Result<StatusCode, APIError> {
+    session::revoke(&state.db, &id, &claims.sub).await?;
+
+    let email = user::read_email(&state.db, &claims.sub).await?;
+    let revoke_audit = AuditBuilder::session_operation("REVOKE", &claims.sub, &claims.role, email)
+        .resource_id(id)
+        .with_metadata(&metadata)
+        .build();
+    audit::create(&state.db, &revoke_audit).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Signs out the session the request is made from
+#[utoipa::path(
+    post,
+    path = "/logout",
+    tag = AUTH_TAG,
+    responses(
+        (status = 204, description = "Signed out"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn logout(
+    State(state): State<AppState>,
+    claims: Claims,
+    metadata: RequestMetadata,
+) -> Result<StatusCode, APIError> {
+    // Tokens issued before sessions existed have nothing to revoke
+    let Some(session_id) = claims.sid.clone() else {
+        return Ok(StatusCode::NO_CONTENT);
+    };
+    session::revoke(&state.db, &session_id, &claims.sub).await?;
+
+    let email = user::read_email(&state.db, &claims.sub).await?;
+    let logout_audit = AuditBuilder::session_operation("LOGOUT", &claims.sub, &claims.role, email)
+        .resource_id(session_id)
+        .with_metadata(&metadata)
+        .build();
+    audit::create(&state.db, &logout_audit).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Signs the user out on every device, this one included
+#[utoipa::path(
+    post,
+    path = "/logout_all",
+    tag = AUTH_TAG,
+    responses(
+        (status = 204, description = "Signed out everywhere"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn logout_all(
+    State(state): State<AppState>,
+    claims: Claims,
+    metadata: RequestMetadata,
+) -> Result<StatusCode, APIError> {
+    let revoked = session::revoke_all(&state.db, &claims.sub, "logout_all").await?;
+
+    let email = user::read_email(&state.db, &claims.sub).await?;
+    let logout_audit =
+        AuditBuilder::session_operation("LOGOUT_ALL", &claims.sub, &claims.role, email)
+            .with_metadata(&metadata)
+            .payload(serde_json::json!({ "revoked_sessions": revoked }))
+            .build();
+    audit::create(&state.db, &logout_audit).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
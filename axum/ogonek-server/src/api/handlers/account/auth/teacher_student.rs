@@ -1,20 +1,42 @@
 use axum::{
     Json,
-    extract::{Query, State},
+    extract::{Path, Query, State},
 };
-use ogonek_db::core::account::auth;
-use ogonek_types::{BindPayload, InviteQuery};
+use chrono::{DateTime, Utc};
+use ogonek_db::core::account::{invite, user};
+use ogonek_notifications::NotificationType;
+use ogonek_types::{BindPayload, Invite, InviteCreate, InviteLinks, InviteQuery, InviteRedemption};
 use reqwest::StatusCode;
+use validator::Validate;
 
 use crate::{
     AppError, AppState, Claims,
     api::AUTH_TAG,
-    services::{AuthError, decode_invite_token, encode_invite_token},
+    services::{decode_invite_token, encode_invite_token},
 };
 
-/// Binds a student to a teacher using an invite token
+fn frontend_url() -> String {
+    std::env::var("FRONTEND_URL")
+        .unwrap_or_else(|_| "http://localhost:5173".to_string())
+        .trim_end_matches('/')
+        .to_string()
+}
+
+async fn sign_invite(
+    invite_id: &str,
+    expires_at: DateTime<Utc>,
+) -> Result<(String, String, String), AppError> {
+    let token = encode_invite_token(invite_id.to_string(), expires_at).await?;
+    let frontend_url = frontend_url();
+    let signup_url = format!("{frontend_url}/signup?invite={token}");
+    let bind_url = format!("{frontend_url}/bind?invite={token}");
+    Ok((token, signup_url, bind_url))
+}
+
+/// Binds the signed-in student to a teacher using an invite token
 ///
-/// Creates a relationship between student and teacher accounts.
+/// Creates a relationship between student and teacher accounts and lets the teacher know.
+/// Using the same invite twice does nothing.
 #[utoipa::path(
     post,
     path = "/bind",
@@ -23,27 +45,50 @@ use crate::{
     responses(
         (status = 204, description = "Student bound to teacher successfully"),
         (status = 400, description = "Invalid bind data"),
-        (status = 401, description = "Invalid invite token")
+        (status = 401, description = "Unauthorized or invalid invite token"),
+        (status = 404, description = "Invite not found"),
+        (status = 412, description = "Invite revoked, expired, used up or meant for someone else"),
+        (status = 429, description = "Too many requests, see Retry-After")
     )
 )]
 pub async fn bind_student_to_teacher(
     State(state): State<AppState>,
+    claims: Claims,
     Json(payload): Json<BindPayload>,
 ) -> Result<StatusCode, AppError> {
-    let teacher_id = decode_invite_token(payload.invite_token).await?;
+    let invite_id = decode_invite_token(payload.invite_token).await?;
+
+    let Some(invite) = invite::redeem(&state.db, &invite_id, &claims.sub).await? else {
+        return Ok(StatusCode::NO_CONTENT);
+    };
+    tracing::info!(
+        "Student {} joined teacher {} through invite {}",
+        claims.sub,
+        invite.teacher_id,
+        invite.id
+    );
 
-    let _ = auth::bind(&state.db, &teacher_id, &payload.student_id)
-        .await
-        .map_err(|_| AuthError::InvalidToken);
+    let student_name = user::read_name(&state.db, &claims.sub).await?;
+    let _ = state
+        .notification_service
+        .notify_user(
+            &invite.teacher_id,
+            NotificationType::InviteRedeemed {
+                student_name,
+                student_id: claims.sub,
+            },
+        )
+        .await;
 
     Ok(StatusCode::NO_CONTENT)
 }
 
 /// Generates an invite link for teacher-student binding
 ///
-/// Creates a secure invite URL that can be used for student registration or binding.
+/// Creates a week-long invite with no use limit and returns its URL for registration
+/// or binding. Use `POST /invites` to pick the limits.
 #[utoipa::path(
-    get,
+    post,
     path = "/invite",
     params(
         ("isRegistered" = InviteQuery, Query, description = "Invite token")
@@ -56,21 +101,116 @@ pub async fn bind_student_to_teacher(
     )
 )]
 pub async fn generate_invite_link(
+    State(state): State<AppState>,
     claims: Claims,
     query: Query<InviteQuery>,
 ) -> Result<Json<String>, AppError> {
-    let frontend_url = std::env::var("FRONTEND_URL")
-        .unwrap_or_else(|_| "http://localhost:5173".to_string())
-        .trim_end_matches('/')
-        .to_string();
-
-    let encoded = encode_invite_token(claims.sub).await?;
-
-    tracing::info!("Encoded token: {encoded}");
+    let invite = invite::create(&state.db, &claims.sub, &InviteCreate::default()).await?;
+    let (_, signup_url, bind_url) = sign_invite(&invite.id, invite.expires_at).await?;
 
     if query.is_registered == "true" {
-        Ok(Json(format!("{frontend_url}/bind?invite={encoded}",)))
+        Ok(Json(bind_url))
     } else {
-        Ok(Json(format!("{frontend_url}/signup?invite={encoded}",)))
+        Ok(Json(signup_url))
     }
 }
+
+/// Creates an invite
+///
+/// The invite expires, can be limited to a number of uses and to a single student's email.
+#[utoipa::path(
+    post,
+    path = "/invites",
+    request_body = InviteCreate,
+    tag = AUTH_TAG,
+    responses(
+        (status = 201, description = "Invite created", body = InviteLinks),
+        (status = 400, description = "Invalid invite data"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn create_invite(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<InviteCreate>,
+) -> Result<(StatusCode, Json<InviteLinks>), AppError> {
+    payload.validate()?;
+
+    let invite = invite::create(&state.db, &claims.sub, &payload).await?;
+    let (token, signup_url, bind_url) = sign_invite(&invite.id, invite.expires_at).await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(InviteLinks {
+            invite,
+            token,
+            signup_url,
+            bind_url,
+        }),
+    ))
+}
+
+/// Lists the teacher's invites, revoked and expired ones included
+#[utoipa::path(
+    get,
+    path = "/invites",
+    tag = AUTH_TAG,
+    responses(
+        (status = 200, description = "Invites", body = Vec<Invite>),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn list_invites(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<Vec<Invite>>, AppError> {
+    let invites = invite::read_all(&state.db, &claims.sub).await?;
+    Ok(Json(invites))
+}
+
+/// Revokes an invite
+///
+/// Students who already joined stay bound.
+#[utoipa::path(
+    delete,
+    path = "/invites/{id}",
+    params(
+        ("id" = String, Path, description = "Invite ID")
+    ),
+    tag = AUTH_TAG,
+    responses(
+        (status = 204, description = "Invite revoked"),
+        (status = 404, description = "Invite not found"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn revoke_invite(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    claims: Claims,
+) -> Result<StatusCode, AppError> {
+    invite::revoke(&state.db, &id, &claims.sub).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Lists the students who joined through an invite
+#[utoipa::path(
+    get,
+    path = "/invites/{id}/redemptions",
+    params(
+        ("id" = String, Path, description = "Invite ID")
+    ),
+    tag = AUTH_TAG,
+    responses(
+        (status = 200, description = "Redemptions", body = Vec<InviteRedemption>),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn list_invite_redemptions(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    claims: Claims,
+) -> Result<Json<Vec<InviteRedemption>>, AppError> {
+    let redemptions = invite::read_redemptions(&state.db, &id, &claims.sub).await?;
+    Ok(Json(redemptions))
+}
@@ -0,0 +1,357 @@
+use axum::{
+    extract::{Json, State},
+    http::StatusCode,
+};
+use ogonek_db::{
+    DbError, LockoutPolicy,
+    core::account::{two_factor, user},
+    tracking::audit,
+};
+use ogonek_types::{
+    RecoveryCodes, TokenPair, TotpCodePayload, TotpSetup, TwoFactorEnabled, TwoFactorStatus,
+    TwoFactorVerifyPayload, User,
+};
+
+use super::start_session;
+use crate::{
+    api::{AUTH_TAG, error::APIError},
+    app::AppState,
+    services::{
+        AuditBuilder, AuthError, ChallengePurpose, Claims, Enrolling, RequestMetadata,
+        decode_challenge_token, generate_recovery_codes, generate_totp_secret, hash_token,
+        normalize_recovery_code, provisioning_uri, two_factor_required, verify_totp,
+    },
+};
+
+/// Wrong codes lock the second factor out like wrong passwords lock out signing in,
+/// otherwise six digits are quickly guessed by anyone holding the password
+const CODE_LOCKOUT: LockoutPolicy = LockoutPolicy {
+    threshold: 5,
+    base_secs: 60,
+    max_secs: 60 * 60 * 24,
+    memory_secs: 60 * 60 * 24,
+};
+
+fn hash_recovery_codes(codes: &[String]) -> Vec<String> {
+    codes
+        .iter()
+        .map(|code| hash_token(&normalize_recovery_code(code)))
+        .collect()
+}
+
+/// Accepts a current authenticator code or an unused recovery code, spending it
+async fn check_code(state: &AppState, user_id: &str, code: &str) -> Result<bool, APIError> {
+    let Some(secret) = two_factor::read_secret(&state.db, user_id).await? else {
+        return Ok(false);
+    };
+    if !secret.enabled {
+        return Ok(false);
+    }
+
+    if let Some(step) = verify_totp(&secret.secret, code)? {
+        return Ok(two_factor::use_step(&state.db, user_id, step).await?);
+    }
+    let code_hash = hash_token(&normalize_recovery_code(code));
+    Ok(two_factor::use_recovery_code(&state.db, user_id, &code_hash).await?)
+}
+
+/// Checks a code on behalf of the user, counting wrong ones towards a lockout.
+/// Rejections are audited.
+async fn require_code(
+    state: &mut AppState,
+    user: &User,
+    code: &str,
+    action: &str,
+    metadata: &RequestMetadata,
+) -> Result<(), APIError> {
+    let lockout_subject = format!("2fa:{}", user.id);
+    match state.redis.lockout_remaining(&lockout_subject).await {
+        Ok(Some(retry_after)) => return Err(APIError::TooManyRequests { retry_after }),
+        Ok(None) => {}
+        Err(e) => tracing::warn!("Lockout check skipped, Redis failed: {e}"),
+    }
+
+    if check_code(state, &user.id, code).await? {
+        if let Err(e) = state.redis.clear_failures(&lockout_subject).await {
+            tracing::warn!("Failed codes not cleared, Redis failed: {e}");
+        }
+        return Ok(());
+    }
+
+    let lockout = state
+        .redis
+        .record_failure(&lockout_subject, &CODE_LOCKOUT)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::warn!("Failed code not counted, Redis failed: {e}");
+            None
+        });
+    let failure_audit =
+        AuditBuilder::two_factor_operation(action, &user.id, &user.role, user.email.clone())
+            .failed()
+            .security_event()
+            .with_metadata(metadata);
+    match lockout {
+        Some(lockout_secs) => {
+            let lockout_audit = failure_audit
+                .payload(serde_json::json!({
+                    "reason": "invalid_code",
+                    "lockout_secs": lockout_secs
+                }))
+                .build();
+            audit::create(&state.db, &lockout_audit).await?;
+            Err(APIError::TooManyRequests {
+                retry_after: lockout_secs,
+            })
+        }
+        None => {
+            let failure_audit = failure_audit
+                .payload(serde_json::json!({ "reason": "invalid_code" }))
+                .build();
+            audit::create(&state.db, &failure_audit).await?;
+            Err(APIError::AuthError(AuthError::AuthenticationFailed))
+        }
+    }
+}
+
+async fn audit_failure(
+    state: &AppState,
+    user: User,
+    action: &str,
+    metadata: &RequestMetadata,
+) -> Result<(), APIError> {
+    let failure_audit =
+        AuditBuilder::two_factor_operation(action, &user.id, &user.role, user.email)
+            .failed()
+            .security_event()
+            .with_metadata(metadata)
+            .payload(serde_json::json!({ "reason": "invalid_code" }))
+            .build();
+    audit::create(&state.db, &failure_audit).await?;
+    Ok(())
+}
+
+/// Shows whether 2FA is on for the user
+#[utoipa::path(
+    get,
+    path = "/2fa",
+    tag = AUTH_TAG,
+    responses(
+        (status = 200, description = "Two-factor status", body = TwoFactorStatus),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn two_factor_status(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<TwoFactorStatus>, APIError> {
+    Ok(Json(TwoFactorStatus {
+        enabled: two_factor::is_enabled(&state.db, &claims.sub).await?,
+        required: two_factor_required(&claims.role),
+        recovery_codes_left: two_factor::count_recovery_codes(&state.db, &claims.sub).await?,
+    }))
+}
+
+/// Starts enrolling in 2FA
+///
+/// Returns a new secret and its provisioning URI for a QR code. Nothing changes until
+/// a code from it is confirmed. Users made to enrol while signing in send their setup
+/// challenge token as bearer.
+#[utoipa::path(
+    post,
+    path = "/2fa/setup",
+    tag = AUTH_TAG,
+    responses(
+        (status = 200, description = "Secret to scan", body = TotpSetup),
+        (status = 401, description = "Unauthorized"),
+        (status = 409, description = "Two-factor authentication is already enabled")
+    )
+)]
+pub async fn setup_two_factor(
+    State(state): State<AppState>,
+    enrolling: Enrolling,
+) -> Result<Json<TotpSetup>, APIError> {
+    let user = user::read_by_id(&state.db, &enrolling.user_id).await?;
+
+    let secret = generate_totp_secret();
+    two_factor::set_pending(&state.db, &user.id, &secret).await?;
+    let provisioning_uri = provisioning_uri(&secret, &user.username)?;
+
+    Ok(Json(TotpSetup {
+        secret,
+        provisioning_uri,
+    }))
+}
+
+/// Confirms enrolment with a first code
+///
+/// Turns 2FA on and returns recovery codes, which are shown this once. When enrolling
+/// from a setup challenge the user is signed in as well.
+#[utoipa::path(
+    post,
+    path = "/2fa/confirm",
+    request_body = TotpCodePayload,
+    tag = AUTH_TAG,
+    responses(
+        (status = 200, description = "Two-factor authentication enabled", body = TwoFactorEnabled),
+        (status = 401, description = "Wrong code"),
+        (status = 412, description = "No setup pending")
+    )
+)]
+pub async fn confirm_two_factor(
+    State(state): State<AppState>,
+    enrolling: Enrolling,
+    metadata: RequestMetadata,
+    Json(payload): Json<TotpCodePayload>,
+) -> Result<Json<TwoFactorEnabled>, APIError> {
+    let user = user::read_by_id(&state.db, &enrolling.user_id).await?;
+    let Some(secret) = two_factor::read_secret(&state.db, &user.id)
+        .await?
+        .filter(|secret| !secret.enabled)
+    else {
+        return Err(DbError::PreconditionFailed("No two-factor setup is pending".into()).into());
+    };
+
+    let Some(step) = verify_totp(&secret.secret, &payload.code)? else {
+        audit_failure(&state, user, "ENABLE", &metadata).await?;
+        return Err(APIError::AuthError(AuthError::AuthenticationFailed));
+    };
+
+    let recovery_codes = generate_recovery_codes();
+    two_factor::enable(&state.db, &user.id, &hash_recovery_codes(&recovery_codes)).await?;
+    two_factor::use_step(&state.db, &user.id, step).await?;
+
+    let enable_audit =
+        AuditBuilder::two_factor_operation("ENABLE", &user.id, &user.role, user.email.clone())
+            .with_metadata(&metadata)
+            .build();
+    audit::create(&state.db, &enable_audit).await?;
+
+    let tokens = if enrolling.from_challenge {
+        Some(start_session(&state, &user.id, &user.role, &metadata).await?)
+    } else {
+        None
+    };
+
+    Ok(Json(TwoFactorEnabled {
+        recovery_codes,
+        tokens,
+    }))
+}
+
+/// Finishes signing in with a second factor
+///
+/// Exchanges the challenge from `/signin` and an authenticator or recovery code for
+/// tokens. Each code works once.
+#[utoipa::path(
+    post,
+    path = "/2fa/verify",
+    request_body = TwoFactorVerifyPayload,
+    tag = AUTH_TAG,
+    responses(
+        (status = 200, description = "Authentication successful", body = TokenPair),
+        (status = 401, description = "Invalid challenge or wrong code"),
+        (status = 429, description = "Too many wrong codes, see Retry-After"),
+        (status = 429, description = "Too many requests, see Retry-After")
+    )
+)]
+pub async fn verify_two_factor(
+    State(mut state): State<AppState>,
+    metadata: RequestMetadata,
+    Json(payload): Json<TwoFactorVerifyPayload>,
+) -> Result<Json<TokenPair>, APIError> {
+    let user_id = decode_challenge_token(&payload.challenge_token, ChallengePurpose::Verify)?;
+    let user = user::read_by_id(&state.db, &user_id).await?;
+
+    require_code(&mut state, &user, &payload.code, "VERIFY", &metadata).await?;
+
+    let tokens = start_session(&state, &user.id, &user.role, &metadata).await?;
+    Ok(Json(tokens))
+}
+
+/// Replaces the recovery codes
+///
+/// Every earlier code stops working. Needs a current code.
+#[utoipa::path(
+    post,
+    path = "/2fa/recovery_codes",
+    request_body = TotpCodePayload,
+    tag = AUTH_TAG,
+    responses(
+        (status = 200, description = "New recovery codes", body = RecoveryCodes),
+        (status = 401, description = "Wrong code"),
+        (status = 429, description = "Too many wrong codes, see Retry-After")
+    )
+)]
+pub async fn regenerate_recovery_codes(
+    State(mut state): State<AppState>,
+    claims: Claims,
+    metadata: RequestMetadata,
+    Json(payload): Json<TotpCodePayload>,
+) -> Result<Json<RecoveryCodes>, APIError> {
+    let user = user::read_by_id(&state.db, &claims.sub).await?;
+    require_code(
+        &mut state,
+        &user,
+        &payload.code,
+        "REGENERATE_RECOVERY_CODES",
+        &metadata,
+    )
+    .await?;
+
+    let codes = generate_recovery_codes();
+    two_factor::replace_recovery_codes(&state.db, &user.id, &hash_recovery_codes(&codes)).await?;
+
+    let regenerate_audit = AuditBuilder::two_factor_operation(
+        "REGENERATE_RECOVERY_CODES",
+        &user.id,
+        &user.role,
+        user.email,
+    )
+    .with_metadata(&metadata)
+    .build();
+    audit::create(&state.db, &regenerate_audit).await?;
+
+    Ok(Json(RecoveryCodes { codes }))
+}
+
+/// Turns 2FA off
+///
+/// Needs a current code. Roles that require 2FA can't turn it off.
+#[utoipa::path(
+    post,
+    path = "/2fa/disable",
+    request_body = TotpCodePayload,
+    tag = AUTH_TAG,
+    responses(
+        (status = 204, description = "Two-factor authentication disabled"),
+        (status = 401, description = "Wrong code"),
+        (status = 412, description = "Required for the user's role"),
+        (status = 429, description = "Too many wrong codes, see Retry-After")
+    )
+)]
+pub async fn disable_two_factor(
+    State(mut state): State<AppState>,
+    claims: Claims,
+    metadata: RequestMetadata,
+    Json(payload): Json<TotpCodePayload>,
+) -> Result<StatusCode, APIError> {
+    if two_factor_required(&claims.role) {
+        return Err(APIError::PreconditionFailed(
+            "Two-factor authentication is required for your role".into(),
+        ));
+    }
+
+    let user = user::read_by_id(&state.db, &claims.sub).await?;
+    require_code(&mut state, &user, &payload.code, "DISABLE", &metadata).await?;
+
+    two_factor::disable(&state.db, &user.id).await?;
+
+    let disable_audit =
+        AuditBuilder::two_factor_operation("DISABLE", &user.id, &user.role, user.email)
+            .with_metadata(&metadata)
+            .build();
+    audit::create(&state.db, &disable_audit).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -15,7 +15,8 @@ use crate::{AppError, AppState, Claims, api::AUTH_TAG, services::generate_secure
     responses(
         (status = 202, description = "Confirmation link resent"),
         (status = 401, description = "Unauthorized"),
-        (status = 400, description = "Invalid token")
+        (status = 400, description = "Invalid token"),
+        (status = 429, description = "Too many requests, see Retry-After")
     )
 )]
 pub async fn resend_verification(
@@ -61,7 +62,8 @@ pub async fn resend_verification(
     responses(
         (status = 202, description = "Confirmation Successful"),
         (status = 401, description = "Unauthorized"),
-        (status = 400, description = "Invalid token")
+        (status = 400, description = "Invalid token"),
+        (status = 429, description = "Too many requests, see Retry-After")
     )
 )]
 pub async fn confirm_email(
@@ -21,6 +21,13 @@ pub async fn fetch_preferences(
         push_notifications: prefs.push_notifications,
         theme: prefs.theme,
         language: prefs.language,
+        scheduler: prefs.scheduler,
+        learning_steps: prefs.learning_steps,
+        relearning_steps: prefs.relearning_steps,
+        new_cards_per_day: prefs.new_cards_per_day,
+        reviews_per_day: prefs.reviews_per_day,
+        daily_review_goal: prefs.daily_review_goal,
+        daily_task_goal: prefs.daily_task_goal,
     };
 
     Ok(Json(response))
@@ -8,12 +8,13 @@ use axum::{
     http::StatusCode,
 };
 
-use ogonek_db::core::account::user;
+use ogonek_db::core::account::{invite, user};
 use ogonek_types::{InviterQuery, User, users::UserUpdate};
 
 /// Retrieves teacher information from an invite token
 ///
-/// Decodes an invite token and returns the teacher's details who created the invite.
+/// Decodes an invite token and returns the teacher's details who created the invite,
+/// as long as the invite can still be used.
 #[utoipa::path(
     get,
     path = "/inviter",
@@ -24,7 +25,8 @@ use ogonek_types::{InviterQuery, User, users::UserUpdate};
     responses(
         (status = 200, description = "Inviter details retrieved", body = User),
         (status = 401, description = "Unauthorized"),
-        (status = 400, description = "Invalid invite token")
+        (status = 400, description = "Invalid invite token"),
+        (status = 412, description = "Invite revoked, expired or used up")
     )
 )]
 
@@ -32,8 +34,9 @@ pub async fn fetch_inviter(
     State(state): State<AppState>,
     query: Query<InviterQuery>,
 ) -> Result<Json<User>, APIError> {
-    let teacher_id = decode_invite_token(query.invite.clone()).await?;
-    let inviter = user::read_by_id(&state.db, &teacher_id).await?;
+    let invite_id = decode_invite_token(query.invite.clone()).await?;
+    let invite = invite::read_valid(&state.db, &invite_id).await?;
+    let inviter = user::read_by_id(&state.db, &invite.teacher_id).await?;
 
     Ok(Json(inviter))
 }
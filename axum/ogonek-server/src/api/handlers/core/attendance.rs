@@ -0,0 +1,144 @@
+use crate::{
+    AppState, Claims,
+    api::{CALENDAR_TAG, error::APIError},
+};
+use axum::{
+    extract::{Json, Path, Query, State},
+    http::StatusCode,
+};
+use ogonek_db::core::calendar::attendance;
+use ogonek_types::{
+    AttendanceReportQuery, EventAttendance, EventAttendanceUpsert, MonthlyAttendance,
+    StudentAttendanceReport,
+};
+use validator::Validate;
+
+/// Records attendance for an event or occurrence
+///
+/// Replaces the outcome already recorded for the student. The lesson, if any, must be one
+/// of the user's.
+#[utoipa::path(
+    put,
+    path = "/events/{id}/attendance",
+    tag = CALENDAR_TAG,
+    params(
+        ("id" = String, Path, description = "Event ID, virtual occurrence IDs included")
+    ),
+    request_body = EventAttendanceUpsert,
+    responses(
+        (status = 200, description = "Attendance recorded successfully", body = String),
+        (status = 404, description = "Event, attendee or lesson not found"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn upsert_attendance(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    claims: Claims,
+    Json(payload): Json<EventAttendanceUpsert>,
+) -> Result<Json<String>, APIError> {
+    payload.validate()?;
+    let attendance_id = attendance::upsert(&state.db, &claims.sub, &id, &payload).await?;
+    Ok(Json(attendance_id))
+}
+
+/// Lists the attendance recorded for an event or occurrence
+#[utoipa::path(
+    get,
+    path = "/events/{id}/attendance",
+    tag = CALENDAR_TAG,
+    params(
+        ("id" = String, Path, description = "Event ID, virtual occurrence IDs included")
+    ),
+    responses(
+        (status = 200, description = "Attendance retrieved successfully", body = Vec<EventAttendance>),
+        (status = 404, description = "Event not found"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn list_attendance(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    claims: Claims,
+) -> Result<Json<Vec<EventAttendance>>, APIError> {
+    let records = attendance::read_by_event(&state.db, &claims.sub, &id).await?;
+    Ok(Json(records))
+}
+
+/// Deletes an attendance record
+#[utoipa::path(
+    delete,
+    path = "/attendance/{id}",
+    tag = CALENDAR_TAG,
+    params(
+        ("id" = String, Path, description = "Attendance ID")
+    ),
+    responses(
+        (status = 204, description = "Attendance deleted successfully"),
+        (status = 404, description = "Attendance not found"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn delete_attendance(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    claims: Claims,
+) -> Result<StatusCode, APIError> {
+    attendance::delete(&state.db, &claims.sub, &id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Reports a student's attendance
+///
+/// Lists the records of the user's calendar for the student, newest first, with totals.
+#[utoipa::path(
+    get,
+    path = "/attendance/students/{id}",
+    tag = CALENDAR_TAG,
+    params(
+        ("id" = String, Path, description = "Student ID"),
+        ("from" = Option<String>, Query),
+        ("to" = Option<String>, Query)
+    ),
+    responses(
+        (status = 200, description = "Report retrieved successfully", body = StudentAttendanceReport),
+        (status = 404, description = "Student not found"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn fetch_student_attendance(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<AttendanceReportQuery>,
+    claims: Claims,
+) -> Result<Json<StudentAttendanceReport>, APIError> {
+    let report =
+        attendance::read_student_report(&state.db, &claims.sub, &id, query.from, query.to).await?;
+    Ok(Json(report))
+}
+
+/// Reports attendance per month and student
+///
+/// Months follow the timezone of the user's calendar, the latest first.
+#[utoipa::path(
+    get,
+    path = "/attendance/monthly",
+    tag = CALENDAR_TAG,
+    params(
+        ("from" = Option<String>, Query),
+        ("to" = Option<String>, Query)
+    ),
+    responses(
+        (status = 200, description = "Report retrieved successfully", body = Vec<MonthlyAttendance>),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn fetch_monthly_attendance(
+    State(state): State<AppState>,
+    Query(query): Query<AttendanceReportQuery>,
+    claims: Claims,
+) -> Result<Json<Vec<MonthlyAttendance>>, APIError> {
+    let report =
+        attendance::read_monthly_report(&state.db, &claims.sub, query.from, query.to).await?;
+    Ok(Json(report))
+}
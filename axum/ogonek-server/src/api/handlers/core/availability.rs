@@ -0,0 +1,232 @@
+use crate::{
+    AppState, Claims,
+    api::{CALENDAR_TAG, error::APIError},
+};
+use axum::{
+    extract::{Json, Path, Query, State},
+    http::StatusCode,
+};
+use chrono::Duration;
+use ogonek_db::core::calendar::availability;
+use ogonek_types::{
+    Availability, AvailabilityExceptionCreate, AvailabilityRuleCreate, AvailabilitySettings,
+    BookableSlot, BookingCreate, SlotQuery,
+};
+use validator::Validate;
+
+/// The longest range free slots are listed for
+const MAX_SLOT_RANGE: Duration = Duration::days(62);
+
+/// Retrieves the user's availability
+///
+/// Returns the lesson settings, the weekly windows and the upcoming exceptions.
+#[utoipa::path(
+    get,
+    path = "/availability",
+    tag = CALENDAR_TAG,
+    responses(
+        (status = 200, description = "Availability retrieved successfully", body = Availability),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn fetch_availability(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<Availability>, APIError> {
+    let availability = availability::read(&state.db, &claims.sub).await?;
+    Ok(Json(availability))
+}
+
+/// Sets the lesson length and the buffer kept around events
+#[utoipa::path(
+    put,
+    path = "/availability/settings",
+    tag = CALENDAR_TAG,
+    request_body = AvailabilitySettings,
+    responses(
+        (status = 204, description = "Settings updated successfully"),
+        (status = 400, description = "Invalid lesson length or buffer"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn update_availability_settings(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<AvailabilitySettings>,
+) -> Result<StatusCode, APIError> {
+    payload.validate()?;
+    availability::update_settings(&state.db, &claims.sub, &payload).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Adds a weekly availability window
+///
+/// Times are in the wall clock of the user's calendar.
+#[utoipa::path(
+    post,
+    path = "/availability/rules",
+    tag = CALENDAR_TAG,
+    request_body = AvailabilityRuleCreate,
+    responses(
+        (status = 201, description = "Rule created successfully", body = String),
+        (status = 400, description = "Invalid weekday or time range"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn create_availability_rule(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<AvailabilityRuleCreate>,
+) -> Result<(StatusCode, Json<String>), APIError> {
+    payload.validate()?;
+    if payload.end_time <= payload.start_time {
+        return Err(APIError::BadRequest(
+            "The end time must be after the start time".into(),
+        ));
+    }
+
+    let rule_id = availability::create_rule(&state.db, &claims.sub, &payload).await?;
+    Ok((StatusCode::CREATED, Json(rule_id)))
+}
+
+/// Deletes a weekly availability window
+#[utoipa::path(
+    delete,
+    path = "/availability/rules/{id}",
+    tag = CALENDAR_TAG,
+    params(
+        ("id" = String, Path, description = "Rule ID")
+    ),
+    responses(
+        (status = 204, description = "Rule deleted successfully"),
+        (status = 404, description = "Rule not found"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn delete_availability_rule(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    claims: Claims,
+) -> Result<StatusCode, APIError> {
+    availability::delete_rule(&state.db, &claims.sub, &id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Adds an availability exception
+///
+/// Blocks the time, or opens an extra window when `available` is set.
+#[utoipa::path(
+    post,
+    path = "/availability/exceptions",
+    tag = CALENDAR_TAG,
+    request_body = AvailabilityExceptionCreate,
+    responses(
+        (status = 201, description = "Exception created successfully", body = String),
+        (status = 400, description = "Invalid time range"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn create_availability_exception(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<AvailabilityExceptionCreate>,
+) -> Result<(StatusCode, Json<String>), APIError> {
+    payload.validate()?;
+    if payload.ends_at <= payload.starts_at {
+        return Err(APIError::BadRequest(
+            "The end must be after the start".into(),
+        ));
+    }
+
+    let exception_id = availability::create_exception(&state.db, &claims.sub, &payload).await?;
+    Ok((StatusCode::CREATED, Json(exception_id)))
+}
+
+/// Deletes an availability exception
+#[utoipa::path(
+    delete,
+    path = "/availability/exceptions/{id}",
+    tag = CALENDAR_TAG,
+    params(
+        ("id" = String, Path, description = "Exception ID")
+    ),
+    responses(
+        (status = 204, description = "Exception deleted successfully"),
+        (status = 404, description = "Exception not found"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn delete_availability_exception(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    claims: Claims,
+) -> Result<StatusCode, APIError> {
+    availability::delete_exception(&state.db, &claims.sub, &id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Lists a teacher's bookable slots
+///
+/// Only available to the teacher's active students. Slots leave out the teacher's
+/// opaque events, recurring ones included, and the buffer around them.
+#[utoipa::path(
+    get,
+    path = "/availability/{teacher_id}/slots",
+    tag = CALENDAR_TAG,
+    params(
+        ("teacher_id" = String, Path, description = "Teacher ID"),
+        ("start" = String, Query, description = "Start of the range"),
+        ("end" = String, Query, description = "End of the range, at most 62 days after the start")
+    ),
+    responses(
+        (status = 200, description = "Slots retrieved successfully", body = Vec<BookableSlot>),
+        (status = 400, description = "Invalid range"),
+        (status = 404, description = "Teacher not found"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn list_slots(
+    State(state): State<AppState>,
+    Path(teacher_id): Path<String>,
+    Query(query): Query<SlotQuery>,
+    claims: Claims,
+) -> Result<Json<Vec<BookableSlot>>, APIError> {
+    if query.end <= query.start || query.end - query.start > MAX_SLOT_RANGE {
+        return Err(APIError::BadRequest(
+            "The range must end after it starts and span at most 62 days".into(),
+        ));
+    }
+
+    let slots =
+        availability::read_slots(&state.db, &teacher_id, &claims.sub, query.start, query.end)
+            .await?;
+    Ok(Json(slots))
+}
+
+/// Books a slot with a teacher
+///
+/// Creates a tentative event on the teacher's calendar with the student attending.
+#[utoipa::path(
+    post,
+    path = "/availability/{teacher_id}/bookings",
+    tag = CALENDAR_TAG,
+    params(
+        ("teacher_id" = String, Path, description = "Teacher ID")
+    ),
+    request_body = BookingCreate,
+    responses(
+        (status = 201, description = "Slot booked successfully", body = String),
+        (status = 404, description = "Teacher not found"),
+        (status = 409, description = "The slot is not available"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn book_slot(
+    State(state): State<AppState>,
+    Path(teacher_id): Path<String>,
+    claims: Claims,
+    Json(payload): Json<BookingCreate>,
+) -> Result<(StatusCode, Json<String>), APIError> {
+    let event_id = availability::book(&state.db, &teacher_id, &claims.sub, payload.start).await?;
+    Ok((StatusCode::CREATED, Json(event_id)))
+}
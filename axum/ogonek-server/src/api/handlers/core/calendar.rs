@@ -1,17 +1,29 @@
 use crate::{
     AppState, Claims,
     api::{CALENDAR_TAG, error::APIError},
+    services::calendar::{parse_ics, write_ics},
 };
 use axum::{
-    extract::{Json, Path, State},
-    http::StatusCode,
+    extract::{Json, Multipart, Path, State},
+    http::{StatusCode, header},
+    response::IntoResponse,
 };
-use ogonek_db::core::calendar::cal::{delete, get_or_create, update};
-use ogonek_types::{CalendarFull, CalendarUpdate};
+use chrono_tz::Tz;
+use ogonek_db::core::calendar::{
+    cal::{
+        create, delete, get_or_create, read_all, read_by_feed_token, read_feed_token,
+        read_timezone, revoke_feed_token, set_default, update,
+    },
+    event, event_attendee,
+};
+use ogonek_types::{
+    CalendarCreate, CalendarFeed, CalendarFull, CalendarSmall, CalendarUpdate, EventImportSummary,
+};
+use validator::Validate;
 
-/// Retrieves or creates the user's calendar
+/// Retrieves or creates the user's default calendar
 ///
-/// Returns the user's calendar, creating it if it doesn't exist.
+/// Returns the calendar events land in by default, creating it if it doesn't exist.
 #[utoipa::path(
     get,
     path = "",
@@ -30,9 +42,78 @@ pub async fn fetch_calendar(
     Ok(Json(calendar))
 }
 
+/// Lists the user's calendars
+///
+/// Calendars the user owns come first, followed by those shared with them.
+#[utoipa::path(
+    get,
+    path = "/all",
+    tag = CALENDAR_TAG,
+    responses(
+        (status = 200, description = "Calendars retrieved successfully", body = Vec<CalendarSmall>),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn list_calendars(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<Vec<CalendarSmall>>, APIError> {
+    let calendars = read_all(&state.db, &claims.sub).await?;
+    Ok(Json(calendars))
+}
+
+/// Creates another calendar for the user
+#[utoipa::path(
+    post,
+    path = "",
+    tag = CALENDAR_TAG,
+    request_body = CalendarCreate,
+    responses(
+        (status = 201, description = "Calendar created successfully", body = String),
+        (status = 400, description = "Invalid name or colour"),
+        (status = 404, description = "Timezone not found"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn create_calendar(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<CalendarCreate>,
+) -> Result<(StatusCode, Json<String>), APIError> {
+    payload.validate()?;
+    let calendar_id = create(&state.db, &claims.sub, &payload).await?;
+    Ok((StatusCode::CREATED, Json(calendar_id)))
+}
+
+/// Makes a calendar the user owns their default one
+///
+/// New events and imports land in the default calendar unless another one is picked.
+#[utoipa::path(
+    post,
+    path = "/{id}/default",
+    tag = CALENDAR_TAG,
+    params(
+        ("id" = String, Path, description = "Calendar ID")
+    ),
+    responses(
+        (status = 204, description = "Default calendar changed"),
+        (status = 404, description = "Calendar not found"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn set_default_calendar(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    claims: Claims,
+) -> Result<StatusCode, APIError> {
+    set_default(&state.db, &id, &claims.sub).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
 /// Deletes a specific calendar owned by the user
 ///
-/// Removes the calendar if the user has ownership permissions.
+/// Removes the calendar if the user has ownership permissions. The default calendar
+/// can't be deleted.
 #[utoipa::path(
     delete,
     path = "/{id}",
@@ -43,6 +124,7 @@ pub async fn fetch_calendar(
     responses(
         (status = 204, description = "Calendar deleted successfully"),
         (status = 404, description = "Calendar not found"),
+        (status = 412, description = "The calendar is the default one"),
         (status = 401, description = "Unauthorized")
     )
 )]
@@ -81,3 +163,128 @@ pub async fn update_calendar(
     update(&state.db, &id, &claims.sub, &payload).await?;
     Ok(StatusCode::NO_CONTENT)
 }
+
+/// Returns the iCalendar subscription links for the user's calendar
+///
+/// Creates the feed token on first use. Anyone holding the link can read the calendar.
+#[utoipa::path(
+    get,
+    path = "/feed",
+    tag = CALENDAR_TAG,
+    responses(
+        (status = 200, description = "Feed links", body = CalendarFeed),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn fetch_calendar_feed(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<CalendarFeed>, APIError> {
+    let token = read_feed_token(&state.db, &claims.sub).await?;
+
+    let url = format!("{}/public/calendar/{token}.ics", state.api_url);
+    let webcal_url = match url.split_once("://") {
+        Some((_, rest)) => format!("webcal://{rest}"),
+        None => url.clone(),
+    };
+
+    Ok(Json(CalendarFeed { url, webcal_url }))
+}
+
+/// Revokes the calendar's feed link
+///
+/// Existing subscriptions stop updating, the next request for the feed creates a new link.
+#[utoipa::path(
+    delete,
+    path = "/feed",
+    tag = CALENDAR_TAG,
+    responses(
+        (status = 204, description = "Feed revoked"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn revoke_calendar_feed(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<StatusCode, APIError> {
+    revoke_feed_token(&state.db, &claims.sub).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Serves the calendar as an iCalendar feed (public endpoint)
+#[utoipa::path(
+    get,
+    path = "/calendar/{token}",
+    tag = CALENDAR_TAG,
+    params(
+        ("token" = String, Path, description = "Feed token, optionally followed by .ics")
+    ),
+    responses(
+        (status = 200, description = "The calendar in iCalendar format", content_type = "text/calendar"),
+        (status = 404, description = "Calendar feed not found")
+    )
+)]
+pub async fn fetch_calendar_feed_public(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> Result<impl IntoResponse, APIError> {
+    let token = token.trim_end_matches(".ics");
+    let calendar = read_by_feed_token(&state.db, token).await?;
+
+    let events = event::read_all_for_feed(&state.db, &calendar).await?;
+    let event_ids: Vec<String> = events.iter().map(|e| e.id.clone()).collect();
+    let attendees = event_attendee::find_by_event_ids(&state.db, &event_ids).await?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/calendar; charset=utf-8"),
+            (
+                header::CONTENT_DISPOSITION,
+                "inline; filename=\"ogonek.ics\"",
+            ),
+        ],
+        write_ics(&calendar, &events, &attendees),
+    ))
+}
+
+/// Imports events from an iCalendar file
+///
+/// Expects the file in the `file` field. Events already imported are updated by UID,
+/// RECURRENCE-ID overrides become exceptions of their recurring event.
+#[utoipa::path(
+    post,
+    path = "/import",
+    tag = CALENDAR_TAG,
+    request_body(
+        content_type = "multipart/form-data",
+        description = "An .ics file in the `file` field"
+    ),
+    responses(
+        (status = 200, description = "Events imported", body = EventImportSummary),
+        (status = 400, description = "Not an iCalendar file"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn import_calendar(
+    State(state): State<AppState>,
+    claims: Claims,
+    mut multipart: Multipart,
+) -> Result<Json<EventImportSummary>, APIError> {
+    let mut upload = None;
+    while let Some(field) = multipart.next_field().await? {
+        if field.name() == Some("file") {
+            upload = Some(field.bytes().await?);
+        }
+    }
+    let data = upload.ok_or_else(|| APIError::BadRequest("No file provided".into()))?;
+    let input = String::from_utf8(data.to_vec())
+        .map_err(|_| APIError::BadRequest("The file is not valid UTF-8".into()))?;
+
+    let timezone = read_timezone(&state.db, &claims.sub).await?;
+    let (events, skipped) = parse_ics(&input, timezone.parse().unwrap_or(Tz::UTC))?;
+
+    let mut summary = event::import(&state.db, &claims.sub, &events).await?;
+    summary.skipped = skipped;
+
+    Ok(Json(summary))
+}
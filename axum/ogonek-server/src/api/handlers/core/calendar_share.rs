@@ -0,0 +1,87 @@
+use crate::{
+    AppState, Claims,
+    api::{CALENDAR_TAG, error::APIError},
+};
+use axum::{
+    extract::{Json, Path, State},
+    http::StatusCode,
+};
+use ogonek_db::core::calendar::calendar_share::{delete, read_all, upsert};
+use ogonek_types::{CalendarShare, CalendarShareUpsert};
+
+/// Lists the users a calendar is shared with
+#[utoipa::path(
+    get,
+    path = "/{id}/shares",
+    tag = CALENDAR_TAG,
+    params(
+        ("id" = String, Path, description = "Calendar ID")
+    ),
+    responses(
+        (status = 200, description = "Shares retrieved successfully", body = Vec<CalendarShare>),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn list_calendar_shares(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    claims: Claims,
+) -> Result<Json<Vec<CalendarShare>>, APIError> {
+    let shares = read_all(&state.db, &id, &claims.sub).await?;
+    Ok(Json(shares))
+}
+
+/// Shares a calendar with another user
+///
+/// Sharing again with the same user changes their access. Read-write access lets them
+/// add events to the calendar.
+#[utoipa::path(
+    put,
+    path = "/{id}/shares",
+    tag = CALENDAR_TAG,
+    params(
+        ("id" = String, Path, description = "Calendar ID")
+    ),
+    request_body = CalendarShareUpsert,
+    responses(
+        (status = 204, description = "Calendar shared successfully"),
+        (status = 404, description = "Calendar or user not found"),
+        (status = 412, description = "The user owns the calendar"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn upsert_calendar_share(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    claims: Claims,
+    Json(payload): Json<CalendarShareUpsert>,
+) -> Result<StatusCode, APIError> {
+    upsert(&state.db, &id, &claims.sub, &payload).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Stops sharing a calendar with a user
+///
+/// The owner can remove anyone, other users can only remove themselves.
+#[utoipa::path(
+    delete,
+    path = "/{id}/shares/{user_id}",
+    tag = CALENDAR_TAG,
+    params(
+        ("id" = String, Path, description = "Calendar ID"),
+        ("user_id" = String, Path, description = "The user the calendar is shared with")
+    ),
+    responses(
+        (status = 204, description = "Share removed successfully"),
+        (status = 404, description = "Share not found"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn delete_calendar_share(
+    State(state): State<AppState>,
+    Path((id, user_id)): Path<(String, String)>,
+    claims: Claims,
+) -> Result<StatusCode, APIError> {
+    delete(&state.db, &id, &claims.sub, &user_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -4,11 +4,16 @@ use axum::{
 };
 use ogonek_db::{core::flashcards, tracking::mark_as_seen};
 use ogonek_types::{
-    DeckPaginationParams, DeckPublic, DeckSmall, DeckWithCards, ModelType, PaginatedDecks,
+    CardSearchParams, CardSearchResult, DeckExportFormat, DeckExportQuery, DeckPaginationParams,
+    DeckPublic, DeckSmall, DeckWithCards, ModelType, PaginatedCards, PaginatedDecks,
     PaginatedResponse, SortField, SortOrder, Visibility,
 };
 
-use crate::{AppError, AppState, Claims, api::DECK_TAG};
+use crate::{
+    AppError, AppState, Claims,
+    api::DECK_TAG,
+    services::{deck_io, parse_card_filter, sanitize_filename},
+};
 
 /// Retrieves a single deck with all its cards
 ///
@@ -31,14 +36,68 @@ pub async fn fetch_deck(
     claims: Claims,
     Path(id): Path<String>,
 ) -> Result<Json<DeckWithCards>, AppError> {
-    let deck_with_cards =
+    let mut deck_with_cards =
         flashcards::deck::read_deck_with_cards(&state.db, &id, &claims.sub).await?;
+    for card in &mut deck_with_cards.cards {
+        deck_io::presign_media(&state.s3, &mut card.media_url).await?;
+    }
 
     mark_as_seen(&state.db, &claims.sub, &id, ModelType::Deck).await?;
 
     Ok(Json(deck_with_cards))
 }
 
+/// Exports a deck as a CSV file or an Anki package
+///
+/// Uploads the export to S3 and returns a presigned download URL.
+#[utoipa::path(
+    get,
+    tag = DECK_TAG,
+    path = "/{id}/export",
+    params(
+        ("id" = String, Path, description = "Deck ID"),
+        ("format" = Option<DeckExportFormat>, Query, description = "Export format, csv by default")
+    ),
+    responses(
+        (status = 200, description = "Export ready for download"),
+        (status = 404, description = "Deck not found"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn export_deck(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<String>,
+    Query(query): Query<DeckExportQuery>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let deck = flashcards::deck::read_deck_with_cards(&state.db, &id, &claims.sub).await?;
+
+    // CSV keeps the stored media keys, packages carry the files themselves
+    let (data, extension, content_type) = match query.format {
+        DeckExportFormat::Csv => (deck_io::write_csv(&deck)?, "csv", "text/csv"),
+        DeckExportFormat::Apkg => {
+            let media = deck_io::download_media(&state.s3, &deck).await?;
+            (
+                deck_io::write_apkg(&deck, &media).await?,
+                "apkg",
+                "application/octet-stream",
+            )
+        }
+    };
+
+    // Decks change, so unlike PDFs the export is rebuilt every time
+    let s3_key = format!("exports/{}/decks/{}.{}", claims.sub, id, extension);
+    state
+        .s3
+        .upload_object(&s3_key, data, Some(content_type))
+        .await?;
+
+    let filename = format!("{}.{}", sanitize_filename(&deck.deck.title), extension);
+    let url = state.s3.get_presigned_url(s3_key, filename).await?;
+
+    Ok(Json(serde_json::json!({ "url": url })))
+}
+
 /// Retrieves a paginated list of decks accessible to the user
 ///
 /// Returns decks the user owns or has been assigned with pagination and filtering support.
@@ -96,3 +155,89 @@ pub async fn list_decks_public(
 
     Ok(Json(decks))
 }
+
+/// Searches cards in every deck the user can study
+///
+/// The query accepts free text along with `tag:`, `deck:`, `failed:` and `is:` terms.
+#[utoipa::path(
+    get,
+    tag = DECK_TAG,
+    path = "/search",
+    params(
+        ("page" = Option<u32>, Query, description = "Page number"),
+        ("per_page" = Option<u32>, Query, description = "Items per page"),
+        ("q" = Option<String>, Query, description = "Filter expression, e.g. `tag:verbs failed:7 perro`")
+    ),
+    responses(
+        (status = 200, description = "Matching cards", body = PaginatedCards),
+        (status = 400, description = "Invalid filter"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn search_cards(
+    State(state): State<AppState>,
+    Query(params): Query<CardSearchParams>,
+    claims: Claims,
+) -> Result<Json<PaginatedResponse<CardSearchResult>>, AppError> {
+    search(&state, &claims.sub, &params, None).await
+}
+
+/// Searches cards within a single deck
+#[utoipa::path(
+    get,
+    tag = DECK_TAG,
+    path = "/{id}/search",
+    params(
+        ("id" = String, Path, description = "Deck ID"),
+        ("page" = Option<u32>, Query, description = "Page number"),
+        ("per_page" = Option<u32>, Query, description = "Items per page"),
+        ("q" = Option<String>, Query, description = "Filter expression, e.g. `tag:verbs failed:7 perro`")
+    ),
+    responses(
+        (status = 200, description = "Matching cards", body = PaginatedCards),
+        (status = 400, description = "Invalid filter"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn search_deck_cards(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<CardSearchParams>,
+    claims: Claims,
+) -> Result<Json<PaginatedResponse<CardSearchResult>>, AppError> {
+    search(&state, &claims.sub, &params, Some(id)).await
+}
+
+async fn search(
+    state: &AppState,
+    user_id: &str,
+    params: &CardSearchParams,
+    deck_id: Option<String>,
+) -> Result<Json<PaginatedResponse<CardSearchResult>>, AppError> {
+    let mut filter = parse_card_filter(params.q.as_deref().unwrap_or_default())?;
+    if deck_id.is_some() {
+        filter.deck_id = deck_id;
+    }
+
+    let (mut cards, count) = flashcards::deck::search_cards(
+        &state.db,
+        user_id,
+        &filter,
+        params.limit(),
+        params.offset(),
+    )
+    .await?;
+
+    for card in &mut cards {
+        deck_io::presign_media(&state.s3, &mut card.media_url).await?;
+    }
+
+    let total_pages = (count as f64 / params.limit() as f64).ceil() as i64;
+    Ok(Json(PaginatedResponse {
+        data: cards,
+        page: params.page(),
+        total_pages,
+        count,
+        per_page: params.limit(),
+    }))
+}
@@ -10,7 +10,7 @@ use ogonek_notifications::NotificationType;
 use ogonek_types::{ActionType, DeckWithCardsUpdate, ModelType};
 use reqwest::StatusCode;
 
-use crate::{AppError as APIError, AppState, Claims, api::DECK_TAG};
+use crate::{AppError as APIError, AppState, Claims, api::DECK_TAG, services::deck_io};
 
 /// Updates a deck and its cards with assignment tracking
 ///
@@ -33,8 +33,12 @@ pub async fn update_deck(
     State(state): State<AppState>,
     claims: Claims,
     Path(id): Path<String>,
-    Json(payload): Json<DeckWithCardsUpdate>,
+    Json(mut payload): Json<DeckWithCardsUpdate>,
 ) -> Result<StatusCode, APIError> {
+    for card in &mut payload.cards {
+        card.media_url = deck_io::unsign_media(card.media_url.take());
+    }
+
     let current_assignee = deck::read_assignee(&state.db, &id, &claims.sub).await?;
     let new_assignee = payload.deck.assignee.clone();
 
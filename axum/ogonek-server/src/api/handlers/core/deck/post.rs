@@ -1,14 +1,23 @@
+use std::{collections::HashMap, sync::Arc};
+
 use axum::{
     Json,
-    extract::{Path, State},
+    extract::{Multipart, Path, State},
 };
 use ogonek_db::{
     core::flashcards::{self},
     tracking::log_activity,
 };
-use ogonek_types::{ActionType, ModelType};
+use ogonek_types::{ActionType, CardUpsert, DeckCreate, ModelType};
 
-use crate::{AppError as APIError, AppState, Claims, api::DECK_TAG};
+use crate::{
+    AppError as APIError, AppState, Claims,
+    api::DECK_TAG,
+    services::{
+        deck_io::{self, ImportedMedia},
+        infer_card_type,
+    },
+};
 
 /// Creates a new flashcard deck with default settings
 ///
@@ -77,3 +86,115 @@ pub async fn duplicate_deck(
 
     Ok(Json(new_id))
 }
+
+/// Imports a deck from an Anki package or a CSV/TSV file
+///
+/// Expects the file in the `file` field. Media bundled with an Anki package is uploaded to S3
+/// and the cards keep its key.
+#[utoipa::path(
+    post,
+    tag = DECK_TAG,
+    path = "/import",
+    request_body(
+        content_type = "multipart/form-data",
+        description = "A .apkg, .csv, .tsv or .txt file in the `file` field"
+    ),
+    responses(
+        (status = 200, description = "Deck imported successfully", body = String),
+        (status = 400, description = "Unsupported or malformed file"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn import_deck(
+    State(state): State<AppState>,
+    claims: Claims,
+    mut multipart: Multipart,
+) -> Result<Json<String>, APIError> {
+    let mut upload = None;
+    while let Some(field) = multipart.next_field().await? {
+        if field.name() == Some("file") {
+            let filename = field.file_name().unwrap_or_default().to_string();
+            upload = Some((filename, field.bytes().await?));
+        }
+    }
+    let (filename, data) = upload.ok_or_else(|| APIError::BadRequest("No file provided".into()))?;
+
+    let imported = deck_io::read_deck_file(&filename, data.to_vec()).await?;
+
+    // Packages often reuse one file across several notes
+    let mut keys: HashMap<String, String> = HashMap::new();
+    let mut media = Vec::new();
+    let mut cards = Vec::with_capacity(imported.cards.len());
+    for card in imported.cards {
+        let media_url = match card.media {
+            Some(ImportedMedia::Url(url)) => Some(url),
+            Some(ImportedMedia::File { name, data }) => match keys.get(&name) {
+                Some(key) => Some(key.clone()),
+                None => {
+                    let key = deck_io::media_key(&claims.sub, &name);
+                    keys.insert(name.clone(), key.clone());
+                    media.push((key.clone(), name, data));
+                    Some(key)
+                }
+            },
+            None => None,
+        };
+
+        cards.push(CardUpsert {
+            id: None,
+            card_type: infer_card_type(&card.front),
+            front: card.front,
+            back: card.back,
+            media_url,
+            tags: card.tags,
+        });
+    }
+
+    let deck = DeckCreate {
+        title: imported.title.unwrap_or_default(),
+        description: None,
+        visibility: None,
+        assignee: None,
+    };
+
+    // Nothing is left in the bucket unless the deck made it into the database
+    let mut uploaded = Vec::with_capacity(media.len());
+    let created = async {
+        for (key, name, data) in media {
+            state
+                .s3
+                .upload_object(
+                    &key,
+                    Arc::unwrap_or_clone(data),
+                    Some(deck_io::media_content_type(&name)),
+                )
+                .await?;
+            uploaded.push(key);
+        }
+        Ok::<_, APIError>(
+            flashcards::deck::create_with_cards(&state.db, &claims.sub, deck, cards).await?,
+        )
+    }
+    .await;
+    let id = match created {
+        Ok(id) => id,
+        Err(err) => {
+            for key in &uploaded {
+                let _ = state.s3.delete_s3(key).await;
+            }
+            return Err(err);
+        }
+    };
+
+    log_activity(
+        &state.db,
+        &claims.sub,
+        &id,
+        ModelType::Deck,
+        ActionType::Create,
+        None,
+    )
+    .await?;
+
+    Ok(Json(id))
+}
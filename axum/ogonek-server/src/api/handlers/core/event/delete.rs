@@ -23,16 +23,17 @@ use ogonek_types::EventDelete;
     responses(
         (status = 204, description = "Event deleted successfully"),
         (status = 404, description = "Event not found"),
+        (status = 412, description = "The calendar is shared read-only"),
         (status = 401, description = "Unauthorized")
     )
 )]
 pub async fn delete_event(
     State(state): State<AppState>,
     Path(id): Path<String>,
-    _claims: Claims,
+    claims: Claims,
     Json(payload): Json<EventDelete>,
 ) -> Result<StatusCode, APIError> {
-    delete(&state.db, id, payload).await?;
+    delete(&state.db, &claims.sub, id, payload).await?;
 
     Ok(StatusCode::NO_CONTENT)
 }
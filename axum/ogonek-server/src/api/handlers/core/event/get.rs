@@ -13,7 +13,8 @@ use ogonek_types::{CalendarQuery, CalendarRole, EventSmall, EventWithAttendees};
 
 /// Retrieves a single event by its unique identifier
 ///
-/// Returns the event details along with attendee information.
+/// Returns the event details along with attendee information. Only events on calendars
+/// the user owns or that are shared with them, and those they attend, are found.
 #[utoipa::path(
     get,
     path = "/events/{id}",
@@ -30,9 +31,9 @@ use ogonek_types::{CalendarQuery, CalendarRole, EventSmall, EventWithAttendees};
 pub async fn fetch_event(
     State(state): State<AppState>,
     Path(id): Path<String>,
-    _claims: Claims,
+    claims: Claims,
 ) -> Result<Json<EventWithAttendees>, APIError> {
-    let event = read_one(&state.db, id.clone()).await?;
+    let event = read_one(&state.db, &claims.sub, id.clone()).await?;
 
     // Extract master event ID for attendee lookup (virtual events use master's attendees)
     let (master_id, _) = extract_id_and_occurence(id);
@@ -43,6 +44,7 @@ pub async fn fetch_event(
 /// Lists events within a specified date range
 ///
 /// Retrieves all events for the user within the given start and end dates, optionally filtered by role.
+/// Events of every calendar the user can see are merged unless some are picked.
 #[utoipa::path(
     get,
     path = "/events",
@@ -50,7 +52,8 @@ pub async fn fetch_event(
     params(
         ("start" = String, Query),
         ("end" = String, Query),
-        ("role" = Option<CalendarRole>, Query)
+        ("role" = Option<CalendarRole>, Query),
+        ("calendars" = Option<String>, Query, description = "Comma-separated calendar IDs")
     ),
     responses(
         (status = 200, description = "Events retrieved successfully", body = Vec<EventSmall>),
@@ -64,12 +67,22 @@ pub async fn list_events(
     State(state): State<AppState>,
     claims: Claims,
 ) -> Result<Json<Vec<EventSmall>>, APIError> {
+    let calendar_ids: Vec<String> = query
+        .calendars
+        .as_deref()
+        .unwrap_or_default()
+        .split(',')
+        .filter(|id| !id.is_empty())
+        .map(str::to_string)
+        .collect();
+
     let calendar_events = read_all(
         &state.db,
         &claims.sub,
         query.start,
         query.end,
         query.role.unwrap_or_default(),
+        &calendar_ids,
     )
     .await?;
     Ok(Json(calendar_events))
@@ -11,7 +11,8 @@ use ogonek_types::{EventUpdate, EventUpdateRequest};
 
 /// Updates an existing calendar event
 ///
-/// Modifies event properties based on the provided update payload.
+/// Modifies event properties based on the provided update payload. Moving the event onto
+/// busy time is refused with the clashing events unless `allowConflicts` is set.
 #[utoipa::path(
     patch,
     path = "/events/{id}",
@@ -23,15 +24,17 @@ use ogonek_types::{EventUpdate, EventUpdateRequest};
     responses(
         (status = 204, description = "Event updated successfully"),
         (status = 404, description = "Event not found"),
+        (status = 412, description = "The calendar is shared read-only"),
+        (status = 409, description = "The event overlaps busy time", body = Vec<ogonek_types::BusyPeriod>),
         (status = 401, description = "Unauthorized")
     )
 )]
 pub async fn update_event(
     State(state): State<AppState>,
     Path(id): Path<String>,
-    _claims: Claims,
+    claims: Claims,
     Json(payload): Json<EventUpdateRequest>,
 ) -> Result<StatusCode, APIError> {
-    update(&state.db, id, payload).await?;
+    update(&state.db, &claims.sub, id, payload).await?;
     Ok(StatusCode::NO_CONTENT)
 }
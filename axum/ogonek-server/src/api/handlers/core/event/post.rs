@@ -11,6 +11,7 @@ use ogonek_types::EventCreate;
 /// Creates a new calendar event for the authenticated user
 ///
 /// Creates an event with automatic end time if not provided (defaults to 1 hour duration).
+/// Overlaps with busy time are refused with the clashing events unless `allowConflicts` is set.
 #[utoipa::path(
     post,
     path = "/events",
@@ -19,6 +20,7 @@ use ogonek_types::EventCreate;
     responses(
         (status = 201, description = "Event created successfully", body = String),
         (status = 400, description = "Bad request"),
+        (status = 409, description = "The event overlaps busy time", body = Vec<ogonek_types::BusyPeriod>),
         (status = 401, description = "Unauthorized")
     )
 )]
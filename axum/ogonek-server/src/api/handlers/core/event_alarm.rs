@@ -0,0 +1,142 @@
+use crate::{
+    AppState, Claims,
+    api::{CALENDAR_TAG, error::APIError},
+};
+use axum::{
+    extract::{Json, Path, State},
+    http::StatusCode,
+};
+use ogonek_db::core::calendar::event_alarm::{create, delete, read_by_event, update};
+use ogonek_types::{EventAlarm, EventAlarmCreate, EventAlarmUpdate};
+use validator::Validate;
+
+/// Lists the alarms of an event
+///
+/// For an occurrence of a recurring event, `firesAt` is when each alarm goes off for it.
+#[utoipa::path(
+    get,
+    path = "/events/{id}/alarms",
+    tag = CALENDAR_TAG,
+    params(
+        ("id" = String, Path, description = "Event ID, virtual occurrence IDs included")
+    ),
+    responses(
+        (status = 200, description = "Alarms retrieved successfully", body = Vec<EventAlarm>),
+        (status = 404, description = "Event not found"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn list_alarms(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    claims: Claims,
+) -> Result<Json<Vec<EventAlarm>>, APIError> {
+    let alarms = read_by_event(&state.db, &claims.sub, &id).await?;
+    Ok(Json(alarms))
+}
+
+/// Adds an alarm to an event
+///
+/// Alarms added to an occurrence apply to the whole series. Recipients left out are taken
+/// from the event's attendees.
+#[utoipa::path(
+    post,
+    path = "/events/{id}/alarms",
+    tag = CALENDAR_TAG,
+    params(
+        ("id" = String, Path, description = "Event ID, virtual occurrence IDs included")
+    ),
+    request_body = EventAlarmCreate,
+    responses(
+        (status = 201, description = "Alarm created successfully", body = String),
+        (status = 400, description = "Invalid trigger or repetition"),
+        (status = 404, description = "Event not found"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn create_alarm(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    claims: Claims,
+    Json(payload): Json<EventAlarmCreate>,
+) -> Result<(StatusCode, Json<String>), APIError> {
+    payload.validate()?;
+    if payload.trigger_offset_seconds.is_some() == payload.trigger_datetime.is_some() {
+        return Err(APIError::BadRequest(
+            "Set either a trigger offset or a trigger time".into(),
+        ));
+    }
+    check_repeat(payload.repeat_count, payload.repeat_interval_seconds)?;
+
+    let alarm_id = create(&state.db, &claims.sub, &id, &payload).await?;
+    Ok((StatusCode::CREATED, Json(alarm_id)))
+}
+
+/// Updates an alarm
+#[utoipa::path(
+    patch,
+    path = "/alarms/{id}",
+    tag = CALENDAR_TAG,
+    params(
+        ("id" = String, Path, description = "Alarm ID")
+    ),
+    request_body = EventAlarmUpdate,
+    responses(
+        (status = 204, description = "Alarm updated successfully"),
+        (status = 400, description = "Invalid trigger or repetition"),
+        (status = 404, description = "Alarm not found"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn update_alarm(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    claims: Claims,
+    Json(payload): Json<EventAlarmUpdate>,
+) -> Result<StatusCode, APIError> {
+    payload.validate()?;
+    if payload.trigger_offset_seconds.is_some() && payload.trigger_datetime.is_some() {
+        return Err(APIError::BadRequest(
+            "Set either a trigger offset or a trigger time".into(),
+        ));
+    }
+    if let Some(repeat_count) = payload.repeat_count {
+        check_repeat(repeat_count, payload.repeat_interval_seconds)?;
+    }
+
+    update(&state.db, &claims.sub, &id, &payload).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Deletes an alarm
+#[utoipa::path(
+    delete,
+    path = "/alarms/{id}",
+    tag = CALENDAR_TAG,
+    params(
+        ("id" = String, Path, description = "Alarm ID")
+    ),
+    responses(
+        (status = 204, description = "Alarm deleted successfully"),
+        (status = 404, description = "Alarm not found"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn delete_alarm(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    claims: Claims,
+) -> Result<StatusCode, APIError> {
+    delete(&state.db, &claims.sub, &id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Repeated alarms need a positive interval, as RFC 5545 asks for REPEAT and DURATION together
+fn check_repeat(repeat_count: i32, repeat_interval_seconds: Option<i64>) -> Result<(), APIError> {
+    if repeat_count > 0 && repeat_interval_seconds.is_none_or(|seconds| seconds <= 0) {
+        return Err(APIError::BadRequest(
+            "Repeated alarms need a positive repeat interval".into(),
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,159 @@
+use crate::{
+    AppState, Claims,
+    api::{CALENDAR_TAG, error::APIError},
+};
+use axum::{
+    extract::{Json, Path, Query, State},
+    http::StatusCode,
+};
+use ogonek_db::core::calendar::event_proposal::{accept, create, decline, read_all};
+use ogonek_notifications::NotificationType;
+use ogonek_types::{EventProposal, EventProposalCreate, ProposalQuery};
+use validator::Validate;
+
+/// Proposes a new time or place for an event
+///
+/// Only attendees can propose. A proposal on a virtual occurrence ID applies to that
+/// occurrence only.
+#[utoipa::path(
+    post,
+    path = "/events/{id}/proposals",
+    tag = CALENDAR_TAG,
+    params(
+        ("id" = String, Path, description = "Event ID, virtual occurrence IDs included")
+    ),
+    request_body = EventProposalCreate,
+    responses(
+        (status = 201, description = "Proposal created successfully", body = String),
+        (status = 400, description = "Nothing proposed or invalid time range"),
+        (status = 404, description = "Event not found"),
+        (status = 409, description = "A proposal for this event is already pending"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn create_proposal(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    claims: Claims,
+    Json(payload): Json<EventProposalCreate>,
+) -> Result<(StatusCode, Json<String>), APIError> {
+    payload.validate()?;
+    if payload.proposed_start.is_none() && payload.proposed_location.is_none() {
+        return Err(APIError::BadRequest(
+            "Propose a new time or a new location".into(),
+        ));
+    }
+    if payload.proposed_end.is_some() && payload.proposed_start.is_none() {
+        return Err(APIError::BadRequest(
+            "A proposed end needs a proposed start".into(),
+        ));
+    }
+    if let (Some(start), Some(end)) = (payload.proposed_start, payload.proposed_end)
+        && end <= start
+    {
+        return Err(APIError::BadRequest(
+            "The proposed end must be after the proposed start".into(),
+        ));
+    }
+
+    let proposal_id = create(&state.db, &claims.sub, &id, &payload).await?;
+    Ok((StatusCode::CREATED, Json(proposal_id)))
+}
+
+/// Lists the proposals the user made or received
+#[utoipa::path(
+    get,
+    path = "/proposals",
+    tag = CALENDAR_TAG,
+    params(
+        ("status" = Option<ogonek_types::ProposalStatus>, Query, description = "Defaults to pending")
+    ),
+    responses(
+        (status = 200, description = "Proposals retrieved successfully", body = Vec<EventProposal>),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn list_proposals(
+    State(state): State<AppState>,
+    Query(query): Query<ProposalQuery>,
+    claims: Claims,
+) -> Result<Json<Vec<EventProposal>>, APIError> {
+    let proposals = read_all(&state.db, &claims.sub, query.status).await?;
+    Ok(Json(proposals))
+}
+
+/// Accepts a proposal and moves the event
+///
+/// The change is applied to the proposal's occurrence only. The proposer is notified.
+#[utoipa::path(
+    post,
+    path = "/proposals/{id}/accept",
+    tag = CALENDAR_TAG,
+    params(
+        ("id" = String, Path, description = "Proposal ID")
+    ),
+    responses(
+        (status = 200, description = "Proposal accepted", body = EventProposal),
+        (status = 404, description = "Pending proposal not found"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn accept_proposal(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    claims: Claims,
+) -> Result<Json<EventProposal>, APIError> {
+    let proposal = accept(&state.db, &claims.sub, &id).await?;
+
+    let _ = state
+        .notification_service
+        .notify_student(
+            &claims.sub,
+            &proposal.proposer,
+            NotificationType::ProposalAccepted {
+                summary: proposal.summary.clone(),
+                event_id: proposal.event_id.clone(),
+            },
+        )
+        .await;
+
+    Ok(Json(proposal))
+}
+
+/// Declines a proposal
+///
+/// The event is left as it is. The proposer is notified.
+#[utoipa::path(
+    post,
+    path = "/proposals/{id}/decline",
+    tag = CALENDAR_TAG,
+    params(
+        ("id" = String, Path, description = "Proposal ID")
+    ),
+    responses(
+        (status = 200, description = "Proposal declined", body = EventProposal),
+        (status = 404, description = "Pending proposal not found"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn decline_proposal(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    claims: Claims,
+) -> Result<Json<EventProposal>, APIError> {
+    let proposal = decline(&state.db, &claims.sub, &id).await?;
+
+    let _ = state
+        .notification_service
+        .notify_student(
+            &claims.sub,
+            &proposal.proposer,
+            NotificationType::ProposalDeclined {
+                summary: proposal.summary.clone(),
+                event_id: proposal.event_id.clone(),
+            },
+        )
+        .await;
+
+    Ok(Json(proposal))
+}
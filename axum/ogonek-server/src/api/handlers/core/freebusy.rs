@@ -0,0 +1,43 @@
+use crate::{
+    AppState, Claims,
+    api::{CALENDAR_TAG, error::APIError},
+};
+use axum::extract::{Json, Query, State};
+use chrono::Duration;
+use ogonek_db::core::calendar::freebusy;
+use ogonek_types::{BusyPeriod, FreeBusyQuery};
+
+/// The longest range busy time is listed for
+const MAX_FREEBUSY_RANGE: Duration = Duration::days(366);
+
+/// Lists the busy time across the user's calendars
+///
+/// Returns the opaque events within the range, occurrences of recurring events included.
+#[utoipa::path(
+    get,
+    path = "/freebusy",
+    tag = CALENDAR_TAG,
+    params(
+        ("start" = String, Query),
+        ("end" = String, Query)
+    ),
+    responses(
+        (status = 200, description = "Busy time retrieved successfully", body = Vec<BusyPeriod>),
+        (status = 400, description = "Invalid range"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn fetch_freebusy(
+    Query(query): Query<FreeBusyQuery>,
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<Vec<BusyPeriod>>, APIError> {
+    if query.end <= query.start || query.end - query.start > MAX_FREEBUSY_RANGE {
+        return Err(APIError::BadRequest(
+            "The range must end after it starts and span at most a year".into(),
+        ));
+    }
+
+    let busy = freebusy::read(&state.db, &claims.sub, query.start, query.end).await?;
+    Ok(Json(busy))
+}
@@ -1,17 +1,26 @@
 use crate::{
     AppState, Claims,
     api::{LEARN_TAG, error::APIError},
-    services::SM2Calculator,
+    services::{
+        LearningSteps, build_study_queue, check_typed_answer, deck_io, parse_card_filter,
+        render_card, scheduler_for,
+    },
 };
 use axum::{
-    extract::{Json, Path, State},
+    extract::{Json, Path, Query, State},
     http::StatusCode,
 };
-use chrono::{Duration, Utc};
-use ogonek_db::{core::flashcards, tracking::log_activity};
+use chrono::Utc;
+use ogonek_db::{
+    core::flashcards,
+    tracking::{log_activity, log_activity_with_metadata},
+};
 use ogonek_types::{
-    ActionType, CardProgressWithFields, ModelType, ReviewPayload, UpdateCardProgress,
+    ActionType, CardFilter, CardProgressWithFields, CardReview, CardReviewCreate, CardType,
+    LearnStats, ModelType, PaginatedResponse, ReviewOutcome, ReviewPaginationParams, ReviewPayload,
+    StatsQuery, StudySession, StudySessionCreate, StudySessionSummary, UpdateCardProgress,
 };
+use validator::Validate;
 
 /// Subscribes the user to the deck
 #[utoipa::path(
@@ -90,9 +99,14 @@ pub async fn fetch_due_cards(
     State(state): State<AppState>,
     claims: Claims,
 ) -> Result<Json<Vec<CardProgressWithFields>>, APIError> {
-    let due = flashcards::learn::fetch_due(&state.db, &claims.sub).await?;
+    let due = flashcards::learn::fetch_due(&state.db, &claims.sub, &CardFilter::default()).await?;
+
+    let mut cards: Vec<_> = due.into_iter().map(render_card).collect();
+    for card in &mut cards {
+        deck_io::presign_media(&state.s3, &mut card.media_url).await?;
+    }
 
-    Ok(Json(due))
+    Ok(Json(cards))
 }
 
 /// Updates the learn progress on a card
@@ -104,7 +118,8 @@ pub async fn fetch_due_cards(
     ),
     request_body = ReviewPayload,
     responses(
-        (status = 204, description = "Card progress updated successfully"),
+        (status = 200, description = "Card progress updated successfully", body = ReviewOutcome),
+        (status = 400, description = "Typed card answered without an answer"),
         (status = 401, description = "Unauthorized")
     )
 )]
@@ -113,29 +128,67 @@ pub async fn update_card_progress(
     claims: Claims,
     Path(id): Path<String>,
     Json(payload): Json<ReviewPayload>,
-) -> Result<StatusCode, APIError> {
-    let calculator = SM2Calculator::default();
+) -> Result<Json<ReviewOutcome>, APIError> {
+    let (outcome, update, review) = grade_review(&state, &claims.sub, &id, &payload).await?;
+
+    flashcards::learn::update_with_review(&state.db, &id, &claims.sub, update, &review).await?;
+
+    Ok(Json(outcome))
+}
 
-    let current_progress = flashcards::learn::find_by_id(&state.db, &id, &claims.sub).await?;
+/// Grades an answer and works out the new progress and the review log entry for it
+async fn grade_review(
+    state: &AppState,
+    user_id: &str,
+    progress_id: &str,
+    payload: &ReviewPayload,
+) -> Result<(ReviewOutcome, UpdateCardProgress, CardReviewCreate), APIError> {
+    let current_progress = flashcards::learn::find_by_id(&state.db, progress_id, user_id).await?;
+    let settings =
+        flashcards::learn::read_scheduler_settings(&state.db, progress_id, user_id).await?;
 
-    let (new_ease, new_interval, new_review_count) = calculator.calculate_next_review(
-        payload.quality,
-        current_progress.ease_factor,
-        current_progress.interval,
-        current_progress.review_count,
+    let mut outcome = ReviewOutcome {
+        quality: payload.quality,
+        correct: None,
+        expected: None,
+    };
+    let card = flashcards::learn::find_fields_by_id(&state.db, progress_id, user_id).await?;
+    if card.card_type == CardType::Typed {
+        // The learner doesn't get to grade a typed card themselves
+        let answer = payload
+            .answer
+            .as_deref()
+            .ok_or_else(|| APIError::BadRequest("Typed cards need an answer".into()))?;
+        let correct = check_typed_answer(&card.back, answer);
+        outcome.quality = if correct { 4 } else { 1 };
+        outcome.correct = Some(correct);
+        outcome.expected = Some(card.back);
+    }
+
+    let now = Utc::now();
+    let steps = LearningSteps::new(settings.learning_steps, settings.relearning_steps);
+    let update = steps.next_review(
+        scheduler_for(settings.scheduler).as_ref(),
+        &current_progress,
+        outcome.quality,
+        now,
     );
 
-    let update = UpdateCardProgress {
-        review_count: new_review_count,
-        ease_factor: new_ease,
-        interval: new_interval,
-        last_reviewed: Utc::now(),
-        due_date: Utc::now() + Duration::days(new_interval.into()),
+    let review = CardReviewCreate {
+        quality: outcome.quality,
+        ease_before: current_progress.ease_factor,
+        ease_after: update.ease_factor,
+        interval_before: current_progress.interval,
+        interval_after: update.interval,
+        elapsed_seconds: current_progress
+            .last_reviewed
+            .map(|last| (now - last).num_seconds()),
+        reviewed_at: now,
     };
-    flashcards::learn::update(&state.db, &id, &claims.sub, update).await?;
 
-    Ok(StatusCode::NO_CONTENT)
+    Ok((outcome, update, review))
 }
+
 /// Resets the progress for a particular deck
 #[utoipa::path(
     delete,
@@ -157,3 +210,335 @@ pub async fn reset_deck_progress(
 
     Ok(StatusCode::OK)
 }
+
+/// Pages through the review history of the user or one of their students
+#[utoipa::path(
+    get,
+    path = "/reviews",
+    tag = LEARN_TAG,
+    params(
+        ("page" = Option<u32>, Query, description = "Page number"),
+        ("per_page" = Option<u32>, Query, description = "Items per page"),
+        ("student_id" = Option<String>, Query, description = "Student whose history to read")
+    ),
+    responses(
+        (status = 200, description = "Review history retrieved", body = PaginatedResponse<CardReview>),
+        (status = 400, description = "Invalid pagination"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Student not found")
+    )
+)]
+pub async fn list_reviews(
+    State(state): State<AppState>,
+    claims: Claims,
+    Query(params): Query<ReviewPaginationParams>,
+) -> Result<Json<PaginatedResponse<CardReview>>, APIError> {
+    params.validate()?;
+
+    let (reviews, count) =
+        flashcards::review::read_all(&state.db, &claims.sub, None, &params).await?;
+
+    Ok(Json(paginate_reviews(reviews, count, &params)))
+}
+
+/// Pages through the review history of a single deck
+#[utoipa::path(
+    get,
+    path = "/reviews/{id}",
+    tag = LEARN_TAG,
+    params(
+        ("id" = String, Path, description = "Deck ID"),
+        ("page" = Option<u32>, Query, description = "Page number"),
+        ("per_page" = Option<u32>, Query, description = "Items per page"),
+        ("student_id" = Option<String>, Query, description = "Student whose history to read")
+    ),
+    responses(
+        (status = 200, description = "Deck review history retrieved", body = PaginatedResponse<CardReview>),
+        (status = 400, description = "Invalid pagination"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Student not found")
+    )
+)]
+pub async fn list_deck_reviews(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(deck_id): Path<String>,
+    Query(params): Query<ReviewPaginationParams>,
+) -> Result<Json<PaginatedResponse<CardReview>>, APIError> {
+    params.validate()?;
+
+    let (reviews, count) =
+        flashcards::review::read_all(&state.db, &claims.sub, Some(&deck_id), &params).await?;
+
+    Ok(Json(paginate_reviews(reviews, count, &params)))
+}
+
+/// Learning statistics across all decks of the user or one of their students
+#[utoipa::path(
+    get,
+    path = "/stats",
+    tag = LEARN_TAG,
+    params(
+        ("student_id" = Option<String>, Query, description = "Student whose statistics to read")
+    ),
+    responses(
+        (status = 200, description = "Statistics retrieved", body = LearnStats),
+        (status = 404, description = "Student not found"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn fetch_stats(
+    State(state): State<AppState>,
+    claims: Claims,
+    Query(params): Query<StatsQuery>,
+) -> Result<Json<LearnStats>, APIError> {
+    let stats =
+        flashcards::stats::read(&state.db, &claims.sub, params.student_id.as_deref(), None).await?;
+
+    Ok(Json(stats))
+}
+
+/// Learning statistics for a single deck
+#[utoipa::path(
+    get,
+    path = "/stats/{id}",
+    tag = LEARN_TAG,
+    params(
+        ("id" = String, Path, description = "Deck ID"),
+        ("student_id" = Option<String>, Query, description = "Student whose statistics to read")
+    ),
+    responses(
+        (status = 200, description = "Deck statistics retrieved", body = LearnStats),
+        (status = 404, description = "Student not found"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn fetch_deck_stats(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(deck_id): Path<String>,
+    Query(params): Query<StatsQuery>,
+) -> Result<Json<LearnStats>, APIError> {
+    let stats = flashcards::stats::read(
+        &state.db,
+        &claims.sub,
+        params.student_id.as_deref(),
+        Some(&deck_id),
+    )
+    .await?;
+
+    Ok(Json(stats))
+}
+
+/// Starts a study session over the cards due now, optionally from a single deck.
+/// A filter such as `tag:verbs` or `failed:7` studies the matching cards even if they are not due yet
+#[utoipa::path(
+    post,
+    path = "/sessions",
+    tag = LEARN_TAG,
+    request_body = StudySessionCreate,
+    responses(
+        (status = 201, description = "Study session started", body = StudySession),
+        (status = 400, description = "Invalid filter"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn create_study_session(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(payload): Json<StudySessionCreate>,
+) -> Result<(StatusCode, Json<StudySession>), APIError> {
+    let mut filter = match payload.filter.as_deref() {
+        Some(expression) => parse_card_filter(expression)?,
+        None => CardFilter::default(),
+    };
+    if filter.deck_id.is_none() {
+        filter.deck_id = payload.deck_id;
+    }
+
+    let due = flashcards::learn::fetch_due(&state.db, &claims.sub, &filter).await?;
+    let queue = build_study_queue(&due);
+
+    let id = flashcards::session::create(
+        &state.db,
+        &claims.sub,
+        filter.deck_id.as_deref(),
+        payload.filter.as_deref(),
+        &queue,
+    )
+    .await?;
+    let session = flashcards::session::read_by_id(&state.db, &id, &claims.sub).await?;
+
+    Ok((StatusCode::CREATED, Json(session)))
+}
+
+/// Returns the state of a study session
+#[utoipa::path(
+    get,
+    path = "/sessions/{id}",
+    tag = LEARN_TAG,
+    params(
+        ("id" = String, Path, description = "Session ID")
+    ),
+    responses(
+        (status = 200, description = "Study session retrieved", body = StudySession),
+        (status = 404, description = "Session not found"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn fetch_study_session(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<String>,
+) -> Result<Json<StudySession>, APIError> {
+    let session = flashcards::session::read_by_id(&state.db, &id, &claims.sub).await?;
+
+    Ok(Json(session))
+}
+
+/// Hands out the next card of a study session, null once the queue is empty
+#[utoipa::path(
+    get,
+    path = "/sessions/{id}/next",
+    tag = LEARN_TAG,
+    params(
+        ("id" = String, Path, description = "Session ID")
+    ),
+    responses(
+        (status = 200, description = "Next card retrieved", body = Option<CardProgressWithFields>),
+        (status = 404, description = "Session not found or already finished"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn fetch_next_session_card(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<String>,
+) -> Result<Json<Option<CardProgressWithFields>>, APIError> {
+    let next = flashcards::session::next_card(&state.db, &id, &claims.sub).await?;
+
+    let mut card = next.map(|next| render_card(next.card));
+    if let Some(card) = &mut card {
+        deck_io::presign_media(&state.s3, &mut card.media_url).await?;
+    }
+
+    Ok(Json(card))
+}
+
+/// Answers the card last handed out by the session
+#[utoipa::path(
+    post,
+    path = "/sessions/{id}/answer",
+    tag = LEARN_TAG,
+    params(
+        ("id" = String, Path, description = "Session ID")
+    ),
+    request_body = ReviewPayload,
+    responses(
+        (status = 200, description = "Answer recorded", body = ReviewOutcome),
+        (status = 400, description = "No cards left in the session or typed card answered without an answer"),
+        (status = 404, description = "Session not found or already finished"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn answer_session_card(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<String>,
+    Json(payload): Json<ReviewPayload>,
+) -> Result<Json<ReviewOutcome>, APIError> {
+    let current = flashcards::session::next_card(&state.db, &id, &claims.sub)
+        .await?
+        .ok_or_else(|| APIError::BadRequest("No cards left in the session".into()))?;
+
+    let (outcome, update, review) =
+        grade_review(&state, &claims.sub, &current.card.id, &payload).await?;
+    flashcards::session::answer(
+        &state.db,
+        &id,
+        &claims.sub,
+        current.position,
+        update,
+        &review,
+    )
+    .await?;
+
+    Ok(Json(outcome))
+}
+
+/// Takes back the last answer of a study session
+#[utoipa::path(
+    post,
+    path = "/sessions/{id}/undo",
+    tag = LEARN_TAG,
+    params(
+        ("id" = String, Path, description = "Session ID")
+    ),
+    responses(
+        (status = 204, description = "Last answer undone"),
+        (status = 404, description = "Nothing to undo"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn undo_session_answer(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<String>,
+) -> Result<StatusCode, APIError> {
+    flashcards::session::undo(&state.db, &id, &claims.sub).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Finishes a study session and logs its summary
+#[utoipa::path(
+    post,
+    path = "/sessions/{id}/finish",
+    tag = LEARN_TAG,
+    params(
+        ("id" = String, Path, description = "Session ID")
+    ),
+    responses(
+        (status = 200, description = "Study session finished", body = StudySessionSummary),
+        (status = 404, description = "Session not found"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn finish_study_session(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(id): Path<String>,
+) -> Result<Json<StudySessionSummary>, APIError> {
+    let (summary, finished) = flashcards::session::finish(&state.db, &id, &claims.sub).await?;
+
+    // Finishing again only returns the summary
+    if finished {
+        log_activity_with_metadata(
+            &state.db,
+            &claims.sub,
+            &id,
+            ModelType::StudySession,
+            ActionType::Complete,
+            None,
+            serde_json::to_value(&summary).ok(),
+        )
+        .await?;
+    }
+
+    Ok(Json(summary))
+}
+
+fn paginate_reviews(
+    reviews: Vec<CardReview>,
+    count: i64,
+    params: &ReviewPaginationParams,
+) -> PaginatedResponse<CardReview> {
+    let total_pages = (count as f64 / params.limit() as f64).ceil() as i64;
+    PaginatedResponse {
+        data: reviews,
+        page: params.page(),
+        total_pages,
+        count,
+        per_page: params.limit(),
+    }
+}
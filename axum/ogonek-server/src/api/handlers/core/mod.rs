@@ -1,16 +1,28 @@
+pub mod attendance;
+pub mod availability;
 pub mod calendar;
+pub mod calendar_share;
 pub mod deck;
 pub mod event;
+pub mod event_alarm;
 pub mod event_attendee;
+pub mod event_proposal;
+pub mod freebusy;
 pub mod learn;
 pub mod lesson;
 pub mod state;
 pub mod task;
 
+pub use attendance::*;
+pub use availability::*;
 pub use calendar::*;
+pub use calendar_share::*;
 pub use deck::*;
 pub use event::*;
+pub use event_alarm::*;
 pub use event_attendee::*;
+pub use event_proposal::*;
+pub use freebusy::*;
 pub use learn::*;
 pub use lesson::*;
 pub use state::*;
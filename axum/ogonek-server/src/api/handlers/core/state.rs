@@ -2,8 +2,8 @@ use crate::{
     AppState, Claims,
     api::{STATE_TAG, error::APIError},
 };
-use axum::extract::{Json, State};
-use chrono::{Datelike, TimeZone, Utc};
+use axum::extract::{Json, Query, State};
+use chrono::{Datelike, Duration, TimeZone, Utc};
 use ogonek_db::{
     core::{
         calendar::event,
@@ -12,13 +12,16 @@ use ogonek_db::{
         state::read_context,
         task,
     },
-    tracking::seen,
+    tracking::{seen, study},
 };
 use ogonek_types::{
-    AppContext, DashboardData, LessonPaginationParams, ModelType, NotificationBadges, SortField,
-    SortOrder, TaskPaginationParams,
+    AppContext, DashboardData, HeatmapDay, HeatmapQuery, LessonPaginationParams, ModelType,
+    NotificationBadges, SortField, SortOrder, TaskPaginationParams,
 };
 
+/// Longest range the heatmap can be asked for
+const MAX_HEATMAP_DAYS: i64 = 3 * 366;
+
 /// This data populates the dashboard/home view
 #[utoipa::path(
     get,
@@ -93,15 +96,61 @@ pub async fn fetch_dashboard(
         )
         .unwrap();
 
-    let events = event::read_all(&state.db, &claims.sub, start, end, claims.role.into()).await?;
+    let events =
+        event::read_all(&state.db, &claims.sub, start, end, claims.role.into(), &[]).await?;
+
+    let streak = study::read_streak(&state.db, &claims.sub).await?;
+    let goals = study::read_daily_goals(&state.db, &claims.sub).await?;
 
     Ok(Json(DashboardData {
         tasks,
         lessons,
         events,
+        streak,
+        goals,
     }))
 }
 
+/// Activity per day for a calendar heatmap, a year back by default
+#[utoipa::path(
+    get,
+    tag = STATE_TAG,
+    path = "/heatmap",
+    params(
+        ("from" = Option<String>, Query, description = "First day, YYYY-MM-DD"),
+        ("to" = Option<String>, Query, description = "Last day, YYYY-MM-DD")
+    ),
+    responses(
+        (status = 200, description = "Heatmap retrieved", body = Vec<HeatmapDay>),
+        (status = 400, description = "Invalid date range"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn fetch_heatmap(
+    State(state): State<AppState>,
+    claims: Claims,
+    Query(params): Query<HeatmapQuery>,
+) -> Result<Json<Vec<HeatmapDay>>, APIError> {
+    let to = match params.to {
+        Some(to) => to,
+        None => study::read_today(&state.db, &claims.sub).await?,
+    };
+    let from = params.from.unwrap_or(to - Duration::days(365));
+
+    if from > to {
+        return Err(APIError::BadRequest("from must not be after to".into()));
+    }
+    if to - from > Duration::days(MAX_HEATMAP_DAYS) {
+        return Err(APIError::BadRequest(format!(
+            "The heatmap spans at most {MAX_HEATMAP_DAYS} days"
+        )));
+    }
+
+    let days = study::read_heatmap(&state.db, &claims.sub, from, to).await?;
+
+    Ok(Json(days))
+}
+
 /// This data gives info about notification badges
 #[utoipa::path(
     get,
@@ -0,0 +1,549 @@
+//! CalDAV access to the user's calendar, so native calendar clients can sync both ways.
+//!
+//! Every method of a path goes through one handler which dispatches on the method name,
+//! as PROPFIND and REPORT have no routing helpers of their own.
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, HeaderValue, Method, StatusCode, header},
+    response::{IntoResponse, Redirect, Response},
+};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use ogonek_db::core::{
+    account::user,
+    calendar::{
+        cal::{read_all_owned, read_owned},
+        dav, event_attendee,
+    },
+};
+use ogonek_types::{CalendarFull, CalendarResource, ResourcePrecondition};
+
+use crate::{
+    AppState,
+    api::error::APIError,
+    services::{
+        DavUser,
+        calendar::{
+            APPLE_NS, CALSERVER_NS, DavRequest, DavResponse, PropName, href_xml, parse_dav_request,
+            parse_ics, parse_sync_token, sync_token, text_xml, wants, write_dav_error,
+            write_multistatus, write_resource,
+        },
+    },
+};
+
+const DAV_ROOT: &str = "/dav/";
+const CALENDAR_HOME: &str = "/dav/calendars/";
+const ALLOWED_METHODS: &str = "OPTIONS, GET, HEAD, PUT, DELETE, PROPFIND, REPORT";
+const CALENDAR_CONTENT_TYPE: &str = "text/calendar; charset=utf-8";
+const RESOURCE_CONTENT_TYPE: &str = "text/calendar; charset=utf-8; component=vevent";
+const PRIVILEGES: &str = "<D:privilege><D:read/></D:privilege>\
+    <D:privilege><D:write/></D:privilege>\
+    <D:privilege><D:write-content/></D:privilege>\
+    <D:privilege><D:bind/></D:privilege>\
+    <D:privilege><D:unbind/></D:privilege>";
+const REPORTS: &str = "<D:supported-report><D:report><C:calendar-query/></D:report></D:supported-report>\
+    <D:supported-report><D:report><C:calendar-multiget/></D:report></D:supported-report>\
+    <D:supported-report><D:report><D:sync-collection/></D:report></D:supported-report>";
+
+/// Service discovery (RFC 6764), clients then look up the principal at the DAV root
+pub async fn dav_well_known() -> Redirect {
+    Redirect::permanent(DAV_ROOT)
+}
+
+/// The DAV root, which points clients at the signed-in user's principal
+pub async fn dav_root(user: DavUser, method: Method, body: String) -> Result<Response, APIError> {
+    match method.as_str() {
+        "OPTIONS" => Ok(options()),
+        "PROPFIND" => {
+            let request = parse_dav_request(&body)?;
+            let available = vec![
+                (PropName::dav("resourcetype"), "<D:collection/>".to_string()),
+                (
+                    PropName::dav("current-user-principal"),
+                    href_xml(&principal_href(&user.id)),
+                ),
+                (
+                    PropName::caldav("calendar-home-set"),
+                    href_xml(CALENDAR_HOME),
+                ),
+            ];
+            let response = DavResponse::select(DAV_ROOT.to_string(), available, request.props());
+            Ok(multistatus(&[response], None))
+        }
+        _ => Ok(not_allowed()),
+    }
+}
+
+/// The user's principal, with where their calendars live
+pub async fn dav_principal(
+    State(state): State<AppState>,
+    user: DavUser,
+    Path(user_id): Path<String>,
+    method: Method,
+    body: String,
+) -> Result<Response, APIError> {
+    if user_id != user.id {
+        return Err(APIError::NotFound("Principal not found".into()));
+    }
+
+    match method.as_str() {
+        "OPTIONS" => Ok(options()),
+        "PROPFIND" => {
+            let request = parse_dav_request(&body)?;
+            let profile = user::read_by_id(&state.db, &user.id).await?;
+            let href = principal_href(&user.id);
+            let available = vec![
+                (
+                    PropName::dav("resourcetype"),
+                    "<D:collection/><D:principal/>".to_string(),
+                ),
+                (PropName::dav("displayname"), text_xml(&profile.name)),
+                (PropName::dav("current-user-principal"), href_xml(&href)),
+                (PropName::dav("principal-URL"), href_xml(&href)),
+                (
+                    PropName::caldav("calendar-home-set"),
+                    href_xml(CALENDAR_HOME),
+                ),
+                (
+                    PropName::caldav("calendar-user-address-set"),
+                    href_xml(&format!("mailto:{}", profile.email)),
+                ),
+            ];
+            let response = DavResponse::select(href, available, request.props());
+            Ok(multistatus(&[response], None))
+        }
+        _ => Ok(not_allowed()),
+    }
+}
+
+/// The calendar home, listing the user's calendars at depth 1
+pub async fn dav_calendar_home(
+    State(state): State<AppState>,
+    user: DavUser,
+    method: Method,
+    headers: HeaderMap,
+    body: String,
+) -> Result<Response, APIError> {
+    match method.as_str() {
+        "OPTIONS" => Ok(options()),
+        "PROPFIND" => {
+            let request = parse_dav_request(&body)?;
+            let available = vec![
+                (PropName::dav("resourcetype"), "<D:collection/>".to_string()),
+                (
+                    PropName::dav("current-user-principal"),
+                    href_xml(&principal_href(&user.id)),
+                ),
+                (PropName::dav("owner"), href_xml(&principal_href(&user.id))),
+            ];
+            let mut responses = vec![DavResponse::select(
+                CALENDAR_HOME.to_string(),
+                available,
+                request.props(),
+            )];
+
+            if with_children(&headers) {
+                for calendar in read_all_owned(&state.db, &user.id).await? {
+                    let synced_at = dav::read_synced_at(&state.db, &calendar.id).await?;
+                    responses.push(DavResponse::select(
+                        collection_href(&calendar.id),
+                        collection_props(&calendar, synced_at),
+                        request.props(),
+                    ));
+                }
+            }
+            Ok(multistatus(&responses, None))
+        }
+        _ => Ok(not_allowed()),
+    }
+}
+
+/// A calendar collection: its properties, its resources and the CalDAV reports
+pub async fn dav_calendar(
+    State(state): State<AppState>,
+    user: DavUser,
+    Path(calendar_id): Path<String>,
+    method: Method,
+    headers: HeaderMap,
+    body: String,
+) -> Result<Response, APIError> {
+    let calendar = read_owned(&state.db, &calendar_id, &user.id).await?;
+
+    match method.as_str() {
+        "OPTIONS" => Ok(options()),
+        "PROPFIND" => {
+            let request = parse_dav_request(&body)?;
+            let synced_at = dav::read_synced_at(&state.db, &calendar.id).await?;
+            let mut responses = vec![DavResponse::select(
+                collection_href(&calendar.id),
+                collection_props(&calendar, synced_at),
+                request.props(),
+            )];
+
+            if with_children(&headers) {
+                let resources = dav::read_resources(&state.db, &calendar.id, None, None).await?;
+                responses.extend(
+                    resource_responses(&state, &calendar.id, &resources, request.props()).await?,
+                );
+            }
+            Ok(multistatus(&responses, None))
+        }
+        "REPORT" => report(&state, &calendar, parse_dav_request(&body)?).await,
+        _ => Ok(not_allowed()),
+    }
+}
+
+/// A single event resource: the events of one UID as an iCalendar object
+pub async fn dav_calendar_resource(
+    State(state): State<AppState>,
+    user: DavUser,
+    Path((calendar_id, href)): Path<(String, String)>,
+    method: Method,
+    headers: HeaderMap,
+    body: String,
+) -> Result<Response, APIError> {
+    let calendar = read_owned(&state.db, &calendar_id, &user.id).await?;
+
+    match method.as_str() {
+        "OPTIONS" => Ok(options()),
+        "GET" | "HEAD" => {
+            let resource = dav::read_resources_by_href(&state.db, &calendar.id, &[href])
+                .await?
+                .pop()
+                .ok_or_else(|| APIError::NotFound("Resource not found".into()))?;
+            let ics = render_resources(&state, &calendar.id, std::slice::from_ref(&resource))
+                .await?
+                .remove(&resource.uid)
+                .unwrap_or_default();
+
+            Ok((
+                [
+                    (
+                        header::CONTENT_TYPE,
+                        HeaderValue::from_static(CALENDAR_CONTENT_TYPE),
+                    ),
+                    (header::ETAG, etag_header(&resource.etag)?),
+                ],
+                ics,
+            )
+                .into_response())
+        }
+        "PUT" => {
+            let timezone = calendar.timezone.parse().unwrap_or(Tz::UTC);
+            let (events, _) = parse_ics(&body, timezone)?;
+            if events.is_empty() {
+                return Err(APIError::BadRequest("The resource holds no event".into()));
+            }
+
+            let (created, etag) = dav::put_resource(
+                &state.db,
+                &calendar.id,
+                &href,
+                &events,
+                &precondition(&headers),
+            )
+            .await?;
+            let status = if created {
+                StatusCode::CREATED
+            } else {
+                StatusCode::NO_CONTENT
+            };
+            Ok((status, [(header::ETAG, etag_header(&etag)?)]).into_response())
+        }
+        "DELETE" => {
+            dav::delete_resource(&state.db, &calendar.id, &href, &precondition(&headers)).await?;
+            Ok(StatusCode::NO_CONTENT.into_response())
+        }
+        "PROPFIND" => {
+            let request = parse_dav_request(&body)?;
+            let resources = dav::read_resources_by_href(&state.db, &calendar.id, &[href]).await?;
+            if resources.is_empty() {
+                return Err(APIError::NotFound("Resource not found".into()));
+            }
+            let responses =
+                resource_responses(&state, &calendar.id, &resources, request.props()).await?;
+            Ok(multistatus(&responses, None))
+        }
+        _ => Ok(not_allowed()),
+    }
+}
+
+async fn report(
+    state: &AppState,
+    calendar: &CalendarFull,
+    request: DavRequest,
+) -> Result<Response, APIError> {
+    let props = request.props();
+    match &request {
+        DavRequest::CalendarQuery { start, end, .. } => {
+            let resources = dav::read_resources(&state.db, &calendar.id, *start, *end).await?;
+            let responses = resource_responses(state, &calendar.id, &resources, props).await?;
+            Ok(multistatus(&responses, None))
+        }
+        DavRequest::CalendarMultiget { hrefs, .. } => {
+            let names: Vec<String> = hrefs.iter().map(|href| resource_name(href)).collect();
+            let resources = dav::read_resources_by_href(&state.db, &calendar.id, &names).await?;
+
+            let mut responses = resource_responses(state, &calendar.id, &resources, props).await?;
+            for (href, name) in hrefs.iter().zip(&names) {
+                if !resources.iter().any(|r| &r.href == name) {
+                    responses.push(DavResponse::NotFound { href: href.clone() });
+                }
+            }
+            Ok(multistatus(&responses, None))
+        }
+        DavRequest::SyncCollection {
+            sync_token: token, ..
+        } => {
+            // Read first, changes made meanwhile are sent again next time rather than lost
+            let synced_at = dav::read_synced_at(&state.db, &calendar.id).await?;
+
+            let resources = match token {
+                None => dav::read_resources(&state.db, &calendar.id, None, None).await?,
+                Some(token) => {
+                    let Some(since) = parse_sync_token(token) else {
+                        return Ok((
+                            StatusCode::FORBIDDEN,
+                            [(header::CONTENT_TYPE, "application/xml; charset=utf-8")],
+                            write_dav_error(&PropName::dav("valid-sync-token")),
+                        )
+                            .into_response());
+                    };
+                    dav::read_changes(&state.db, &calendar.id, since).await?
+                }
+            };
+
+            let (deleted, live): (Vec<_>, Vec<_>) = resources.into_iter().partition(|r| r.deleted);
+            let mut responses = resource_responses(state, &calendar.id, &live, props).await?;
+            responses.extend(deleted.iter().map(|r| DavResponse::NotFound {
+                href: resource_href(&calendar.id, &r.href),
+            }));
+            Ok(multistatus(&responses, Some(&sync_token(synced_at))))
+        }
+        DavRequest::Propfind { .. } => Err(APIError::BadRequest("Not a REPORT request".into())),
+    }
+}
+
+async fn resource_responses(
+    state: &AppState,
+    calendar_id: &str,
+    resources: &[CalendarResource],
+    props: Option<&[PropName]>,
+) -> Result<Vec<DavResponse>, APIError> {
+    let mut data = if wants(props, &PropName::caldav("calendar-data")) {
+        render_resources(state, calendar_id, resources).await?
+    } else {
+        HashMap::new()
+    };
+
+    let responses = resources
+        .iter()
+        .map(|resource| {
+            let mut available = vec![
+                (
+                    PropName::dav("getetag"),
+                    text_xml(&format!("\"{}\"", resource.etag)),
+                ),
+                (
+                    PropName::dav("getcontenttype"),
+                    RESOURCE_CONTENT_TYPE.to_string(),
+                ),
+                (PropName::dav("resourcetype"), String::new()),
+            ];
+            if let Some(ics) = data.remove(&resource.uid) {
+                available.push((PropName::caldav("calendar-data"), text_xml(&ics)));
+            }
+            DavResponse::select(resource_href(calendar_id, &resource.href), available, props)
+        })
+        .collect();
+
+    Ok(responses)
+}
+
+/// Renders each resource as iCalendar, keyed by UID
+async fn render_resources(
+    state: &AppState,
+    calendar_id: &str,
+    resources: &[CalendarResource],
+) -> Result<HashMap<String, String>, APIError> {
+    let uids: Vec<String> = resources.iter().map(|r| r.uid.clone()).collect();
+    let events = dav::read_resource_events(&state.db, calendar_id, &uids).await?;
+    let event_ids: Vec<String> = events.iter().map(|e| e.id.clone()).collect();
+    let attendees = event_attendee::find_by_event_ids(&state.db, &event_ids).await?;
+
+    let rendered = events
+        .chunk_by(|a, b| a.uid == b.uid)
+        .map(|group| (group[0].uid.clone(), write_resource(group, &attendees)))
+        .collect();
+
+    Ok(rendered)
+}
+
+fn collection_props(calendar: &CalendarFull, synced_at: DateTime<Utc>) -> Vec<(PropName, String)> {
+    let token = sync_token(synced_at);
+    let mut props = vec![
+        (
+            PropName::dav("resourcetype"),
+            "<D:collection/><C:calendar/>".to_string(),
+        ),
+        (PropName::dav("displayname"), text_xml(&calendar.name)),
+        (
+            PropName::dav("owner"),
+            href_xml(&principal_href(&calendar.owner_id)),
+        ),
+        (
+            PropName::dav("current-user-principal"),
+            href_xml(&principal_href(&calendar.owner_id)),
+        ),
+        (
+            PropName::dav("current-user-privilege-set"),
+            PRIVILEGES.to_string(),
+        ),
+        (PropName::dav("supported-report-set"), REPORTS.to_string()),
+        (PropName::dav("sync-token"), text_xml(&token)),
+        (PropName::new(CALSERVER_NS, "getctag"), text_xml(&token)),
+        (
+            PropName::caldav("supported-calendar-component-set"),
+            "<C:comp name=\"VEVENT\"/>".to_string(),
+        ),
+        (
+            PropName::new(APPLE_NS, "calendar-color"),
+            text_xml(&calendar.colour),
+        ),
+    ];
+    if let Some(description) = &calendar.description {
+        props.push((
+            PropName::caldav("calendar-description"),
+            text_xml(description),
+        ));
+    }
+    props
+}
+
+fn precondition(headers: &HeaderMap) -> ResourcePrecondition {
+    let header = |name| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+    };
+
+    if header(header::IF_NONE_MATCH) == Some("*") {
+        return ResourcePrecondition::IfNoneMatch;
+    }
+    match header(header::IF_MATCH) {
+        Some(etags) => {
+            let etag = etags.split(',').next().unwrap_or_default().trim();
+            let etag = etag.trim_start_matches("W/").trim_matches('"');
+            ResourcePrecondition::IfMatch(etag.to_string())
+        }
+        None => ResourcePrecondition::None,
+    }
+}
+
+/// PROPFIND goes one level down unless told `Depth: 0`
+fn with_children(headers: &HeaderMap) -> bool {
+    headers
+        .get("Depth")
+        .and_then(|value| value.to_str().ok())
+        .is_none_or(|depth| depth.trim() != "0")
+}
+
+/// The last path segment of an href, which may be a full URL
+fn resource_name(href: &str) -> String {
+    let name = href
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or(href);
+    urlencoding::decode(name)
+        .map(|name| name.into_owned())
+        .unwrap_or_else(|_| name.to_string())
+}
+
+fn principal_href(user_id: &str) -> String {
+    format!("/dav/principals/{user_id}/")
+}
+
+fn collection_href(calendar_id: &str) -> String {
+    format!("{CALENDAR_HOME}{calendar_id}/")
+}
+
+fn resource_href(calendar_id: &str, href: &str) -> String {
+    format!(
+        "{}{}",
+        collection_href(calendar_id),
+        urlencoding::encode(href)
+    )
+}
+
+fn etag_header(etag: &str) -> Result<HeaderValue, APIError> {
+    HeaderValue::from_str(&format!("\"{etag}\""))
+        .map_err(|_| APIError::Internal("Invalid etag".into()))
+}
+
+fn multistatus(responses: &[DavResponse], sync_token: Option<&str>) -> Response {
+    (
+        StatusCode::MULTI_STATUS,
+        [(header::CONTENT_TYPE, "application/xml; charset=utf-8")],
+        write_multistatus(responses, sync_token),
+    )
+        .into_response()
+}
+
+fn options() -> Response {
+    (
+        StatusCode::OK,
+        [
+            (
+                header::HeaderName::from_static("dav"),
+                "1, 3, calendar-access",
+            ),
+            (header::ALLOW, ALLOWED_METHODS),
+        ],
+    )
+        .into_response()
+}
+
+fn not_allowed() -> Response {
+    (
+        StatusCode::METHOD_NOT_ALLOWED,
+        [(header::ALLOW, ALLOWED_METHODS)],
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_precondition_headers() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(precondition(&headers), ResourcePrecondition::None);
+
+        headers.insert(header::IF_MATCH, HeaderValue::from_static("W/\"abc\""));
+        assert_eq!(
+            precondition(&headers),
+            ResourcePrecondition::IfMatch("abc".into())
+        );
+
+        headers.insert(header::IF_NONE_MATCH, HeaderValue::from_static("*"));
+        assert_eq!(precondition(&headers), ResourcePrecondition::IfNoneMatch);
+    }
+
+    #[test]
+    fn test_resource_name() {
+        assert_eq!(resource_name("/dav/calendars/abc/a%20b.ics"), "a b.ics");
+        assert_eq!(
+            resource_name("https://ogonek.app/dav/calendars/abc/lesson.ics"),
+            "lesson.ics"
+        );
+        assert_eq!(
+            resource_href("abc", "a b.ics"),
+            "/dav/calendars/abc/a%20b.ics"
+        );
+    }
+}
@@ -5,7 +5,12 @@ use axum::{
 };
 use ogonek_db::core::{lesson, task};
 
-use crate::{AppState, Claims, api::error::APIError, openapi::FILE_TAG, services::generate_pdf};
+use crate::{
+    AppState, Claims,
+    api::error::APIError,
+    openapi::FILE_TAG,
+    services::{generate_pdf, sanitize_filename},
+};
 use ogonek_types::{PDFQuery, PDFType};
 
 /// Generates or retrieves a PDF for the specified resource
@@ -76,19 +81,3 @@ pub async fn get_pdf(
         "url": presigned_url
     })))
 }
-
-/// Sanitizes a filename by replacing invalid characters
-///
-/// Converts special characters to underscores for safe file naming.
-fn sanitize_filename(title: &str) -> String {
-    title
-        .chars()
-        .map(|c| match c {
-            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' => c,
-            ' ' => '_',
-            _ => '_',
-        })
-        .collect::<String>()
-        .trim_matches('_')
-        .to_string()
-}
@@ -2,6 +2,7 @@ pub mod account;
 pub mod admin;
 pub mod content;
 pub mod core;
+pub mod dav;
 mod debug;
 pub mod files;
 pub mod notifications;
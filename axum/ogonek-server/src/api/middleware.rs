@@ -1,7 +1,18 @@
-use axum::{extract::Request, middleware::Next, response::Response};
+use axum::{
+    Extension,
+    body::{Body, to_bytes},
+    extract::{Request, State},
+    http::header::AUTHORIZATION,
+    middleware::Next,
+    response::Response,
+};
+use ogonek_db::RedisClient;
 use reqwest::StatusCode;
 
-use crate::services::Claims;
+use crate::{
+    AppError,
+    services::{Claims, RequestMetadata, decode_token},
+};
 pub async fn require_elevated_role(
     claims: Claims, // Custom extractors come after Request
     req: Request,   // Request should be first
@@ -13,3 +24,90 @@ pub async fn require_elevated_role(
     }
     Ok(next.run(req).await)
 }
+
+/// Credentials fit in far less, bigger bodies on limited routes are refused
+const ACCOUNT_BODY_LIMIT: usize = 16 * 1024;
+
+/// Sliding-window limits for a group of routes, layered with `rate_limit`
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit {
+    /// Keeps the counters of different groups apart
+    pub group: &'static str,
+    pub window_secs: u64,
+    /// Requests per window from one IP
+    pub per_ip: u32,
+    /// Requests per window for one account, whatever the IP. The account is the
+    /// bearer token's user, or the `username` or `email` in a JSON body.
+    pub per_account: Option<u32>,
+}
+
+impl RateLimit {
+    /// The windows a request counts against, with their limits
+    pub fn keys(&self, metadata: &RequestMetadata, account: Option<&str>) -> Vec<(String, u32)> {
+        let ip = metadata
+            .ip_address
+            .map_or_else(|| "unknown".to_string(), |ip| ip.ip().to_string());
+        let mut keys = vec![(format!("{}:ip:{}", self.group, ip), self.per_ip)];
+        if let (Some(per_account), Some(account)) = (self.per_account, account) {
+            keys.push((format!("{}:account:{}", self.group, account), per_account));
+        }
+        keys
+    }
+}
+
+/// Turns requests past the group's limits away with 429 and `Retry-After`. Lets them
+/// through when Redis is unreachable rather than locking everyone out.
+pub async fn rate_limit(
+    State(limit): State<RateLimit>,
+    Extension(mut redis): Extension<RedisClient>,
+    metadata: RequestMetadata,
+    req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let (account, req) = match limit.per_account {
+        Some(_) => read_account(req).await?,
+        None => (None, req),
+    };
+
+    for (key, max) in limit.keys(&metadata, account.as_deref()) {
+        match redis.hit_rate_limit(&key, max, limit.window_secs).await {
+            Ok(Some(retry_after)) => {
+                tracing::warn!("Rate limit hit for {key}, retry in {retry_after}s");
+                return Err(AppError::TooManyRequests { retry_after });
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Rate limiting skipped, Redis failed: {e}"),
+        }
+    }
+
+    Ok(next.run(req).await)
+}
+
+/// Finds who the request acts for, putting the body back for the handler
+async fn read_account(req: Request) -> Result<(Option<String>, Request), AppError> {
+    let bearer_user = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix("Bearer "))
+        .and_then(|token| decode_token(token).ok())
+        .map(|claims| claims.sub);
+    if bearer_user.is_some() {
+        return Ok((bearer_user, req));
+    }
+
+    let (parts, body) = req.into_parts();
+    let bytes = to_bytes(body, ACCOUNT_BODY_LIMIT)
+        .await
+        .map_err(|_| AppError::BadRequest("Request body too large".into()))?;
+    let account = serde_json::from_slice::<serde_json::Value>(&bytes)
+        .ok()
+        .and_then(|json| {
+            ["username", "email"]
+                .iter()
+                .find_map(|field| json.get(field)?.as_str().map(str::to_lowercase))
+        })
+        .filter(|account| !account.is_empty());
+
+    Ok((account, Request::from_parts(parts, Body::from(bytes))))
+}
@@ -1,16 +1,89 @@
 use crate::{
     AppState,
-    api::account::{self, resend_verification},
+    api::{
+        account::{self, resend_verification},
+        middleware::{RateLimit, rate_limit},
+    },
+};
+use axum::{
+    Router,
+    middleware::from_fn_with_state,
+    routing::{delete, get, post},
+};
+
+/// Password and code guessing, per IP and per account across IPs. Repeated wrong
+/// passwords also lock the account out, see `verify_signin_password`. CalDAV counts
+/// its failed sign ins against the same windows.
+pub(crate) const CREDENTIALS_LIMIT: RateLimit = RateLimit {
+    group: "credentials",
+    window_secs: 60 * 5,
+    per_ip: 20,
+    per_account: Some(10),
+};
+/// Every request sends an email
+const EMAIL_LIMIT: RateLimit = RateLimit {
+    group: "email",
+    window_secs: 60 * 60,
+    per_ip: 10,
+    per_account: Some(3),
+};
+/// Guessing emailed and invite tokens
+const TOKEN_LIMIT: RateLimit = RateLimit {
+    group: "token",
+    window_secs: 60 * 15,
+    per_ip: 30,
+    per_account: None,
 };
-use axum::{Router, routing::post};
 
 pub fn auth_routes() -> Router<AppState> {
     Router::new()
-        .route("/signup", post(account::signup))
-        .route("/signin", post(account::signin))
+        .merge(credential_routes())
+        .merge(email_routes())
+        .merge(token_routes())
         .route("/refresh", post(account::refresh))
+        .route("/2fa", get(account::two_factor_status))
+        .route("/2fa/setup", post(account::setup_two_factor))
+        .route("/2fa/confirm", post(account::confirm_two_factor))
+        .route(
+            "/2fa/recovery_codes",
+            post(account::regenerate_recovery_codes),
+        )
+        .route("/2fa/disable", post(account::disable_two_factor))
         .route("/invite", post(account::generate_invite_link))
-        .route("/bind", post(account::bind_student_to_teacher))
-        .route("/confirm_email", post(account::confirm_email))
+        .route(
+            "/invites",
+            post(account::create_invite).get(account::list_invites),
+        )
+        .route("/invites/{id}", delete(account::revoke_invite))
+        .route(
+            "/invites/{id}/redemptions",
+            get(account::list_invite_redemptions),
+        )
+        .route("/sessions", get(account::list_sessions))
+        .route("/sessions/{id}", delete(account::revoke_session))
+        .route("/logout", post(account::logout))
+        .route("/logout_all", post(account::logout_all))
+}
+
+fn credential_routes() -> Router<AppState> {
+    Router::new()
+        .route("/signup", post(account::signup))
+        .route("/signin", post(account::signin))
+        .route("/2fa/verify", post(account::verify_two_factor))
+        .route_layer(from_fn_with_state(CREDENTIALS_LIMIT, rate_limit))
+}
+
+fn email_routes() -> Router<AppState> {
+    Router::new()
         .route("/resend_email", post(resend_verification))
+        .route("/forgot_password", post(account::forgot_password))
+        .route_layer(from_fn_with_state(EMAIL_LIMIT, rate_limit))
+}
+
+fn token_routes() -> Router<AppState> {
+    Router::new()
+        .route("/confirm_email", post(account::confirm_email))
+        .route("/reset_password", post(account::reset_password))
+        .route("/bind", post(account::bind_student_to_teacher))
+        .route_layer(from_fn_with_state(TOKEN_LIMIT, rate_limit))
 }
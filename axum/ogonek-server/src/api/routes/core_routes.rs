@@ -48,6 +48,10 @@ pub fn deck_routes() -> Router<AppState> {
                 .post(learn::reset_deck_progress),
         )
         .route("/{id}/duplicate", post(deck::duplicate_deck))
+        .route("/{id}/export", get(deck::export_deck))
+        .route("/{id}/search", get(deck::search_deck_cards))
+        .route("/search", get(deck::search_cards))
+        .route("/import", post(deck::import_deck))
         .route("/public", get(deck::list_decks_public))
         .route("/many", delete(core::delete_deck_many))
 }
@@ -58,6 +62,16 @@ pub fn learn_routes() -> Router<AppState> {
             post(learn::subscribe_to_deck).delete(learn::unsubscribe_from_deck),
         )
         .route("/{id}", put(learn::update_card_progress))
+        .route("/reviews", get(learn::list_reviews))
+        .route("/reviews/{id}", get(learn::list_deck_reviews))
+        .route("/stats", get(learn::fetch_stats))
+        .route("/stats/{id}", get(learn::fetch_deck_stats))
+        .route("/sessions", post(learn::create_study_session))
+        .route("/sessions/{id}", get(learn::fetch_study_session))
+        .route("/sessions/{id}/next", get(learn::fetch_next_session_card))
+        .route("/sessions/{id}/answer", post(learn::answer_session_card))
+        .route("/sessions/{id}/undo", post(learn::undo_session_answer))
+        .route("/sessions/{id}/finish", post(learn::finish_study_session))
         .route("/", get(learn::fetch_due_cards))
 }
 
@@ -66,16 +80,32 @@ pub fn state_routes() -> Router<AppState> {
         .route("/dashboard", get(state::fetch_dashboard))
         .route("/badges", get(state::fetch_badges))
         .route("/context", get(state::fetch_context))
+        .route("/heatmap", get(state::fetch_heatmap))
 }
 
 pub fn calendar_routes() -> Router<AppState> {
     Router::new()
+        .route("/", get(core::fetch_calendar).post(core::create_calendar))
+        .route("/all", get(core::list_calendars))
         .route(
-            "/",
-            get(core::fetch_calendar)
-                .patch(core::update_calendar)
-                .delete(core::delete_calendar),
+            "/{id}",
+            patch(core::update_calendar).delete(core::delete_calendar),
+        )
+        .route("/{id}/default", post(core::set_default_calendar))
+        .route(
+            "/{id}/shares",
+            get(core::list_calendar_shares).put(core::upsert_calendar_share),
+        )
+        .route(
+            "/{id}/shares/{user_id}",
+            delete(core::delete_calendar_share),
+        )
+        .route(
+            "/feed",
+            get(core::fetch_calendar_feed).delete(core::revoke_calendar_feed),
         )
+        .route("/import", post(core::import_calendar))
+        .route("/freebusy", get(core::fetch_freebusy))
         .route("/events", post(core::create_event))
         .route("/events", get(list_events))
         .route(
@@ -84,8 +114,50 @@ pub fn calendar_routes() -> Router<AppState> {
                 .patch(core::update_event)
                 .delete(core::delete_event),
         )
+        .route(
+            "/events/{id}/alarms",
+            get(core::list_alarms).post(core::create_alarm),
+        )
+        .route("/events/{id}/proposals", post(core::create_proposal))
+        .route(
+            "/events/{id}/attendance",
+            get(core::list_attendance).put(core::upsert_attendance),
+        )
         .route(
             "/attendees/{id}",
             patch(core::update_attendee).delete(core::delete_attendee),
         )
+        .route(
+            "/alarms/{id}",
+            patch(core::update_alarm).delete(core::delete_alarm),
+        )
+        .route("/proposals", get(core::list_proposals))
+        .route("/proposals/{id}/accept", post(core::accept_proposal))
+        .route("/proposals/{id}/decline", post(core::decline_proposal))
+        .route("/attendance/{id}", delete(core::delete_attendance))
+        .route(
+            "/attendance/students/{id}",
+            get(core::fetch_student_attendance),
+        )
+        .route("/attendance/monthly", get(core::fetch_monthly_attendance))
+        .route("/availability", get(core::fetch_availability))
+        .route(
+            "/availability/settings",
+            put(core::update_availability_settings),
+        )
+        .route("/availability/rules", post(core::create_availability_rule))
+        .route(
+            "/availability/rules/{id}",
+            delete(core::delete_availability_rule),
+        )
+        .route(
+            "/availability/exceptions",
+            post(core::create_availability_exception),
+        )
+        .route(
+            "/availability/exceptions/{id}",
+            delete(core::delete_availability_exception),
+        )
+        .route("/availability/{teacher_id}/slots", get(core::list_slots))
+        .route("/availability/{teacher_id}/bookings", post(core::book_slot))
 }
@@ -0,0 +1,27 @@
+use crate::{
+    AppState,
+    api::dav::{
+        dav_calendar, dav_calendar_home, dav_calendar_resource, dav_principal, dav_root,
+        dav_well_known,
+    },
+};
+use axum::{Router, routing::any};
+
+/// CalDAV lives outside of the JSON API, where clients expect it.
+/// Collections are registered with and without the trailing slash.
+pub fn dav_routes() -> Router<AppState> {
+    Router::new()
+        .route("/.well-known/caldav", any(dav_well_known))
+        .route("/dav", any(dav_root))
+        .route("/dav/", any(dav_root))
+        .route("/dav/principals/{user_id}", any(dav_principal))
+        .route("/dav/principals/{user_id}/", any(dav_principal))
+        .route("/dav/calendars", any(dav_calendar_home))
+        .route("/dav/calendars/", any(dav_calendar_home))
+        .route("/dav/calendars/{calendar_id}", any(dav_calendar))
+        .route("/dav/calendars/{calendar_id}/", any(dav_calendar))
+        .route(
+            "/dav/calendars/{calendar_id}/{href}",
+            any(dav_calendar_resource),
+        )
+}
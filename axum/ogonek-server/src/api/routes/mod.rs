@@ -1,6 +1,7 @@
 mod admin_routes;
 mod auth_routes;
 mod core_routes;
+mod dav_routes;
 mod debug_routes;
 mod file_routes;
 mod notification_routes;
@@ -11,6 +12,7 @@ pub use admin_routes::*;
 pub use auth_routes::*;
 use axum::http::{Method, StatusCode};
 pub use core_routes::*;
+pub use dav_routes::dav_routes;
 pub use file_routes::file_routes;
 pub use notification_routes::notification_routes;
 pub use public_routes::*;
@@ -23,7 +25,7 @@ use tower_http::{
 pub use user_routes::user_routes;
 
 use axum::{
-    Router,
+    Extension, Router,
     http::{HeaderName, HeaderValue},
     response::IntoResponse,
     routing::get,
@@ -56,12 +58,16 @@ fn router() -> Router<AppState> {
 }
 
 pub fn root(state: AppState, cors: String) -> Result<Router, anyhow::Error> {
+    // For the rate limits layered on route groups
+    let redis = state.redis.clone();
     let router = Router::new()
         .nest("/api/v1", router())
         .merge(public_routes())
+        .merge(dav_routes())
         .route("/health", get(health_check))
         .fallback(handler_404)
         .with_state(state)
+        .layer(Extension(redis))
         .layer(SetRequestIdLayer::new(
             HeaderName::from_static(REQUEST_ID_HEADER),
             MakeRequestUuid,
@@ -88,7 +94,9 @@ pub fn root(state: AppState, cors: String) -> Result<Router, anyhow::Error> {
                     axum::http::header::CONTENT_TYPE,
                     axum::http::header::AUTHORIZATION,
                     HeaderName::from_static("x-api-key"),
-                ]),
+                ])
+                // Rate-limited clients need to see when to come back
+                .expose_headers([axum::http::header::RETRY_AFTER]),
         )
         .layer(sentry_tower::SentryHttpLayer::new().enable_transaction());
 
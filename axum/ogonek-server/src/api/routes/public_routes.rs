@@ -2,7 +2,7 @@ use crate::{
     AppState,
     api::{
         content,
-        core::{fetch_task_public, toggle_task_public},
+        core::{fetch_calendar_feed_public, fetch_task_public, toggle_task_public},
     },
 };
 use axum::{Router, routing::get};
@@ -11,4 +11,5 @@ pub fn content_routes() -> Router<AppState> {
     Router::new()
         .route("/content/{slug}", get(content::fetch_content_public))
         .route("/task/{id}", get(fetch_task_public).put(toggle_task_public))
+        .route("/calendar/{token}", get(fetch_calendar_feed_public))
 }
@@ -10,6 +10,8 @@ pub struct AppState {
     pub notification_service: NotificationService,
     pub ses: SESProvider,
     pub redis: RedisClient,
+    /// Public address of this API, for links that point back to it like calendar feeds
+    pub api_url: String,
 }
 
 impl AppState {
@@ -20,6 +22,10 @@ impl AppState {
         let redis = RedisClient::new().await?;
 
         let notification_service = NotificationService::new(db.clone())?;
+        let api_url = std::env::var("API_URL")
+            .map_err(|_| anyhow::anyhow!("API_URL needs to be set"))?
+            .trim_end_matches('/')
+            .to_string();
 
         Ok(Self {
             db: db.clone(),
@@ -27,6 +33,7 @@ impl AppState {
             notification_service,
             ses,
             redis,
+            api_url,
         })
     }
 }
@@ -1,16 +1,27 @@
-use crate::{api::routes::root, app::AppState, services::init_tracing};
+use std::net::SocketAddr;
+
+use crate::{
+    api::routes::root,
+    app::AppState,
+    services::{event_alarms::alarm_scheduler, init_tracing},
+};
 
 pub async fn server() -> anyhow::Result<()> {
     init_tracing().await?;
     let state = AppState::new().await?;
+    tokio::spawn(alarm_scheduler(state.clone()));
     let cors = std::env::var("CORS").expect("CORS needs to be set");
     let app = root(state, cors)?;
     let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await?;
     tracing::info!("🚀 Server starting on http://0.0.0.0:3000");
 
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal())
-        .await?;
+    // The peer address tells trusted proxies apart from clients
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown_signal())
+    .await?;
     Ok(())
 }
 
@@ -1,6 +1,6 @@
 use axum::{
     extract::multipart::MultipartError,
-    http::StatusCode,
+    http::{StatusCode, header::RETRY_AFTER},
     response::{IntoResponse, Response},
 };
 use thiserror::Error;
@@ -16,6 +16,12 @@ pub enum AppError {
     #[error("Resource already exists: {0}")]
     AlreadyExists(String),
 
+    #[error("Precondition failed: {0}")]
+    PreconditionFailed(String),
+
+    #[error("Conflicts with {} events", .0.len())]
+    Conflict(Vec<ogonek_types::BusyPeriod>),
+
     // Validation errors
     #[error("Validation error: {0}")]
     Validation(String),
@@ -31,6 +37,9 @@ pub enum AppError {
     #[error("Bad Request: {0}")]
     BadRequest(String),
 
+    #[error("Too many requests, retry in {retry_after} seconds")]
+    TooManyRequests { retry_after: u64 },
+
     #[error("Notification failed: {0}")]
     NotificationFailed(#[from] ogonek_notifications::NotificationError),
 
@@ -49,11 +58,25 @@ impl IntoResponse for AppError {
             // Resource errors -> 404/409
             Self::NotFound(_resource) => (StatusCode::NOT_FOUND, self.to_string()),
             Self::AlreadyExists(_resource) => (StatusCode::CONFLICT, self.to_string()),
+            Self::PreconditionFailed(_) => (StatusCode::PRECONDITION_FAILED, self.to_string()),
+            // The client shows the clashing events and may retry with the override
+            Self::Conflict(conflicts) => {
+                return (StatusCode::CONFLICT, axum::Json(conflicts)).into_response();
+            }
 
             // Validation errors -> 400
             Self::Validation(_) => (StatusCode::BAD_REQUEST, self.to_string()),
             Self::BadRequest(e) => (StatusCode::BAD_REQUEST, e.to_string()),
 
+            Self::TooManyRequests { retry_after } => {
+                return (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    [(RETRY_AFTER, retry_after.to_string())],
+                    self.to_string(),
+                )
+                    .into_response();
+            }
+
             Self::NotificationFailed(notification_err) => {
                 let status_code = match notification_err.error_code() {
                     4001..=4999 => StatusCode::BAD_REQUEST,
@@ -127,6 +150,8 @@ impl From<DbError> for AppError {
             DbError::AlreadyExists(msg) => {
                 Self::AlreadyExists(format!("Resource already exists: {msg}"))
             }
+            DbError::PreconditionFailed(msg) => Self::PreconditionFailed(msg),
+            DbError::Conflict(conflicts) => Self::Conflict(conflicts),
             DbError::NotRecurring => Self::Validation("Event is not recurring".into()),
             DbError::InvalidRecurrenceId => Self::Validation("Invalid recurrence ID".into()),
             DbError::InvalidRRule(rrule) => {
@@ -7,21 +7,52 @@ use utoipa::OpenApi;
         account::signup,
         account::signin,
         account::refresh,
+        account::two_factor_status,
+        account::setup_two_factor,
+        account::confirm_two_factor,
+        account::verify_two_factor,
+        account::regenerate_recovery_codes,
+        account::disable_two_factor,
         account::bind_student_to_teacher,
         account::generate_invite_link,
+        account::create_invite,
+        account::list_invites,
+        account::revoke_invite,
+        account::list_invite_redemptions,
         account::resend_verification,
-        account::confirm_email
+        account::confirm_email,
+        account::forgot_password,
+        account::reset_password,
+        account::list_sessions,
+        account::revoke_session,
+        account::logout,
+        account::logout_all
     ),
     components(schemas(
         ogonek_types::SignUpPayload,
         ogonek_types::AuthPayload,
         ogonek_types::TokenPair,
+        ogonek_types::SignInResponse,
+        ogonek_types::TwoFactorChallenge,
+        ogonek_types::TwoFactorVerifyPayload,
+        ogonek_types::TotpSetup,
+        ogonek_types::TotpCodePayload,
+        ogonek_types::TwoFactorEnabled,
+        ogonek_types::RecoveryCodes,
+        ogonek_types::TwoFactorStatus,
         ogonek_types::RefreshTokenPayload,
         ogonek_types::RefreshTokenResponse,
         ogonek_types::BindPayload,
         ogonek_types::InviteQuery,
         ogonek_types::InviterQuery,
+        ogonek_types::Invite,
+        ogonek_types::InviteCreate,
+        ogonek_types::InviteLinks,
+        ogonek_types::InviteRedemption,
         ogonek_types::EmailVerificationQuery,
+        ogonek_types::ForgotPasswordPayload,
+        ogonek_types::PasswordResetPayload,
+        ogonek_types::RefreshSession,
     ))
 )]
 pub struct AuthApi;
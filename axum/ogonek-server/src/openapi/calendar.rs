@@ -1,26 +1,89 @@
-use crate::api::handlers::core::{calendar::*, event::*, event_attendee::*};
+use crate::api::handlers::core::{
+    attendance::*, availability::*, calendar::*, calendar_share::*, event::*, event_alarm::*,
+    event_attendee::*, event_proposal::*, freebusy::*,
+};
 use utoipa::OpenApi;
 
 #[derive(OpenApi)]
 #[openapi(
     paths(
         fetch_calendar,
+        list_calendars,
+        create_calendar,
+        set_default_calendar,
         delete_calendar,
         update_calendar,
+        list_calendar_shares,
+        upsert_calendar_share,
+        delete_calendar_share,
+        fetch_calendar_feed,
+        revoke_calendar_feed,
+        import_calendar,
         fetch_event,
         list_events,
+        fetch_freebusy,
         create_event,
         delete_event,
         update_event,
         delete_attendee,
         update_attendee,
+        list_alarms,
+        create_alarm,
+        update_alarm,
+        delete_alarm,
+        create_proposal,
+        list_proposals,
+        accept_proposal,
+        decline_proposal,
+        upsert_attendance,
+        list_attendance,
+        delete_attendance,
+        fetch_student_attendance,
+        fetch_monthly_attendance,
+        fetch_availability,
+        update_availability_settings,
+        create_availability_rule,
+        delete_availability_rule,
+        create_availability_exception,
+        delete_availability_exception,
+        list_slots,
+        book_slot,
     ),
     components(schemas(
         ogonek_types::CalendarQuery,
         ogonek_types::EditScope,
         ogonek_types::DeleteScope,
         ogonek_types::EventAttendeeRole,
-        ogonek_types::EventAttendeeStatus
+        ogonek_types::EventAttendeeStatus,
+        ogonek_types::CalendarFeed,
+        ogonek_types::CalendarCreate,
+        ogonek_types::CalendarSmall,
+        ogonek_types::CalendarPermission,
+        ogonek_types::CalendarShare,
+        ogonek_types::CalendarShareUpsert,
+        ogonek_types::EventImportSummary,
+        ogonek_types::EventAlarm,
+        ogonek_types::EventAlarmAction,
+        ogonek_types::EventAlarmCreate,
+        ogonek_types::EventAlarmUpdate,
+        ogonek_types::EventProposal,
+        ogonek_types::EventProposalCreate,
+        ogonek_types::ProposalStatus,
+        ogonek_types::Availability,
+        ogonek_types::AvailabilitySettings,
+        ogonek_types::AvailabilityRule,
+        ogonek_types::AvailabilityRuleCreate,
+        ogonek_types::AvailabilityException,
+        ogonek_types::AvailabilityExceptionCreate,
+        ogonek_types::BookableSlot,
+        ogonek_types::BookingCreate,
+        ogonek_types::BusyPeriod,
+        ogonek_types::AttendanceStatus,
+        ogonek_types::AttendanceTotals,
+        ogonek_types::EventAttendance,
+        ogonek_types::EventAttendanceUpsert,
+        ogonek_types::StudentAttendanceReport,
+        ogonek_types::MonthlyAttendance
     ))
 )]
 pub struct CalendarApi;
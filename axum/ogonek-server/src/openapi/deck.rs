@@ -11,6 +11,10 @@ use utoipa::OpenApi;
         deck::update_deck,
         deck::delete_deck,
         deck::duplicate_deck,
+        deck::import_deck,
+        deck::export_deck,
+        deck::search_cards,
+        deck::search_deck_cards,
         deck::delete_deck_many,
     ),
     components(schemas(
@@ -18,6 +22,10 @@ use utoipa::OpenApi;
         ogonek_types::DeckFull,
         ogonek_types::DeckUpdate,
         ogonek_types::DeckPublic,
+        ogonek_types::SchedulerKind,
+        ogonek_types::DeckExportFormat,
+        ogonek_types::CardSearchResult,
+        ogonek_types::PaginatedCards,
     ))
 )]
 pub struct DeckApi;
@@ -9,7 +9,31 @@ use utoipa::OpenApi;
         learn::reset_deck_progress,
         learn::subscribe_to_deck,
         learn::unsubscribe_from_deck,
+        learn::list_reviews,
+        learn::list_deck_reviews,
+        learn::fetch_stats,
+        learn::fetch_deck_stats,
+        learn::create_study_session,
+        learn::fetch_study_session,
+        learn::fetch_next_session_card,
+        learn::answer_session_card,
+        learn::undo_session_answer,
+        learn::finish_study_session,
     ),
-    components(schemas(ogonek_types::CardProgressWithFields, ogonek_types::UpdateCardProgress,))
+    components(schemas(
+        ogonek_types::CardProgressWithFields,
+        ogonek_types::UpdateCardProgress,
+        ogonek_types::CardState,
+        ogonek_types::CardType,
+        ogonek_types::ReviewOutcome,
+        ogonek_types::CardReview,
+        ogonek_types::LearnStats,
+        ogonek_types::CardCounts,
+        ogonek_types::DailyReviews,
+        ogonek_types::DueForecast,
+        ogonek_types::StudySession,
+        ogonek_types::StudySessionCreate,
+        ogonek_types::StudySessionSummary,
+    ))
 )]
 pub struct LearnApi;
@@ -1,10 +1,17 @@
-use crate::api::{content::*, core::task};
+use crate::api::{
+    content::*,
+    core::{calendar, task},
+};
 
 use utoipa::OpenApi;
 
 #[derive(OpenApi)]
 #[openapi(
-    paths(fetch_content_public, task::fetch_task_public),
+    paths(
+        fetch_content_public,
+        task::fetch_task_public,
+        calendar::fetch_calendar_feed_public
+    ),
     components(schemas(ogonek_types::TaskPublic,))
 )]
 pub struct ContentApi;
@@ -2,5 +2,12 @@ use crate::api::core::state::*;
 use utoipa::OpenApi;
 
 #[derive(OpenApi)]
-#[openapi(paths(fetch_dashboard, fetch_context, fetch_badges))]
+#[openapi(
+    paths(fetch_dashboard, fetch_context, fetch_badges, fetch_heatmap),
+    components(schemas(
+        ogonek_types::HeatmapDay,
+        ogonek_types::StudyStreak,
+        ogonek_types::DailyGoals,
+    ))
+)]
 pub struct StateApi;
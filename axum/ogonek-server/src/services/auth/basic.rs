@@ -0,0 +1,112 @@
+use axum::{
+    RequestPartsExt,
+    extract::FromRequestParts,
+    http::{StatusCode, header, request::Parts},
+    response::{IntoResponse, Response},
+};
+use axum_extra::{
+    TypedHeader,
+    headers::{Authorization, authorization::Basic},
+};
+use ogonek_db::core::account::{auth, two_factor};
+
+use crate::{
+    AppError, AppState,
+    api::routes::CREDENTIALS_LIMIT,
+    services::{AuthError, RequestMetadata, two_factor_required, verify_signin_password},
+};
+
+/// A user signed in with HTTP Basic credentials.
+/// Calendar clients cannot follow the token flow, so CalDAV uses username and password.
+/// A password alone isn't enough for accounts with 2FA, so they can't use CalDAV.
+/// Failed attempts are throttled and locked out like on `/auth/signin`.
+#[derive(Debug, Clone)]
+pub struct DavUser {
+    pub id: String,
+}
+
+impl FromRequestParts<AppState> for DavUser {
+    type Rejection = Response;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let TypedHeader(Authorization(basic)) = parts
+            .extract::<TypedHeader<Authorization<Basic>>>()
+            .await
+            .map_err(|_| unauthorized())?;
+
+        let metadata = RequestMetadata::from_request_parts(parts, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        let mut state = state.clone();
+
+        // Only failures count, calendar clients sync often with good credentials
+        let username = basic.username().to_lowercase();
+        let windows = CREDENTIALS_LIMIT.keys(&metadata, Some(&username));
+        for (key, max) in &windows {
+            match state
+                .redis
+                .peek_rate_limit(key, *max, CREDENTIALS_LIMIT.window_secs)
+                .await
+            {
+                Ok(Some(retry_after)) => {
+                    return Err(AppError::TooManyRequests { retry_after }.into_response());
+                }
+                Ok(None) => {}
+                Err(e) => tracing::warn!("Rate limiting skipped, Redis failed: {e}"),
+            }
+        }
+
+        let user = auth::read_by_username(&state.db, basic.username()).await;
+        let verified = match &user {
+            Ok(user) => verify_signin_password(&mut state, user, basic.password(), &metadata).await,
+            Err(_) => Err(AppError::AuthError(AuthError::AuthenticationFailed)),
+        };
+        if let Err(e) = verified {
+            for (key, max) in &windows {
+                if let Err(e) = state
+                    .redis
+                    .hit_rate_limit(key, *max, CREDENTIALS_LIMIT.window_secs)
+                    .await
+                {
+                    tracing::warn!("Failed CalDAV sign in not counted, Redis failed: {e}");
+                }
+            }
+            return Err(match e {
+                AppError::TooManyRequests { .. } => e.into_response(),
+                _ => unauthorized(),
+            });
+        }
+        let Ok(user) = user else {
+            return Err(unauthorized());
+        };
+
+        let two_factor = two_factor_required(&user.role)
+            || two_factor::is_enabled(&state.db, &user.id)
+                .await
+                .map_err(|_| unauthorized())?;
+        if two_factor {
+            return Err((
+                StatusCode::FORBIDDEN,
+                "CalDAV can't be used with two-factor authentication",
+            )
+                .into_response());
+        }
+
+        Ok(Self { id: user.id })
+    }
+}
+
+fn unauthorized() -> Response {
+    (
+        StatusCode::UNAUTHORIZED,
+        [(
+            header::WWW_AUTHENTICATE,
+            "Basic realm=\"Ogonek\", charset=\"UTF-8\"",
+        )],
+        "Authentication required",
+    )
+        .into_response()
+}
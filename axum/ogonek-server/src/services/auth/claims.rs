@@ -63,4 +63,7 @@ pub struct Claims {
     pub role: UserRole,
     pub exp: usize,
     pub iat: usize,
+    /// The refresh session the token was issued for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
 }
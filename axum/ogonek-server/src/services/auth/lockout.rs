@@ -0,0 +1,66 @@
+use ogonek_db::{LockoutPolicy, core::account::user, tracking::audit};
+use ogonek_types::UserForClaims;
+
+use crate::{
+    AppError, AppState,
+    services::{AuditBuilder, AuthError, RequestMetadata, verify_password},
+};
+
+/// Five wrong passwords in a row lock the account for a minute, doubling with every
+/// further one up to a day
+const SIGNIN_LOCKOUT: LockoutPolicy = LockoutPolicy {
+    threshold: 5,
+    base_secs: 60,
+    max_secs: 60 * 60 * 24,
+    memory_secs: 60 * 60 * 24,
+};
+
+/// Checks the password of a user signing in, on the web or over CalDAV. Locked out
+/// users are refused before the password is looked at, wrong passwords count towards
+/// a lockout and the lockout is audited.
+pub async fn verify_signin_password(
+    state: &mut AppState,
+    user: &UserForClaims,
+    password: &str,
+    metadata: &RequestMetadata,
+) -> Result<(), AppError> {
+    let lockout_subject = format!("signin:{}", user.id);
+    match state.redis.lockout_remaining(&lockout_subject).await {
+        Ok(Some(retry_after)) => return Err(AppError::TooManyRequests { retry_after }),
+        Ok(None) => {}
+        Err(e) => tracing::warn!("Lockout check skipped, Redis failed: {e}"),
+    }
+
+    if verify_password(&user.pass, password)? {
+        if let Err(e) = state.redis.clear_failures(&lockout_subject).await {
+            tracing::warn!("Failed sign ins not cleared, Redis failed: {e}");
+        }
+        return Ok(());
+    }
+
+    let lockout = state
+        .redis
+        .record_failure(&lockout_subject, &SIGNIN_LOCKOUT)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::warn!("Failed sign in not counted, Redis failed: {e}");
+            None
+        });
+    let Some(lockout_secs) = lockout else {
+        return Err(AppError::AuthError(AuthError::AuthenticationFailed));
+    };
+
+    let email = user::read_email(&state.db, &user.id).await?;
+    let lockout_audit = AuditBuilder::session_operation("LOCKOUT", &user.id, &user.role, email)
+        .failed()
+        .security_event()
+        .severity("warning")
+        .with_metadata(metadata)
+        .payload(serde_json::json!({ "lockout_secs": lockout_secs }))
+        .build();
+    audit::create(&state.db, &lockout_audit).await?;
+
+    Err(AppError::TooManyRequests {
+        retry_after: lockout_secs,
+    })
+}
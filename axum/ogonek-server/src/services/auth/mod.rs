@@ -1,9 +1,15 @@
+mod basic;
 mod claims;
 mod error;
+mod lockout;
 mod password;
 mod tokens;
+mod two_factor;
 
+pub use basic::DavUser;
 pub use claims::{Claims, KEYS};
 pub use error::{AuthError, PasswordHashError};
+pub use lockout::verify_signin_password;
 pub use password::*;
 pub use tokens::*;
+pub use two_factor::*;
@@ -1,7 +1,8 @@
 use crate::services::{Claims, KEYS};
 
 use crate::services::AuthError;
-use base64::{Engine as _, engine::general_purpose::URL_SAFE, prelude::BASE64_URL_SAFE_NO_PAD};
+use base64::{Engine as _, prelude::BASE64_URL_SAFE_NO_PAD};
+use chrono::{DateTime, Utc};
 use jsonwebtoken::{Algorithm, Header, Validation, decode, encode};
 use ogonek_types::{InviteToken, TokenWithExpiry, UserRole};
 use std::time::{SystemTime, UNIX_EPOCH};
@@ -10,6 +11,16 @@ pub fn generate_token(
     user_id: &str,
     user_role: &UserRole,
     secs: u64,
+) -> Result<TokenWithExpiry, AuthError> {
+    generate_session_token(user_id, user_role, secs, None)
+}
+
+/// Like `generate_token`, tied to the refresh session the token was issued for
+pub fn generate_session_token(
+    user_id: &str,
+    user_role: &UserRole,
+    secs: u64,
+    session_id: Option<&str>,
 ) -> Result<TokenWithExpiry, AuthError> {
     let now = SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -23,6 +34,7 @@ pub fn generate_token(
         role: user_role.clone(),
         exp: exp as usize,
         iat: now as usize,
+        sid: session_id.map(str::to_string),
     };
 
     let token = encode(&Header::new(Algorithm::RS256), &claims, &KEYS.encoding).map_err(|e| {
@@ -47,28 +59,33 @@ pub fn decode_token(token: &str) -> Result<Claims, AuthError> {
     Ok(token_data.claims)
 }
 
+/// Reads the invite ID out of a signed invite token. Whether the invite can still be
+/// used is up to its record in the database.
 pub async fn decode_invite_token(token: String) -> Result<String, AuthError> {
-    let decoded = URL_SAFE
-        .decode(token)
-        .map_err(|_| AuthError::InvalidToken)?;
-
-    let decoded_str = String::from_utf8(decoded).map_err(|_| AuthError::InvalidToken)?;
+    let validation = Validation::new(Algorithm::RS256);
 
-    let token: InviteToken =
-        serde_json::from_str(&decoded_str).map_err(|_| AuthError::InvalidToken)?;
+    let token_data = decode::<InviteToken>(&token, &KEYS.decoding, &validation)
+        .map_err(|_| AuthError::InvalidToken)?;
 
-    Ok(token.teacher_id)
+    Ok(token_data.claims.invite_id)
 }
 
-pub async fn encode_invite_token(id: String) -> Result<String, AuthError> {
-    let token = InviteToken::new(id);
-    let json = serde_json::to_string(&token).map_err(|_| AuthError::TokenCreation)?;
+/// Signs a token naming the invite, valid until the invite expires
+pub async fn encode_invite_token(
+    invite_id: String,
+    expires_at: DateTime<Utc>,
+) -> Result<String, AuthError> {
+    let claims = InviteToken {
+        invite_id,
+        exp: expires_at.timestamp() as usize,
+    };
 
-    let encoded = URL_SAFE.encode(json.as_bytes());
-    Ok(encoded)
+    encode(&Header::new(Algorithm::RS256), &claims, &KEYS.encoding)
+        .map_err(|_| AuthError::TokenCreation)
 }
 
 use rand::{Rng, rng};
+use sha2::{Digest, Sha256};
 
 pub fn generate_secure_token() -> String {
     let mut bytes = [0u8; 32];
@@ -76,11 +93,15 @@ pub fn generate_secure_token() -> String {
     BASE64_URL_SAFE_NO_PAD.encode(bytes)
 }
 
+/// Digest of an opaque token, which is all the database gets to see
+pub fn hash_token(token: &str) -> String {
+    BASE64_URL_SAFE_NO_PAD.encode(Sha256::digest(token.as_bytes()))
+}
+
 #[cfg(test)]
 mod tests {
-    use core::panic;
-
     use super::*;
+    use chrono::Duration;
     #[test]
     fn test_generate_token_success() {
         let user_id = "test_user";
@@ -239,11 +260,30 @@ mod tests {
         assert!(token.expires_at <= after_generation + duration);
     }
 
+    #[test]
+    fn test_session_token_carries_session_id() {
+        let token = generate_session_token("user", &UserRole::Student, 900, Some("session"))
+            .expect("Generation failed");
+        let claims = decode_token(&token.token).expect("Decoding failed");
+        assert_eq!(claims.sid.as_deref(), Some("session"));
+
+        let plain = generate_token("user", &UserRole::Student, 900).unwrap();
+        assert!(decode_token(&plain.token).unwrap().sid.is_none());
+    }
+
+    #[test]
+    fn test_hash_token() {
+        let token = generate_secure_token();
+        assert_eq!(hash_token(&token), hash_token(&token));
+        assert_ne!(hash_token(&token), token);
+        assert!(hash_token(&token).len() <= 64);
+    }
+
     #[tokio::test]
     async fn test_encode_decode_invite_token() {
-        let teacher_id = "teacher_123".to_string();
+        let invite_id = "invite_123".to_string();
 
-        let encoded = encode_invite_token(teacher_id.clone())
+        let encoded = encode_invite_token(invite_id.clone(), Utc::now() + Duration::days(7))
             .await
             .expect("Encoding failed");
 
@@ -251,41 +291,30 @@ mod tests {
 
         let decoded = decode_invite_token(encoded).await.expect("Decoding failed");
 
-        assert_eq!(decoded, teacher_id);
+        assert_eq!(decoded, invite_id);
     }
 
     #[tokio::test]
-    async fn test_encode_decode_invite_token_edge_cases() {
-        let test_cases = vec![
-            "".to_string(),
-            "a".to_string(),
-            "very_long_teacher_id_with_special_characters_123456789".to_string(),
-            "teacher@example.com".to_string(),
-            "teacher-with-dashes".to_string(),
-            "teacher_with_underscores".to_string(),
-            "123456789".to_string(),
-        ];
-
-        for teacher_id in test_cases {
-            let encoded = encode_invite_token(teacher_id.clone())
-                .await
-                .unwrap_or_else(|_| panic!("Encoding failed for {teacher_id}"));
-
-            let decoded = decode_invite_token(encoded)
-                .await
-                .unwrap_or_else(|_| panic!("Decoding failed for: {teacher_id}"));
+    async fn test_expired_invite_token() {
+        let encoded = encode_invite_token("invite_123".to_string(), Utc::now() - Duration::days(1))
+            .await
+            .expect("Encoding failed");
 
-            assert_eq!(decoded, teacher_id);
-        }
+        let result = decode_invite_token(encoded).await;
+        assert!(matches!(result, Err(AuthError::InvalidToken)));
     }
 
     #[tokio::test]
     async fn test_decode_invite_token_invalid_cases() {
+        // The old unsigned format must not be accepted anymore
+        let forged = base64::engine::general_purpose::URL_SAFE
+            .encode(r#"{"teacher_id":"teacher_123","created_at":"2025-01-01T00:00:00Z"}"#);
         let invalid_tokens = vec![
             "invalid_base64!@#".to_string(),
             "not_json_at_all".to_string(),
             "".to_string(),
             "🚀🎉".to_string(), // Unicode characters
+            forged,
         ];
 
         for invalid_token in invalid_tokens {
@@ -295,29 +324,6 @@ mod tests {
         }
     }
 
-    #[tokio::test]
-    async fn test_invite_token_contains_valid_json() {
-        let teacher_id = "teacher_456".to_string();
-
-        let encoded = encode_invite_token(teacher_id.clone())
-            .await
-            .expect("Encoding failed");
-
-        // Manually decode to verify JSON structure
-        let decoded_bytes = base64::engine::general_purpose::URL_SAFE
-            .decode(&encoded)
-            .expect("Base64 decode failed");
-
-        let decoded_str = String::from_utf8(decoded_bytes).expect("UTF-8 conversion failed");
-
-        // Should be valid JSON
-        let json_value: serde_json::Value =
-            serde_json::from_str(&decoded_str).expect("Invalid JSON");
-
-        // Should contain teacher_id field
-        assert!(json_value.get("teacher_id").is_some());
-    }
-
     #[test]
     fn test_token_uniqueness() {
         let user_id = "test_user";
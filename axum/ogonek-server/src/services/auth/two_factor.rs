@@ -0,0 +1,248 @@
+use axum::{RequestPartsExt, extract::FromRequestParts, http::request::Parts};
+use axum_extra::{
+    TypedHeader,
+    headers::{Authorization, authorization::Bearer},
+};
+use jsonwebtoken::{Algorithm, Header, Validation, decode, encode};
+use ogonek_types::{TokenWithExpiry, TwoFactorChallengeClaims, UserRole};
+use rand::{Rng, rng};
+use std::time::{SystemTime, UNIX_EPOCH};
+use totp_rs::{Secret, TOTP};
+
+use crate::services::{AuthError, KEYS, decode_token};
+
+const ISSUER: &str = "Ogonek";
+/// Seconds a code stays current, as authenticator apps expect
+const TOTP_STEP: u64 = 30;
+/// How long the user has to come up with a code after their password checked out
+const CHALLENGE_SECS: u64 = 60 * 5;
+const RECOVERY_CODE_COUNT: usize = 10;
+/// No look-alike characters, codes get typed from paper
+const RECOVERY_CODE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+/// What a challenge token lets its holder do
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChallengePurpose {
+    /// Finish signing in with a code
+    Verify,
+    /// Enrol first, the account's role can't go without 2FA
+    Setup,
+}
+
+impl ChallengePurpose {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Verify => "2fa",
+            Self::Setup => "2fa_setup",
+        }
+    }
+}
+
+/// Whether the role has to use 2FA. Admins and Gods are held to it when
+/// `REQUIRE_ADMIN_2FA` is `true`.
+pub fn two_factor_required(role: &UserRole) -> bool {
+    matches!(role, UserRole::Admin | UserRole::God)
+        && std::env::var("REQUIRE_ADMIN_2FA").as_deref() == Ok("true")
+}
+
+/// A new base32 secret for an authenticator app
+pub fn generate_totp_secret() -> String {
+    Secret::generate_secret().to_encoded().to_string()
+}
+
+fn totp(secret: &str, account_name: &str) -> Result<TOTP, AuthError> {
+    let bytes = Secret::Encoded(secret.to_string())
+        .to_bytes()
+        .map_err(|_| AuthError::InvalidCredentials)?;
+    TOTP::new(
+        totp_rs::Algorithm::SHA1,
+        6,
+        0,
+        TOTP_STEP,
+        bytes,
+        Some(ISSUER.to_string()),
+        account_name.to_string(),
+    )
+    .map_err(|e| {
+        eprintln!("TOTP setup failed: {e}");
+        AuthError::InvalidCredentials
+    })
+}
+
+/// The `otpauth://` URI authenticator apps scan from a QR code
+pub fn provisioning_uri(secret: &str, account_name: &str) -> Result<String, AuthError> {
+    Ok(totp(secret, account_name)?.get_url())
+}
+
+/// Checks a code against the secret, allowing one step of clock drift either way.
+/// Returns the time step the code belongs to, so that it can't be used twice.
+pub fn verify_totp(secret: &str, code: &str) -> Result<Option<i64>, AuthError> {
+    let code = code.trim();
+    if code.len() != 6 || !code.chars().all(|c| c.is_ascii_digit()) {
+        return Ok(None);
+    }
+
+    let totp = totp(secret, "")?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let step = (now / TOTP_STEP) as i64;
+
+    Ok([step, step - 1, step + 1]
+        .into_iter()
+        .find(|&candidate| totp.check(code, candidate as u64 * TOTP_STEP)))
+}
+
+/// A fresh set of recovery codes, formatted `xxxxx-xxxxx`
+pub fn generate_recovery_codes() -> Vec<String> {
+    let mut rng = rng();
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let chars: String = (0..10)
+                .map(|_| {
+                    RECOVERY_CODE_ALPHABET[rng.random_range(0..RECOVERY_CODE_ALPHABET.len())]
+                        as char
+                })
+                .collect();
+            format!("{}-{}", &chars[..5], &chars[5..])
+        })
+        .collect()
+}
+
+/// Recovery codes are compared without dashes, spaces or case
+pub fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+pub fn encode_challenge_token(
+    user_id: &str,
+    purpose: ChallengePurpose,
+) -> Result<TokenWithExpiry, AuthError> {
+    let exp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+        + CHALLENGE_SECS;
+
+    let claims = TwoFactorChallengeClaims {
+        sub: user_id.to_string(),
+        exp: exp as usize,
+        purpose: purpose.as_str().to_string(),
+    };
+    let token = encode(&Header::new(Algorithm::RS256), &claims, &KEYS.encoding)
+        .map_err(|_| AuthError::TokenCreation)?;
+
+    Ok(TokenWithExpiry {
+        token,
+        expires_at: exp,
+    })
+}
+
+/// Returns the user the challenge was issued to
+pub fn decode_challenge_token(token: &str, purpose: ChallengePurpose) -> Result<String, AuthError> {
+    let validation = Validation::new(Algorithm::RS256);
+    let claims = decode::<TwoFactorChallengeClaims>(token, &KEYS.decoding, &validation)
+        .map_err(|_| AuthError::InvalidToken)?
+        .claims;
+
+    if claims.purpose != purpose.as_str() {
+        return Err(AuthError::InvalidToken);
+    }
+    Ok(claims.sub)
+}
+
+/// The user setting up 2FA: signed in, or signing in with a setup challenge as bearer
+pub struct Enrolling {
+    pub user_id: String,
+    /// Enrolling finishes the sign in
+    pub from_challenge: bool,
+}
+
+impl<S> FromRequestParts<S> for Enrolling
+where
+    S: Send + Sync,
+{
+    type Rejection = AuthError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let TypedHeader(Authorization(bearer)) = parts
+            .extract::<TypedHeader<Authorization<Bearer>>>()
+            .await
+            .map_err(|_| AuthError::InvalidToken)?;
+
+        if let Ok(claims) = decode_token(bearer.token()) {
+            return Ok(Self {
+                user_id: claims.sub,
+                from_challenge: false,
+            });
+        }
+        let user_id = decode_challenge_token(bearer.token(), ChallengePurpose::Setup)?;
+        Ok(Self {
+            user_id,
+            from_challenge: true,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_totp() {
+        let secret = generate_totp_secret();
+        let totp = totp(&secret, "teacher").unwrap();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let step = (now / TOTP_STEP) as i64;
+
+        let current = totp.generate(now);
+        assert_eq!(verify_totp(&secret, &current).unwrap(), Some(step));
+
+        let previous = totp.generate(now - TOTP_STEP);
+        assert_eq!(verify_totp(&secret, &previous).unwrap(), Some(step - 1));
+
+        let stale = totp.generate(now - TOTP_STEP * 3);
+        if stale != current && stale != previous {
+            assert_eq!(verify_totp(&secret, &stale).unwrap(), None);
+        }
+        assert_eq!(verify_totp(&secret, "abcdef").unwrap(), None);
+    }
+
+    #[test]
+    fn test_provisioning_uri() {
+        let secret = generate_totp_secret();
+        let uri = provisioning_uri(&secret, "teacher").unwrap();
+        assert!(uri.starts_with("otpauth://totp/Ogonek:teacher?"));
+        assert!(uri.contains(&format!("secret={secret}")));
+    }
+
+    #[test]
+    fn test_recovery_codes() {
+        let codes = generate_recovery_codes();
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+        assert!(codes.iter().all(|code| code.len() == 11));
+        assert_eq!(
+            normalize_recovery_code(&codes[0].to_uppercase().replace('-', " ")),
+            normalize_recovery_code(&codes[0])
+        );
+    }
+
+    #[test]
+    fn test_challenge_token_purpose() {
+        let challenge = encode_challenge_token("user_123", ChallengePurpose::Verify).unwrap();
+        assert_eq!(
+            decode_challenge_token(&challenge.token, ChallengePurpose::Verify).unwrap(),
+            "user_123"
+        );
+        assert!(decode_challenge_token(&challenge.token, ChallengePurpose::Setup).is_err());
+        // Nor does it pass for an access token
+        assert!(decode_token(&challenge.token).is_err());
+    }
+}
@@ -0,0 +1,561 @@
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use quick_xml::{
+    NsReader,
+    escape::{escape, resolve_predefined_entity},
+    events::Event,
+    name::ResolveResult,
+};
+
+use crate::AppError;
+
+pub const DAV_NS: &str = "DAV:";
+pub const CALDAV_NS: &str = "urn:ietf:params:xml:ns:caldav";
+pub const CALSERVER_NS: &str = "http://calendarserver.org/ns/";
+pub const APPLE_NS: &str = "http://apple.com/ns/ical/";
+
+const SYNC_TOKEN_PREFIX: &str = "http://ogonek.app/ns/sync/";
+const NAMESPACES: [(&str, &str); 4] = [
+    (DAV_NS, "D"),
+    (CALDAV_NS, "C"),
+    (CALSERVER_NS, "CS"),
+    (APPLE_NS, "ICAL"),
+];
+
+/// A property name qualified by its XML namespace
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PropName {
+    pub namespace: String,
+    pub name: String,
+}
+
+impl PropName {
+    pub fn new(namespace: &str, name: &str) -> Self {
+        Self {
+            namespace: namespace.to_string(),
+            name: name.to_string(),
+        }
+    }
+
+    pub fn dav(name: &str) -> Self {
+        Self::new(DAV_NS, name)
+    }
+
+    pub fn caldav(name: &str) -> Self {
+        Self::new(CALDAV_NS, name)
+    }
+
+    fn open(&self, output: &mut String) {
+        match prefix(&self.namespace) {
+            Some(prefix) => output.push_str(&format!("<{prefix}:{}>", self.name)),
+            None => output.push_str(&format!(
+                "<{} xmlns=\"{}\">",
+                self.name,
+                escape(self.namespace.as_str())
+            )),
+        }
+    }
+
+    fn close(&self, output: &mut String) {
+        match prefix(&self.namespace) {
+            Some(prefix) => output.push_str(&format!("</{prefix}:{}>", self.name)),
+            None => output.push_str(&format!("</{}>", self.name)),
+        }
+    }
+}
+
+/// The body of a PROPFIND or REPORT request.
+/// `props` is `None` when the client asks for all properties.
+#[derive(Debug, PartialEq)]
+pub enum DavRequest {
+    Propfind {
+        props: Option<Vec<PropName>>,
+    },
+    CalendarQuery {
+        props: Option<Vec<PropName>>,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
+    },
+    CalendarMultiget {
+        props: Option<Vec<PropName>>,
+        hrefs: Vec<String>,
+    },
+    SyncCollection {
+        props: Option<Vec<PropName>>,
+        sync_token: Option<String>,
+    },
+}
+
+impl DavRequest {
+    pub fn props(&self) -> Option<&[PropName]> {
+        match self {
+            Self::Propfind { props }
+            | Self::CalendarQuery { props, .. }
+            | Self::CalendarMultiget { props, .. }
+            | Self::SyncCollection { props, .. } => props.as_deref(),
+        }
+    }
+}
+
+/// Whether a property was asked for. Calendar data is never part of `allprop`
+pub fn wants(props: Option<&[PropName]>, prop: &PropName) -> bool {
+    match props {
+        Some(props) => props.contains(prop),
+        None => *prop != PropName::caldav("calendar-data"),
+    }
+}
+
+/// Parses a PROPFIND or REPORT body, an empty PROPFIND asks for all properties
+pub fn parse_dav_request(body: &str) -> Result<DavRequest, AppError> {
+    if body.trim().is_empty() {
+        return Ok(DavRequest::Propfind { props: None });
+    }
+
+    let root = parse_xml(body)?;
+    let props = root.child(DAV_NS, "prop").map(|prop| {
+        prop.children
+            .iter()
+            .map(|p| PropName::new(&p.namespace, &p.name))
+            .collect()
+    });
+
+    let request = match (root.namespace.as_str(), root.name.as_str()) {
+        (DAV_NS, "propfind") => DavRequest::Propfind { props },
+        (CALDAV_NS, "calendar-query") => {
+            let range = root.find(CALDAV_NS, "time-range");
+            let time = |name| {
+                range
+                    .and_then(|r| r.attribute(name))
+                    .map(|value| {
+                        parse_dav_datetime(value)
+                            .ok_or_else(|| AppError::BadRequest(format!("Invalid {name} time")))
+                    })
+                    .transpose()
+            };
+            DavRequest::CalendarQuery {
+                props,
+                start: time("start")?,
+                end: time("end")?,
+            }
+        }
+        (CALDAV_NS, "calendar-multiget") => DavRequest::CalendarMultiget {
+            props,
+            hrefs: root
+                .children
+                .iter()
+                .filter(|c| c.is(DAV_NS, "href"))
+                .map(|c| c.text.trim().to_string())
+                .collect(),
+        },
+        (DAV_NS, "sync-collection") => DavRequest::SyncCollection {
+            props,
+            sync_token: root
+                .child(DAV_NS, "sync-token")
+                .map(|t| t.text.trim().to_string())
+                .filter(|t| !t.is_empty()),
+        },
+        (namespace, name) => {
+            return Err(AppError::BadRequest(format!(
+                "Unsupported request {{{namespace}}}{name}"
+            )));
+        }
+    };
+
+    Ok(request)
+}
+
+pub fn sync_token(synced_at: DateTime<Utc>) -> String {
+    format!("{SYNC_TOKEN_PREFIX}{}", synced_at.timestamp_micros())
+}
+
+pub fn parse_sync_token(token: &str) -> Option<DateTime<Utc>> {
+    token
+        .strip_prefix(SYNC_TOKEN_PREFIX)?
+        .parse()
+        .ok()
+        .and_then(DateTime::from_timestamp_micros)
+}
+
+/// One `response` of a multistatus
+#[derive(Debug)]
+pub enum DavResponse {
+    Props {
+        href: String,
+        /// Properties with their values as XML
+        found: Vec<(PropName, String)>,
+        missing: Vec<PropName>,
+    },
+    /// The resource is gone, reported by sync-collection and multiget
+    NotFound { href: String },
+}
+
+impl DavResponse {
+    /// Picks the requested properties out of the available ones
+    pub fn select(
+        href: String,
+        available: Vec<(PropName, String)>,
+        requested: Option<&[PropName]>,
+    ) -> Self {
+        let Some(requested) = requested else {
+            return Self::Props {
+                href,
+                found: available,
+                missing: vec![],
+            };
+        };
+
+        let mut found = Vec::new();
+        let mut missing = Vec::new();
+        for prop in requested {
+            match available.iter().find(|(name, _)| name == prop) {
+                Some(pair) => found.push(pair.clone()),
+                None => missing.push(prop.clone()),
+            }
+        }
+        Self::Props {
+            href,
+            found,
+            missing,
+        }
+    }
+}
+
+/// Renders a 207 multistatus body
+pub fn write_multistatus(responses: &[DavResponse], sync_token: Option<&str>) -> String {
+    let mut output = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<D:multistatus");
+    for (namespace, prefix) in NAMESPACES {
+        output.push_str(&format!(" xmlns:{prefix}=\"{namespace}\""));
+    }
+    output.push('>');
+
+    for response in responses {
+        output.push_str("<D:response>");
+        match response {
+            DavResponse::Props {
+                href,
+                found,
+                missing,
+            } => {
+                output.push_str(&href_xml(href));
+                if !found.is_empty() {
+                    output.push_str("<D:propstat><D:prop>");
+                    for (name, value) in found {
+                        name.open(&mut output);
+                        output.push_str(value);
+                        name.close(&mut output);
+                    }
+                    output.push_str("</D:prop><D:status>HTTP/1.1 200 OK</D:status></D:propstat>");
+                }
+                if !missing.is_empty() {
+                    output.push_str("<D:propstat><D:prop>");
+                    for name in missing {
+                        name.open(&mut output);
+                        name.close(&mut output);
+                    }
+                    output.push_str(
+                        "</D:prop><D:status>HTTP/1.1 404 Not Found</D:status></D:propstat>",
+                    );
+                }
+            }
+            DavResponse::NotFound { href } => {
+                output.push_str(&href_xml(href));
+                output.push_str("<D:status>HTTP/1.1 404 Not Found</D:status>");
+            }
+        }
+        output.push_str("</D:response>");
+    }
+
+    if let Some(token) = sync_token {
+        output.push_str(&format!("<D:sync-token>{}</D:sync-token>", escape(token)));
+    }
+    output.push_str("</D:multistatus>");
+    output
+}
+
+/// Renders a `DAV:error` body carrying a single precondition
+pub fn write_dav_error(precondition: &PropName) -> String {
+    let mut output = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<D:error");
+    for (namespace, prefix) in NAMESPACES {
+        output.push_str(&format!(" xmlns:{prefix}=\"{namespace}\""));
+    }
+    output.push('>');
+    precondition.open(&mut output);
+    precondition.close(&mut output);
+    output.push_str("</D:error>");
+    output
+}
+
+pub fn href_xml(href: &str) -> String {
+    format!("<D:href>{}</D:href>", escape(href))
+}
+
+pub fn text_xml(value: &str) -> String {
+    escape(value).into_owned()
+}
+
+fn prefix(namespace: &str) -> Option<&'static str> {
+    NAMESPACES
+        .iter()
+        .find(|(ns, _)| *ns == namespace)
+        .map(|(_, prefix)| *prefix)
+}
+
+/// `20250303T150000Z`, the only form time ranges may take
+fn parse_dav_datetime(value: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%SZ")
+        .ok()
+        .map(|naive| Utc.from_utc_datetime(&naive))
+}
+
+#[derive(Debug, Default)]
+struct Element {
+    namespace: String,
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Element>,
+    text: String,
+}
+
+impl Element {
+    fn is(&self, namespace: &str, name: &str) -> bool {
+        self.namespace == namespace && self.name == name
+    }
+
+    fn child(&self, namespace: &str, name: &str) -> Option<&Element> {
+        self.children.iter().find(|c| c.is(namespace, name))
+    }
+
+    /// Depth-first search below this element
+    fn find(&self, namespace: &str, name: &str) -> Option<&Element> {
+        self.children.iter().find_map(|c| {
+            if c.is(namespace, name) {
+                Some(c)
+            } else {
+                c.find(namespace, name)
+            }
+        })
+    }
+
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+fn parse_xml(input: &str) -> Result<Element, AppError> {
+    let invalid = |e: &dyn std::fmt::Display| AppError::BadRequest(format!("Invalid XML: {e}"));
+
+    let mut reader = NsReader::from_str(input);
+    let mut stack: Vec<Element> = Vec::new();
+    loop {
+        let (namespace, event) = reader.read_resolved_event().map_err(|e| invalid(&e))?;
+        let namespace = match namespace {
+            ResolveResult::Bound(ns) => String::from_utf8_lossy(ns.as_ref()).into_owned(),
+            _ => String::new(),
+        };
+
+        let empty = matches!(event, Event::Empty(_));
+        match event {
+            Event::Start(start) | Event::Empty(start) => {
+                let mut element = Element {
+                    namespace,
+                    name: String::from_utf8_lossy(start.local_name().as_ref()).into_owned(),
+                    ..Default::default()
+                };
+                for attribute in start.attributes() {
+                    let attribute = attribute.map_err(|e| invalid(&e))?;
+                    let value = attribute.unescape_value().map_err(|e| invalid(&e))?;
+                    element.attributes.push((
+                        String::from_utf8_lossy(attribute.key.local_name().as_ref()).into_owned(),
+                        value.into_owned(),
+                    ));
+                }
+
+                if empty {
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(element),
+                        None => return Ok(element),
+                    }
+                } else {
+                    stack.push(element);
+                }
+            }
+            Event::End(_) => {
+                let element = stack.pop().ok_or_else(|| invalid(&"unbalanced tags"))?;
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => return Ok(element),
+                }
+            }
+            Event::Text(text) => {
+                if let Some(element) = stack.last_mut() {
+                    element
+                        .text
+                        .push_str(&text.xml_content().map_err(|e| invalid(&e))?);
+                }
+            }
+            Event::CData(data) => {
+                if let Some(element) = stack.last_mut() {
+                    element
+                        .text
+                        .push_str(&data.decode().map_err(|e| invalid(&e))?);
+                }
+            }
+            Event::GeneralRef(reference) => {
+                if let Some(element) = stack.last_mut() {
+                    if let Some(c) = reference.resolve_char_ref().map_err(|e| invalid(&e))? {
+                        element.text.push(c);
+                    } else {
+                        let name = reference.decode().map_err(|e| invalid(&e))?;
+                        let entity = resolve_predefined_entity(&name)
+                            .ok_or_else(|| invalid(&format!("unknown entity {name}")))?;
+                        element.text.push_str(entity);
+                    }
+                }
+            }
+            Event::Eof => return Err(invalid(&"unexpected end of document")),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_propfind() {
+        let body = r#"<?xml version="1.0" encoding="utf-8" ?>
+            <d:propfind xmlns:d="DAV:" xmlns:cs="http://calendarserver.org/ns/">
+              <d:prop>
+                <d:displayname />
+                <cs:getctag />
+                <x:custom xmlns:x="urn:example"/>
+              </d:prop>
+            </d:propfind>"#;
+
+        let request = parse_dav_request(body).unwrap();
+        assert_eq!(
+            request,
+            DavRequest::Propfind {
+                props: Some(vec![
+                    PropName::dav("displayname"),
+                    PropName::new(CALSERVER_NS, "getctag"),
+                    PropName::new("urn:example", "custom"),
+                ])
+            }
+        );
+
+        assert_eq!(
+            parse_dav_request("").unwrap(),
+            DavRequest::Propfind { props: None }
+        );
+        let allprop = r#"<propfind xmlns="DAV:"><allprop/></propfind>"#;
+        assert_eq!(
+            parse_dav_request(allprop).unwrap(),
+            DavRequest::Propfind { props: None }
+        );
+    }
+
+    #[test]
+    fn test_parse_reports() {
+        let query = r#"<c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+              <d:prop><d:getetag/></d:prop>
+              <c:filter>
+                <c:comp-filter name="VCALENDAR">
+                  <c:comp-filter name="VEVENT">
+                    <c:time-range start="20250301T000000Z" end="20250401T000000Z"/>
+                  </c:comp-filter>
+                </c:comp-filter>
+              </c:filter>
+            </c:calendar-query>"#;
+        let DavRequest::CalendarQuery { props, start, end } = parse_dav_request(query).unwrap()
+        else {
+            panic!("expected a calendar-query");
+        };
+        assert_eq!(props, Some(vec![PropName::dav("getetag")]));
+        assert_eq!(
+            start,
+            Some(Utc.with_ymd_and_hms(2025, 3, 1, 0, 0, 0).unwrap())
+        );
+        assert_eq!(
+            end,
+            Some(Utc.with_ymd_and_hms(2025, 4, 1, 0, 0, 0).unwrap())
+        );
+
+        let multiget = r#"<C:calendar-multiget xmlns:D="DAV:" xmlns:C="urn:ietf:params:xml:ns:caldav">
+              <D:prop><D:getetag/><C:calendar-data/></D:prop>
+              <D:href>/dav/calendars/abc/a%20b.ics</D:href>
+              <D:href>/dav/calendars/abc/x&amp;y.ics</D:href>
+            </C:calendar-multiget>"#;
+        let request = parse_dav_request(multiget).unwrap();
+        assert!(wants(request.props(), &PropName::caldav("calendar-data")));
+        let DavRequest::CalendarMultiget { hrefs, .. } = request else {
+            panic!("expected a calendar-multiget");
+        };
+        assert_eq!(
+            hrefs,
+            ["/dav/calendars/abc/a%20b.ics", "/dav/calendars/abc/x&y.ics"]
+        );
+
+        let sync = r#"<d:sync-collection xmlns:d="DAV:">
+              <d:sync-token/>
+              <d:sync-level>1</d:sync-level>
+              <d:prop><d:getetag/></d:prop>
+            </d:sync-collection>"#;
+        assert_eq!(
+            parse_dav_request(sync).unwrap(),
+            DavRequest::SyncCollection {
+                props: Some(vec![PropName::dav("getetag")]),
+                sync_token: None
+            }
+        );
+
+        assert!(parse_dav_request("<d:lock xmlns:d=\"DAV:\"/>").is_err());
+        assert!(parse_dav_request("<d:propfind xmlns:d=\"DAV:\">").is_err());
+    }
+
+    #[test]
+    fn test_write_multistatus() {
+        let response = DavResponse::select(
+            "/dav/calendars/abc/".to_string(),
+            vec![
+                (PropName::dav("displayname"), text_xml("Lessons & more")),
+                (
+                    PropName::new(APPLE_NS, "calendar-color"),
+                    text_xml("#df7055"),
+                ),
+            ],
+            Some(&[
+                PropName::dav("displayname"),
+                PropName::new("urn:example", "custom"),
+            ]),
+        );
+        let gone = DavResponse::NotFound {
+            href: "/dav/calendars/abc/old.ics".to_string(),
+        };
+
+        let xml = write_multistatus(&[response, gone], Some("token"));
+        assert!(xml.contains("<D:displayname>Lessons &amp; more</D:displayname>"));
+        assert!(!xml.contains("calendar-color"));
+        assert!(xml.contains(
+            "<D:propstat><D:prop><custom xmlns=\"urn:example\"></custom></D:prop><D:status>HTTP/1.1 404 Not Found</D:status></D:propstat>"
+        ));
+        assert!(xml.contains(
+            "<D:href>/dav/calendars/abc/old.ics</D:href><D:status>HTTP/1.1 404 Not Found</D:status>"
+        ));
+        assert!(xml.ends_with("<D:sync-token>token</D:sync-token></D:multistatus>"));
+
+        // The written document reads back
+        assert!(parse_xml(&xml).is_ok());
+    }
+
+    #[test]
+    fn test_sync_token_roundtrip() {
+        let synced_at = DateTime::from_timestamp_micros(1_740_000_000_123_456).unwrap();
+        assert_eq!(parse_sync_token(&sync_token(synced_at)), Some(synced_at));
+        assert_eq!(parse_sync_token("http://example.com/sync/1"), None);
+        assert_eq!(parse_sync_token("http://ogonek.app/ns/sync/abc"), None);
+    }
+}
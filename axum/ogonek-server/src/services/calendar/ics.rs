@@ -0,0 +1,872 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Offset,
+    TimeZone, Utc,
+};
+use chrono_tz::{OffsetComponents, OffsetName, Tz};
+use ogonek_types::{
+    AttendeeImport, CalendarFull, EventAttendee, EventAttendeeRole, EventAttendeeStatus,
+    EventClass, EventDBFull, EventImport, EventStatus, EventTransp,
+};
+
+use crate::{
+    AppError,
+    services::calendar::{RRule, local_to_utc},
+};
+
+use super::parse_date_flexible;
+
+const PRODID: &str = "-//Ogonek//Calendar//EN";
+/// RFC 5545 asks for lines of at most 75 octets
+const MAX_LINE: usize = 75;
+const ICS_DATETIME: &str = "%Y%m%dT%H%M%SZ";
+const ICS_LOCAL_DATETIME: &str = "%Y%m%dT%H%M%S";
+
+/// Renders a calendar and its events as a VCALENDAR.
+/// Events with a zone have their times written in it, along with a VTIMEZONE, so that
+/// clients expand recurrences on the wall clock across DST changes like the server does.
+pub fn write_ics(
+    calendar: &CalendarFull,
+    events: &[EventDBFull],
+    attendees: &[EventAttendee],
+) -> String {
+    let mut ics = IcsWriter::default();
+    ics.line("BEGIN:VCALENDAR");
+    ics.line("VERSION:2.0");
+    ics.property("PRODID", PRODID);
+    ics.line("CALSCALE:GREGORIAN");
+    ics.line("METHOD:PUBLISH");
+    ics.text("X-WR-CALNAME", &calendar.name);
+    if let Some(description) = &calendar.description {
+        ics.text("X-WR-CALDESC", description);
+    }
+    ics.property("X-WR-TIMEZONE", &calendar.timezone);
+    ics.line("REFRESH-INTERVAL;VALUE=DURATION:PT1H");
+    ics.line("X-PUBLISHED-TTL:PT1H");
+
+    write_events(&mut ics, events, attendees);
+    ics.line("END:VCALENDAR");
+    ics.finish()
+}
+
+/// Renders a single CalDAV resource: the events sharing one UID
+pub fn write_resource(events: &[EventDBFull], attendees: &[EventAttendee]) -> String {
+    let mut ics = IcsWriter::default();
+    ics.line("BEGIN:VCALENDAR");
+    ics.line("VERSION:2.0");
+    ics.property("PRODID", PRODID);
+    ics.line("CALSCALE:GREGORIAN");
+
+    write_events(&mut ics, events, attendees);
+    ics.line("END:VCALENDAR");
+    ics.finish()
+}
+
+fn write_events(ics: &mut IcsWriter, events: &[EventDBFull], attendees: &[EventAttendee]) {
+    let mut by_event: HashMap<&str, Vec<&EventAttendee>> = HashMap::new();
+    for attendee in attendees {
+        by_event
+            .entry(attendee.event_id.as_str())
+            .or_default()
+            .push(attendee);
+    }
+
+    // Moved occurrences are stored as an exdate on the master too,
+    // clients would drop the override along with it
+    let overridden: HashSet<(&str, DateTime<Utc>)> = events
+        .iter()
+        .filter_map(|e| e.recurrence_id.map(|r| (e.uid.as_str(), r)))
+        .collect();
+
+    // A RECURRENCE-ID names the occurrence the way the master's DTSTART does
+    let master_zones: HashMap<&str, Option<Tz>> = events
+        .iter()
+        .filter(|e| e.recurrence_id.is_none())
+        .map(|e| (e.uid.as_str(), event_zone(e)))
+        .collect();
+
+    let mut zones: BTreeMap<&str, (Tz, DateTime<Utc>)> = BTreeMap::new();
+    for event in events {
+        if let Some(tz) = event_zone(event) {
+            let since = event.recurrence_id.unwrap_or(event.dtstart_time);
+            let earliest = zones.entry(tz.name()).or_insert((tz, since));
+            earliest.1 = earliest.1.min(since);
+        }
+    }
+    for (tz, since) in zones.into_values() {
+        write_timezone(ics, tz, since);
+    }
+
+    for event in events {
+        let zone = event_zone(event);
+        ics.line("BEGIN:VEVENT");
+        ics.property("UID", &event.uid);
+        ics.property("DTSTAMP", &format_datetime(event.updated_at));
+        ics.property("CREATED", &format_datetime(event.created_at));
+        ics.property("LAST-MODIFIED", &format_datetime(event.updated_at));
+        ics.property("SEQUENCE", &event.sequence.to_string());
+        ics.datetime("DTSTART", event.dtstart_time, zone);
+        if let Some(end) = event.dtend_time {
+            ics.datetime("DTEND", end, zone);
+        }
+        if let Some(recurrence_id) = event.recurrence_id {
+            let master_zone = master_zones
+                .get(event.uid.as_str())
+                .copied()
+                .unwrap_or(zone);
+            ics.datetime("RECURRENCE-ID", recurrence_id, master_zone);
+        }
+        ics.text("SUMMARY", &event.summary);
+        if let Some(description) = &event.description {
+            ics.text("DESCRIPTION", description);
+        }
+        if let Some(location) = &event.location {
+            ics.text("LOCATION", location);
+        }
+        if let Some(url) = &event.url {
+            ics.property("URL", url);
+        }
+        ics.property("STATUS", &event.status.to_string().to_uppercase());
+        ics.property("CLASS", &event.class.to_string().to_uppercase());
+        ics.property("TRANSP", &event.transp.to_string().to_uppercase());
+        if let Some(priority) = event.priority {
+            ics.property("PRIORITY", &priority.to_string());
+        }
+        if let Some(categories) = event.categories.as_ref().filter(|c| !c.is_empty()) {
+            let categories: Vec<String> = categories.iter().map(|c| escape_text(c)).collect();
+            ics.property("CATEGORIES", &categories.join(","));
+        }
+
+        if event.recurrence_id.is_none() {
+            if let Some(rrule) = event.rrule.as_ref().filter(|r| !r.is_empty()) {
+                ics.property("RRULE", rrule);
+            }
+            for rdate in parse_dates(&event.rdate) {
+                ics.datetime("RDATE", rdate, zone);
+            }
+            for exdate in parse_dates(&event.exdate) {
+                if !overridden.contains(&(event.uid.as_str(), exdate)) {
+                    ics.datetime("EXDATE", exdate, zone);
+                }
+            }
+        }
+
+        for attendee in by_event.get(event.id.as_str()).into_iter().flatten() {
+            let mut name = String::from("ATTENDEE");
+            if let Some(cn) = &attendee.name {
+                name.push_str(&format!(";CN=\"{}\"", cn.replace('"', "'")));
+            }
+            name.push_str(&format!(
+                ";ROLE={};PARTSTAT={}",
+                attendee.role.to_string().to_uppercase(),
+                attendee.status.to_string().to_uppercase()
+            ));
+            if attendee.rsvp {
+                name.push_str(";RSVP=TRUE");
+            }
+            ics.property(&name, &format!("mailto:{}", attendee.email));
+        }
+        ics.line("END:VEVENT");
+    }
+}
+
+/// The zone an event's times are written in, `None` for UTC
+fn event_zone(event: &EventDBFull) -> Option<Tz> {
+    event
+        .dtstart_tz
+        .as_deref()
+        .and_then(|tzid| tzid.parse::<Tz>().ok())
+        .filter(|tz| *tz != Tz::UTC)
+}
+
+/// Writes a VTIMEZONE covering the zone from `since` on: the offset in force then and
+/// every change up to the end of next year. If the zone still changes its clocks then,
+/// that year's changes repeat yearly.
+fn write_timezone(ics: &mut IcsWriter, tz: Tz, since: DateTime<Utc>) {
+    let year_start = |year| {
+        NaiveDate::from_ymd_opt(year, 1, 1)
+            .unwrap_or_default()
+            .and_time(NaiveTime::MIN)
+            .and_utc()
+    };
+    let last_year = Utc::now().year() + 1;
+    let from = year_start(since.year().min(last_year));
+
+    ics.line("BEGIN:VTIMEZONE");
+    ics.property("TZID", tz.name());
+    write_observance(ics, tz, from, false);
+    for change in clock_changes(tz, from, year_start(last_year + 1)) {
+        write_observance(ics, tz, change, change.year() == last_year);
+    }
+    ics.line("END:VTIMEZONE");
+}
+
+/// A STANDARD or DAYLIGHT component for the offset taking effect at `at`
+fn write_observance(ics: &mut IcsWriter, tz: Tz, at: DateTime<Utc>, yearly: bool) {
+    let offset = tz.offset_from_utc_datetime(&at.naive_utc());
+    let before = tz
+        .offset_from_utc_datetime(&(at - Duration::seconds(1)).naive_utc())
+        .fix();
+    let component = if offset.dst_offset().is_zero() {
+        "STANDARD"
+    } else {
+        "DAYLIGHT"
+    };
+    // The start is given on the wall clock as it was before the change
+    let local = at.with_timezone(&before);
+
+    ics.line(&format!("BEGIN:{component}"));
+    ics.property("DTSTART", &local.format(ICS_LOCAL_DATETIME).to_string());
+    ics.property("TZOFFSETFROM", &format_offset(before));
+    ics.property("TZOFFSETTO", &format_offset(offset.fix()));
+    if let Some(name) = offset.abbreviation() {
+        ics.property("TZNAME", name);
+    }
+    if yearly {
+        // Changes happen on the nth or the last weekday of a month
+        let week = if (local.date_naive() + Duration::days(7)).month() != local.month() {
+            -1
+        } else {
+            local.day0() as i32 / 7 + 1
+        };
+        let weekday = &local.weekday().to_string()[..2];
+        ics.property(
+            "RRULE",
+            &format!(
+                "FREQ=YEARLY;BYMONTH={};BYDAY={week}{}",
+                local.month(),
+                weekday.to_uppercase()
+            ),
+        );
+    }
+    ics.line(&format!("END:{component}"));
+}
+
+/// The instants within the range at which the zone's offset changes
+fn clock_changes(tz: Tz, from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<DateTime<Utc>> {
+    let offset = |at: DateTime<Utc>| tz.offset_from_utc_datetime(&at.naive_utc()).fix();
+
+    let mut changes = Vec::new();
+    let mut day = from;
+    while day < to {
+        let next = day + Duration::days(1);
+        if offset(day) != offset(next) {
+            // Narrow the day down to the first second of the new offset
+            let (mut before, mut after) = (day, next);
+            while after - before > Duration::seconds(1) {
+                let middle = before + (after - before) / 2;
+                if offset(middle) == offset(day) {
+                    before = middle;
+                } else {
+                    after = middle;
+                }
+            }
+            changes.push(after);
+        }
+        day = next;
+    }
+    changes
+}
+
+fn format_offset(offset: FixedOffset) -> String {
+    let seconds = offset.local_minus_utc();
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let seconds = seconds.abs();
+    let mut formatted = format!("{sign}{:02}{:02}", seconds / 3600, seconds / 60 % 60);
+    if seconds % 60 != 0 {
+        formatted.push_str(&format!("{:02}", seconds % 60));
+    }
+    formatted
+}
+
+/// Reads the events of an iCalendar file.
+/// Floating times and zones chrono-tz does not know are read in `default_tz`.
+/// Returns the events along with how many were skipped as unreadable.
+pub fn parse_ics(input: &str, default_tz: Tz) -> Result<(Vec<EventImport>, usize), AppError> {
+    let lines = unfold(input);
+    if !lines
+        .first()
+        .is_some_and(|line| line.eq_ignore_ascii_case("BEGIN:VCALENDAR"))
+    {
+        return Err(AppError::BadRequest("Not an iCalendar file".into()));
+    }
+
+    let mut events = Vec::new();
+    let mut skipped = 0;
+    let mut current: Option<Vec<Property>> = None;
+    // Nested components such as VALARM have their own DTSTART and DESCRIPTION
+    let mut nested = 0;
+
+    for line in &lines {
+        let Some(property) = Property::parse(line) else {
+            continue;
+        };
+
+        match (
+            property.name.as_str(),
+            property.value.to_uppercase().as_str(),
+        ) {
+            ("BEGIN", "VEVENT") if current.is_none() => current = Some(Vec::new()),
+            ("END", "VEVENT") if nested == 0 => {
+                if let Some(properties) = current.take() {
+                    match read_event(&properties, default_tz) {
+                        Some(event) => events.push(event),
+                        None => skipped += 1,
+                    }
+                }
+            }
+            ("BEGIN", _) if current.is_some() => nested += 1,
+            ("END", _) if current.is_some() => nested -= 1,
+            _ => {
+                if let Some(properties) = current.as_mut()
+                    && nested == 0
+                {
+                    properties.push(property);
+                }
+            }
+        }
+    }
+
+    Ok((events, skipped))
+}
+
+/// Builds an event out of a VEVENT's properties, `None` when it cannot be stored
+fn read_event(properties: &[Property], default_tz: Tz) -> Option<EventImport> {
+    let find = |name: &str| properties.iter().find(|p| p.name == name);
+    let text = |name: &str| {
+        find(name)
+            .map(|p| unescape_text(&p.value))
+            .filter(|v| !v.is_empty())
+    };
+
+    let dtstart = find("DTSTART")?;
+    let dtstart_time = parse_datetime(dtstart, default_tz)?;
+    let dtend_time = match find("DTEND") {
+        Some(dtend) => Some(parse_datetime(dtend, default_tz)?),
+        None => find("DURATION")
+            .and_then(|p| parse_duration(&p.value))
+            .map(|duration| dtstart_time + duration),
+    };
+
+    let recurrence_id = match find("RECURRENCE-ID") {
+        Some(recurrence_id) => Some(parse_datetime(recurrence_id, default_tz)?),
+        None => None,
+    };
+
+    // Overrides cannot recur themselves
+    let rrule = match find("RRULE") {
+        Some(rrule) if recurrence_id.is_none() => {
+            let rule = RRule::parse(Some(rrule.value.clone())).ok()??;
+            Some(rule.to_string())
+        }
+        _ => None,
+    };
+
+    let dates = |name: &str| -> Option<Option<Vec<String>>> {
+        let mut dates = Vec::new();
+        for property in properties.iter().filter(|p| p.name == name) {
+            for value in property.value.split(',') {
+                let single = Property {
+                    name: property.name.clone(),
+                    params: property.params.clone(),
+                    value: value.to_string(),
+                };
+                dates.push(parse_datetime(&single, default_tz)?.to_rfc3339());
+            }
+        }
+        Some((!dates.is_empty()).then_some(dates))
+    };
+
+    let attendees = properties
+        .iter()
+        .filter(|p| p.name == "ATTENDEE")
+        .filter_map(|p| {
+            let email = p
+                .value
+                .get(..7)?
+                .eq_ignore_ascii_case("mailto:")
+                .then(|| &p.value[7..])?;
+            Some(AttendeeImport {
+                email: email.to_string(),
+                name: p.param("CN").map(str::to_string),
+                role: match p.param("ROLE") {
+                    Some("CHAIR") => EventAttendeeRole::Chair,
+                    Some("OPT-PARTICIPANT") => EventAttendeeRole::OptParticipant,
+                    Some("NON-PARTICIPANT") => EventAttendeeRole::NonParticipant,
+                    _ => EventAttendeeRole::ReqParticipant,
+                },
+                status: match p.param("PARTSTAT") {
+                    Some("ACCEPTED") => EventAttendeeStatus::Accepted,
+                    Some("DECLINED") => EventAttendeeStatus::Declined,
+                    Some("TENTATIVE") => EventAttendeeStatus::Tentative,
+                    Some("DELEGATED") => EventAttendeeStatus::Delegated,
+                    _ => EventAttendeeStatus::NeedsAction,
+                },
+            })
+        })
+        .collect();
+
+    Some(EventImport {
+        uid: text("UID")
+            .filter(|uid| uid.len() <= 255)
+            .unwrap_or_else(|| nanoid::nanoid!()),
+        summary: text("SUMMARY")
+            .map(|s| s.chars().take(255).collect())
+            .unwrap_or_else(|| "Untitled event".to_string()),
+        description: text("DESCRIPTION"),
+        location: text("LOCATION"),
+        url: text("URL"),
+        dtstart_time,
+        dtend_time,
+        dtstart_tz: dtstart.param("TZID").map(str::to_string),
+        rrule,
+        rdate: dates("RDATE")?,
+        exdate: dates("EXDATE")?,
+        recurrence_id,
+        status: match find("STATUS").map(|p| p.value.to_uppercase()).as_deref() {
+            Some("TENTATIVE") => EventStatus::Tentative,
+            Some("CANCELLED") => EventStatus::Cancelled,
+            _ => EventStatus::Confirmed,
+        },
+        class: match find("CLASS").map(|p| p.value.to_uppercase()).as_deref() {
+            Some("PRIVATE") => EventClass::Private,
+            Some("CONFIDENTIAL") => EventClass::Confidential,
+            _ => EventClass::Public,
+        },
+        transp: match find("TRANSP").map(|p| p.value.to_uppercase()).as_deref() {
+            Some("TRANSPARENT") => EventTransp::Transparent,
+            _ => EventTransp::Opaque,
+        },
+        priority: find("PRIORITY")
+            .and_then(|p| p.value.parse().ok())
+            .filter(|p| (0..=9).contains(p)),
+        categories: find("CATEGORIES").map(|p| {
+            split_unescaped(&p.value)
+                .iter()
+                .map(|c| unescape_text(c))
+                .collect()
+        }),
+        sequence: find("SEQUENCE")
+            .and_then(|p| p.value.parse().ok())
+            .unwrap_or(0),
+        attendees,
+    })
+}
+
+#[derive(Debug, Clone)]
+struct Property {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl Property {
+    /// Splits `NAME;PARAM=VALUE:value`, colons inside quoted parameters are not separators
+    fn parse(line: &str) -> Option<Self> {
+        let mut in_quotes = false;
+        let split = line.char_indices().find(|&(_, c)| {
+            if c == '"' {
+                in_quotes = !in_quotes;
+            }
+            c == ':' && !in_quotes
+        })?;
+        let (head, value) = (&line[..split.0], &line[split.0 + 1..]);
+
+        let mut parts = head.split(';');
+        let name = parts.next()?.trim().to_uppercase();
+        let params = parts
+            .filter_map(|param| param.split_once('='))
+            .map(|(key, value)| (key.to_uppercase(), value.trim_matches('"').to_string()))
+            .collect();
+
+        Some(Self {
+            name,
+            params,
+            value: value.to_string(),
+        })
+    }
+
+    fn param(&self, key: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Joins folded lines back together
+fn unfold(input: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in input.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ if line.trim().is_empty() => {}
+            _ => lines.push(line.trim_end_matches('\r').to_string()),
+        }
+    }
+    lines
+}
+
+fn parse_datetime(property: &Property, default_tz: Tz) -> Option<DateTime<Utc>> {
+    let value = property.value.trim();
+    let tz = property
+        .param("TZID")
+        .and_then(|tzid| tzid.parse::<Tz>().ok())
+        .unwrap_or(default_tz);
+
+    if let Some(utc) = value.strip_suffix('Z') {
+        return NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S")
+            .ok()
+            .map(|naive| naive.and_utc());
+    }
+
+    let naive = match NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S") {
+        Ok(naive) => naive,
+        // All-day values start at midnight
+        Err(_) => NaiveDate::parse_from_str(value, "%Y%m%d")
+            .ok()?
+            .and_hms_opt(0, 0, 0)?,
+    };
+
+    Some(local_to_utc(&tz, naive))
+}
+
+/// Reads durations such as `PT1H30M`, `P1D` or `P2W`
+fn parse_duration(value: &str) -> Option<Duration> {
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let value = value.strip_prefix('P')?;
+
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    let mut in_time = false;
+    for c in value.chars() {
+        match c {
+            'T' => in_time = true,
+            '0'..='9' => number.push(c),
+            unit => {
+                let amount: i64 = std::mem::take(&mut number).parse().ok()?;
+                total += match (unit, in_time) {
+                    ('W', false) => Duration::weeks(amount),
+                    ('D', false) => Duration::days(amount),
+                    ('H', true) => Duration::hours(amount),
+                    ('M', true) => Duration::minutes(amount),
+                    ('S', true) => Duration::seconds(amount),
+                    _ => return None,
+                };
+            }
+        }
+    }
+
+    Some(if negative { -total } else { total })
+}
+
+fn parse_dates(dates: &Option<Vec<String>>) -> Vec<DateTime<Utc>> {
+    dates
+        .iter()
+        .flatten()
+        .filter_map(|date| parse_date_flexible(date.trim()).ok())
+        .collect()
+}
+
+fn format_datetime(datetime: DateTime<Utc>) -> String {
+    datetime.format(ICS_DATETIME).to_string()
+}
+
+fn escape_text(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+fn unescape_text(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n' | 'N') => result.push('\n'),
+                Some(other) => result.push(other),
+                None => {}
+            }
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+/// Splits a list value on commas that are not escaped
+fn split_unescaped(value: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+    for (i, c) in value.char_indices() {
+        match c {
+            '\\' if !escaped => escaped = true,
+            ',' if !escaped => {
+                parts.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => escaped = false,
+        }
+    }
+    parts.push(&value[start..]);
+    parts
+}
+
+#[derive(Default)]
+struct IcsWriter {
+    output: String,
+}
+
+impl IcsWriter {
+    /// Writes a content line, folded so no physical line exceeds 75 octets
+    fn line(&mut self, line: &str) {
+        let mut width = 0;
+        for c in line.chars() {
+            if width + c.len_utf8() > MAX_LINE {
+                self.output.push_str("\r\n ");
+                width = 1;
+            }
+            self.output.push(c);
+            width += c.len_utf8();
+        }
+        self.output.push_str("\r\n");
+    }
+
+    fn property(&mut self, name: &str, value: &str) {
+        self.line(&format!("{name}:{value}"));
+    }
+
+    fn text(&mut self, name: &str, value: &str) {
+        self.property(name, &escape_text(value));
+    }
+
+    /// Writes a time on the wall clock of the zone, in UTC without one
+    fn datetime(&mut self, name: &str, datetime: DateTime<Utc>, tz: Option<Tz>) {
+        match tz {
+            Some(tz) => {
+                let local = datetime.with_timezone(&tz).format(ICS_LOCAL_DATETIME);
+                self.property(&format!("{name};TZID={}", tz.name()), &local.to_string());
+            }
+            None => self.property(name, &format_datetime(datetime)),
+        }
+    }
+
+    fn finish(self) -> String {
+        self.output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    const GOOGLE_EXPORT: &str = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+PRODID:-//Google Inc//Google Calendar 70.9054//EN\r
+BEGIN:VTIMEZONE\r
+TZID:Europe/Berlin\r
+BEGIN:STANDARD\r
+DTSTART:19701025T030000\r
+END:STANDARD\r
+END:VTIMEZONE\r
+BEGIN:VEVENT\r
+DTSTART;TZID=Europe/Berlin:20250303T180000\r
+DTEND;TZID=Europe/Berlin:20250303T190000\r
+RRULE:FREQ=WEEKLY;BYDAY=MO\r
+EXDATE;TZID=Europe/Berlin:20250310T180000,20250317T180000\r
+UID:lesson@google.com\r
+ATTENDEE;CN=\"Doe, Jane\";ROLE=REQ-PARTICIPANT;PARTSTAT=ACCEPTED:mailto:jane@\r
+ example.com\r
+SUMMARY:Spanish\\, B1\r
+DESCRIPTION:Bring the\\nworkbook\r
+SEQUENCE:2\r
+BEGIN:VALARM\r
+ACTION:DISPLAY\r
+DESCRIPTION:Reminder\r
+TRIGGER:-PT15M\r
+END:VALARM\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART;TZID=Europe/Berlin:20250324T190000\r
+DURATION:PT1H30M\r
+RECURRENCE-ID;TZID=Europe/Berlin:20250324T180000\r
+UID:lesson@google.com\r
+SUMMARY:Spanish\\, B1\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+    #[test]
+    fn test_parse_recurring_event_with_override() {
+        let (events, skipped) = parse_ics(GOOGLE_EXPORT, Tz::UTC).unwrap();
+        assert_eq!(skipped, 0);
+        assert_eq!(events.len(), 2);
+
+        let master = &events[0];
+        assert_eq!(master.uid, "lesson@google.com");
+        assert_eq!(master.summary, "Spanish, B1");
+        assert_eq!(master.description.as_deref(), Some("Bring the\nworkbook"));
+        assert_eq!(master.dtstart_tz.as_deref(), Some("Europe/Berlin"));
+        assert_eq!(
+            master.dtstart_time,
+            Utc.with_ymd_and_hms(2025, 3, 3, 17, 0, 0).unwrap()
+        );
+        assert_eq!(master.rrule.as_deref(), Some("FREQ=WEEKLY;BYDAY=MO"));
+        assert_eq!(
+            master.exdate.as_deref().unwrap(),
+            ["2025-03-10T17:00:00+00:00", "2025-03-17T17:00:00+00:00"]
+        );
+        assert_eq!(master.sequence, 2);
+        assert_eq!(master.attendees.len(), 1);
+        assert_eq!(master.attendees[0].email, "jane@example.com");
+        assert_eq!(master.attendees[0].name.as_deref(), Some("Doe, Jane"));
+        assert_eq!(master.attendees[0].status, EventAttendeeStatus::Accepted);
+
+        let moved = &events[1];
+        assert_eq!(
+            moved.recurrence_id,
+            Some(Utc.with_ymd_and_hms(2025, 3, 24, 17, 0, 0).unwrap())
+        );
+        assert_eq!(
+            moved.dtend_time,
+            Some(Utc.with_ymd_and_hms(2025, 3, 24, 19, 30, 0).unwrap())
+        );
+        assert_eq!(moved.rrule, None);
+    }
+
+    #[test]
+    fn test_parse_skips_unreadable_events() {
+        let input = "BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:a\nSUMMARY:No start\nEND:VEVENT\n\
+            BEGIN:VEVENT\nUID:b\nDTSTART:20250101T100000Z\nRRULE:FREQ=HOURLY\nEND:VEVENT\n\
+            BEGIN:VEVENT\nUID:c\nDTSTART;VALUE=DATE:20250101\nEND:VEVENT\nEND:VCALENDAR\n";
+
+        let (events, skipped) = parse_ics(input, "Europe/Moscow".parse().unwrap()).unwrap();
+        assert_eq!(skipped, 2);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].summary, "Untitled event");
+        // Floating dates are read in the default zone
+        assert_eq!(
+            events[0].dtstart_time,
+            Utc.with_ymd_and_hms(2024, 12, 31, 21, 0, 0).unwrap()
+        );
+
+        assert!(parse_ics("hello", Tz::UTC).is_err());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("PT1H30M"), Some(Duration::minutes(90)));
+        assert_eq!(parse_duration("P1DT2H"), Some(Duration::hours(26)));
+        assert_eq!(parse_duration("-P2W"), Some(Duration::weeks(-2)));
+        assert_eq!(parse_duration("1H"), None);
+    }
+
+    #[test]
+    fn test_write_round_trip() {
+        let (events, _) = parse_ics(GOOGLE_EXPORT, Tz::UTC).unwrap();
+        let now = Utc::now();
+        let stored: Vec<EventDBFull> = events
+            .iter()
+            .enumerate()
+            .map(|(i, e)| EventDBFull {
+                id: i.to_string(),
+                uid: e.uid.clone(),
+                created_at: now,
+                updated_at: now,
+                calendar_id: "calendar".into(),
+                summary: e.summary.clone(),
+                description: e.description.clone(),
+                location: Some(
+                    "A very long location string that will definitely need to be folded".into(),
+                ),
+                url: None,
+                dtstart_time: e.dtstart_time,
+                dtend_time: e.dtend_time,
+                dtend_tz: None,
+                dtstart_tz: e.dtstart_tz.clone(),
+                rrule: e.rrule.clone(),
+                rdate: None,
+                // The override's occurrence is excluded on the master as well
+                exdate: e.exdate.clone().map(|mut exdate| {
+                    exdate.push("2025-03-24T17:00:00+00:00".into());
+                    exdate
+                }),
+                recurrence_id: e.recurrence_id,
+                status: e.status.clone(),
+                class: e.class.clone(),
+                transp: e.transp.clone(),
+                priority: None,
+                categories: Some(vec!["lessons".into(), "a,b".into()]),
+                sequence: e.sequence,
+                etag: String::new(),
+                deleted_at: None,
+                caldav_href: None,
+                content_type: None,
+            })
+            .collect();
+        let attendees = vec![EventAttendee {
+            id: "attendee".into(),
+            user_id: "user".into(),
+            event_id: "0".into(),
+            email: "jane@example.com".into(),
+            name: Some("Jane".into()),
+            role: EventAttendeeRole::ReqParticipant,
+            status: EventAttendeeStatus::Accepted,
+            rsvp: false,
+            created_at: now,
+            updated_at: now,
+        }];
+        let calendar = CalendarFull {
+            id: "calendar".into(),
+            name: "Lessons".into(),
+            description: None,
+            colour: "#df7055".into(),
+            timezone: "Europe/Moscow".into(),
+            owner_id: "user".into(),
+            is_default: true,
+            caldav_url: None,
+            sync_token: None,
+            sync_state: "active".into(),
+            last_sync_at: None,
+            sync_error: None,
+            created_at: now,
+            updated_at: now,
+            feed_token: None,
+        };
+
+        let ics = write_ics(&calendar, &stored, &attendees);
+        assert!(ics.lines().all(|line| line.len() <= MAX_LINE + 1));
+        // Times are on the wall clock of the event's zone
+        assert!(ics.contains("DTSTART;TZID=Europe/Berlin:20250303T180000\r\n"));
+        assert!(ics.contains("EXDATE;TZID=Europe/Berlin:20250310T180000\r\n"));
+        assert!(!ics.contains("EXDATE;TZID=Europe/Berlin:20250324T180000"));
+        assert!(ics.contains("RECURRENCE-ID;TZID=Europe/Berlin:20250324T180000\r\n"));
+        assert_eq!(ics.matches("BEGIN:VTIMEZONE").count(), 1);
+        assert!(ics.contains(
+            "BEGIN:DAYLIGHT\r\nDTSTART:20250330T020000\r\nTZOFFSETFROM:+0100\r\nTZOFFSETTO:+0200\r\n"
+        ));
+        assert!(ics.contains("RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU\r\n"));
+        assert!(ics.contains("CATEGORIES:lessons,a\\,b\r\n"));
+
+        let (parsed, skipped) = parse_ics(&ics, Tz::UTC).unwrap();
+        assert_eq!(skipped, 0);
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].summary, "Spanish, B1");
+        assert_eq!(parsed[0].dtstart_time, events[0].dtstart_time);
+        assert_eq!(parsed[0].exdate, events[0].exdate);
+        assert_eq!(parsed[0].attendees.len(), 1);
+        assert_eq!(parsed[0].categories.as_deref().unwrap(), ["lessons", "a,b"]);
+        assert_eq!(parsed[1].recurrence_id, events[1].recurrence_id);
+        assert_eq!(parsed[1].dtend_time, events[1].dtend_time);
+    }
+}
@@ -1,7 +1,10 @@
 use chrono::{DateTime, TimeZone, Utc};
 
-pub mod rrule;
-pub use rrule::*;
+pub mod dav;
+pub mod ics;
+pub use dav::*;
+pub use ics::*;
+pub use ogonek_db::rrule::*;
 pub mod parsers;
 pub use parsers::*;
 
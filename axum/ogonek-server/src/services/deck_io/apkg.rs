@@ -0,0 +1,701 @@
+use std::{
+    collections::HashMap,
+    io::{Cursor, Read, Write},
+    sync::Arc,
+};
+
+use chrono::Utc;
+use ogonek_types::DeckWithCards;
+use serde_json::{Value, json};
+use sqlx::{
+    Connection,
+    sqlite::{SqliteConnectOptions, SqliteConnection},
+};
+use zip::{ZipArchive, ZipWriter, write::SimpleFileOptions};
+
+use super::{ImportedCard, ImportedDeck, ImportedMedia};
+use crate::AppError;
+
+/// Separates the fields of a note in `notes.flds`
+const FIELD_SEPARATOR: char = '\x1f';
+
+/// Largest entry we unpack, so a small archive can't inflate into gigabytes
+const MAX_ENTRY_SIZE: u64 = 100 * 1024 * 1024;
+/// Largest amount of data we unpack from a whole package
+const MAX_PACKAGE_SIZE: u64 = 250 * 1024 * 1024;
+
+/// Tables of the legacy (schema 11) collection, the one every Anki version can import
+const SCHEMA: &str = r#"
+CREATE TABLE col (
+    id integer primary key, crt integer not null, mod integer not null, scm integer not null,
+    ver integer not null, dty integer not null, usn integer not null, ls integer not null,
+    conf text not null, models text not null, decks text not null, dconf text not null,
+    tags text not null
+);
+CREATE TABLE notes (
+    id integer primary key, guid text not null, mid integer not null, mod integer not null,
+    usn integer not null, tags text not null, flds text not null, sfld integer not null,
+    csum integer not null, flags integer not null, data text not null
+);
+CREATE TABLE cards (
+    id integer primary key, nid integer not null, did integer not null, ord integer not null,
+    mod integer not null, usn integer not null, type integer not null, queue integer not null,
+    due integer not null, ivl integer not null, factor integer not null, reps integer not null,
+    lapses integer not null, left integer not null, odue integer not null, odid integer not null,
+    flags integer not null, data text not null
+);
+CREATE TABLE revlog (
+    id integer primary key, cid integer not null, usn integer not null, ease integer not null,
+    ivl integer not null, lastIvl integer not null, factor integer not null, time integer not null,
+    type integer not null
+);
+CREATE TABLE graves (usn integer not null, oid integer not null, type integer not null);
+CREATE INDEX ix_notes_usn ON notes (usn);
+CREATE INDEX ix_cards_usn ON cards (usn);
+CREATE INDEX ix_revlog_usn ON revlog (usn);
+CREATE INDEX ix_cards_nid ON cards (nid);
+CREATE INDEX ix_cards_sched ON cards (did, queue, due);
+CREATE INDEX ix_revlog_cid ON revlog (cid);
+CREATE INDEX ix_notes_csum ON notes (csum);
+"#;
+
+/// Reads the notes of an Anki package. The first two fields become front and back,
+/// the first image or sound they reference becomes the card's media
+pub async fn read_apkg(data: Vec<u8>) -> Result<ImportedDeck, AppError> {
+    let archive = ZipArchive::new(Cursor::new(data))
+        .map_err(|_| AppError::BadRequest("Not a valid .apkg file".into()))?;
+    let mut package = Package {
+        archive,
+        budget: MAX_PACKAGE_SIZE,
+    };
+
+    let collection = match package.read_entry("collection.anki21")? {
+        Some(collection) => Some(collection),
+        None => package.read_entry("collection.anki2")?,
+    };
+    let collection = match collection {
+        // Packages from Anki 23.10+ only carry a placeholder in the legacy collection
+        Some(_)
+            if package
+                .archive
+                .index_for_name("collection.anki21b")
+                .is_some() =>
+        {
+            return Err(AppError::BadRequest(
+                "This package uses the latest Anki format, export it again with \"Support older Anki versions\" checked".into(),
+            ));
+        }
+        Some(collection) => collection,
+        None => {
+            return Err(AppError::BadRequest(
+                "The package contains no collection".into(),
+            ));
+        }
+    };
+
+    // The media entry maps the numbered files in the archive to their original names
+    let media_files: HashMap<String, String> = package
+        .read_entry("media")?
+        .and_then(|media| serde_json::from_slice::<HashMap<String, String>>(&media).ok())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(entry, name)| (name, entry))
+        .collect();
+
+    let file = tempfile::NamedTempFile::new()
+        .map_err(|e| AppError::Internal(format!("Failed to create temporary file: {e}")))?;
+    std::fs::write(file.path(), &collection)
+        .map_err(|e| AppError::Internal(format!("Failed to write temporary file: {e}")))?;
+
+    let invalid = |e: sqlx::Error| AppError::BadRequest(format!("Invalid Anki collection: {e}"));
+
+    let mut conn = SqliteConnection::connect_with(
+        &SqliteConnectOptions::new()
+            .filename(file.path())
+            .read_only(true),
+    )
+    .await
+    .map_err(invalid)?;
+
+    let title = deck_title(&mut conn).await.map_err(invalid)?;
+    let notes: Vec<(String, String)> = sqlx::query_as("SELECT flds, tags FROM notes ORDER BY id")
+        .fetch_all(&mut conn)
+        .await
+        .map_err(invalid)?;
+    conn.close().await.map_err(invalid)?;
+
+    // Notes often share a file, it is only unpacked the first time
+    let mut unpacked: HashMap<String, Option<Arc<Vec<u8>>>> = HashMap::new();
+    let cards = notes
+        .iter()
+        .map(|(fields, tags)| {
+            let mut fields = fields.split(FIELD_SEPARATOR);
+            let front = fields.next().unwrap_or_default();
+            let back = fields.next().unwrap_or_default();
+
+            let media = match first_media_ref(front).or_else(|| first_media_ref(back)) {
+                Some(name) => match media_files.get(&name) {
+                    Some(entry) => {
+                        let data = match unpacked.get(entry) {
+                            Some(data) => data.clone(),
+                            None => {
+                                let data = package.read_entry(entry)?.map(Arc::new);
+                                unpacked.insert(entry.clone(), data.clone());
+                                data
+                            }
+                        };
+                        data.map(|data| ImportedMedia::File { name, data })
+                    }
+                    None if name.starts_with("http://") || name.starts_with("https://") => {
+                        Some(ImportedMedia::Url(name))
+                    }
+                    None => None,
+                },
+                None => None,
+            };
+
+            Ok(ImportedCard {
+                front: html_to_text(front),
+                back: html_to_text(back),
+                media,
+                tags: tags.split_whitespace().map(str::to_string).collect(),
+            })
+        })
+        .collect::<Result<Vec<_>, AppError>>()?
+        .into_iter()
+        .filter(|card| !card.front.is_empty() || !card.back.is_empty())
+        .collect();
+
+    Ok(ImportedDeck { title, cards })
+}
+
+/// Packs a deck into a single-deck Anki package using the "Basic" note type.
+/// Media found in `media` is shipped inside the package, other media is referenced by URL
+pub async fn write_apkg(
+    deck: &DeckWithCards,
+    media: &HashMap<String, Vec<u8>>,
+) -> Result<Vec<u8>, AppError> {
+    let failed =
+        |e: sqlx::Error| AppError::Internal(format!("Failed to build Anki collection: {e}"));
+
+    let file = tempfile::NamedTempFile::new()
+        .map_err(|e| AppError::Internal(format!("Failed to create temporary file: {e}")))?;
+
+    let mut conn = SqliteConnection::connect_with(
+        &SqliteConnectOptions::new()
+            .filename(file.path())
+            .create_if_missing(true),
+    )
+    .await
+    .map_err(failed)?;
+
+    let now = Utc::now();
+    let (seconds, millis) = (now.timestamp(), now.timestamp_millis());
+    let deck_id = millis;
+    let model_id = millis + 1;
+
+    let mut tx = conn.begin().await.map_err(failed)?;
+    for statement in SCHEMA.split(';').map(str::trim).filter(|s| !s.is_empty()) {
+        sqlx::query(statement)
+            .execute(&mut *tx)
+            .await
+            .map_err(failed)?;
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO col (id, crt, mod, scm, ver, dty, usn, ls, conf, models, decks, dconf, tags)
+        VALUES (1, ?, ?, ?, 11, 0, 0, 0, ?, ?, ?, ?, '{}')
+        "#,
+    )
+    .bind(seconds)
+    .bind(millis)
+    .bind(millis)
+    .bind(collection_config(deck_id, model_id, deck.cards.len()).to_string())
+    .bind(basic_model(model_id, deck_id, seconds).to_string())
+    .bind(decks(deck_id, &deck.deck.title, seconds).to_string())
+    .bind(deck_options().to_string())
+    .execute(&mut *tx)
+    .await
+    .map_err(failed)?;
+
+    // Anki stores media as numbered entries, the manifest gives their file names
+    let mut packed: Vec<(&str, String)> = Vec::new();
+    for (position, card) in deck.cards.iter().enumerate() {
+        let id = millis + 2 + position as i64;
+        let mut back = text_to_html(&card.back);
+        match card.media_url.as_deref() {
+            Some(key) if media.contains_key(key) => {
+                let name = match packed.iter().find(|(packed_key, _)| *packed_key == key) {
+                    Some((_, name)) => name.clone(),
+                    None => {
+                        let name = key.rsplit('/').next().unwrap_or(key).to_string();
+                        packed.push((key, name.clone()));
+                        name
+                    }
+                };
+                back.push_str(&media_html(&name));
+            }
+            Some(url) => back.push_str(&media_html(url)),
+            None => {}
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO notes (id, guid, mid, mod, usn, tags, flds, sfld, csum, flags, data)
+            VALUES (?, ?, ?, ?, -1, ?, ?, ?, ?, 0, '')
+            "#,
+        )
+        .bind(id)
+        .bind(nanoid::nanoid!(10))
+        .bind(model_id)
+        .bind(seconds)
+        // Anki pads the tag list with spaces
+        .bind(match card.tags.is_empty() {
+            true => String::new(),
+            false => format!(" {} ", card.tags.join(" ")),
+        })
+        .bind(format!(
+            "{}{FIELD_SEPARATOR}{back}",
+            text_to_html(&card.front)
+        ))
+        .bind(&card.front)
+        .bind(checksum(&card.front))
+        .execute(&mut *tx)
+        .await
+        .map_err(failed)?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO cards (
+                id, nid, did, ord, mod, usn, type, queue, due, ivl,
+                factor, reps, lapses, left, odue, odid, flags, data
+            )
+            VALUES (?, ?, ?, 0, ?, -1, 0, 0, ?, 0, 0, 0, 0, 0, 0, 0, 0, '')
+            "#,
+        )
+        .bind(id)
+        .bind(id)
+        .bind(deck_id)
+        .bind(seconds)
+        .bind(position as i64 + 1)
+        .execute(&mut *tx)
+        .await
+        .map_err(failed)?;
+    }
+
+    tx.commit().await.map_err(failed)?;
+    conn.close().await.map_err(failed)?;
+
+    let collection = std::fs::read(file.path())
+        .map_err(|e| AppError::Internal(format!("Failed to read Anki collection: {e}")))?;
+
+    let zip_error =
+        |e: zip::result::ZipError| AppError::Internal(format!("Failed to write Anki package: {e}"));
+    let io_error =
+        |e: std::io::Error| AppError::Internal(format!("Failed to write Anki package: {e}"));
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    zip.start_file("collection.anki2", SimpleFileOptions::default())
+        .map_err(zip_error)?;
+    zip.write_all(&collection).map_err(io_error)?;
+    let mut manifest = serde_json::Map::new();
+    for (entry, (key, name)) in packed.iter().enumerate() {
+        zip.start_file(entry.to_string(), SimpleFileOptions::default())
+            .map_err(zip_error)?;
+        zip.write_all(&media[*key]).map_err(io_error)?;
+        manifest.insert(entry.to_string(), Value::String(name.clone()));
+    }
+    zip.start_file("media", SimpleFileOptions::default())
+        .map_err(zip_error)?;
+    zip.write_all(Value::Object(manifest).to_string().as_bytes())
+        .map_err(io_error)?;
+
+    Ok(zip.finish().map_err(zip_error)?.into_inner())
+}
+
+/// An uploaded package along with how much more of it we are willing to unpack
+struct Package {
+    archive: ZipArchive<Cursor<Vec<u8>>>,
+    budget: u64,
+}
+
+impl Package {
+    /// Unpacks an entry of the archive. The size in its header can't be trusted, so the
+    /// read itself is capped as well
+    fn read_entry(&mut self, name: &str) -> Result<Option<Vec<u8>>, AppError> {
+        let Ok(entry) = self.archive.by_name(name) else {
+            return Ok(None);
+        };
+        let too_large = |size: u64| match size > MAX_ENTRY_SIZE {
+            true => AppError::BadRequest(format!("\"{name}\" in the package is too large")),
+            false => AppError::BadRequest("The package is too large once unpacked".into()),
+        };
+        let limit = MAX_ENTRY_SIZE.min(self.budget);
+        if entry.size() > limit {
+            return Err(too_large(entry.size()));
+        }
+
+        let mut data = Vec::new();
+        if entry.take(limit + 1).read_to_end(&mut data).is_err() {
+            return Ok(None);
+        }
+        let size = data.len() as u64;
+        if size > limit {
+            return Err(too_large(size));
+        }
+        self.budget -= size;
+        Ok(Some(data))
+    }
+}
+
+/// Name of the deck holding most of the cards, without its parent decks
+async fn deck_title(conn: &mut SqliteConnection) -> Result<Option<String>, sqlx::Error> {
+    let deck_id: Option<i64> =
+        sqlx::query_scalar("SELECT did FROM cards GROUP BY did ORDER BY COUNT(*) DESC LIMIT 1")
+            .fetch_optional(&mut *conn)
+            .await?;
+    let decks: String = sqlx::query_scalar("SELECT decks FROM col")
+        .fetch_one(&mut *conn)
+        .await?;
+    let decks: Value = serde_json::from_str(&decks).unwrap_or_default();
+
+    Ok(deck_id
+        .and_then(|id| decks.get(id.to_string())?.get("name")?.as_str())
+        .and_then(|name| name.rsplit("::").next())
+        .filter(|name| *name != "Default")
+        .map(str::to_string))
+}
+
+/// First file referenced by an `<img src>` or a `[sound:]` tag
+fn first_media_ref(html: &str) -> Option<String> {
+    let image = html.find("src=").and_then(|start| {
+        let rest = &html[start + 4..];
+        let (rest, end) = match rest.chars().next()? {
+            quote @ ('"' | '\'') => (&rest[1..], rest[1..].find(quote)?),
+            _ => (rest, rest.find(|c: char| c.is_whitespace() || c == '>')?),
+        };
+        Some(&rest[..end])
+    });
+    let sound = || {
+        let start = html.find("[sound:")? + 7;
+        let end = html[start..].find(']')?;
+        Some(&html[start..start + end])
+    };
+
+    image
+        .or_else(sound)
+        .map(decode_entities)
+        .filter(|name| !name.is_empty())
+}
+
+/// Flattens a field to plain text, keeping line breaks and dropping media tags
+fn html_to_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(start) = rest.find(['<', '[']) {
+        text.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let close = if rest.starts_with('<') {
+            '>'
+        } else if rest.starts_with("[sound:") {
+            ']'
+        } else {
+            text.push('[');
+            rest = &rest[1..];
+            continue;
+        };
+
+        let Some(end) = rest.find(close) else {
+            break;
+        };
+        let tag = rest[1..end].trim_matches('/').to_lowercase();
+        if ["br", "div", "p", "li"]
+            .iter()
+            .any(|name| tag == *name || tag.starts_with(&format!("{name} ")))
+            && !text.ends_with('\n')
+        {
+            text.push('\n');
+        }
+        rest = &rest[end + 1..];
+    }
+    if !rest.starts_with(['<', '[']) {
+        text.push_str(rest);
+    }
+
+    decode_entities(text.trim())
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+fn text_to_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('\n', "<br>")
+}
+
+fn media_html(url: &str) -> String {
+    let url = url.replace('"', "&quot;");
+    match super::media_content_type(&url).split('/').next() {
+        Some("image") => format!("<br><img src=\"{url}\">"),
+        Some("audio") => format!("<br><audio controls src=\"{url}\"></audio>"),
+        _ => format!("<br><a href=\"{url}\">{url}</a>"),
+    }
+}
+
+/// Anki's duplicate check: the first 8 hex digits of the sort field's SHA-1
+fn checksum(field: &str) -> i64 {
+    let digest = sha1_smol::Sha1::from(field).digest().to_string();
+    i64::from_str_radix(&digest[..8], 16).unwrap_or_default()
+}
+
+fn collection_config(deck_id: i64, model_id: i64, card_count: usize) -> Value {
+    json!({
+        "nextPos": card_count + 1,
+        "estTimes": true,
+        "activeDecks": [deck_id],
+        "sortType": "noteFld",
+        "timeLim": 0,
+        "sortBackwards": false,
+        "addToCur": true,
+        "curDeck": deck_id,
+        "newBury": true,
+        "newSpread": 0,
+        "dueCounts": true,
+        "curModel": model_id.to_string(),
+        "collapseTime": 1200
+    })
+}
+
+fn basic_model(model_id: i64, deck_id: i64, modified: i64) -> Value {
+    let field = |name: &str, ord: i32| {
+        json!({
+            "name": name,
+            "ord": ord,
+            "sticky": false,
+            "rtl": false,
+            "font": "Arial",
+            "size": 20,
+            "media": []
+        })
+    };
+
+    json!({
+        model_id.to_string(): {
+            "id": model_id,
+            "name": "Basic",
+            "type": 0,
+            "mod": modified,
+            "usn": -1,
+            "sortf": 0,
+            "did": deck_id,
+            "tmpls": [{
+                "name": "Card 1",
+                "ord": 0,
+                "qfmt": "{{Front}}",
+                "afmt": "{{FrontSide}}\n\n<hr id=answer>\n\n{{Back}}",
+                "did": null,
+                "bqfmt": "",
+                "bafmt": ""
+            }],
+            "flds": [field("Front", 0), field("Back", 1)],
+            "css": ".card {\n font-family: arial;\n font-size: 20px;\n text-align: center;\n color: black;\n background-color: white;\n}\n",
+            "latexPre": "\\documentclass[12pt]{article}\n\\special{papersize=3in,5in}\n\\usepackage[utf8]{inputenc}\n\\usepackage{amssymb,amsmath}\n\\pagestyle{empty}\n\\setlength{\\parindent}{0in}\n\\begin{document}\n",
+            "latexPost": "\\end{document}",
+            "latexsvg": false,
+            "req": [[0, "any", [0]]],
+            "tags": [],
+            "vers": []
+        }
+    })
+}
+
+fn decks(deck_id: i64, title: &str, modified: i64) -> Value {
+    let deck = |id: i64, name: &str| {
+        json!({
+            "id": id,
+            "name": name,
+            "mod": modified,
+            "usn": -1,
+            "lrnToday": [0, 0],
+            "revToday": [0, 0],
+            "newToday": [0, 0],
+            "timeToday": [0, 0],
+            "collapsed": false,
+            "browserCollapsed": false,
+            "desc": "",
+            "dyn": 0,
+            "conf": 1,
+            "extendNew": 0,
+            "extendRev": 0
+        })
+    };
+
+    json!({
+        "1": deck(1, "Default"),
+        deck_id.to_string(): deck(deck_id, title),
+    })
+}
+
+fn deck_options() -> Value {
+    json!({
+        "1": {
+            "id": 1,
+            "name": "Default",
+            "mod": 0,
+            "usn": 0,
+            "maxTaken": 60,
+            "autoplay": true,
+            "timer": 0,
+            "replayq": true,
+            "dyn": false,
+            "new": {
+                "delays": [1.0, 10.0],
+                "ints": [1, 4, 0],
+                "initialFactor": 2500,
+                "order": 1,
+                "perDay": 20,
+                "bury": false
+            },
+            "rev": {
+                "perDay": 200,
+                "ease4": 1.3,
+                "ivlFct": 1.0,
+                "maxIvl": 36500,
+                "hardFactor": 1.2,
+                "bury": false
+            },
+            "lapse": {
+                "delays": [10.0],
+                "mult": 0.0,
+                "minInt": 1,
+                "leechFails": 8,
+                "leechAction": 1
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ogonek_types::{Card, CardType, DeckFull, Visibility};
+
+    fn test_deck() -> DeckWithCards {
+        DeckWithCards {
+            deck: DeckFull {
+                id: "deck".into(),
+                title: "Spanish::Animals".into(),
+                description: None,
+                visibility: Visibility::Private,
+                assignee: None,
+                is_subscribed: None,
+                created_by: "user".into(),
+                card_count: 2,
+                scheduler: None,
+                new_cards_per_day: None,
+                reviews_per_day: None,
+                created_at: Utc::now(),
+            },
+            cards: vec![
+                Card {
+                    id: "1".into(),
+                    front: "Gato".into(),
+                    back: "Cat\n<small>feline</small>".into(),
+                    media_url: Some("https://example.com/cat.jpg".into()),
+                    card_type: CardType::Basic,
+                    tags: vec!["animals".into(), "nouns".into()],
+                },
+                Card {
+                    id: "2".into(),
+                    front: "Perro & co".into(),
+                    back: "Dog".into(),
+                    media_url: None,
+                    card_type: CardType::Basic,
+                    tags: vec![],
+                },
+                Card {
+                    id: "3".into(),
+                    front: "Pájaro".into(),
+                    back: "Bird".into(),
+                    media_url: Some("deck-media/user/bird.png".into()),
+                    card_type: CardType::Basic,
+                    tags: vec![],
+                },
+            ],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_export_round_trips() {
+        let media = HashMap::from([("deck-media/user/bird.png".to_string(), b"png".to_vec())]);
+        let package = write_apkg(&test_deck(), &media).await.unwrap();
+        let imported = read_apkg(package).await.unwrap();
+
+        assert_eq!(imported.title.as_deref(), Some("Animals"));
+        assert_eq!(imported.cards.len(), 3);
+        assert_eq!(imported.cards[0].front, "Gato");
+        assert_eq!(imported.cards[0].back, "Cat\n<small>feline</small>");
+        assert!(
+            matches!(&imported.cards[0].media, Some(ImportedMedia::Url(url)) if url == "https://example.com/cat.jpg")
+        );
+        assert_eq!(imported.cards[0].tags, ["animals", "nouns"]);
+        assert_eq!(imported.cards[1].front, "Perro & co");
+        assert!(imported.cards[1].media.is_none());
+        assert!(imported.cards[1].tags.is_empty());
+        assert!(
+            matches!(&imported.cards[2].media, Some(ImportedMedia::File { name, data }) if name == "bird.png" && data.as_slice() == b"png")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_shared_media_is_unpacked_once() {
+        let mut deck = test_deck();
+        deck.cards[1].media_url = Some("deck-media/user/bird.png".into());
+        let media = HashMap::from([("deck-media/user/bird.png".to_string(), b"png".to_vec())]);
+        let package = write_apkg(&deck, &media).await.unwrap();
+        let imported = read_apkg(package).await.unwrap();
+
+        match (&imported.cards[1].media, &imported.cards[2].media) {
+            (
+                Some(ImportedMedia::File { data: a, .. }),
+                Some(ImportedMedia::File { data: b, .. }),
+            ) => {
+                assert!(Arc::ptr_eq(a, b))
+            }
+            _ => panic!("expected packaged media"),
+        }
+    }
+
+    #[test]
+    fn test_html_to_text() {
+        assert_eq!(
+            html_to_text("<div>Hello&nbsp;<b>world</b></div><div>[sound:hi.mp3]again</div>"),
+            "Hello world\nagain"
+        );
+        assert_eq!(html_to_text("a [b] c<br/>d"), "a [b] c\nd");
+    }
+
+    #[test]
+    fn test_first_media_ref() {
+        assert_eq!(
+            first_media_ref("Dog <img src=\"dog.jpg\">").as_deref(),
+            Some("dog.jpg")
+        );
+        assert_eq!(
+            first_media_ref("[sound:bark.mp3]").as_deref(),
+            Some("bark.mp3")
+        );
+        assert_eq!(first_media_ref("no media"), None);
+    }
+}
@@ -0,0 +1,186 @@
+use csv::{ReaderBuilder, WriterBuilder};
+use ogonek_types::DeckWithCards;
+
+use super::{ImportedCard, ImportedMedia};
+use crate::AppError;
+
+/// Reads front/back/media/tags rows from a CSV or TSV file.
+/// A header row is optional, without one the columns are taken in that order
+pub fn read_delimited(data: &[u8], extension: &str) -> Result<Vec<ImportedCard>, AppError> {
+    let text = String::from_utf8_lossy(data);
+    let text = text.trim_start_matches('\u{feff}');
+
+    // Anki's plain text export starts with "#separator:tab"-like directives
+    let body: String = text
+        .lines()
+        .skip_while(|line| is_directive(line))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let delimiter = match extension {
+        "tsv" => b'\t',
+        _ => sniff_delimiter(body.lines().next().unwrap_or_default()),
+    };
+
+    let mut reader = ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(body.as_bytes());
+
+    let mut columns = Columns::default();
+    let mut cards = Vec::new();
+
+    for (index, record) in reader.records().enumerate() {
+        let record = record.map_err(|e| AppError::BadRequest(format!("Invalid row: {e}")))?;
+
+        if index == 0
+            && let Some(header) = Columns::from_header(&record)
+        {
+            columns = header;
+            continue;
+        }
+
+        let field = |column: Option<usize>| {
+            column
+                .and_then(|i| record.get(i))
+                .map(str::trim)
+                .unwrap_or_default()
+                .to_string()
+        };
+
+        let front = field(Some(columns.front));
+        let back = field(Some(columns.back));
+        let media = field(columns.media);
+        let tags = field(columns.tags);
+
+        if front.is_empty() && back.is_empty() {
+            continue;
+        }
+
+        cards.push(ImportedCard {
+            front,
+            back,
+            media: (!media.is_empty()).then_some(ImportedMedia::Url(media)),
+            tags: tags.split_whitespace().map(str::to_string).collect(),
+        });
+    }
+
+    Ok(cards)
+}
+
+/// Writes a deck as a CSV file with a front,back,media_url,tags header
+pub fn write_csv(deck: &DeckWithCards) -> Result<Vec<u8>, AppError> {
+    let mut writer = WriterBuilder::new().from_writer(Vec::new());
+
+    let write_error = |e: csv::Error| AppError::Internal(format!("Failed to write CSV: {e}"));
+
+    writer
+        .write_record(["front", "back", "media_url", "tags"])
+        .map_err(write_error)?;
+    for card in &deck.cards {
+        writer
+            .write_record([
+                card.front.as_str(),
+                card.back.as_str(),
+                card.media_url.as_deref().unwrap_or_default(),
+                card.tags.join(" ").as_str(),
+            ])
+            .map_err(write_error)?;
+    }
+
+    writer
+        .into_inner()
+        .map_err(|e| AppError::Internal(format!("Failed to write CSV: {e}")))
+}
+
+struct Columns {
+    front: usize,
+    back: usize,
+    media: Option<usize>,
+    tags: Option<usize>,
+}
+
+impl Default for Columns {
+    fn default() -> Self {
+        Self {
+            front: 0,
+            back: 1,
+            media: Some(2),
+            tags: Some(3),
+        }
+    }
+}
+
+impl Columns {
+    /// Column positions if the row is a header naming at least the front
+    fn from_header(record: &csv::StringRecord) -> Option<Self> {
+        let position = |names: &[&str]| {
+            record
+                .iter()
+                .position(|column| names.contains(&column.trim().to_lowercase().as_str()))
+        };
+
+        let front = position(&["front", "question"])?;
+        Some(Self {
+            front,
+            back: position(&["back", "answer"]).unwrap_or(front + 1),
+            media: position(&["media", "media_url", "mediaurl"]),
+            tags: position(&["tags"]),
+        })
+    }
+}
+
+fn is_directive(line: &str) -> bool {
+    line.strip_prefix('#')
+        .and_then(|rest| rest.split_once(':'))
+        .is_some_and(|(key, _)| !key.is_empty() && key.chars().all(|c| c.is_ascii_alphabetic()))
+}
+
+fn sniff_delimiter(line: &str) -> u8 {
+    [b'\t', b';', b',']
+        .into_iter()
+        .max_by_key(|d| line.bytes().filter(|b| b == d).count())
+        .filter(|d| line.as_bytes().contains(d))
+        .unwrap_or(b',')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reads_csv_with_header() {
+        let data = "back,front,media_url,tags\n\"Hello, world\",Hola,https://example.com/a.png,greetings\nCat,Gato,,\n";
+
+        let cards = read_delimited(data.as_bytes(), "csv").unwrap();
+        assert_eq!(cards.len(), 2);
+        assert_eq!(cards[0].front, "Hola");
+        assert_eq!(cards[0].back, "Hello, world");
+        assert!(
+            matches!(&cards[0].media, Some(ImportedMedia::Url(url)) if url == "https://example.com/a.png")
+        );
+        assert_eq!(cards[0].tags, ["greetings"]);
+        assert!(cards[1].media.is_none());
+        assert!(cards[1].tags.is_empty());
+    }
+
+    #[test]
+    fn test_reads_tsv_without_header() {
+        let data = "Hola\tHello\nGato\tCat\n\n";
+
+        let cards = read_delimited(data.as_bytes(), "tsv").unwrap();
+        assert_eq!(cards.len(), 2);
+        assert_eq!(cards[1].front, "Gato");
+        assert_eq!(cards[1].back, "Cat");
+    }
+
+    #[test]
+    fn test_skips_anki_directives() {
+        let data = "#separator:tab\n#html:true\n#1 rule\tNever give up\n";
+
+        let cards = read_delimited(data.as_bytes(), "txt").unwrap();
+        assert_eq!(cards.len(), 1);
+        assert_eq!(cards[0].front, "#1 rule");
+    }
+}
@@ -0,0 +1,170 @@
+mod apkg;
+mod delimited;
+
+pub use apkg::{read_apkg, write_apkg};
+pub use delimited::{read_delimited, write_csv};
+
+use std::{collections::HashMap, sync::Arc};
+
+use ogonek_aws::S3Provider;
+use ogonek_types::DeckWithCards;
+
+use crate::AppError;
+
+/// Imported media lives in the private bucket under this prefix and cards keep only the key
+const MEDIA_KEY_PREFIX: &str = "deck-media/";
+
+/// Media attached to an imported card
+pub enum ImportedMedia {
+    /// Already hosted somewhere, kept as is
+    Url(String),
+    /// Shipped inside the file, has to be uploaded before the card can point to it.
+    /// Cards using the same file share its data
+    File { name: String, data: Arc<Vec<u8>> },
+}
+
+pub struct ImportedCard {
+    pub front: String,
+    pub back: String,
+    pub media: Option<ImportedMedia>,
+    pub tags: Vec<String>,
+}
+
+pub struct ImportedDeck {
+    pub title: Option<String>,
+    pub cards: Vec<ImportedCard>,
+}
+
+/// Parses an uploaded deck, picking the format from the file extension.
+/// Falls back to the file name when the file carries no title of its own
+pub async fn read_deck_file(filename: &str, data: Vec<u8>) -> Result<ImportedDeck, AppError> {
+    let (stem, extension) = match filename.rsplit_once('.') {
+        Some((stem, extension)) => (stem, extension.to_lowercase()),
+        None => (filename, String::new()),
+    };
+
+    let mut deck = match extension.as_str() {
+        "apkg" => read_apkg(data).await?,
+        "csv" | "tsv" | "txt" => ImportedDeck {
+            title: None,
+            cards: read_delimited(&data, &extension)?,
+        },
+        _ => {
+            return Err(AppError::BadRequest(format!(
+                "Unsupported file type: .{extension}"
+            )));
+        }
+    };
+
+    if deck.cards.is_empty() {
+        return Err(AppError::BadRequest("The file contains no cards".into()));
+    }
+    if deck.title.is_none() {
+        deck.title = Some(stem.to_string());
+    }
+
+    Ok(deck)
+}
+
+/// Content type for media pulled out of an import, guessed from its extension
+pub fn media_content_type(name: &str) -> &'static str {
+    let extension = name.rsplit('.').next().unwrap_or_default().to_lowercase();
+    match extension.as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "mp3" => "audio/mpeg",
+        "ogg" => "audio/ogg",
+        "wav" => "audio/wav",
+        "m4a" => "audio/mp4",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Bucket key for a media file imported by the user, keeping the extension when it is a sane one
+pub fn media_key(user_id: &str, name: &str) -> String {
+    let extension = name
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_lowercase())
+        .filter(|ext| {
+            !ext.is_empty() && ext.len() <= 8 && ext.chars().all(|c| c.is_ascii_alphanumeric())
+        })
+        .unwrap_or_else(|| "bin".to_string());
+    format!(
+        "{MEDIA_KEY_PREFIX}{user_id}/{}.{extension}",
+        nanoid::nanoid!()
+    )
+}
+
+/// Swaps a stored media key for a short-lived link. Media hosted elsewhere passes through
+pub async fn presign_media(
+    s3: &S3Provider,
+    media_url: &mut Option<String>,
+) -> Result<(), AppError> {
+    if let Some(key) = media_url
+        .as_deref()
+        .filter(|url| url.starts_with(MEDIA_KEY_PREFIX))
+    {
+        *media_url = Some(s3.get_presigned_inline_url(key).await?);
+    }
+    Ok(())
+}
+
+/// Fetches the media we host for a deck, keyed by the card's `media_url`, so exports can carry it
+pub async fn download_media(
+    s3: &S3Provider,
+    deck: &DeckWithCards,
+) -> Result<HashMap<String, Vec<u8>>, AppError> {
+    let mut media = HashMap::new();
+    for key in deck
+        .cards
+        .iter()
+        .filter_map(|card| card.media_url.as_deref())
+    {
+        if key.starts_with(MEDIA_KEY_PREFIX) && !media.contains_key(key) {
+            media.insert(key.to_string(), s3.download_object(key).await?);
+        }
+    }
+    Ok(media)
+}
+
+/// Turns a link handed out by `presign_media` back into its key, so saving
+/// a deck that was just read doesn't store a link that expires
+pub fn unsign_media(media_url: Option<String>) -> Option<String> {
+    let url = media_url?;
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+    match path.find(&format!("/{MEDIA_KEY_PREFIX}")) {
+        Some(start) if query.contains("X-Amz-Signature") => Some(path[start + 1..].to_string()),
+        _ => Some(url),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_media_key_keeps_plain_extensions() {
+        assert!(media_key("user", "cat.JPG").starts_with("deck-media/user/"));
+        assert!(media_key("user", "cat.JPG").ends_with(".jpg"));
+        assert!(media_key("user", "cat.x/../y").ends_with(".bin"));
+        assert!(media_key("user", "cat").ends_with(".bin"));
+    }
+
+    #[test]
+    fn test_unsign_media() {
+        let signed = "http://localhost:9000/bucket/deck-media/user/abc.png?X-Amz-Algorithm=AWS4-HMAC-SHA256&X-Amz-Signature=123";
+        assert_eq!(
+            unsign_media(Some(signed.into())),
+            Some("deck-media/user/abc.png".into())
+        );
+
+        let external = "https://example.com/deck-media/cat.png";
+        assert_eq!(unsign_media(Some(external.into())), Some(external.into()));
+        assert_eq!(unsign_media(None), None);
+    }
+}
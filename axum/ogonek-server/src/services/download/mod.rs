@@ -1,3 +1,19 @@
 mod markdown;
 mod pdf;
 pub use pdf::generate_pdf;
+
+/// Sanitizes a filename by replacing invalid characters
+///
+/// Converts special characters to underscores for safe file naming.
+pub fn sanitize_filename(title: &str) -> String {
+    title
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' => c,
+            ' ' => '_',
+            _ => '_',
+        })
+        .collect::<String>()
+        .trim_matches('_')
+        .to_string()
+}
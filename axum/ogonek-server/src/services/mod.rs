@@ -1,5 +1,6 @@
 pub mod auth;
 pub mod calendar;
+pub mod deck_io;
 pub mod observability;
 pub mod tools;
 pub use auth::*;
@@ -1,5 +1,5 @@
 use chrono::{DateTime, Utc};
-use ogonek_types::AuditLogCreate;
+use ogonek_types::{AuditLogCreate, UserRole};
 use serde_json::Value;
 use sqlx::types::ipnetwork::IpNetwork;
 
@@ -29,13 +29,24 @@ pub struct AuditBuilder {
 impl AuditBuilder {
     /// The bare minimum
     pub fn new(event_type: &str, action: &str, claims: &Claims, user_email: String) -> Self {
+        Self::for_user(event_type, action, &claims.sub, &claims.role, user_email)
+    }
+
+    /// For requests made before the user holds an access token, e.g. signing in
+    pub fn for_user(
+        event_type: &str,
+        action: &str,
+        user_id: &str,
+        user_role: &UserRole,
+        user_email: String,
+    ) -> Self {
         Self {
             event_type: event_type.to_string(),
             action: action.to_string(),
             outcome: "success".to_string(), // optimistic by default
-            user_id: Some(claims.sub.clone()),
+            user_id: Some(user_id.to_string()),
             user_email,
-            user_role: claims.role.to_string(),
+            user_role: user_role.to_string(),
             impersonated_by: None,
             resource_type: "unknown".to_string(), // you'll probably want to set this
             resource_id: None,
@@ -58,6 +69,31 @@ impl AuditBuilder {
             .tags(vec!["user_management".to_string()])
     }
 
+    /// Sign-ins, refreshes and logouts of a refresh session
+    pub fn session_operation(
+        action: &str,
+        user_id: &str,
+        user_role: &UserRole,
+        user_email: String,
+    ) -> Self {
+        Self::for_user("session.operation", action, user_id, user_role, user_email)
+            .resource_type("session")
+            .tags(vec!["authentication".to_string()])
+    }
+
+    /// Enrolling in, using and turning off two-factor authentication
+    pub fn two_factor_operation(
+        action: &str,
+        user_id: &str,
+        user_role: &UserRole,
+        user_email: String,
+    ) -> Self {
+        Self::for_user("2fa.operation", action, user_id, user_role, user_email)
+            .resource_type("user")
+            .resource_id(user_id.to_string())
+            .tags(vec!["authentication".to_string()])
+    }
+
     /// Set outcome (success/failure/pending)
     pub fn outcome(mut self, outcome: &str) -> Self {
         self.outcome = outcome.to_string();
@@ -0,0 +1,78 @@
+use ogonek_types::{CardFilter, CardState};
+
+use crate::AppError;
+
+/// How far back `failed` looks without a number of days
+const DEFAULT_FAILED_DAYS: i32 = 7;
+
+/// Parses a filter expression. Terms are `deck:ID`, `tag:NAME`, `failed` or `failed:DAYS`,
+/// `is:new|learning|review|relearning`; everything else is searched for in the card text
+pub fn parse_card_filter(query: &str) -> Result<CardFilter, AppError> {
+    let mut filter = CardFilter::default();
+    let mut text = Vec::new();
+
+    for term in query.split_whitespace() {
+        match term.split_once(':') {
+            Some(("deck", deck_id)) if !deck_id.is_empty() => {
+                filter.deck_id = Some(deck_id.to_string());
+            }
+            Some(("tag", tag)) if !tag.is_empty() => filter.tags.push(tag.to_lowercase()),
+            Some(("failed", days)) => {
+                let days = days.parse().ok().filter(|days| *days > 0).ok_or_else(|| {
+                    AppError::BadRequest(format!("Invalid number of days: {days}"))
+                })?;
+                filter.failed_within_days = Some(days);
+            }
+            Some(("is", state)) => {
+                filter.state = Some(match state {
+                    "new" => CardState::New,
+                    "learning" => CardState::Learning,
+                    "review" => CardState::Review,
+                    "relearning" => CardState::Relearning,
+                    _ => return Err(AppError::BadRequest(format!("Unknown card state: {state}"))),
+                });
+            }
+            _ if term == "failed" => filter.failed_within_days = Some(DEFAULT_FAILED_DAYS),
+            _ => text.push(term),
+        }
+    }
+
+    if !text.is_empty() {
+        filter.text = Some(text.join(" "));
+    }
+
+    Ok(filter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_terms() {
+        let filter = parse_card_filter("tag:Verbs failed:14 is:review deck:abc to speak").unwrap();
+
+        assert_eq!(filter.tags, ["verbs"]);
+        assert_eq!(filter.failed_within_days, Some(14));
+        assert_eq!(filter.state, Some(CardState::Review));
+        assert_eq!(filter.deck_id.as_deref(), Some("abc"));
+        assert_eq!(filter.text.as_deref(), Some("to speak"));
+        assert!(filter.is_custom());
+    }
+
+    #[test]
+    fn test_deck_alone_is_not_custom() {
+        let filter = parse_card_filter("  deck:abc ").unwrap();
+        assert!(!filter.is_custom());
+
+        let failed = parse_card_filter("failed").unwrap();
+        assert_eq!(failed.failed_within_days, Some(DEFAULT_FAILED_DAYS));
+    }
+
+    #[test]
+    fn test_rejects_invalid_terms() {
+        assert!(parse_card_filter("failed:soon").is_err());
+        assert!(parse_card_filter("failed:0").is_err());
+        assert!(parse_card_filter("is:mature").is_err());
+    }
+}
@@ -0,0 +1,127 @@
+use std::sync::LazyLock;
+
+use ogonek_types::{CardProgressWithFields, CardType};
+use regex::{Captures, Regex};
+
+/// `{{c1::answer}}` or `{{c1::answer::hint}}`, the same markup Anki uses
+static CLOZE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{\{c(\d+)::(.*?)(?:::(.*?))?\}\}").unwrap());
+
+/// Turns a stored card into the side the learner is studying
+pub fn render_card(mut card: CardProgressWithFields) -> CardProgressWithFields {
+    match card.card_type {
+        CardType::Reversed if card.ordinal == 1 => {
+            std::mem::swap(&mut card.front, &mut card.back);
+        }
+        CardType::Cloze => {
+            let ordinal = card.ordinal.to_string();
+            let question = CLOZE.replace_all(&card.front, |caps: &Captures| {
+                if caps[1] == ordinal {
+                    format!("[{}]", caps.get(3).map_or("...", |hint| hint.as_str()))
+                } else {
+                    caps[2].to_string()
+                }
+            });
+            let answer = CLOZE.replace_all(&card.front, "$2");
+
+            // The back of a cloze card holds extra notes shown with the answer
+            card.back = match card.back.trim() {
+                "" => answer.into_owned(),
+                extra => format!("{answer}\n\n{extra}"),
+            };
+            card.front = question.into_owned();
+        }
+        // The back is the expected answer, it only comes back once the answer is graded
+        CardType::Typed => card.back.clear(),
+        _ => {}
+    }
+
+    card
+}
+
+/// Type for imported cards, which only carry a front and a back
+pub fn infer_card_type(front: &str) -> CardType {
+    if CLOZE.is_match(front) {
+        CardType::Cloze
+    } else {
+        CardType::Basic
+    }
+}
+
+/// Grades a typed answer, ignoring case, punctuation and spacing
+pub fn check_typed_answer(expected: &str, answer: &str) -> bool {
+    normalize_answer(expected) == normalize_answer(answer)
+}
+
+fn normalize_answer(text: &str) -> String {
+    text.to_lowercase()
+        .replace('ё', "е")
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ogonek_types::CardState;
+
+    fn card(card_type: CardType, ordinal: i32, front: &str, back: &str) -> CardProgressWithFields {
+        CardProgressWithFields {
+            id: "progress".into(),
+            front: front.into(),
+            back: back.into(),
+            media_url: None,
+            card_type,
+            state: CardState::New,
+            ordinal,
+        }
+    }
+
+    #[test]
+    fn test_reversed_card_swaps_sides() {
+        let forward = render_card(card(CardType::Reversed, 0, "Perro", "Dog"));
+        assert_eq!(forward.front, "Perro");
+
+        let reverse = render_card(card(CardType::Reversed, 1, "Perro", "Dog"));
+        assert_eq!(reverse.front, "Dog");
+        assert_eq!(reverse.back, "Perro");
+    }
+
+    #[test]
+    fn test_cloze_hides_only_its_deletion() {
+        let text = "{{c1::El perro}} {{c2::ladra::verb}} mucho";
+
+        let first = render_card(card(CardType::Cloze, 1, text, ""));
+        assert_eq!(first.front, "[...] ladra mucho");
+        assert_eq!(first.back, "El perro ladra mucho");
+
+        let second = render_card(card(CardType::Cloze, 2, text, "Present tense"));
+        assert_eq!(second.front, "El perro [verb] mucho");
+        assert_eq!(second.back, "El perro ladra mucho\n\nPresent tense");
+    }
+
+    #[test]
+    fn test_typed_card_hides_answer() {
+        let typed = render_card(card(CardType::Typed, 0, "Dog", "Perro"));
+        assert_eq!(typed.front, "Dog");
+        assert!(typed.back.is_empty());
+    }
+
+    #[test]
+    fn test_infer_card_type() {
+        assert_eq!(
+            infer_card_type("{{c1::Madrid}} is in Spain"),
+            CardType::Cloze
+        );
+        assert_eq!(infer_card_type("Madrid"), CardType::Basic);
+    }
+
+    #[test]
+    fn test_typed_answer_is_normalized() {
+        assert!(check_typed_answer("Ça va?", "  ça   VA "));
+        assert!(check_typed_answer("Ёлка", "елка"));
+        assert!(!check_typed_answer("perro", "pero"));
+    }
+}
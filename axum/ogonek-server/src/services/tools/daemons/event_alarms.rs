@@ -0,0 +1,97 @@
+use std::{fmt::Debug, future::Future};
+
+use chrono::{Duration, Utc};
+use ogonek_db::core::calendar::event_alarm::{claim_delivery, read_due, release_delivery};
+use ogonek_notifications::NotificationType;
+use ogonek_types::{AlarmChannel, DueAlarm};
+
+use crate::{app::AppState, services::calendar::event_zone};
+
+/// How often due alarms are looked for
+const TICK: std::time::Duration = std::time::Duration::from_secs(60);
+/// Alarms missed while the server was down are still sent within this window
+const LOOKBACK: Duration = Duration::minutes(30);
+
+pub async fn alarm_scheduler(state: AppState) {
+    let mut interval = tokio::time::interval(TICK);
+    loop {
+        interval.tick().await;
+
+        let now = Utc::now();
+        match read_due(&state.db, now - LOOKBACK, now).await {
+            Ok(alarms) => {
+                for alarm in alarms {
+                    deliver(&state, &alarm).await;
+                }
+            }
+            Err(e) => {
+                tracing::error!("Failed to fetch due alarms: {:?}", e);
+            }
+        }
+    }
+}
+
+async fn deliver(state: &AppState, alarm: &DueAlarm) {
+    let starts_at = alarm
+        .occurrence
+        .with_timezone(&event_zone(alarm.dtstart_tz.as_deref()))
+        .format("%B %d, %H:%M %Z")
+        .to_string();
+    let notification = NotificationType::EventReminder {
+        summary: alarm.summary.clone(),
+        starts_at: starts_at.clone(),
+        event_id: alarm.event_id.clone(),
+    };
+
+    for email in &alarm.attendee_email {
+        let send = state.ses.send_event_reminder(
+            email,
+            &alarm.summary,
+            &starts_at,
+            alarm.description.as_deref(),
+        );
+        deliver_once(state, alarm, AlarmChannel::Email, email, send).await;
+    }
+    for telegram_id in &alarm.attendee_telegram_id {
+        let send = state
+            .notification_service
+            .notify_telegram(telegram_id, &notification);
+        deliver_once(state, alarm, AlarmChannel::Telegram, telegram_id, send).await;
+    }
+    for user_id in &alarm.attendee_apns {
+        let send = state
+            .notification_service
+            .notify_devices(user_id, &notification);
+        deliver_once(state, alarm, AlarmChannel::Apns, user_id, send).await;
+    }
+}
+
+/// Sends unless the delivery was already recorded, a failed one is retried next tick
+async fn deliver_once<E: Debug>(
+    state: &AppState,
+    alarm: &DueAlarm,
+    channel: AlarmChannel,
+    recipient: &str,
+    send: impl Future<Output = Result<(), E>>,
+) {
+    match claim_delivery(&state.db, alarm, channel, recipient).await {
+        Ok(true) => {}
+        Ok(false) => return,
+        Err(e) => {
+            tracing::error!("Failed to record alarm {}: {:?}", alarm.alarm_id, e);
+            return;
+        }
+    }
+
+    if let Err(e) = send.await {
+        tracing::error!(
+            "Failed to deliver alarm {} through {:?}: {:?}",
+            alarm.alarm_id,
+            channel,
+            e
+        );
+        if let Err(e) = release_delivery(&state.db, alarm, channel, recipient).await {
+            tracing::error!("Failed to release alarm {}: {:?}", alarm.alarm_id, e);
+        }
+    }
+}
@@ -1 +1,2 @@
+pub mod event_alarms;
 pub mod task_cleanup;
@@ -2,16 +2,31 @@ use axum::{
     extract::{ConnectInfo, FromRequestParts},
     http::{
         Extensions, StatusCode,
-        header::{HeaderMap, HeaderName, USER_AGENT},
+        header::{HeaderMap, USER_AGENT},
         request::Parts,
     },
 };
 use sqlx::types::ipnetwork::{IpNetwork, Ipv4Network, Ipv6Network};
-use std::net::{IpAddr, SocketAddr};
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::LazyLock,
+};
 pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Reverse proxies whose forwarding headers are believed, from `TRUSTED_PROXIES` as
+/// comma-separated addresses or CIDR ranges. Anyone else could send any address.
+static TRUSTED_PROXIES: LazyLock<Vec<IpNetwork>> = LazyLock::new(|| {
+    std::env::var("TRUSTED_PROXIES")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|proxy| proxy.trim().parse().ok())
+        .collect()
+});
 #[derive(Debug, Clone)]
 pub struct RequestMetadata {
     pub user_agent: String,
+    /// The device name the client sent, or the platform guessed from the user agent
+    pub device: Option<String>,
     pub ip_address: Option<IpNetwork>,
     pub request_id: String,
     pub session_id: Option<String>,
@@ -32,7 +47,7 @@ where
             .unwrap_or("unknown")
             .to_string();
 
-        let ip_string = extract_client_ip(&parts.headers, &parts.extensions)
+        let ip_string = extract_client_ip(&parts.headers, &parts.extensions, &TRUSTED_PROXIES)
             .unwrap_or_else(|| "unknown".to_string());
 
         let ip_address = if let Ok(ip) = ip_string.parse::<IpAddr>() {
@@ -56,12 +71,15 @@ where
             .map(|s| s.to_string())
             .expect("Request ID should always be set by SetRequestIdLayer");
 
+        let device = extract_device(&parts.headers, &user_agent);
+
         // Extract session ID from cookie or header
         let session_id = extract_session_id(&parts.headers);
 
         tracing::info!(user_agent, request_id, session_id, ip_string);
         Ok(RequestMetadata {
             user_agent,
+            device,
             ip_address,
             request_id,
             session_id,
@@ -69,34 +87,72 @@ where
     }
 }
 
-fn extract_client_ip(headers: &HeaderMap, extensions: &Extensions) -> Option<String> {
-    // Priority order for IP extraction (reverse proxy aware)
+fn extract_client_ip(
+    headers: &HeaderMap,
+    extensions: &Extensions,
+    trusted_proxies: &[IpNetwork],
+) -> Option<String> {
+    let peer = extensions
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    let is_trusted = |ip: IpAddr| trusted_proxies.iter().any(|proxy| proxy.contains(ip));
+
+    // Forwarding headers only mean something when a proxy of ours set them
+    if !peer.is_some_and(is_trusted) {
+        return peer.map(|ip| ip.to_string());
+    }
+
+    // Each proxy appends the address it got the request from, so the client is the
+    // last entry that isn't one of ours. Whatever comes before it is made up.
+    if let Some(forwarded) = headers.get("x-forwarded-for")
+        && let Ok(forwarded) = forwarded.to_str()
+        && let Some(client) = forwarded
+            .rsplit(',')
+            .filter_map(|ip| ip.trim().parse::<IpAddr>().ok())
+            .find(|&ip| !is_trusted(ip))
+    {
+        return Some(client.to_string());
+    }
+
     let ip_headers = [
-        "x-forwarded-for",
         "x-real-ip",
         "cf-connecting-ip", // Cloudflare
         "x-client-ip",
     ];
-
-    // Check proxy headers first
     for header_name in ip_headers {
-        let header_name = HeaderName::from_static(header_name);
-
-        if let Some(header_value) = headers.get(&header_name)
-            && let Ok(ip_str) = header_value.to_str()
+        if let Some(ip) = headers
+            .get(header_name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<IpAddr>().ok())
         {
-            // X-Forwarded-For can contain multiple IPs, take the first
-            let ip = ip_str.split(',').next()?.trim();
-            if !ip.is_empty() {
-                return Some(ip.to_string());
-            }
+            return Some(ip.to_string());
         }
     }
 
-    // Fallback to connection info
-    extensions
-        .get::<ConnectInfo<SocketAddr>>()
-        .map(|ConnectInfo(addr)| addr.ip().to_string())
+    peer.map(|ip| ip.to_string())
+}
+
+fn extract_device(headers: &HeaderMap, user_agent: &str) -> Option<String> {
+    if let Some(device) = headers.get("x-device-name")
+        && let Ok(device_str) = device.to_str()
+        && !device_str.trim().is_empty()
+    {
+        return Some(device_str.trim().chars().take(255).collect());
+    }
+
+    // Order matters: iPhone user agents mention Mac OS X, Android ones Linux
+    let platforms = [
+        ("iPhone", "iPhone"),
+        ("iPad", "iPad"),
+        ("Android", "Android"),
+        ("Mac OS X", "Mac"),
+        ("Windows", "Windows"),
+        ("Linux", "Linux"),
+    ];
+    platforms
+        .iter()
+        .find(|(needle, _)| user_agent.contains(needle))
+        .map(|(_, platform)| platform.to_string())
 }
 
 fn extract_session_id(headers: &HeaderMap) -> Option<String> {
@@ -131,3 +187,41 @@ fn extract_session_id(headers: &HeaderMap) -> Option<String> {
 
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request_from(peer: &str, forwarded_for: &str) -> (HeaderMap, Extensions) {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", forwarded_for.parse().unwrap());
+        let mut extensions = Extensions::new();
+        extensions.insert(ConnectInfo(SocketAddr::new(peer.parse().unwrap(), 443)));
+        (headers, extensions)
+    }
+
+    #[test]
+    fn test_forwarding_headers_need_a_trusted_proxy() {
+        let proxies: Vec<IpNetwork> = vec!["10.0.0.0/8".parse().unwrap()];
+
+        // Straight from the client, the header is ignored
+        let (headers, extensions) = request_from("203.0.113.7", "198.51.100.1");
+        assert_eq!(
+            extract_client_ip(&headers, &extensions, &proxies).as_deref(),
+            Some("203.0.113.7")
+        );
+
+        // Through our proxy, the client is the last address the proxy didn't add itself
+        let (headers, extensions) = request_from("10.0.0.2", "198.51.100.1, 203.0.113.7, 10.0.0.3");
+        assert_eq!(
+            extract_client_ip(&headers, &extensions, &proxies).as_deref(),
+            Some("203.0.113.7")
+        );
+
+        let (headers, extensions) = request_from("10.0.0.2", "198.51.100.1");
+        assert_eq!(
+            extract_client_ip(&headers, &extensions, &[]).as_deref(),
+            Some("10.0.0.2")
+        );
+    }
+}
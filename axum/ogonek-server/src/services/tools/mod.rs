@@ -1,7 +1,13 @@
+mod card_filter;
+mod card_types;
 mod daemons;
 mod extractors;
-mod sm2;
+mod scheduler;
+mod study_queue;
 
-pub use daemons::task_cleanup;
+pub use card_filter::*;
+pub use card_types::*;
+pub use daemons::{event_alarms, task_cleanup};
 pub use extractors::*;
-pub use sm2::SM2Calculator;
+pub use scheduler::*;
+pub use study_queue::*;
@@ -0,0 +1,257 @@
+use chrono::{DateTime, Duration, Utc};
+use ogonek_types::{CardProgress, CardState, UpdateCardProgress};
+
+use super::Scheduler;
+
+const DECAY: f64 = -0.5;
+const FACTOR: f64 = 19.0 / 81.0;
+
+/// FSRS-4.5 default parameters, fitted on the public Anki review dataset
+const DEFAULT_WEIGHTS: [f64; 17] = [
+    0.4872, 1.4003, 3.7145, 13.8206, 5.1618, 1.2298, 0.8975, 0.031, 1.6474, 0.1367, 1.0461, 2.1072,
+    0.0793, 0.3246, 1.587, 0.2272, 2.8755,
+];
+
+/// Free Spaced Repetition Scheduler. Models each card with a stability
+/// (days until recall probability falls to 90%) and a difficulty (1-10),
+/// and schedules the next review when retrievability hits the desired retention
+pub struct FSRSScheduler {
+    weights: [f64; 17],
+    desired_retention: f64,
+    maximum_interval: i32,
+}
+
+impl Default for FSRSScheduler {
+    fn default() -> Self {
+        Self {
+            weights: DEFAULT_WEIGHTS,
+            desired_retention: 0.9,
+            maximum_interval: 36500,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Rating {
+    Again = 1,
+    Hard = 2,
+    Good = 3,
+    Easy = 4,
+}
+
+impl Rating {
+    /// Maps the 0-5 SM-2 quality scale onto the four FSRS buttons
+    fn from_quality(quality: i32) -> Self {
+        match quality {
+            ..=2 => Rating::Again,
+            3 => Rating::Hard,
+            4 => Rating::Good,
+            _ => Rating::Easy,
+        }
+    }
+
+    fn value(self) -> f64 {
+        self as i32 as f64
+    }
+}
+
+impl FSRSScheduler {
+    fn retrievability(&self, elapsed_days: f64, stability: f64) -> f64 {
+        (1.0 + FACTOR * elapsed_days / stability).powf(DECAY)
+    }
+
+    fn initial_stability(&self, rating: Rating) -> f64 {
+        self.weights[rating as usize - 1].max(0.1)
+    }
+
+    fn initial_difficulty(&self, rating: Rating) -> f64 {
+        (self.weights[4] - (rating.value() - 3.0) * self.weights[5]).clamp(1.0, 10.0)
+    }
+
+    fn next_difficulty(&self, difficulty: f64, rating: Rating) -> f64 {
+        let next = difficulty - self.weights[6] * (rating.value() - 3.0);
+        // Mean reversion towards the difficulty of a fresh card answered "good"
+        (self.weights[7] * self.weights[4] + (1.0 - self.weights[7]) * next).clamp(1.0, 10.0)
+    }
+
+    fn next_recall_stability(
+        &self,
+        difficulty: f64,
+        stability: f64,
+        retrievability: f64,
+        rating: Rating,
+    ) -> f64 {
+        let hard_penalty = if rating == Rating::Hard {
+            self.weights[15]
+        } else {
+            1.0
+        };
+        let easy_bonus = if rating == Rating::Easy {
+            self.weights[16]
+        } else {
+            1.0
+        };
+
+        stability
+            * (1.0
+                + self.weights[8].exp()
+                    * (11.0 - difficulty)
+                    * stability.powf(-self.weights[9])
+                    * ((self.weights[10] * (1.0 - retrievability)).exp() - 1.0)
+                    * hard_penalty
+                    * easy_bonus)
+    }
+
+    fn next_forget_stability(&self, difficulty: f64, stability: f64, retrievability: f64) -> f64 {
+        let next = self.weights[11]
+            * difficulty.powf(-self.weights[12])
+            * ((stability + 1.0).powf(self.weights[13]) - 1.0)
+            * (self.weights[14] * (1.0 - retrievability)).exp();
+
+        // Forgetting a card never makes it more stable
+        next.min(stability)
+    }
+
+    fn next_interval(&self, stability: f64) -> i32 {
+        let interval = stability / FACTOR * (self.desired_retention.powf(1.0 / DECAY) - 1.0);
+        (interval.round() as i32).clamp(1, self.maximum_interval)
+    }
+
+    /// Memory state of a card that has been reviewed before. Cards scheduled by SM-2 so far
+    /// have none, so it is approximated from their interval and ease
+    fn memory_state(&self, progress: &CardProgress) -> Option<(f64, f64)> {
+        match (progress.stability, progress.difficulty) {
+            (Some(stability), Some(difficulty)) => Some((stability, difficulty)),
+            _ if progress.review_count > 0 => {
+                let difficulty = (5.0 - (progress.ease_factor - 2.5) * 4.0).clamp(1.0, 10.0);
+                Some((progress.interval.max(1) as f64, difficulty))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Scheduler for FSRSScheduler {
+    fn next_review(
+        &self,
+        progress: &CardProgress,
+        quality: i32,
+        now: DateTime<Utc>,
+    ) -> UpdateCardProgress {
+        let rating = Rating::from_quality(quality);
+
+        let (stability, difficulty) = match (self.memory_state(progress), progress.last_reviewed) {
+            (Some((stability, difficulty)), Some(last_reviewed)) => {
+                let elapsed_days = ((now - last_reviewed).num_seconds() as f64 / 86400.0).max(0.0);
+                let retrievability = self.retrievability(elapsed_days, stability);
+
+                let next_stability = if rating == Rating::Again {
+                    self.next_forget_stability(difficulty, stability, retrievability)
+                } else {
+                    self.next_recall_stability(difficulty, stability, retrievability, rating)
+                };
+
+                (next_stability, self.next_difficulty(difficulty, rating))
+            }
+            _ => (
+                self.initial_stability(rating),
+                self.initial_difficulty(rating),
+            ),
+        };
+
+        let interval = self.next_interval(stability);
+
+        UpdateCardProgress {
+            review_count: progress.review_count + 1,
+            ease_factor: progress.ease_factor,
+            interval,
+            last_reviewed: now,
+            due_date: now + Duration::days(interval.into()),
+            stability: Some(stability),
+            difficulty: Some(difficulty),
+            state: CardState::Review,
+            step: 0,
+            lapses: progress.lapses,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn progress(
+        review_count: i32,
+        interval: i32,
+        stability: Option<f64>,
+        difficulty: Option<f64>,
+        last_reviewed: Option<DateTime<Utc>>,
+    ) -> CardProgress {
+        CardProgress {
+            id: "progress".into(),
+            user_id: "user".into(),
+            card_id: "card".into(),
+            review_count,
+            last_reviewed,
+            due_date: None,
+            ease_factor: 2.5,
+            interval,
+            stability,
+            difficulty,
+            state: CardState::Review,
+            step: 0,
+            lapses: 0,
+        }
+    }
+
+    #[test]
+    fn test_new_card_uses_initial_stability() {
+        let scheduler = FSRSScheduler::default();
+        let now = Utc::now();
+
+        let good = scheduler.next_review(&progress(0, 1, None, None, None), 4, now);
+        assert_eq!(good.stability, Some(DEFAULT_WEIGHTS[2]));
+        assert_eq!(good.interval, 4);
+        assert_eq!(good.review_count, 1);
+
+        let again = scheduler.next_review(&progress(0, 1, None, None, None), 1, now);
+        assert_eq!(again.interval, 1);
+        assert!(again.difficulty.unwrap() > good.difficulty.unwrap());
+    }
+
+    #[test]
+    fn test_successful_review_grows_interval() {
+        let scheduler = FSRSScheduler::default();
+        let now = Utc::now();
+        let card = progress(3, 10, Some(10.0), Some(5.0), Some(now - Duration::days(10)));
+
+        let good = scheduler.next_review(&card, 4, now);
+        let easy = scheduler.next_review(&card, 5, now);
+        assert!(good.stability.unwrap() > 10.0);
+        assert!(good.interval > 10);
+        assert!(easy.interval > good.interval);
+    }
+
+    #[test]
+    fn test_lapse_shrinks_stability() {
+        let scheduler = FSRSScheduler::default();
+        let now = Utc::now();
+        let card = progress(5, 30, Some(30.0), Some(5.0), Some(now - Duration::days(30)));
+
+        let again = scheduler.next_review(&card, 0, now);
+        assert!(again.stability.unwrap() < 30.0);
+        assert!(again.interval < 30);
+        assert!(again.difficulty.unwrap() > 5.0);
+    }
+
+    #[test]
+    fn test_sm2_card_is_migrated_from_interval() {
+        let scheduler = FSRSScheduler::default();
+        let now = Utc::now();
+        let card = progress(4, 15, None, None, Some(now - Duration::days(15)));
+
+        let good = scheduler.next_review(&card, 4, now);
+        assert!(good.stability.unwrap() > 15.0);
+        assert!(good.difficulty.is_some());
+    }
+}
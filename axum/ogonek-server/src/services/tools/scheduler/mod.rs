@@ -0,0 +1,28 @@
+mod fsrs;
+mod sm2;
+mod steps;
+
+use chrono::{DateTime, Utc};
+use ogonek_types::{CardProgress, SchedulerKind, UpdateCardProgress};
+
+pub use fsrs::FSRSScheduler;
+pub use sm2::SM2Calculator;
+pub use steps::LearningSteps;
+
+/// A spaced-repetition algorithm: takes the current progress on a card
+/// and the quality of the answer (0-5), returns where the card goes next
+pub trait Scheduler: Send + Sync {
+    fn next_review(
+        &self,
+        progress: &CardProgress,
+        quality: i32,
+        now: DateTime<Utc>,
+    ) -> UpdateCardProgress;
+}
+
+pub fn scheduler_for(kind: SchedulerKind) -> Box<dyn Scheduler> {
+    match kind {
+        SchedulerKind::Sm2 => Box::new(SM2Calculator::default()),
+        SchedulerKind::Fsrs => Box::new(FSRSScheduler::default()),
+    }
+}
@@ -1,3 +1,8 @@
+use chrono::{DateTime, Duration, Utc};
+use ogonek_types::{CardProgress, CardState, UpdateCardProgress};
+
+use super::Scheduler;
+
 pub struct SM2Calculator {
     min_ease_factor: f64,
     max_ease_factor: f64,
@@ -45,3 +50,33 @@ impl SM2Calculator {
         (new_ease, new_interval, new_review_count)
     }
 }
+
+impl Scheduler for SM2Calculator {
+    fn next_review(
+        &self,
+        progress: &CardProgress,
+        quality: i32,
+        now: DateTime<Utc>,
+    ) -> UpdateCardProgress {
+        let (ease_factor, interval, review_count) = self.calculate_next_review(
+            quality,
+            progress.ease_factor,
+            progress.interval,
+            progress.review_count,
+        );
+
+        // SM-2 keeps no memory state; FSRS will rebuild it from ease and interval if switched back on
+        UpdateCardProgress {
+            review_count,
+            ease_factor,
+            interval,
+            last_reviewed: now,
+            due_date: now + Duration::days(interval.into()),
+            stability: None,
+            difficulty: None,
+            state: CardState::Review,
+            step: 0,
+            lapses: progress.lapses,
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use chrono::{DateTime, Duration, Utc};
+use ogonek_types::{CardProgress, CardState, UpdateCardProgress};
+
+use super::Scheduler;
+
+/// Anki-style intra-day steps. New cards walk through the learning steps and
+/// forgotten cards through the relearning steps before the scheduler takes over again
+pub struct LearningSteps {
+    /// Minutes
+    learning: Vec<i32>,
+    /// Minutes
+    relearning: Vec<i32>,
+}
+
+impl LearningSteps {
+    pub fn new(learning: Vec<i32>, relearning: Vec<i32>) -> Self {
+        Self {
+            learning,
+            relearning,
+        }
+    }
+
+    pub fn next_review(
+        &self,
+        scheduler: &dyn Scheduler,
+        progress: &CardProgress,
+        quality: i32,
+        now: DateTime<Utc>,
+    ) -> UpdateCardProgress {
+        match progress.state {
+            CardState::New | CardState::Learning => {
+                match self.next_step(&self.learning, progress, quality) {
+                    Some(step) => {
+                        self.stay(CardState::Learning, step, &self.learning, progress, now)
+                    }
+                    // Graduating answers are the first ones the scheduler gets to see
+                    None => scheduler.next_review(progress, quality, now),
+                }
+            }
+            CardState::Relearning => {
+                match self.next_step(&self.relearning, progress, quality) {
+                    Some(step) => {
+                        self.stay(CardState::Relearning, step, &self.relearning, progress, now)
+                    }
+                    // The lapse was already scheduled when the card was failed
+                    None => UpdateCardProgress {
+                        state: CardState::Review,
+                        step: 0,
+                        due_date: now + Duration::days(progress.interval.into()),
+                        ..self.unchanged(progress, now)
+                    },
+                }
+            }
+            CardState::Review => {
+                let mut update = scheduler.next_review(progress, quality, now);
+
+                if quality < 3 {
+                    update.lapses = progress.lapses + 1;
+
+                    if let Some(first) = self.relearning.first() {
+                        update.state = CardState::Relearning;
+                        update.step = 0;
+                        update.due_date = now + Duration::minutes((*first).into());
+                    }
+                }
+
+                update
+            }
+        }
+    }
+
+    /// The step the card moves to, or None once it graduates
+    fn next_step(&self, steps: &[i32], progress: &CardProgress, quality: i32) -> Option<i32> {
+        let step = match quality {
+            ..=2 => 0,
+            3 => progress.step,     // Hard repeats the current step
+            4 => progress.step + 1, // Good moves on
+            _ => return None,       // Easy skips the remaining steps
+        };
+
+        ((step as usize) < steps.len()).then_some(step)
+    }
+
+    fn stay(
+        &self,
+        state: CardState,
+        step: i32,
+        steps: &[i32],
+        progress: &CardProgress,
+        now: DateTime<Utc>,
+    ) -> UpdateCardProgress {
+        UpdateCardProgress {
+            state,
+            step,
+            due_date: now + Duration::minutes(steps[step as usize].into()),
+            ..self.unchanged(progress, now)
+        }
+    }
+
+    fn unchanged(&self, progress: &CardProgress, now: DateTime<Utc>) -> UpdateCardProgress {
+        UpdateCardProgress {
+            review_count: progress.review_count,
+            due_date: progress.due_date.unwrap_or(now),
+            ease_factor: progress.ease_factor,
+            interval: progress.interval,
+            last_reviewed: now,
+            stability: progress.stability,
+            difficulty: progress.difficulty,
+            state: progress.state,
+            step: progress.step,
+            lapses: progress.lapses,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::SM2Calculator;
+
+    fn progress(state: CardState, step: i32, interval: i32) -> CardProgress {
+        CardProgress {
+            id: "progress".into(),
+            user_id: "user".into(),
+            card_id: "card".into(),
+            review_count: match state {
+                CardState::New | CardState::Learning => 0,
+                _ => 3,
+            },
+            last_reviewed: None,
+            due_date: None,
+            ease_factor: 2.5,
+            interval,
+            stability: None,
+            difficulty: None,
+            state,
+            step,
+            lapses: 0,
+        }
+    }
+
+    fn steps() -> LearningSteps {
+        LearningSteps::new(vec![1, 10, 60], vec![10])
+    }
+
+    #[test]
+    fn test_new_card_walks_learning_steps() {
+        let now = Utc::now();
+        let sm2 = SM2Calculator::default();
+
+        let first = steps().next_review(&sm2, &progress(CardState::New, 0, 1), 4, now);
+        assert_eq!(first.state, CardState::Learning);
+        assert_eq!(first.step, 1);
+        assert_eq!(first.due_date, now + Duration::minutes(10));
+
+        let failed = steps().next_review(&sm2, &progress(CardState::Learning, 2, 1), 1, now);
+        assert_eq!(failed.step, 0);
+        assert_eq!(failed.due_date, now + Duration::minutes(1));
+
+        let hard = steps().next_review(&sm2, &progress(CardState::Learning, 1, 1), 3, now);
+        assert_eq!(hard.step, 1);
+        assert_eq!(hard.review_count, 0);
+    }
+
+    #[test]
+    fn test_last_step_graduates_to_review() {
+        let now = Utc::now();
+        let sm2 = SM2Calculator::default();
+
+        let graduated = steps().next_review(&sm2, &progress(CardState::Learning, 2, 1), 4, now);
+        assert_eq!(graduated.state, CardState::Review);
+        assert_eq!(graduated.review_count, 1);
+        assert_eq!(graduated.due_date, now + Duration::days(1));
+
+        let easy = steps().next_review(&sm2, &progress(CardState::New, 0, 1), 5, now);
+        assert_eq!(easy.state, CardState::Review);
+    }
+
+    #[test]
+    fn test_lapse_enters_relearning() {
+        let now = Utc::now();
+        let sm2 = SM2Calculator::default();
+
+        let lapsed = steps().next_review(&sm2, &progress(CardState::Review, 0, 20), 1, now);
+        assert_eq!(lapsed.state, CardState::Relearning);
+        assert_eq!(lapsed.lapses, 1);
+        assert_eq!(lapsed.interval, 1);
+        assert_eq!(lapsed.due_date, now + Duration::minutes(10));
+
+        let relearned = steps().next_review(&sm2, &progress(CardState::Relearning, 0, 1), 4, now);
+        assert_eq!(relearned.state, CardState::Review);
+        assert_eq!(relearned.due_date, now + Duration::days(1));
+    }
+
+    #[test]
+    fn test_no_steps_schedules_directly() {
+        let now = Utc::now();
+        let sm2 = SM2Calculator::default();
+        let steps = LearningSteps::new(vec![], vec![]);
+
+        let first = steps.next_review(&sm2, &progress(CardState::New, 0, 1), 4, now);
+        assert_eq!(first.state, CardState::Review);
+
+        let lapsed = steps.next_review(&sm2, &progress(CardState::Review, 0, 20), 1, now);
+        assert_eq!(lapsed.state, CardState::Review);
+        assert_eq!(lapsed.lapses, 1);
+        assert_eq!(lapsed.due_date, now + Duration::days(1));
+    }
+}
@@ -0,0 +1,80 @@
+use ogonek_types::{CardProgressWithFields, CardState};
+
+/// Orders due cards for a study session. Cards in their (re)learning steps come first,
+/// then the reviews with the new cards spread evenly between them
+pub fn build_study_queue(cards: &[CardProgressWithFields]) -> Vec<String> {
+    let (learning, rest): (Vec<_>, Vec<_>) = cards
+        .iter()
+        .partition(|card| matches!(card.state, CardState::Learning | CardState::Relearning));
+    let (new, reviews): (Vec<_>, Vec<_>) = rest
+        .into_iter()
+        .partition(|card| card.state == CardState::New);
+
+    let mut interleaved = spread(reviews);
+    interleaved.extend(spread(new));
+    interleaved.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    learning
+        .into_iter()
+        .map(|card| card.id.clone())
+        .chain(interleaved.into_iter().map(|(_, id)| id))
+        .collect()
+}
+
+/// Gives each card a slot in (0, 1) within its own group, merging groups by slot interleaves them
+fn spread(group: Vec<&CardProgressWithFields>) -> Vec<(f64, String)> {
+    let len = group.len() as f64 + 1.0;
+    group
+        .into_iter()
+        .enumerate()
+        .map(|(i, card)| ((i as f64 + 1.0) / len, card.id.clone()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ogonek_types::CardType;
+
+    fn card(id: &str, state: CardState) -> CardProgressWithFields {
+        CardProgressWithFields {
+            id: id.into(),
+            front: "Front".into(),
+            back: "Back".into(),
+            media_url: None,
+            card_type: CardType::Basic,
+            state,
+            ordinal: 0,
+        }
+    }
+
+    #[test]
+    fn test_learning_cards_come_first() {
+        let cards = [
+            card("r1", CardState::Review),
+            card("l1", CardState::Relearning),
+            card("n1", CardState::New),
+            card("l2", CardState::Learning),
+        ];
+
+        let queue = build_study_queue(&cards);
+        assert_eq!(queue[..2], ["l1", "l2"]);
+        assert_eq!(queue.len(), 4);
+    }
+
+    #[test]
+    fn test_new_cards_are_spread_between_reviews() {
+        let cards = [
+            card("r1", CardState::Review),
+            card("r2", CardState::Review),
+            card("r3", CardState::Review),
+            card("r4", CardState::Review),
+            card("r5", CardState::Review),
+            card("n1", CardState::New),
+            card("n2", CardState::New),
+        ];
+
+        let queue = build_study_queue(&cards);
+        assert_eq!(queue, ["r1", "r2", "n1", "r3", "r4", "n2", "r5"]);
+    }
+}
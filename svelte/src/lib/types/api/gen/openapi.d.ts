@@ -1018,7 +1018,6 @@ export interface components {
         };
         BindPayload: {
             inviteToken: string;
-            studentId: string;
         };
         CalendarFull: {
             colour: string;
@@ -25,13 +25,11 @@ export const actions: Actions = {
     const inviteToken = url.searchParams.get("invite");
 
     const accessToken = cookies.get("accessToken");
-
-    const user = await ValidateAccess(accessToken);
-    const studentId = user.sub;
+    await ValidateAccess(accessToken);
 
     const response = await fetch(routes.auth.bind_student_to_teacher(), {
       method: "POST",
-      body: JSON.stringify({ inviteToken, studentId }),
+      body: JSON.stringify({ inviteToken }),
     });
 
     if (!response.ok) {